@@ -18,6 +18,7 @@ use tauri::menu::{Menu, MenuItem};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
 use tauri::{Emitter, Manager, State};
 use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut, ShortcutState};
+use tauri_plugin_notification::NotificationExt;
 use tokio::fs;
 use tokio::sync::{broadcast, oneshot, Mutex};
 use tower_http::cors::CorsLayer;
@@ -40,10 +41,135 @@ pub struct Task {
     pub repeat_type: String, // "none" | "daily" | "weekly"
     pub timer_type: String,  // "none" | "pomodoro" | "countdown"
     pub timer_duration: i32, // minutes
+    #[serde(default)]
+    pub position: i64, // order within its (date, status) board column
     pub created_at: String,
     pub updated_at: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TaskFilter {
+    pub start_date: Option<String>, // YYYY-MM-DD, inclusive
+    pub end_date: Option<String>,   // YYYY-MM-DD, inclusive
+    pub statuses: Option<Vec<String>>,
+    pub priorities: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>, // matches tasks carrying any of these tags
+    pub keyword: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TaskQuery {
+    #[serde(flatten)]
+    pub filter: TaskFilter,
+    pub sort_by: Option<String>,    // "date" | "priority" | "created_at" | "updated_at" | "title"
+    pub sort_order: Option<String>, // "asc" | "desc"
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskQueryPage {
+    pub items: Vec<Task>,
+    pub next_cursor: Option<String>,
+    pub total: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TaskBulkPatch {
+    pub status: Option<String>,
+    pub shift_days: Option<i64>,
+    pub priority: Option<String>,
+    pub add_tags: Option<Vec<String>>,
+    pub remove_tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskBulkResult {
+    pub affected: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct TaskStatusColumn {
+    pub key: String,
+    pub label: String,
+    pub color: String,
+    pub is_done: bool, // terminal column, counted as completed in stats
+    pub sort_order: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SaveTaskStatusPayload {
+    pub key: String,
+    pub label: String,
+    pub color: Option<String>,
+    pub is_done: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MoveTaskPayload {
+    pub status: String,
+    pub date: Option<String>, // defaults to the task's current date
+    pub index: Option<i64>,   // defaults to the end of the target column
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReorderTasksBody {
+    pub date: String,
+    pub status: String,
+    pub task_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskBoardColumn {
+    #[serde(flatten)]
+    pub status: TaskStatusColumn,
+    pub tasks: Vec<Task>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct TaskPlanTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct TaskPlanTemplateItem {
+    pub id: String,
+    pub template_id: String,
+    pub weekday_offset: i32, // 0 = Monday ... 6 = Sunday
+    pub title: String,
+    pub description: String,
+    pub priority: String,
+    pub start_time: String,
+    pub duration: f64,
+    pub tags: String,
+    pub timer_type: String,
+    pub timer_duration: i32,
+    pub sort_order: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaskPlanTemplateDetail {
+    #[serde(flatten)]
+    pub template: TaskPlanTemplate,
+    pub items: Vec<TaskPlanTemplateItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApplyTaskPlanReport {
+    pub week_start: String,
+    pub week_end: String,
+    pub created: usize,
+    pub skipped: usize,
+    pub replaced: usize,
+    pub tasks: Vec<Task>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct DailyLog {
     pub id: String,
@@ -68,20 +194,53 @@ pub struct FocusSession {
     pub active_task_id: Option<String>,
 }
 
+/// Check-in/check-out attendance; focus time itself lives in `focus_runs`.
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct AttendanceRecord {
+    pub id: String,
+    pub date: String,
+    pub checked_in_at: Option<String>,
+    pub checked_out_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct FocusTemplate {
     pub id: String,
     pub name: String,
     pub timer_type: String,
-    pub duration_minutes: i32,
+    pub duration_minutes: i32, // work phase length
     pub tags_json: String,
     pub linked_task_title: Option<String>,
     pub color_token: Option<String>,
     pub is_archived: i32,
+    #[serde(default = "default_short_break_minutes")]
+    pub short_break_minutes: i32,
+    #[serde(default = "default_long_break_minutes")]
+    pub long_break_minutes: i32,
+    #[serde(default = "default_cycles_before_long_break")]
+    pub cycles_before_long_break: i32,
     pub created_at: String,
     pub updated_at: String,
 }
 
+fn default_tags_json() -> String {
+    "[]".to_string()
+}
+
+fn default_short_break_minutes() -> i32 {
+    5
+}
+
+fn default_long_break_minutes() -> i32 {
+    15
+}
+
+fn default_cycles_before_long_break() -> i32 {
+    4
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct FocusRun {
     pub id: String,
@@ -97,6 +256,10 @@ pub struct FocusRun {
     pub date: String,
     pub tags_json: String,
     pub note: Option<String>,
+    #[serde(default)]
+    pub cycle: i32, // current pomodoro cycle, starting at 1
+    #[serde(default)]
+    pub break_seconds: i64,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -111,22 +274,72 @@ pub struct StartFocusRunPayload {
     pub date: Option<String>,
     pub tags_json: Option<String>,
     pub note: Option<String>,
+    #[serde(default)]
+    pub take_over: Option<bool>, // abort the run active elsewhere instead of rejecting the start
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FinishFocusRunPayload {
-    pub actual_seconds: i64,
+    #[serde(default)]
+    pub actual_seconds: Option<i64>, // ignored: focus time is measured by the server
     pub status: String,
-    pub ended_at: Option<String>,
+    pub ended_at: Option<String>, // ignored: runs end when the server receives the finish
     pub tags_json: Option<String>,
     pub note: Option<String>,
 }
 
+impl FinishFocusRunPayload {
+    fn completed() -> Self {
+        FinishFocusRunPayload {
+            actual_seconds: None,
+            status: "completed".to_string(),
+            ended_at: None,
+            tags_json: None,
+            note: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct FocusRunSegment {
+    pub id: String,
+    pub run_id: String,
+    pub kind: String,  // "focus" | "break"
+    pub phase: String, // "work" | "short_break" | "long_break"
+    pub cycle: i32,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub planned_seconds: i64, // break length; 0 for focus segments
+}
+
+/// Live view of a run with timer values computed on the server.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusRunState {
+    #[serde(flatten)]
+    pub run: FocusRun,
+    pub phase: String, // "focus" | "paused" | "break" | final status
+    pub elapsed_seconds: i64,
+    pub remaining_seconds: i64,
+    pub segments: Vec<FocusRunSegment>,
+    pub server_time: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FocusStatsSummary {
     pub total_focus_minutes: i64,
     pub completed_runs: i64,
     pub completion_rate: f64,
+    pub interrupted_runs: i64, // orphaned by a crash; excluded from completion_rate
+    #[serde(default)]
+    pub total_break_minutes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusCycleStat {
+    pub cycle: i32,
+    pub focus_minutes: i64,
+    pub break_minutes: i64,
+    pub runs: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -144,6 +357,142 @@ pub struct FocusStatsResult {
     pub dimension: String,
     pub summary: FocusStatsSummary,
     pub slices: Vec<FocusStatsSlice>,
+    #[serde(default)]
+    pub cycles: Vec<FocusCycleStat>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FocusTimeseriesQuery {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub dimension: Option<String>, // "tag" | "template" | "timer_type"
+    pub key: Option<String>,       // restrict to one tag / template name / timer type
+    pub threshold_minutes: Option<i64>, // minimum daily minutes counted towards a streak
+    pub year: Option<i32>,         // heatmap year, defaults to the year of end_date
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusDayPoint {
+    pub date: String,
+    pub minutes: i64,
+    pub runs: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusHourPoint {
+    pub hour: u32,
+    pub minutes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusStreak {
+    pub threshold_minutes: i64,
+    pub current_days: i64,
+    pub longest_days: i64, // within the FOCUS_STREAK_LOOKBACK_DAYS before end_date
+    pub longest_start: Option<String>,
+    pub longest_end: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusWeekComparison {
+    pub week_start: String,
+    pub week_end: String,
+    pub minutes: i64,
+    pub previous_week_start: String,
+    pub previous_week_end: String,
+    pub previous_minutes: i64,
+    pub change_percent: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusTimeseriesResult {
+    pub start_date: String,
+    pub end_date: String,
+    pub dimension: String,
+    pub key: Option<String>,
+    pub daily: Vec<FocusDayPoint>,
+    pub hourly: Vec<FocusHourPoint>,
+    pub heatmap_year: i32,
+    pub heatmap: Vec<FocusDayPoint>,
+    pub streak: FocusStreak,
+    pub best_hours: Vec<FocusHourPoint>,
+    pub week_over_week: FocusWeekComparison,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct FocusGoal {
+    pub id: String,
+    pub dimension: String,  // "tag" | "template" | "timer_type"
+    pub target_key: String, // e.g. "数学" or "408"
+    pub period: String,     // "day" | "week"
+    pub target_minutes: i64,
+    pub is_active: bool,
+    pub last_reached_period: Option<String>, // start of the last period a reached event was sent for
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusGoalPayload {
+    pub dimension: Option<String>,
+    pub target_key: String,
+    pub period: String,
+    pub target_minutes: i64,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusGoalProgress {
+    #[serde(flatten)]
+    pub goal: FocusGoal,
+    pub period_start: String,
+    pub period_end: String,
+    pub achieved_minutes: i64,
+    pub percent: f64,
+    pub remaining_minutes: i64,
+    pub reached: bool,
+    pub hit_rate: Option<f64>, // share of past periods (since creation) that met the target
+    pub evaluated_periods: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct FocusInterruption {
+    pub id: String,
+    pub run_id: String,
+    pub category: String, // "phone" | "person" | "self" | "other"
+    pub note: Option<String>,
+    pub occurred_at: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LogFocusInterruptionPayload {
+    pub category: Option<String>, // defaults to "other"
+    pub note: Option<String>,
+    pub occurred_at: Option<String>, // RFC3339, defaults to now
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusInterruptionCount {
+    pub key: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusInterruptionHourCount {
+    pub hour: u32,
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusInterruptionStats {
+    pub start_date: String,
+    pub end_date: String,
+    pub total: i64,
+    pub interrupted_run_count: i64,
+    pub by_hour: Vec<FocusInterruptionHourCount>,
+    pub by_tag: Vec<FocusInterruptionCount>,
+    pub by_category: Vec<FocusInterruptionCount>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -201,6 +550,8 @@ pub struct Question {
     pub correct_count: i32,
     pub ease_factor: f64,
     pub r#interval: i32,
+    #[serde(default = "default_tags_json")]
+    pub tags_json: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
@@ -243,6 +594,8 @@ pub struct WrongQuestion {
     pub is_archived: i32,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    pub error_causes_json: Option<String>, // JSON array of WRONG_QUESTION_ERROR_CAUSES keys; absent on update keeps the stored causes
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
@@ -265,6 +618,8 @@ pub struct WrongQuestionFilter {
     pub mastery_level: Option<i32>,
     pub search_keyword: Option<String>,
     pub is_archived: Option<i32>,
+    #[serde(default)]
+    pub error_cause: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
@@ -272,16 +627,35 @@ pub struct SubjectStat {
     pub subject: String,
     pub count: i64,
     pub unmastered: i64,
+    pub mastered: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct WrongQuestionStats {
     pub total_count: i64,
     pub unmastered_count: i64,
+    pub mastered_count: i64,
     pub weekly_pending_count: i64,
     pub weekly_done_count: i64,
     pub this_week_new: i64,
     pub by_subject: Vec<SubjectStat>,
+    pub by_cause: Vec<ErrorCauseStat>,
+    pub cause_trend: Vec<ErrorCauseTrendPoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct ErrorCauseStat {
+    pub cause: String,
+    pub count: i64,
+    pub unmastered: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct ErrorCauseTrendPoint {
+    pub week_start: String,
+    pub subject: String,
+    pub cause: String,
+    pub count: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -357,7 +731,125 @@ fn emit_sync_action(sync_hub: &SyncHub, action: &str) {
     let _ = sync_hub.app.emit("sync-update", payload);
 }
 
-async fn ws_client_loop(mut socket: WebSocket, mut rx: broadcast::Receiver<String>) {
+/// Like `emit_sync_action`, with a data payload for events clients display directly.
+fn emit_sync_event(sync_hub: &SyncHub, action: &str, data: serde_json::Value) {
+    let payload = json!({ "action": action, "data": data }).to_string();
+    let _ = sync_hub.tx.send(payload.clone());
+    let _ = sync_hub.app.emit("sync-update", payload);
+}
+
+fn focus_run_event_data(event: &str, state: &FocusRunState) -> serde_json::Value {
+    let mut data = serde_json::to_value(state).unwrap_or_default();
+    if let Some(obj) = data.as_object_mut() {
+        obj.insert("event".to_string(), json!(event));
+    }
+    data
+}
+
+/// Broadcasts a structured focus run state change (start, pause, resume, finish, ...) with remaining time.
+fn emit_focus_run_event(sync_hub: &SyncHub, event: &str, state: &FocusRunState) {
+    emit_sync_event(sync_hub, "FOCUS_RUN_EVENT", focus_run_event_data(event, state));
+}
+
+const FOCUS_TICK_INTERVAL_SECS: u64 = 5;
+
+#[derive(Debug, Deserialize)]
+struct WsInboundMessage {
+    action: String,
+    #[serde(default)]
+    data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct WsFocusCommand {
+    command: String, // "state" | "start" | "pause" | "resume" | "extend" | "break" | "skip-break" | "finish"
+    run_id: Option<String>, // defaults to the active run
+    minutes: Option<i32>,
+    start: Option<StartFocusRunPayload>,
+    finish: Option<FinishFocusRunPayload>,
+    request_id: Option<String>,
+}
+
+async fn ws_focus_command(
+    state: &LanAppState,
+    cmd: &WsFocusCommand,
+) -> Result<Option<FocusRunState>, String> {
+    let command = cmd.command.trim().to_lowercase();
+    if command == "start" {
+        let payload = cmd.start.as_ref().ok_or_else(|| "start 缺少参数".to_string())?;
+        let run = start_focus_run_with_sync(&state.db, &state.sync_hub, payload).await?;
+        let db = state.db.lock().await;
+        return db_get_focus_run_state(&db.db, &run.id).await.map(Some);
+    }
+
+    let run_id = match &cmd.run_id {
+        Some(id) => id.clone(),
+        None => {
+            let db = state.db.lock().await;
+            match db_get_active_focus_run(&db.db).await? {
+                Some(active) => active.run.id,
+                None if command == "state" => return Ok(None),
+                None => return Err("Active focus run not found".to_string()),
+            }
+        }
+    };
+
+    let action = match command.as_str() {
+        "state" => {
+            let db = state.db.lock().await;
+            return db_get_focus_run_state(&db.db, &run_id).await.map(Some);
+        }
+        "finish" => {
+            let payload = cmd.finish.clone().unwrap_or_else(FinishFocusRunPayload::completed);
+            finish_focus_run_with_sync(&state.db, &state.sync_hub, &run_id, &payload).await?;
+            let db = state.db.lock().await;
+            return db_get_focus_run_state(&db.db, &run_id).await.map(Some);
+        }
+        "pause" => FocusRunAction::Pause,
+        "resume" => FocusRunAction::Resume,
+        "extend" => FocusRunAction::Extend(cmd.minutes.unwrap_or(5)),
+        "break" => FocusRunAction::Break(cmd.minutes),
+        "skip-break" => FocusRunAction::SkipBreak,
+        other => return Err(format!("未知的专注指令: {}", other)),
+    };
+    transition_focus_run(&run_id, action, &state.db, &state.sync_hub).await.map(Some)
+}
+
+/// Handles a message from a LAN client; `FOCUS_COMMAND` lets the phone drive the desktop timer.
+/// Returns a reply meant only for the sender.
+async fn ws_handle_inbound(state: &LanAppState, text: &str) -> Option<String> {
+    let message = serde_json::from_str::<WsInboundMessage>(text).ok()?;
+    if message.action != "FOCUS_COMMAND" {
+        return None;
+    }
+    let (request_id, result) = match serde_json::from_value::<WsFocusCommand>(message.data) {
+        Ok(cmd) => (cmd.request_id.clone(), ws_focus_command(state, &cmd).await),
+        Err(e) => (None, Err(format!("Invalid FOCUS_COMMAND payload: {}", e))),
+    };
+    let data = match result {
+        Ok(run_state) => json!({ "request_id": request_id, "ok": true, "state": run_state }),
+        Err(error) => json!({ "request_id": request_id, "ok": false, "error": error }),
+    };
+    Some(json!({ "action": "FOCUS_COMMAND_RESULT", "data": data }).to_string())
+}
+
+/// Snapshot of the running timer for a connected client, skipped while nothing is counting down.
+async fn ws_focus_tick(state: &LanAppState) -> Option<String> {
+    let db = state.db.lock().await;
+    let active = db_get_active_focus_run(&db.db).await.ok()??;
+    drop(db);
+    if active.run.status == "paused" {
+        return None;
+    }
+    Some(json!({ "action": "FOCUS_RUN_EVENT", "data": focus_run_event_data("tick", &active) }).to_string())
+}
+
+async fn ws_client_loop(
+    mut socket: WebSocket,
+    mut rx: broadcast::Receiver<String>,
+    state: LanAppState,
+) {
+    let mut tick = tokio::time::interval(std::time::Duration::from_secs(FOCUS_TICK_INTERVAL_SECS));
     loop {
         tokio::select! {
             inbound = socket.recv() => {
@@ -368,6 +860,13 @@ async fn ws_client_loop(mut socket: WebSocket, mut rx: broadcast::Receiver<Strin
                             break;
                         }
                     }
+                    Some(Ok(Message::Text(text))) => {
+                        if let Some(reply) = ws_handle_inbound(&state, text.as_str()).await {
+                            if socket.send(Message::Text(reply.into())).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
                     Some(Ok(_)) => {}
                     Some(Err(_)) => break,
                 }
@@ -382,6 +881,13 @@ async fn ws_client_loop(mut socket: WebSocket, mut rx: broadcast::Receiver<Strin
                     Err(_) => break,
                 }
             }
+            _ = tick.tick() => {
+                if let Some(snapshot) = ws_focus_tick(&state).await {
+                    if socket.send(Message::Text(snapshot.into())).await.is_err() {
+                        break;
+                    }
+                }
+            }
         }
     }
 }
@@ -413,51 +919,183 @@ fn toggle_main_window(app: &tauri::AppHandle) {
     }
 }
 
-fn normalize_embedded_asset_path(uri_path: &str) -> String {
-    let trimmed = uri_path.trim_start_matches('/');
-    if trimmed.is_empty() {
-        return "index.html".to_string();
-    }
-    trimmed
-        .split('/')
-        .filter(|seg| !seg.is_empty() && *seg != "." && *seg != "..")
-        .collect::<Vec<_>>()
-        .join("/")
+const TRAY_DEFAULT_TOOLTIP: &str = "EVA 考研辅助终端";
+
+fn format_countdown(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
 }
 
-fn embedded_asset_response(path: &str) -> Response<Body> {
-    if let Some(asset) = FrontendAssets::get(path) {
-        let mime = from_path(path).first_or_octet_stream();
-        return Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, mime.as_ref())
-            .body(Body::from(asset.data.into_owned()))
-            .unwrap_or_else(|_| Response::new(Body::from("Failed to render asset")));
+fn notify_focus_timer(app: &tauri::AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::error!("Failed to show focus notification: {}", e);
     }
+}
 
-    if let Some(index) = FrontendAssets::get("index.html") {
-        return Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
-            .body(Body::from(index.data.into_owned()))
-            .unwrap_or_else(|_| Response::new(Body::from("Failed to render index")));
+/// Identifies the countdown currently running so each phase end is notified only once.
+fn focus_deadline_key(state: &FocusRunState) -> Option<String> {
+    match state.run.status.as_str() {
+        "running" => Some(format!(
+            "{}:work:{}:{}",
+            state.run.id, state.run.cycle, state.run.planned_minutes
+        )),
+        "break" => state
+            .segments
+            .iter()
+            .rev()
+            .find(|s| s.kind == "break" && s.ended_at.is_none())
+            .map(|s| format!("{}:{}", s.id, s.planned_seconds)),
+        _ => None,
     }
-
-    Response::builder()
-        .status(StatusCode::NOT_FOUND)
-        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
-        .body(Body::from("Embedded frontend assets not found"))
-        .unwrap_or_else(|_| Response::new(Body::from("Not Found")))
 }
 
-async fn embedded_static_handler(uri: Uri) -> Response<Body> {
-    let path = normalize_embedded_asset_path(uri.path());
-    embedded_asset_response(&path)
-}
+/// Polls the active run every second so the tray countdown stays current and a native
+/// notification fires when a work phase or break runs out, even with the window hidden.
+async fn focus_timer_watcher(app: tauri::AppHandle, db: Arc<Mutex<AppDb>>) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(1));
+    let mut last_label: Option<String> = None;
+    let mut last_notified: Option<String> = None;
+    loop {
+        interval.tick().await;
+        let active = {
+            let db = db.lock().await;
+            db_get_active_focus_run(&db.db).await
+        };
+        let active = match active {
+            Ok(active) => active,
+            Err(e) => {
+                log::error!("Focus timer watcher failed: {}", e);
+                continue;
+            }
+        };
 
-async fn local_ping_handler() -> &'static str {
-    "EVA Server is running"
-}
+        let label = active.as_ref().map(|state| {
+            let prefix = match state.phase.as_str() {
+                "focus" => "专注",
+                "paused" => "已暂停",
+                _ => "休息",
+            };
+            format!("{} {}", prefix, format_countdown(state.remaining_seconds))
+        });
+        if label != last_label {
+            if let Some(tray) = app.tray_by_id("eva_tray") {
+                let tooltip = match &label {
+                    Some(label) => format!("{} · {}", TRAY_DEFAULT_TOOLTIP, label),
+                    None => TRAY_DEFAULT_TOOLTIP.to_string(),
+                };
+                let _ = tray.set_tooltip(Some(tooltip));
+                let _ = tray.set_title(label.as_deref());
+            }
+            last_label = label;
+        }
+
+        let Some(state) = active else {
+            continue;
+        };
+        if state.remaining_seconds > 0 {
+            continue;
+        }
+        let Some(key) = focus_deadline_key(&state) else {
+            continue;
+        };
+        if last_notified.as_deref() == Some(key.as_str()) {
+            continue;
+        }
+        if state.run.status == "break" {
+            notify_focus_timer(&app, "休息结束", "休息时间到了，准备开始下一个番茄");
+        } else {
+            notify_focus_timer(
+                &app,
+                "专注完成",
+                &format!("第 {} 个番茄已完成，可以休息一下", state.run.cycle.max(1)),
+            );
+        }
+        last_notified = Some(key);
+    }
+}
+
+/// Tray shortcuts for the active run: pause toggles pause/resume (or ends a break), finish completes it.
+async fn tray_focus_action(app: &tauri::AppHandle, finish: bool) -> Result<(), String> {
+    let db = app
+        .try_state::<Arc<Mutex<AppDb>>>()
+        .ok_or_else(|| "Database not initialized".to_string())?
+        .inner()
+        .clone();
+    let sync_hub = app.state::<Arc<SyncHub>>().inner().clone();
+    let active = {
+        let guard = db.lock().await;
+        db_get_active_focus_run(&guard.db).await?
+    }
+    .ok_or_else(|| "Active focus run not found".to_string())?;
+
+    if finish {
+        finish_focus_run_with_sync(&db, &sync_hub, &active.run.id, &FinishFocusRunPayload::completed())
+            .await?;
+        return Ok(());
+    }
+    let action = match active.run.status.as_str() {
+        "running" => FocusRunAction::Pause,
+        "paused" => FocusRunAction::Resume,
+        _ => FocusRunAction::SkipBreak,
+    };
+    transition_focus_run(&active.run.id, action, &db, &sync_hub).await?;
+    Ok(())
+}
+
+fn spawn_tray_focus_action(app: &tauri::AppHandle, finish: bool) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = tray_focus_action(&app, finish).await {
+            log::warn!("Tray focus action failed: {}", e);
+        }
+    });
+}
+
+fn normalize_embedded_asset_path(uri_path: &str) -> String {
+    let trimmed = uri_path.trim_start_matches('/');
+    if trimmed.is_empty() {
+        return "index.html".to_string();
+    }
+    trimmed
+        .split('/')
+        .filter(|seg| !seg.is_empty() && *seg != "." && *seg != "..")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn embedded_asset_response(path: &str) -> Response<Body> {
+    if let Some(asset) = FrontendAssets::get(path) {
+        let mime = from_path(path).first_or_octet_stream();
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, mime.as_ref())
+            .body(Body::from(asset.data.into_owned()))
+            .unwrap_or_else(|_| Response::new(Body::from("Failed to render asset")));
+    }
+
+    if let Some(index) = FrontendAssets::get("index.html") {
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+            .body(Body::from(index.data.into_owned()))
+            .unwrap_or_else(|_| Response::new(Body::from("Failed to render index")));
+    }
+
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Body::from("Embedded frontend assets not found"))
+        .unwrap_or_else(|_| Response::new(Body::from("Not Found")))
+}
+
+async fn embedded_static_handler(uri: Uri) -> Response<Body> {
+    let path = normalize_embedded_asset_path(uri.path());
+    embedded_asset_response(&path)
+}
+
+async fn local_ping_handler() -> &'static str {
+    "EVA Server is running"
+}
 
 #[derive(Debug, Deserialize)]
 struct QuizDueQuery {
@@ -465,8 +1103,35 @@ struct QuizDueQuery {
 }
 
 #[derive(Debug, Deserialize)]
-struct TasksQuery {
-    date: Option<String>,
+struct TaskBoardQuery {
+    start_date: Option<String>,
+    end_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskQueryParams {
+    date: Option<String>, // shorthand for start_date = end_date
+    start_date: Option<String>,
+    end_date: Option<String>,
+    status: Option<String>,   // comma-separated
+    priority: Option<String>, // comma-separated
+    tags: Option<String>,     // comma-separated
+    keyword: Option<String>,
+    sort_by: Option<String>,
+    sort_order: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskBulkUpdateBody {
+    filter: TaskFilter,
+    patch: TaskBulkPatch,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskBulkDeleteBody {
+    filter: TaskFilter,
 }
 
 #[derive(Debug, Deserialize)]
@@ -481,17 +1146,100 @@ struct FocusStatsQuery {
     dimension: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct FocusRunTransitionQuery {
+    minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FocusRunRecoverQuery {
+    action: String,
+    cap_minutes: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FocusGoalProgressQuery {
+    date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FocusExportQuery {
+    format: Option<String>, // "csv" (default) | "json"
+    start_date: Option<String>,
+    end_date: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FocusInterruptionStatsQuery {
+    start_date: Option<String>,
+    end_date: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct WrongQuestionsQuery {
     subject: Option<String>,
     mastery_level: Option<i32>,
     search_keyword: Option<String>,
     is_archived: Option<i32>,
+    error_cause: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DueWrongQuestionsQuery {
+    date: Option<String>,
+    subject: Option<String>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewWrongQuestionBody {
+    grade: String,
+    response_ms: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewHistoryQuery {
+    item_type: String,
+    item_id: String,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReviewCurvesQuery {
+    start_date: Option<String>,
+    end_date: Option<String>,
+    item_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KnowledgeTreeQuery {
+    recent_days: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KnowledgeItemQuery {
+    item_type: String,
+    item_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KnowledgeAssignmentBody {
+    item_type: String,
+    item_id: String,
+    #[serde(default)]
+    node_ids: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct WeeklyReviewItemsQuery {
     week_start: String,
+    auto_populate: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AddWeeklyReviewItemBody {
+    week_start: String,
+    wrong_question_id: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -509,6 +1257,19 @@ struct CarryNextWeekBody {
 struct CreateWrongQuestionRequest {
     question: WrongQuestion,
     add_to_current_week: Option<bool>,
+    allow_duplicate: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WrongQuestionDuplicatesBody {
+    question_content: String,
+    exclude_id: Option<String>,
+    subject: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MergeWrongQuestionsBody {
+    source_ids: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -565,7 +1326,7 @@ fn parse_week_start_monday(week_start: &str) -> Result<(String, String), String>
 
 async fn db_fetch_all_questions(pool: &sqlx::SqlitePool) -> Result<Vec<Question>, String> {
     let rows = sqlx::query_as::<_, Question>(
-        "SELECT id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval FROM questions ORDER BY created_at DESC",
+        "SELECT id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval, tags_json FROM questions ORDER BY created_at DESC",
     )
     .fetch_all(pool)
     .await
@@ -575,7 +1336,7 @@ async fn db_fetch_all_questions(pool: &sqlx::SqlitePool) -> Result<Vec<Question>
 
 async fn db_get_tasks(pool: &sqlx::SqlitePool) -> Result<Vec<Task>, String> {
     let rows = sqlx::query_as::<_, Task>(
-        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, created_at, updated_at FROM tasks ORDER BY date ASC, start_time ASC",
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at FROM tasks ORDER BY date ASC, start_time ASC",
     )
     .fetch_all(pool)
     .await
@@ -585,7 +1346,7 @@ async fn db_get_tasks(pool: &sqlx::SqlitePool) -> Result<Vec<Task>, String> {
 
 async fn db_get_tasks_by_date(pool: &sqlx::SqlitePool, date: &str) -> Result<Vec<Task>, String> {
     let rows = sqlx::query_as::<_, Task>(
-        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, created_at, updated_at FROM tasks WHERE date = ? ORDER BY start_time ASC",
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at FROM tasks WHERE date = ? ORDER BY start_time ASC",
     )
     .bind(date)
     .fetch_all(pool)
@@ -594,3775 +1355,12788 @@ async fn db_get_tasks_by_date(pool: &sqlx::SqlitePool, date: &str) -> Result<Vec
     Ok(rows)
 }
 
-async fn db_create_task(pool: &sqlx::SqlitePool, task: &Task) -> Result<Task, String> {
-    sqlx::query(
-        "INSERT INTO tasks (id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-    )
-    .bind(&task.id)
-    .bind(&task.title)
-    .bind(&task.description)
-    .bind(&task.status)
-    .bind(&task.priority)
-    .bind(&task.date)
-    .bind(&task.start_time)
-    .bind(&task.duration)
-    .bind(&task.tags)
-    .bind(&task.repeat_type)
-    .bind(&task.timer_type)
-    .bind(&task.timer_duration)
-    .bind(&task.created_at)
-    .bind(&task.updated_at)
-    .execute(pool)
-    .await
-    .map_err(|e| format!("Failed to create task: {}", e))?;
-    Ok(task.clone())
+fn split_query_list(raw: Option<String>) -> Option<Vec<String>> {
+    let values = raw?
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<String>>();
+    if values.is_empty() {
+        None
+    } else {
+        Some(values)
+    }
 }
 
-async fn db_update_task(pool: &sqlx::SqlitePool, task: &Task) -> Result<Task, String> {
-    sqlx::query(
-        "UPDATE tasks SET title=?, description=?, status=?, priority=?, date=?, start_time=?, duration=?, tags=?, repeat_type=?, timer_type=?, timer_duration=?, updated_at=? WHERE id=?",
-    )
-    .bind(&task.title)
-    .bind(&task.description)
-    .bind(&task.status)
-    .bind(&task.priority)
-    .bind(&task.date)
-    .bind(&task.start_time)
-    .bind(&task.duration)
-    .bind(&task.tags)
-    .bind(&task.repeat_type)
-    .bind(&task.timer_type)
-    .bind(&task.timer_duration)
-    .bind(&task.updated_at)
-    .bind(&task.id)
-    .execute(pool)
-    .await
-    .map_err(|e| format!("Failed to update task: {}", e))?;
-    Ok(task.clone())
+fn normalize_filter_values(values: Option<&Vec<String>>, lowercase: bool) -> Vec<String> {
+    let mut clean = Vec::<String>::new();
+    for value in values.into_iter().flatten() {
+        let trimmed = value.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let normalized = if lowercase {
+            trimmed.to_lowercase()
+        } else {
+            trimmed.to_string()
+        };
+        if !clean.contains(&normalized) {
+            clean.push(normalized);
+        }
+    }
+    clean
 }
 
-async fn db_delete_task(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
-    sqlx::query("DELETE FROM tasks WHERE id = ?")
-        .bind(id)
-        .execute(pool)
-        .await
-        .map_err(|e| format!("Failed to delete task: {}", e))?;
-    Ok(())
+fn parse_filter_date(label: &str, value: Option<&str>) -> Result<Option<String>, String> {
+    match value.map(|v| v.trim()).filter(|v| !v.is_empty()) {
+        Some(raw) => {
+            let parsed = chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .map_err(|e| format!("Invalid {}, expected YYYY-MM-DD: {}", label, e))?;
+            Ok(Some(parsed.format("%Y-%m-%d").to_string()))
+        }
+        None => Ok(None),
+    }
 }
 
-async fn db_batch_create_tasks(pool: &sqlx::SqlitePool, tasks: &[Task]) -> Result<usize, String> {
-    let mut count = 0;
-    for task in tasks {
-        let result = sqlx::query(
-            "INSERT OR IGNORE INTO tasks (id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-        )
-        .bind(&task.id)
-        .bind(&task.title)
-        .bind(&task.description)
-        .bind(&task.status)
-        .bind(&task.priority)
-        .bind(&task.date)
-        .bind(&task.start_time)
-        .bind(&task.duration)
-        .bind(&task.tags)
-        .bind(&task.repeat_type)
-        .bind(&task.timer_type)
-        .bind(&task.timer_duration)
-        .bind(&task.created_at)
-        .bind(&task.updated_at)
-        .execute(pool)
-        .await;
+/// Escapes LIKE wildcards so user text matches literally; pair with `ESCAPE '\'`.
+fn escape_like(raw: &str) -> String {
+    raw.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
 
-        if result.is_ok() {
-            count += 1;
+fn push_task_filter(
+    builder: &mut sqlx::QueryBuilder<'_, sqlx::Sqlite>,
+    filter: &TaskFilter,
+) -> Result<(), String> {
+    let start = parse_filter_date("start_date", filter.start_date.as_deref())?;
+    let end = parse_filter_date("end_date", filter.end_date.as_deref())?;
+    if let (Some(s), Some(e)) = (start.as_ref(), end.as_ref()) {
+        if s > e {
+            return Err("start_date 不能晚于 end_date".to_string());
         }
     }
-    Ok(count)
-}
 
-async fn db_get_weekly_stats(pool: &sqlx::SqlitePool, end_date: &str) -> Result<WeeklyStats, String> {
-    let (start_date, end_date) = resolve_week_window(end_date)?;
+    if let Some(start) = start {
+        builder.push(" AND date >= ");
+        builder.push_bind(start);
+    }
+    if let Some(end) = end {
+        builder.push(" AND date <= ");
+        builder.push_bind(end);
+    }
 
-    let total_focus_seconds = sqlx::query_scalar::<_, i64>(
-        "SELECT COALESCE(SUM(total_focus_seconds), 0) FROM focus_sessions WHERE date BETWEEN ? AND ?",
-    )
-    .bind(&start_date)
-    .bind(&end_date)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to aggregate focus sessions: {}", e))?;
+    let statuses = normalize_filter_values(filter.statuses.as_ref(), true);
+    if !statuses.is_empty() {
+        builder.push(" AND status IN (");
+        let mut separated = builder.separated(", ");
+        for status in statuses {
+            separated.push_bind(status);
+        }
+        separated.push_unseparated(")");
+    }
 
-    let task_done_count = sqlx::query_scalar::<_, i64>(
-        "SELECT COALESCE(SUM(CASE WHEN status = 'done' THEN 1 ELSE 0 END), 0) FROM tasks WHERE date BETWEEN ? AND ?",
-    )
-    .bind(&start_date)
-    .bind(&end_date)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to aggregate done tasks: {}", e))?;
+    let priorities = normalize_filter_values(filter.priorities.as_ref(), true);
+    if !priorities.is_empty() {
+        builder.push(" AND priority IN (");
+        let mut separated = builder.separated(", ");
+        for priority in priorities {
+            separated.push_bind(priority);
+        }
+        separated.push_unseparated(")");
+    }
 
-    let task_total_count = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM tasks WHERE date BETWEEN ? AND ?",
-    )
-    .bind(&start_date)
-    .bind(&end_date)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to aggregate total tasks: {}", e))?;
+    let tags = normalize_filter_values(filter.tags.as_ref(), false);
+    if !tags.is_empty() {
+        builder.push(" AND (");
+        let mut separated = builder.separated(" OR ");
+        for tag in tags {
+            separated.push("(',' || REPLACE(COALESCE(tags, ''), ', ', ',') || ',') LIKE ");
+            separated.push_bind_unseparated(format!("%,{},%", escape_like(&tag)));
+            separated.push_unseparated(" ESCAPE '\\'");
+        }
+        separated.push_unseparated(")");
+    }
 
-    let (s408, smath, senglish, spolitics) = sqlx::query_as::<_, (i64, i64, i64, i64)>(
-        "SELECT
-            COALESCE(SUM(CASE WHEN (title LIKE '%408%' OR tags LIKE '%408%') THEN 1 ELSE 0 END), 0) AS s408,
-            COALESCE(SUM(CASE WHEN (title LIKE '%数学%' OR title LIKE '%数一%' OR tags LIKE '%数学%' OR tags LIKE '%数一%') THEN 1 ELSE 0 END), 0) AS smath,
-            COALESCE(SUM(CASE WHEN (title LIKE '%英语%' OR title LIKE '%英一%' OR tags LIKE '%英语%' OR tags LIKE '%英一%') THEN 1 ELSE 0 END), 0) AS senglish,
-            COALESCE(SUM(CASE WHEN (title LIKE '%政治%' OR tags LIKE '%政治%') THEN 1 ELSE 0 END), 0) AS spolitics
-         FROM tasks
-         WHERE date BETWEEN ? AND ?",
-    )
-    .bind(&start_date)
-    .bind(&end_date)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to aggregate subject distribution: {}", e))?;
+    if let Some(keyword) = filter.keyword.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let like = format!("%{}%", escape_like(keyword));
+        builder.push(" AND (title LIKE ");
+        builder.push_bind(like.clone());
+        builder.push(" ESCAPE '\\' OR COALESCE(description, '') LIKE ");
+        builder.push_bind(like);
+        builder.push(" ESCAPE '\\')");
+    }
 
-    let total_focus_minutes = total_focus_seconds / 60;
-    let completion_rate = if task_total_count > 0 {
-        (task_done_count as f64 / task_total_count as f64) * 100.0
-    } else {
-        0.0
-    };
+    Ok(())
+}
 
-    let total_for_distribution = task_total_count.max(1) as f64;
-    let mut subject_distribution = HashMap::new();
-    subject_distribution.insert("408".to_string(), (s408 as f64 / total_for_distribution) * 100.0);
-    subject_distribution.insert("数学".to_string(), (smath as f64 / total_for_distribution) * 100.0);
-    subject_distribution.insert("英语".to_string(), (senglish as f64 / total_for_distribution) * 100.0);
-    subject_distribution.insert("政治".to_string(), (spolitics as f64 / total_for_distribution) * 100.0);
+fn resolve_task_sort(raw: Option<&str>) -> (&'static str, &'static str) {
+    match raw.unwrap_or("date").trim().to_lowercase().as_str() {
+        "priority" => (
+            "priority",
+            "(CASE priority WHEN 'high' THEN '0' WHEN 'medium' THEN '1' ELSE '2' END || date || 'T' || COALESCE(start_time, ''))",
+        ),
+        "created_at" => ("created_at", "created_at"),
+        "updated_at" => ("updated_at", "updated_at"),
+        "title" => ("title", "title"),
+        "position" => ("position", "(date || 'T' || printf('%08d', position))"),
+        _ => ("date", "(date || 'T' || COALESCE(start_time, ''))"),
+    }
+}
 
-    Ok(WeeklyStats {
-        total_focus_minutes,
-        completion_rate,
-        subject_distribution,
-    })
+fn task_priority_rank(priority: &str) -> &'static str {
+    match priority {
+        "high" => "0",
+        "medium" => "1",
+        _ => "2",
+    }
 }
 
-fn normalize_focus_dimension(raw: Option<&str>) -> String {
-    match raw.unwrap_or("tag").trim().to_lowercase().as_str() {
-        "template" => "template".to_string(),
-        "timer_type" => "timer_type".to_string(),
-        _ => "tag".to_string(),
+/// Mirrors the SQL expression returned by `resolve_task_sort` for cursor generation.
+fn task_sort_key(task: &Task, sort_name: &str) -> String {
+    match sort_name {
+        "priority" => format!(
+            "{}{}T{}",
+            task_priority_rank(&task.priority),
+            task.date,
+            task.start_time
+        ),
+        "created_at" => task.created_at.clone(),
+        "updated_at" => task.updated_at.clone(),
+        "title" => task.title.clone(),
+        "position" => format!("{}T{:08}", task.date, task.position),
+        _ => format!("{}T{}", task.date, task.start_time),
     }
 }
 
-fn resolve_focus_range(
-    start_date: Option<&str>,
-    end_date: Option<&str>,
-) -> Result<(String, String), String> {
-    let resolved_end = if let Some(end) = end_date {
-        chrono::NaiveDate::parse_from_str(end, "%Y-%m-%d")
-            .map_err(|e| format!("Invalid end_date, expected YYYY-MM-DD: {}", e))?
-    } else {
-        Local::now().date_naive()
-    };
+fn encode_task_cursor(sort_name: &str, key: &str, id: &str) -> String {
+    format!("{}\u{1f}{}\u{1f}{}", sort_name, key, id)
+        .bytes()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
 
-    let resolved_start = if let Some(start) = start_date {
-        chrono::NaiveDate::parse_from_str(start, "%Y-%m-%d")
-            .map_err(|e| format!("Invalid start_date, expected YYYY-MM-DD: {}", e))?
+fn decode_task_cursor(cursor: &str) -> Result<(String, String, String), String> {
+    let invalid = || "cursor 无效".to_string();
+    if cursor.len() % 2 != 0 {
+        return Err(invalid());
+    }
+    let bytes = (0..cursor.len())
+        .step_by(2)
+        .map(|i| {
+            let pair = cursor.get(i..i + 2).ok_or_else(invalid)?;
+            u8::from_str_radix(pair, 16).map_err(|_| invalid())
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+    let raw = String::from_utf8(bytes).map_err(|_| invalid())?;
+    let mut parts = raw.splitn(3, '\u{1f}');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(sort), Some(key), Some(id)) => Ok((sort.to_string(), key.to_string(), id.to_string())),
+        _ => Err(invalid()),
+    }
+}
+
+async fn db_query_tasks(pool: &sqlx::SqlitePool, query: &TaskQuery) -> Result<TaskQueryPage, String> {
+    let (sort_name, sort_expr) = resolve_task_sort(query.sort_by.as_deref());
+    let descending = query
+        .sort_order
+        .as_deref()
+        .map(|v| v.trim().eq_ignore_ascii_case("desc"))
+        .unwrap_or(false);
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+
+    let mut count_builder =
+        sqlx::QueryBuilder::<sqlx::Sqlite>::new("SELECT COUNT(*) FROM tasks WHERE 1=1");
+    push_task_filter(&mut count_builder, &query.filter)?;
+    let total = count_builder
+        .build_query_scalar::<i64>()
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to count queried tasks: {}", e))?;
+
+    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at FROM tasks WHERE 1=1",
+    );
+    push_task_filter(&mut builder, &query.filter)?;
+
+    if let Some(cursor) = query.cursor.as_deref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let (cursor_sort, key, id) = decode_task_cursor(cursor)?;
+        if cursor_sort != sort_name {
+            return Err("cursor 与当前排序方式不匹配".to_string());
+        }
+        let cmp = if descending { "<" } else { ">" };
+        builder.push(format!(" AND ({} {} ", sort_expr, cmp));
+        builder.push_bind(key.clone());
+        builder.push(format!(" OR ({} = ", sort_expr));
+        builder.push_bind(key);
+        builder.push(format!(" AND id {} ", cmp));
+        builder.push_bind(id);
+        builder.push("))");
+    }
+
+    let direction = if descending { "DESC" } else { "ASC" };
+    builder.push(format!(" ORDER BY {} {}, id {}", sort_expr, direction, direction));
+    builder.push(" LIMIT ");
+    builder.push_bind(limit + 1);
+
+    let mut items = builder
+        .build_query_as::<Task>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to query tasks: {}", e))?;
+
+    let next_cursor = if items.len() as i64 > limit {
+        items.truncate(limit as usize);
+        items
+            .last()
+            .map(|task| encode_task_cursor(sort_name, &task_sort_key(task, sort_name), &task.id))
     } else {
-        resolved_end - chrono::Duration::days(6)
+        None
     };
 
-    if resolved_start > resolved_end {
-        return Err("start_date 不能晚于 end_date".to_string());
-    }
+    Ok(TaskQueryPage {
+        items,
+        next_cursor,
+        total,
+    })
+}
 
-    Ok((
-        resolved_start.format("%Y-%m-%d").to_string(),
-        resolved_end.format("%Y-%m-%d").to_string(),
-    ))
+fn task_filter_is_empty(filter: &TaskFilter) -> bool {
+    let blank = |v: &Option<String>| v.as_deref().map(|s| s.trim().is_empty()).unwrap_or(true);
+    blank(&filter.start_date)
+        && blank(&filter.end_date)
+        && blank(&filter.keyword)
+        && normalize_filter_values(filter.statuses.as_ref(), true).is_empty()
+        && normalize_filter_values(filter.priorities.as_ref(), true).is_empty()
+        && normalize_filter_values(filter.tags.as_ref(), false).is_empty()
 }
 
-fn parse_focus_tags(tags_json: &str) -> Vec<String> {
-    let parsed = serde_json::from_str::<Vec<String>>(tags_json).unwrap_or_default();
-    let mut clean = Vec::<String>::new();
-    for tag in parsed {
-        let normalized = tag.trim();
-        if normalized.is_empty() {
-            continue;
-        }
-        if clean.iter().any(|existing| existing == normalized) {
-            continue;
-        }
-        clean.push(normalized.to_string());
+fn split_task_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+async fn db_bulk_update_tasks(
+    pool: &sqlx::SqlitePool,
+    filter: &TaskFilter,
+    patch: &TaskBulkPatch,
+) -> Result<TaskBulkResult, String> {
+    if task_filter_is_empty(filter) {
+        return Err("批量操作至少需要一个筛选条件".to_string());
     }
 
-    if clean.is_empty() {
-        clean.push("未分类".to_string());
+    let next_status = match patch.status.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(status) => Some(normalize_task_status_key(status)?),
+        None => None,
+    };
+    let next_priority = match patch.priority.as_ref().map(|s| s.trim().to_lowercase()) {
+        Some(p) if p.is_empty() => None,
+        Some(p) if p == "low" || p == "medium" || p == "high" => Some(p),
+        Some(_) => return Err("priority 仅支持 low / medium / high".to_string()),
+        None => None,
+    };
+    let shift_days = patch.shift_days.unwrap_or(0);
+    let add_tags = normalize_filter_values(patch.add_tags.as_ref(), false);
+    let remove_tags = normalize_filter_values(patch.remove_tags.as_ref(), false);
+
+    if next_status.is_none()
+        && next_priority.is_none()
+        && shift_days == 0
+        && add_tags.is_empty()
+        && remove_tags.is_empty()
+    {
+        return Err("patch 不能为空".to_string());
     }
-    clean
-}
 
-fn gen_focus_id(prefix: &str) -> String {
-    format!("{}-{}", prefix, Utc::now().timestamp_micros())
-}
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin bulk task update tx: {}", e))?;
 
-#[derive(Debug, sqlx::FromRow)]
-struct FocusSummaryRow {
-    total_seconds: i64,
-    completed_runs: i64,
-    all_runs: i64,
-}
+    if let Some(status) = next_status.as_deref() {
+        if db_get_task_status(&mut tx, status).await?.is_none() {
+            return Err(format!("未知的任务状态: {}", status));
+        }
+    }
 
-#[derive(Debug, sqlx::FromRow)]
-struct FocusGroupedRow {
-    key_name: String,
-    total_seconds: i64,
-    run_count: i64,
-}
+    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at FROM tasks WHERE 1=1",
+    );
+    push_task_filter(&mut builder, filter)?;
+    let rows = builder
+        .build_query_as::<Task>()
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to select tasks for bulk update: {}", e))?;
 
-#[derive(Debug, sqlx::FromRow)]
-struct FocusTagRow {
-    tags_json: String,
-    actual_seconds: i64,
+    let now = now_iso();
+    for task in &rows {
+        let date = if shift_days != 0 {
+            let parsed = chrono::NaiveDate::parse_from_str(&task.date, "%Y-%m-%d")
+                .map_err(|e| format!("Task {} has invalid date {}: {}", task.id, task.date, e))?;
+            (parsed + chrono::Duration::days(shift_days))
+                .format("%Y-%m-%d")
+                .to_string()
+        } else {
+            task.date.clone()
+        };
+
+        let mut tags = split_task_tags(&task.tags);
+        tags.retain(|t| !remove_tags.contains(t));
+        for tag in &add_tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+
+        sqlx::query("UPDATE tasks SET status = ?, priority = ?, date = ?, tags = ?, updated_at = ? WHERE id = ?")
+            .bind(next_status.as_deref().unwrap_or(&task.status))
+            .bind(next_priority.as_deref().unwrap_or(&task.priority))
+            .bind(&date)
+            .bind(tags.join(","))
+            .bind(&now)
+            .bind(&task.id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to bulk update task {}: {}", task.id, e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit bulk task update tx: {}", e))?;
+
+    Ok(TaskBulkResult { affected: rows.len() })
 }
 
-async fn db_get_focus_templates(
+async fn db_bulk_delete_tasks(
     pool: &sqlx::SqlitePool,
-    include_archived: bool,
-) -> Result<Vec<FocusTemplate>, String> {
-    let rows = if include_archived {
-        sqlx::query_as::<_, FocusTemplate>(
-            "SELECT id, name, timer_type, duration_minutes, tags_json, linked_task_title, color_token, is_archived, created_at, updated_at FROM focus_templates ORDER BY is_archived ASC, updated_at DESC",
-        )
-        .fetch_all(pool)
+    filter: &TaskFilter,
+) -> Result<TaskBulkResult, String> {
+    if task_filter_is_empty(filter) {
+        return Err("批量操作至少需要一个筛选条件".to_string());
+    }
+
+    let mut tx = pool
+        .begin()
         .await
-        .map_err(|e| format!("Failed to fetch focus templates: {}", e))?
-    } else {
-        sqlx::query_as::<_, FocusTemplate>(
-            "SELECT id, name, timer_type, duration_minutes, tags_json, linked_task_title, color_token, is_archived, created_at, updated_at FROM focus_templates WHERE is_archived = 0 ORDER BY updated_at DESC",
-        )
-        .fetch_all(pool)
+        .map_err(|e| format!("Failed to begin bulk task delete tx: {}", e))?;
+
+    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new("DELETE FROM tasks WHERE 1=1");
+    push_task_filter(&mut builder, filter)?;
+    let result = builder
+        .build()
+        .execute(&mut *tx)
         .await
-        .map_err(|e| format!("Failed to fetch active focus templates: {}", e))?
-    };
-    Ok(rows)
+        .map_err(|e| format!("Failed to bulk delete tasks: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit bulk task delete tx: {}", e))?;
+
+    Ok(TaskBulkResult {
+        affected: result.rows_affected() as usize,
+    })
 }
 
-async fn db_create_focus_template(
+async fn db_get_task_plan_templates(
     pool: &sqlx::SqlitePool,
-    template: &FocusTemplate,
-) -> Result<FocusTemplate, String> {
-    sqlx::query(
-        "INSERT INTO focus_templates (id, name, timer_type, duration_minutes, tags_json, linked_task_title, color_token, is_archived, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+) -> Result<Vec<TaskPlanTemplateDetail>, String> {
+    let templates = sqlx::query_as::<_, TaskPlanTemplate>(
+        "SELECT id, name, description, created_at, updated_at FROM task_plan_templates ORDER BY updated_at DESC",
     )
-    .bind(&template.id)
-    .bind(&template.name)
-    .bind(&template.timer_type)
-    .bind(template.duration_minutes)
-    .bind(&template.tags_json)
-    .bind(&template.linked_task_title)
-    .bind(&template.color_token)
-    .bind(template.is_archived)
-    .bind(&template.created_at)
-    .bind(&template.updated_at)
-    .execute(pool)
+    .fetch_all(pool)
     .await
-    .map_err(|e| format!("Failed to create focus template: {}", e))?;
-
-    Ok(template.clone())
-}
+    .map_err(|e| format!("Failed to fetch task plan templates: {}", e))?;
 
-async fn db_update_focus_template(
-    pool: &sqlx::SqlitePool,
-    template: &FocusTemplate,
-) -> Result<FocusTemplate, String> {
-    let result = sqlx::query(
-        "UPDATE focus_templates SET name = ?, timer_type = ?, duration_minutes = ?, tags_json = ?, linked_task_title = ?, color_token = ?, is_archived = ?, updated_at = ? WHERE id = ?",
+    let items = sqlx::query_as::<_, TaskPlanTemplateItem>(
+        "SELECT id, template_id, weekday_offset, title, description, priority, start_time, duration, tags, timer_type, timer_duration, sort_order
+         FROM task_plan_template_items
+         ORDER BY weekday_offset ASC, start_time ASC, sort_order ASC",
     )
-    .bind(&template.name)
-    .bind(&template.timer_type)
-    .bind(template.duration_minutes)
-    .bind(&template.tags_json)
-    .bind(&template.linked_task_title)
-    .bind(&template.color_token)
-    .bind(template.is_archived)
-    .bind(&template.updated_at)
-    .bind(&template.id)
-    .execute(pool)
+    .fetch_all(pool)
     .await
-    .map_err(|e| format!("Failed to update focus template: {}", e))?;
+    .map_err(|e| format!("Failed to fetch task plan template items: {}", e))?;
 
-    if result.rows_affected() == 0 {
-        return Err("Focus template not found".to_string());
+    let mut by_template: HashMap<String, Vec<TaskPlanTemplateItem>> = HashMap::new();
+    for item in items {
+        by_template.entry(item.template_id.clone()).or_default().push(item);
     }
 
-    Ok(template.clone())
+    Ok(templates
+        .into_iter()
+        .map(|template| {
+            let items = by_template.remove(&template.id).unwrap_or_default();
+            TaskPlanTemplateDetail { template, items }
+        })
+        .collect())
 }
 
-async fn db_archive_focus_template(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
-    let result = sqlx::query(
-        "UPDATE focus_templates SET is_archived = 1, updated_at = ? WHERE id = ?",
+async fn db_get_task_plan_template(
+    pool: &sqlx::SqlitePool,
+    id: &str,
+) -> Result<TaskPlanTemplateDetail, String> {
+    let template = sqlx::query_as::<_, TaskPlanTemplate>(
+        "SELECT id, name, description, created_at, updated_at FROM task_plan_templates WHERE id = ?",
     )
-    .bind(now_iso())
     .bind(id)
-    .execute(pool)
+    .fetch_optional(pool)
     .await
-    .map_err(|e| format!("Failed to archive focus template: {}", e))?;
+    .map_err(|e| format!("Failed to fetch task plan template: {}", e))?
+    .ok_or_else(|| "Task plan template not found".to_string())?;
+
+    let items = sqlx::query_as::<_, TaskPlanTemplateItem>(
+        "SELECT id, template_id, weekday_offset, title, description, priority, start_time, duration, tags, timer_type, timer_duration, sort_order
+         FROM task_plan_template_items
+         WHERE template_id = ?
+         ORDER BY weekday_offset ASC, start_time ASC, sort_order ASC",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch task plan template items: {}", e))?;
 
-    if result.rows_affected() == 0 {
-        return Err("Focus template not found".to_string());
+    Ok(TaskPlanTemplateDetail { template, items })
+}
+
+async fn db_replace_task_plan_template_items(
+    conn: &mut sqlx::SqliteConnection,
+    template_id: &str,
+    items: &[TaskPlanTemplateItem],
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM task_plan_template_items WHERE template_id = ?")
+        .bind(template_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to clear task plan template items: {}", e))?;
+
+    for (index, item) in items.iter().enumerate() {
+        if !(0..=6).contains(&item.weekday_offset) {
+            return Err(format!("weekday_offset 必须在 0-6 之间 [index={}]", index));
+        }
+        if item.title.trim().is_empty() {
+            return Err(format!("模板任务标题不能为空 [index={}]", index));
+        }
+
+        sqlx::query(
+            "INSERT INTO task_plan_template_items (id, template_id, weekday_offset, title, description, priority, start_time, duration, tags, timer_type, timer_duration, sort_order)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(gen_unique_id("plan-item"))
+        .bind(template_id)
+        .bind(item.weekday_offset)
+        .bind(item.title.trim())
+        .bind(&item.description)
+        .bind(&item.priority)
+        .bind(&item.start_time)
+        .bind(item.duration)
+        .bind(&item.tags)
+        .bind(&item.timer_type)
+        .bind(item.timer_duration)
+        .bind(index as i32)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to insert task plan template item: {}", e))?;
     }
 
     Ok(())
 }
 
-async fn db_start_focus_run(
+async fn db_save_week_as_task_plan_template(
     pool: &sqlx::SqlitePool,
-    payload: &StartFocusRunPayload,
-) -> Result<FocusRun, String> {
-    if payload.source.trim().is_empty() {
-        return Err("source 不能为空".to_string());
-    }
-    if payload.planned_minutes <= 0 {
-        return Err("planned_minutes 必须大于 0".to_string());
+    name: &str,
+    description: &str,
+    week_start: &str,
+) -> Result<TaskPlanTemplateDetail, String> {
+    if name.trim().is_empty() {
+        return Err("模板名称不能为空".to_string());
     }
-    if payload.timer_type.trim().is_empty() {
-        return Err("timer_type 不能为空".to_string());
+    let (start, end) = parse_week_start_monday(week_start)?;
+    let start_date = chrono::NaiveDate::parse_from_str(&start, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid week_start: {}", e))?;
+
+    let tasks = sqlx::query_as::<_, Task>(
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at
+         FROM tasks
+         WHERE date BETWEEN ? AND ?
+         ORDER BY date ASC, start_time ASC",
+    )
+    .bind(&start)
+    .bind(&end)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch week tasks for template: {}", e))?;
+
+    if tasks.is_empty() {
+        return Err("该周没有任务，无法保存为模板".to_string());
+    }
+
+    let template_id = gen_focus_id("plan-template");
+    let mut items = Vec::<TaskPlanTemplateItem>::new();
+    for task in &tasks {
+        let date = chrono::NaiveDate::parse_from_str(&task.date, "%Y-%m-%d")
+            .map_err(|e| format!("Task {} has invalid date {}: {}", task.id, task.date, e))?;
+        items.push(TaskPlanTemplateItem {
+            id: String::new(),
+            template_id: template_id.clone(),
+            weekday_offset: (date - start_date).num_days() as i32,
+            title: task.title.clone(),
+            description: task.description.clone(),
+            priority: task.priority.clone(),
+            start_time: task.start_time.clone(),
+            duration: task.duration,
+            tags: task.tags.clone(),
+            timer_type: task.timer_type.clone(),
+            timer_duration: task.timer_duration,
+            sort_order: 0,
+        });
     }
 
     let now = now_iso();
-    let run = FocusRun {
-        id: gen_focus_id("focus-run"),
-        source: payload.source.trim().to_string(),
-        template_id: payload.template_id.clone(),
-        task_id: payload.task_id.clone(),
-        timer_type: payload.timer_type.trim().to_string(),
-        planned_minutes: payload.planned_minutes,
-        actual_seconds: 0,
-        status: "running".to_string(),
-        started_at: now.clone(),
-        ended_at: None,
-        date: payload
-            .date
-            .clone()
-            .unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string()),
-        tags_json: payload
-            .tags_json
-            .clone()
-            .unwrap_or_else(|| "[]".to_string()),
-        note: payload.note.clone(),
-        created_at: now.clone(),
-        updated_at: now,
-    };
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin task plan template tx: {}", e))?;
 
     sqlx::query(
-        "INSERT INTO focus_runs (id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        "INSERT INTO task_plan_templates (id, name, description, created_at, updated_at) VALUES (?, ?, ?, ?, ?)",
     )
-    .bind(&run.id)
-    .bind(&run.source)
-    .bind(&run.template_id)
-    .bind(&run.task_id)
-    .bind(&run.timer_type)
-    .bind(run.planned_minutes)
-    .bind(run.actual_seconds)
-    .bind(&run.status)
-    .bind(&run.started_at)
-    .bind(&run.ended_at)
-    .bind(&run.date)
-    .bind(&run.tags_json)
-    .bind(&run.note)
-    .bind(&run.created_at)
-    .bind(&run.updated_at)
-    .execute(pool)
+    .bind(&template_id)
+    .bind(name.trim())
+    .bind(description)
+    .bind(&now)
+    .bind(&now)
+    .execute(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to start focus run: {}", e))?;
+    .map_err(|e| format!("Failed to create task plan template: {}", e))?;
 
-    Ok(run)
+    db_replace_task_plan_template_items(&mut tx, &template_id, &items).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit task plan template tx: {}", e))?;
+
+    db_get_task_plan_template(pool, &template_id).await
 }
 
-async fn db_finish_focus_run(
+async fn db_update_task_plan_template(
     pool: &sqlx::SqlitePool,
-    run_id: &str,
-    payload: &FinishFocusRunPayload,
-) -> Result<FocusRun, String> {
-    let next_status = payload.status.trim().to_lowercase();
-    if next_status != "completed" && next_status != "aborted" {
-        return Err("status 仅支持 completed 或 aborted".to_string());
+    id: &str,
+    name: &str,
+    description: &str,
+    items: &[TaskPlanTemplateItem],
+) -> Result<TaskPlanTemplateDetail, String> {
+    if name.trim().is_empty() {
+        return Err("模板名称不能为空".to_string());
     }
 
-    let ended_at = payload.ended_at.clone().unwrap_or_else(now_iso);
-    let updated_at = now_iso();
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin task plan template tx: {}", e))?;
 
     let result = sqlx::query(
-        "UPDATE focus_runs SET actual_seconds = ?, status = ?, ended_at = ?, tags_json = COALESCE(?, tags_json), note = COALESCE(?, note), updated_at = ? WHERE id = ?",
+        "UPDATE task_plan_templates SET name = ?, description = ?, updated_at = ? WHERE id = ?",
     )
-    .bind(payload.actual_seconds.max(0))
-    .bind(&next_status)
-    .bind(&ended_at)
-    .bind(&payload.tags_json)
-    .bind(&payload.note)
-    .bind(&updated_at)
-    .bind(run_id)
-    .execute(pool)
+    .bind(name.trim())
+    .bind(description)
+    .bind(now_iso())
+    .bind(id)
+    .execute(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to finish focus run: {}", e))?;
+    .map_err(|e| format!("Failed to update task plan template: {}", e))?;
 
     if result.rows_affected() == 0 {
-        return Err("Focus run not found".to_string());
+        return Err("Task plan template not found".to_string());
     }
 
-    let run = sqlx::query_as::<_, FocusRun>(
-        "SELECT id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, created_at, updated_at FROM focus_runs WHERE id = ?",
-    )
-    .bind(run_id)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to fetch finished focus run: {}", e))?;
+    db_replace_task_plan_template_items(&mut tx, id, items).await?;
 
-    Ok(run)
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit task plan template tx: {}", e))?;
+
+    db_get_task_plan_template(pool, id).await
 }
 
-async fn db_get_focus_runs(
-    pool: &sqlx::SqlitePool,
-    start_date: Option<&str>,
-    end_date: Option<&str>,
-    status: Option<&str>,
-) -> Result<Vec<FocusRun>, String> {
-    let (start, end) = resolve_focus_range(start_date, end_date)?;
-    let rows = if let Some(status_value) = status {
-        sqlx::query_as::<_, FocusRun>(
-            "SELECT id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, created_at, updated_at FROM focus_runs WHERE date BETWEEN ? AND ? AND status = ? ORDER BY started_at DESC",
-        )
-        .bind(&start)
-        .bind(&end)
-        .bind(status_value)
-        .fetch_all(pool)
-        .await
-        .map_err(|e| format!("Failed to fetch focus runs by status: {}", e))?
-    } else {
-        sqlx::query_as::<_, FocusRun>(
-            "SELECT id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, created_at, updated_at FROM focus_runs WHERE date BETWEEN ? AND ? ORDER BY started_at DESC",
-        )
-        .bind(&start)
-        .bind(&end)
-        .fetch_all(pool)
+async fn db_delete_task_plan_template(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
+    let result = sqlx::query("DELETE FROM task_plan_templates WHERE id = ?")
+        .bind(id)
+        .execute(pool)
         .await
-        .map_err(|e| format!("Failed to fetch focus runs: {}", e))?
-    };
-    Ok(rows)
+        .map_err(|e| format!("Failed to delete task plan template: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err("Task plan template not found".to_string());
+    }
+
+    Ok(())
 }
 
-async fn db_get_focus_stats(
+fn normalize_plan_conflict_strategy(raw: Option<&str>) -> Result<String, String> {
+    match raw.unwrap_or("skip").trim().to_lowercase().as_str() {
+        "" | "skip" => Ok("skip".to_string()),
+        "replace" => Ok("replace".to_string()),
+        "keep_both" => Ok("keep_both".to_string()),
+        _ => Err("conflict_strategy 仅支持 skip / replace / keep_both".to_string()),
+    }
+}
+
+/// A template task conflicts with an existing task on the same date with the same title.
+async fn db_apply_task_plan_template(
     pool: &sqlx::SqlitePool,
-    start_date: Option<&str>,
-    end_date: Option<&str>,
-    dimension: Option<&str>,
-) -> Result<FocusStatsResult, String> {
-    let (start, end) = resolve_focus_range(start_date, end_date)?;
-    let dim = normalize_focus_dimension(dimension);
+    template_id: &str,
+    week_start: &str,
+    conflict_strategy: Option<&str>,
+) -> Result<ApplyTaskPlanReport, String> {
+    let strategy = normalize_plan_conflict_strategy(conflict_strategy)?;
+    let (start, end) = parse_week_start_monday(week_start)?;
+    let start_date = chrono::NaiveDate::parse_from_str(&start, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid week_start: {}", e))?;
+    let detail = db_get_task_plan_template(pool, template_id).await?;
 
-    let summary_row = sqlx::query_as::<_, FocusSummaryRow>(
-        "SELECT
-            COALESCE(SUM(CASE WHEN status = 'completed' AND actual_seconds >= 60 THEN actual_seconds ELSE 0 END), 0) AS total_seconds,
-            COALESCE(SUM(CASE WHEN status = 'completed' AND actual_seconds >= 60 THEN 1 ELSE 0 END), 0) AS completed_runs,
-            COUNT(*) AS all_runs
-         FROM focus_runs
-         WHERE date BETWEEN ? AND ?",
+    let existing = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT id, date, title FROM tasks WHERE date BETWEEN ? AND ?",
     )
     .bind(&start)
     .bind(&end)
-    .fetch_one(pool)
+    .fetch_all(pool)
     .await
-    .map_err(|e| format!("Failed to aggregate focus summary: {}", e))?;
+    .map_err(|e| format!("Failed to fetch existing week tasks: {}", e))?;
 
-    let total_focus_minutes = summary_row.total_seconds / 60;
-    let completion_rate = if summary_row.all_runs > 0 {
-        (summary_row.completed_runs as f64 / summary_row.all_runs as f64) * 100.0
-    } else {
-        0.0
-    };
-
-    let summary = FocusStatsSummary {
-        total_focus_minutes,
-        completed_runs: summary_row.completed_runs,
-        completion_rate,
-    };
+    let mut conflicts: HashMap<(String, String), Vec<String>> = HashMap::new();
+    for (id, date, title) in existing {
+        conflicts
+            .entry((date, title.trim().to_lowercase()))
+            .or_default()
+            .push(id);
+    }
 
-    let slices = if dim == "template" {
-        let rows = sqlx::query_as::<_, FocusGroupedRow>(
-            "SELECT
-                COALESCE(NULLIF(ft.name, ''), '未命名模板') AS key_name,
-                COALESCE(SUM(fr.actual_seconds), 0) AS total_seconds,
-                COUNT(*) AS run_count
-             FROM focus_runs fr
-             LEFT JOIN focus_templates ft ON fr.template_id = ft.id
-             WHERE fr.date BETWEEN ? AND ?
-               AND fr.status = 'completed'
-               AND fr.actual_seconds >= 60
-             GROUP BY COALESCE(NULLIF(ft.name, ''), '未命名模板')
-             ORDER BY total_seconds DESC",
-        )
-        .bind(&start)
-        .bind(&end)
-        .fetch_all(pool)
+    let mut tx = pool
+        .begin()
         .await
-        .map_err(|e| format!("Failed to aggregate focus template slices: {}", e))?;
+        .map_err(|e| format!("Failed to begin apply template tx: {}", e))?;
 
-        rows.into_iter()
-            .map(|row| {
-                let minutes = row.total_seconds / 60;
-                let percent = if total_focus_minutes > 0 {
-                    (minutes as f64 / total_focus_minutes as f64) * 100.0
-                } else {
-                    0.0
-                };
-                FocusStatsSlice {
-                    key: row.key_name,
-                    minutes,
-                    percent,
-                    runs: row.run_count,
+    let now = now_iso();
+    let mut created = Vec::<Task>::new();
+    let mut skipped = 0usize;
+    let mut replaced = 0usize;
+
+    for item in &detail.items {
+        let date = (start_date + chrono::Duration::days(i64::from(item.weekday_offset)))
+            .format("%Y-%m-%d")
+            .to_string();
+        let key = (date.clone(), item.title.trim().to_lowercase());
+
+        if let Some(ids) = conflicts.get(&key) {
+            match strategy.as_str() {
+                "skip" => {
+                    skipped += 1;
+                    continue;
                 }
-            })
-            .collect::<Vec<FocusStatsSlice>>()
-    } else if dim == "timer_type" {
-        let rows = sqlx::query_as::<_, FocusGroupedRow>(
-            "SELECT
-                COALESCE(NULLIF(timer_type, ''), 'unknown') AS key_name,
-                COALESCE(SUM(actual_seconds), 0) AS total_seconds,
-                COUNT(*) AS run_count
-             FROM focus_runs
-             WHERE date BETWEEN ? AND ?
-               AND status = 'completed'
-               AND actual_seconds >= 60
-             GROUP BY COALESCE(NULLIF(timer_type, ''), 'unknown')
-             ORDER BY total_seconds DESC",
+                "replace" => {
+                    for id in ids {
+                        sqlx::query("DELETE FROM tasks WHERE id = ?")
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await
+                            .map_err(|e| format!("Failed to replace conflicting task: {}", e))?;
+                    }
+                    replaced += ids.len();
+                    conflicts.remove(&key);
+                }
+                _ => {}
+            }
+        }
+
+        let task = Task {
+            id: gen_unique_id("task"),
+            title: item.title.clone(),
+            description: item.description.clone(),
+            status: "todo".to_string(),
+            priority: item.priority.clone(),
+            date,
+            start_time: item.start_time.clone(),
+            duration: item.duration,
+            tags: item.tags.clone(),
+            repeat_type: "none".to_string(),
+            timer_type: item.timer_type.clone(),
+            timer_duration: item.timer_duration,
+            position: 0,
+            created_at: now.clone(),
+            updated_at: now.clone(),
+        };
+
+        sqlx::query(
+            "INSERT INTO tasks (id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT COALESCE(MAX(position), -1) + 1 FROM tasks WHERE date = ? AND status = ?), ?, ?)",
         )
-        .bind(&start)
-        .bind(&end)
-        .fetch_all(pool)
+        .bind(&task.id)
+        .bind(&task.title)
+        .bind(&task.description)
+        .bind(&task.status)
+        .bind(&task.priority)
+        .bind(&task.date)
+        .bind(&task.start_time)
+        .bind(task.duration)
+        .bind(&task.tags)
+        .bind(&task.repeat_type)
+        .bind(&task.timer_type)
+        .bind(task.timer_duration)
+        .bind(&task.date)
+        .bind(&task.status)
+        .bind(&task.created_at)
+        .bind(&task.updated_at)
+        .execute(&mut *tx)
         .await
-        .map_err(|e| format!("Failed to aggregate focus timer_type slices: {}", e))?;
+        .map_err(|e| format!("Failed to create task from template: {}", e))?;
 
-        rows.into_iter()
-            .map(|row| {
-                let minutes = row.total_seconds / 60;
-                let percent = if total_focus_minutes > 0 {
-                    (minutes as f64 / total_focus_minutes as f64) * 100.0
-                } else {
-                    0.0
-                };
-                FocusStatsSlice {
-                    key: row.key_name,
-                    minutes,
-                    percent,
-                    runs: row.run_count,
-                }
-            })
-            .collect::<Vec<FocusStatsSlice>>()
-    } else {
-        let rows = sqlx::query_as::<_, FocusTagRow>(
-            "SELECT tags_json, actual_seconds
-             FROM focus_runs
-             WHERE date BETWEEN ? AND ?
-               AND status = 'completed'
-               AND actual_seconds >= 60",
-        )
-        .bind(&start)
-        .bind(&end)
-        .fetch_all(pool)
-        .await
-        .map_err(|e| format!("Failed to fetch focus run tags for aggregation: {}", e))?;
-
-        let mut tag_map: HashMap<String, (i64, i64)> = HashMap::new();
-        for row in rows {
-            let minutes = row.actual_seconds / 60;
-            if minutes <= 0 {
-                continue;
-            }
-            let tags = parse_focus_tags(&row.tags_json);
-            for tag in tags {
-                let entry = tag_map.entry(tag).or_insert((0, 0));
-                entry.0 += minutes;
-                entry.1 += 1;
-            }
-        }
-
-        let mut slices_vec = tag_map
-            .into_iter()
-            .map(|(key, (minutes, runs))| {
-                let percent = if total_focus_minutes > 0 {
-                    (minutes as f64 / total_focus_minutes as f64) * 100.0
-                } else {
-                    0.0
-                };
-                FocusStatsSlice {
-                    key,
-                    minutes,
-                    percent,
-                    runs,
-                }
-            })
-            .collect::<Vec<FocusStatsSlice>>();
-        slices_vec.sort_by(|a, b| b.minutes.cmp(&a.minutes));
-        slices_vec
-    };
+        created.push(task);
+    }
 
-    Ok(FocusStatsResult {
-        start_date: start,
-        end_date: end,
-        dimension: dim,
-        summary,
-        slices,
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit apply template tx: {}", e))?;
+
+    Ok(ApplyTaskPlanReport {
+        week_start: start,
+        week_end: end,
+        created: created.len(),
+        skipped,
+        replaced,
+        tasks: created,
     })
 }
 
-fn make_title_snapshot(content: &str, ai_solution: &str) -> String {
-    let summary_line = ai_solution
-        .lines()
-        .map(|line| line.trim())
-        .find(|line| {
-            !line.is_empty()
-                && (line.starts_with("题型总结:")
-                    || line.starts_with("题型总结：")
-                    || line.starts_with("题型:")
-                    || line.starts_with("题型：")
-                    || line.starts_with("知识点:")
-                    || line.starts_with("知识点："))
-        })
-        .map(|line| {
-            line.trim_start_matches("题型总结:")
-                .trim_start_matches("题型总结：")
-                .trim_start_matches("题型:")
-                .trim_start_matches("题型：")
-                .trim_start_matches("知识点:")
-                .trim_start_matches("知识点：")
-                .trim()
-                .to_string()
-        })
-        .filter(|line| !line.is_empty());
+async fn db_create_task(pool: &sqlx::SqlitePool, task: &Task) -> Result<Task, String> {
+    sqlx::query(
+        "INSERT INTO tasks (id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT COALESCE(MAX(position), -1) + 1 FROM tasks WHERE date = ? AND status = ?), ?, ?)",
+    )
+    .bind(&task.id)
+    .bind(&task.title)
+    .bind(&task.description)
+    .bind(&task.status)
+    .bind(&task.priority)
+    .bind(&task.date)
+    .bind(&task.start_time)
+    .bind(&task.duration)
+    .bind(&task.tags)
+    .bind(&task.repeat_type)
+    .bind(&task.timer_type)
+    .bind(&task.timer_duration)
+    .bind(&task.date)
+    .bind(&task.status)
+    .bind(&task.created_at)
+    .bind(&task.updated_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create task: {}", e))?;
 
-    let first_line = summary_line.unwrap_or_else(|| {
-        content
-            .lines()
-            .find(|line| !line.trim().is_empty())
-            .unwrap_or("未命名错题")
-            .trim()
-            .to_string()
-    });
+    let mut created = task.clone();
+    created.position = sqlx::query_scalar::<_, i64>("SELECT position FROM tasks WHERE id = ?")
+        .bind(&task.id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to read task position: {}", e))?;
+    Ok(created)
+}
 
-    let mut title = first_line
-        .replace("[公式]", " ")
-        .replace("\\(", " ")
-        .replace("\\)", " ")
-        .replace("\\[", " ")
-        .replace("\\]", " ")
-        .replace("$$", " ")
-        .replace('$', " ");
+/// Saves the task's fields and returns the stored row. `position` from the client is ignored;
+/// a task whose status or date changes goes to the end of its new column and both columns are
+/// renumbered in the same transaction, as `db_move_task` does.
+async fn db_update_task(pool: &sqlx::SqlitePool, task: &Task) -> Result<Task, String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin update task tx: {}", e))?;
 
-    if let Some(idx) = title.find("选项") {
-        title = title[..idx].trim().to_string();
-    }
+    let existing = db_get_task_by_id(&mut tx, &task.id)
+        .await?
+        .ok_or_else(|| format!("Task not found: {}", task.id))?;
+    let moved = existing.status != task.status || existing.date != task.date;
 
-    title = title
-        .split_whitespace()
-        .collect::<Vec<_>>()
-        .join(" ");
+    sqlx::query(
+        "UPDATE tasks SET title=?, description=?, status=?, priority=?, date=?, start_time=?, duration=?, tags=?, repeat_type=?, timer_type=?, timer_duration=?, updated_at=? WHERE id=?",
+    )
+    .bind(&task.title)
+    .bind(&task.description)
+    .bind(&task.status)
+    .bind(&task.priority)
+    .bind(&task.date)
+    .bind(&task.start_time)
+    .bind(&task.duration)
+    .bind(&task.tags)
+    .bind(&task.repeat_type)
+    .bind(&task.timer_type)
+    .bind(&task.timer_duration)
+    .bind(&task.updated_at)
+    .bind(&task.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to update task: {}", e))?;
 
-    if title.chars().count() > 50 {
-        title = title.chars().take(50).collect::<String>();
+    if moved {
+        let mut source = db_load_task_column_ids(&mut tx, &existing.date, &existing.status).await?;
+        source.retain(|tid| tid != &task.id);
+        db_write_task_column_positions(&mut tx, &source).await?;
+        let mut target = db_load_task_column_ids(&mut tx, &task.date, &task.status).await?;
+        target.retain(|tid| tid != &task.id);
+        target.push(task.id.clone());
+        db_write_task_column_positions(&mut tx, &target).await?;
     }
 
-    if title.is_empty() {
-        title = "未命名错题".to_string();
-    }
-    title
+    let updated = db_get_task_by_id(&mut tx, &task.id)
+        .await?
+        .ok_or_else(|| format!("Task not found: {}", task.id))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit update task tx: {}", e))?;
+    Ok(updated)
 }
 
-async fn db_get_wrong_questions(
-    pool: &sqlx::SqlitePool,
-    filter: &WrongQuestionFilter,
-) -> Result<Vec<WrongQuestion>, String> {
-    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
-        "SELECT id, subject, tags_json, question_content, question_image_path, ai_solution, user_note, source, ai_session_id, ai_message_ids_json, difficulty, mastery_level, review_count, next_review_date, last_review_date, ease_factor, interval_days, is_archived, created_at, updated_at FROM wrong_questions WHERE 1=1",
-    );
+async fn db_delete_task(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM tasks WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete task: {}", e))?;
+    Ok(())
+}
 
-    builder.push(" AND is_archived = ");
-    builder.push_bind(filter.is_archived.unwrap_or(0));
+async fn db_batch_create_tasks(pool: &sqlx::SqlitePool, tasks: &[Task]) -> Result<usize, String> {
+    let mut count = 0;
+    for task in tasks {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO tasks (id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT COALESCE(MAX(position), -1) + 1 FROM tasks WHERE date = ? AND status = ?), ?, ?)",
+        )
+        .bind(&task.id)
+        .bind(&task.title)
+        .bind(&task.description)
+        .bind(&task.status)
+        .bind(&task.priority)
+        .bind(&task.date)
+        .bind(&task.start_time)
+        .bind(&task.duration)
+        .bind(&task.tags)
+        .bind(&task.repeat_type)
+        .bind(&task.timer_type)
+        .bind(&task.timer_duration)
+        .bind(&task.date)
+        .bind(&task.status)
+        .bind(&task.created_at)
+        .bind(&task.updated_at)
+        .execute(pool)
+        .await;
 
-    if let Some(subject) = filter.subject.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
-        builder.push(" AND subject = ");
-        builder.push_bind(subject);
+        if result.is_ok() {
+            count += 1;
+        }
     }
+    Ok(count)
+}
 
-    if let Some(level) = filter.mastery_level {
-        builder.push(" AND mastery_level = ");
-        builder.push_bind(level);
+fn normalize_task_status_key(raw: &str) -> Result<String, String> {
+    let key = raw.trim().to_lowercase();
+    if key.is_empty() {
+        return Err("状态标识不能为空".to_string());
     }
+    if key.contains(',') || key.chars().any(|c| c.is_whitespace()) {
+        return Err("状态标识不能包含空格或逗号".to_string());
+    }
+    Ok(key)
+}
 
-    if let Some(keyword) = filter.search_keyword.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
-        let like = format!("%{}%", keyword);
-        builder.push(" AND (question_content LIKE ");
-        builder.push_bind(like.clone());
-        builder.push(" OR ai_solution LIKE ");
-        builder.push_bind(like.clone());
-        builder.push(" OR COALESCE(user_note,'') LIKE ");
-        builder.push_bind(like);
-        builder.push(")");
+async fn db_get_task_statuses(pool: &sqlx::SqlitePool) -> Result<Vec<TaskStatusColumn>, String> {
+    sqlx::query_as::<_, TaskStatusColumn>(
+        "SELECT key, label, color, is_done, sort_order, created_at, updated_at FROM task_statuses ORDER BY sort_order ASC, created_at ASC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch task statuses: {}", e))
+}
+
+async fn db_get_task_status(
+    conn: &mut sqlx::SqliteConnection,
+    key: &str,
+) -> Result<Option<TaskStatusColumn>, String> {
+    sqlx::query_as::<_, TaskStatusColumn>(
+        "SELECT key, label, color, is_done, sort_order, created_at, updated_at FROM task_statuses WHERE key = ?",
+    )
+    .bind(key)
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| format!("Failed to fetch task status: {}", e))
+}
+
+async fn db_save_task_status(
+    pool: &sqlx::SqlitePool,
+    payload: &SaveTaskStatusPayload,
+) -> Result<TaskStatusColumn, String> {
+    let key = normalize_task_status_key(&payload.key)?;
+    let label = payload.label.trim();
+    if label.is_empty() {
+        return Err("状态名称不能为空".to_string());
     }
+    let now = now_iso();
 
-    builder.push(" ORDER BY created_at DESC");
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin task status tx: {}", e))?;
+
+    match db_get_task_status(&mut tx, &key).await? {
+        Some(prev) => {
+            let is_done = payload.is_done.unwrap_or(prev.is_done);
+            if prev.is_done && !is_done {
+                let other_done = sqlx::query_scalar::<_, i64>(
+                    "SELECT COUNT(*) FROM task_statuses WHERE is_done = 1 AND key != ?",
+                )
+                .bind(&key)
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to count done statuses: {}", e))?;
+                if other_done == 0 {
+                    return Err("至少需要保留一个完成状态".to_string());
+                }
+            }
+            sqlx::query("UPDATE task_statuses SET label = ?, color = ?, is_done = ?, updated_at = ? WHERE key = ?")
+                .bind(label)
+                .bind(payload.color.as_deref().map(|c| c.trim()).unwrap_or(&prev.color))
+                .bind(is_done)
+                .bind(&now)
+                .bind(&key)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to update task status: {}", e))?;
+        }
+        None => {
+            sqlx::query(
+                "INSERT INTO task_statuses (key, label, color, is_done, sort_order, created_at, updated_at)
+                 VALUES (?, ?, ?, ?, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM task_statuses), ?, ?)",
+            )
+            .bind(&key)
+            .bind(label)
+            .bind(payload.color.as_deref().map(|c| c.trim()).unwrap_or(""))
+            .bind(payload.is_done.unwrap_or(false))
+            .bind(&now)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to create task status: {}", e))?;
+        }
+    }
 
-    builder
-        .build_query_as::<WrongQuestion>()
-        .fetch_all(pool)
+    let saved = db_get_task_status(&mut tx, &key)
+        .await?
+        .ok_or_else(|| format!("Task status not found: {}", key))?;
+    tx.commit()
         .await
-        .map_err(|e| format!("Failed to fetch wrong questions: {}", e))
+        .map_err(|e| format!("Failed to commit task status tx: {}", e))?;
+    Ok(saved)
 }
 
-async fn db_create_wrong_question(
-    pool: &sqlx::SqlitePool,
-    input: &WrongQuestion,
-) -> Result<WrongQuestion, String> {
-    sqlx::query(
-        "INSERT INTO wrong_questions (id, subject, tags_json, question_content, question_image_path, ai_solution, user_note, source, ai_session_id, ai_message_ids_json, difficulty, mastery_level, review_count, next_review_date, last_review_date, ease_factor, interval_days, is_archived, created_at, updated_at)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+async fn db_load_task_column_ids(
+    conn: &mut sqlx::SqliteConnection,
+    date: &str,
+    status: &str,
+) -> Result<Vec<String>, String> {
+    sqlx::query_scalar::<_, String>(
+        "SELECT id FROM tasks WHERE date = ? AND status = ? ORDER BY position ASC, start_time ASC, id ASC",
     )
-    .bind(&input.id)
-    .bind(&input.subject)
-    .bind(&input.tags_json)
-    .bind(&input.question_content)
-    .bind(&input.question_image_path)
-    .bind(&input.ai_solution)
-    .bind(&input.user_note)
-    .bind(&input.source)
-    .bind(&input.ai_session_id)
-    .bind(&input.ai_message_ids_json)
-    .bind(input.difficulty)
-    .bind(input.mastery_level)
-    .bind(input.review_count)
-    .bind(&input.next_review_date)
-    .bind(&input.last_review_date)
-    .bind(input.ease_factor)
-    .bind(input.interval_days)
-    .bind(input.is_archived)
-    .bind(&input.created_at)
-    .bind(&input.updated_at)
-    .execute(pool)
+    .bind(date)
+    .bind(status)
+    .fetch_all(conn)
     .await
-    .map_err(|e| format!("Failed to create wrong question: {}", e))?;
+    .map_err(|e| format!("Failed to load task column: {}", e))
+}
 
-    Ok(input.clone())
+async fn db_write_task_column_positions(
+    conn: &mut sqlx::SqliteConnection,
+    ids: &[String],
+) -> Result<(), String> {
+    for (idx, id) in ids.iter().enumerate() {
+        sqlx::query("UPDATE tasks SET position = ? WHERE id = ?")
+            .bind(idx as i64)
+            .bind(id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| format!("Failed to renumber task positions: {}", e))?;
+    }
+    Ok(())
 }
 
-async fn db_update_wrong_question(
+/// Deletes a status column; tasks still in it are appended to `fallback` (required when non-empty).
+async fn db_delete_task_status(
     pool: &sqlx::SqlitePool,
-    input: &WrongQuestion,
-) -> Result<WrongQuestion, String> {
-    let result = sqlx::query(
-        "UPDATE wrong_questions SET
-           subject = ?,
-           tags_json = ?,
-           question_content = ?,
-           question_image_path = ?,
-           ai_solution = ?,
-           user_note = ?,
-           source = ?,
-           ai_session_id = ?,
-           ai_message_ids_json = ?,
-           difficulty = ?,
-           mastery_level = ?,
-           review_count = ?,
-           next_review_date = ?,
-           last_review_date = ?,
-           ease_factor = ?,
-           interval_days = ?,
-           is_archived = ?,
-           updated_at = ?
-         WHERE id = ?",
+    key: &str,
+    fallback: Option<&str>,
+) -> Result<usize, String> {
+    let key = normalize_task_status_key(key)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin delete task status tx: {}", e))?;
+
+    let target = db_get_task_status(&mut tx, &key)
+        .await?
+        .ok_or_else(|| format!("Task status not found: {}", key))?;
+
+    let (remaining, remaining_done) = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT COUNT(*), COALESCE(SUM(CASE WHEN is_done = 1 THEN 1 ELSE 0 END), 0) FROM task_statuses WHERE key != ?",
     )
-    .bind(&input.subject)
-    .bind(&input.tags_json)
-    .bind(&input.question_content)
-    .bind(&input.question_image_path)
-    .bind(&input.ai_solution)
-    .bind(&input.user_note)
-    .bind(&input.source)
-    .bind(&input.ai_session_id)
-    .bind(&input.ai_message_ids_json)
-    .bind(input.difficulty)
-    .bind(input.mastery_level)
-    .bind(input.review_count)
-    .bind(&input.next_review_date)
-    .bind(&input.last_review_date)
-    .bind(input.ease_factor)
-    .bind(input.interval_days)
-    .bind(input.is_archived)
-    .bind(&input.updated_at)
-    .bind(&input.id)
-    .execute(pool)
+    .bind(&key)
+    .fetch_one(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to update wrong question: {}", e))?;
+    .map_err(|e| format!("Failed to count task statuses: {}", e))?;
+    if remaining == 0 {
+        return Err("至少需要保留一个任务状态".to_string());
+    }
+    if target.is_done && remaining_done == 0 {
+        return Err("至少需要保留一个完成状态".to_string());
+    }
 
-    if result.rows_affected() == 0 {
-        return Err("Wrong question not found".to_string());
+    let dates = sqlx::query_scalar::<_, String>(
+        "SELECT DISTINCT date FROM tasks WHERE status = ?",
+    )
+    .bind(&key)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to inspect tasks of status: {}", e))?;
+
+    let mut moved = 0usize;
+    if !dates.is_empty() {
+        let fallback = normalize_task_status_key(
+            fallback.ok_or_else(|| "该状态下仍有任务，请指定迁移目标状态".to_string())?,
+        )?;
+        if fallback == key {
+            return Err("迁移目标状态不能是被删除的状态".to_string());
+        }
+        if db_get_task_status(&mut tx, &fallback).await?.is_none() {
+            return Err(format!("Task status not found: {}", fallback));
+        }
+
+        for date in &dates {
+            let mut ids = db_load_task_column_ids(&mut tx, date, &fallback).await?;
+            let incoming = db_load_task_column_ids(&mut tx, date, &key).await?;
+            moved += incoming.len();
+            ids.extend(incoming);
+            sqlx::query("UPDATE tasks SET status = ?, updated_at = ? WHERE date = ? AND status = ?")
+                .bind(&fallback)
+                .bind(now_iso())
+                .bind(date)
+                .bind(&key)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to move tasks to fallback status: {}", e))?;
+            db_write_task_column_positions(&mut tx, &ids).await?;
+        }
     }
 
-    Ok(input.clone())
+    sqlx::query("DELETE FROM task_statuses WHERE key = ?")
+        .bind(&key)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to delete task status: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit delete task status tx: {}", e))?;
+    Ok(moved)
 }
 
-async fn db_archive_wrong_question(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
-    let result = sqlx::query(
-        "UPDATE wrong_questions SET is_archived = 1, updated_at = ? WHERE id = ?",
+async fn db_reorder_task_statuses(
+    pool: &sqlx::SqlitePool,
+    keys: &[String],
+) -> Result<Vec<TaskStatusColumn>, String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin reorder task statuses tx: {}", e))?;
+
+    let existing = sqlx::query_scalar::<_, String>(
+        "SELECT key FROM task_statuses ORDER BY sort_order ASC, created_at ASC",
     )
-    .bind(now_iso())
-    .bind(id)
-    .execute(pool)
+    .fetch_all(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to archive wrong question: {}", e))?;
+    .map_err(|e| format!("Failed to fetch task statuses: {}", e))?;
 
-    if result.rows_affected() == 0 {
-        return Err("Wrong question not found".to_string());
+    let mut ordered: Vec<String> = Vec::new();
+    for raw in keys {
+        let key = normalize_task_status_key(raw)?;
+        if !existing.contains(&key) {
+            return Err(format!("Task status not found: {}", key));
+        }
+        if !ordered.contains(&key) {
+            ordered.push(key);
+        }
+    }
+    for key in existing {
+        if !ordered.contains(&key) {
+            ordered.push(key);
+        }
     }
 
-    sqlx::query("DELETE FROM weekly_review_items WHERE wrong_question_id = ?")
-        .bind(id)
-        .execute(pool)
+    for (idx, key) in ordered.iter().enumerate() {
+        sqlx::query("UPDATE task_statuses SET sort_order = ? WHERE key = ?")
+            .bind(idx as i64)
+            .bind(key)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to reorder task statuses: {}", e))?;
+    }
+
+    tx.commit()
         .await
-        .map_err(|e| format!("Failed to cleanup weekly review items on archive: {}", e))?;
+        .map_err(|e| format!("Failed to commit reorder task statuses tx: {}", e))?;
+    db_get_task_statuses(pool).await
+}
 
-    Ok(())
+async fn db_get_task_by_id(
+    conn: &mut sqlx::SqliteConnection,
+    id: &str,
+) -> Result<Option<Task>, String> {
+    sqlx::query_as::<_, Task>(
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at FROM tasks WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| format!("Failed to fetch task: {}", e))
 }
 
-async fn db_delete_wrong_question(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
-    let result = sqlx::query("DELETE FROM wrong_questions WHERE id = ?")
-        .bind(id)
-        .execute(pool)
+/// Moves a task into `status`/`date` at `index`, renumbering source and target columns in one transaction.
+async fn db_move_task(
+    pool: &sqlx::SqlitePool,
+    id: &str,
+    payload: &MoveTaskPayload,
+) -> Result<Task, String> {
+    let status = normalize_task_status_key(&payload.status)?;
+    let target_date = parse_filter_date("date", payload.date.as_deref())?;
+
+    let mut tx = pool
+        .begin()
         .await
-        .map_err(|e| format!("Failed to delete wrong question: {}", e))?;
+        .map_err(|e| format!("Failed to begin move task tx: {}", e))?;
 
-    if result.rows_affected() == 0 {
-        return Err("Wrong question not found".to_string());
+    if db_get_task_status(&mut tx, &status).await?.is_none() {
+        return Err(format!("未知的任务状态: {}", status));
+    }
+    let task = db_get_task_by_id(&mut tx, id)
+        .await?
+        .ok_or_else(|| format!("Task not found: {}", id))?;
+    let date = target_date.unwrap_or_else(|| task.date.clone());
+    let same_column = date == task.date && status == task.status;
+
+    let mut source = db_load_task_column_ids(&mut tx, &task.date, &task.status).await?;
+    source.retain(|tid| tid != id);
+    let mut target = if same_column {
+        source.clone()
+    } else {
+        let mut ids = db_load_task_column_ids(&mut tx, &date, &status).await?;
+        ids.retain(|tid| tid != id);
+        ids
+    };
+    let index = payload
+        .index
+        .unwrap_or(target.len() as i64)
+        .clamp(0, target.len() as i64) as usize;
+    target.insert(index, id.to_string());
+
+    sqlx::query("UPDATE tasks SET status = ?, date = ?, updated_at = ? WHERE id = ?")
+        .bind(&status)
+        .bind(&date)
+        .bind(now_iso())
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to move task: {}", e))?;
+    if !same_column {
+        db_write_task_column_positions(&mut tx, &source).await?;
     }
+    db_write_task_column_positions(&mut tx, &target).await?;
 
-    Ok(())
+    let moved = db_get_task_by_id(&mut tx, id)
+        .await?
+        .ok_or_else(|| format!("Task not found: {}", id))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit move task tx: {}", e))?;
+    Ok(moved)
 }
 
-async fn db_create_weekly_review_item_if_absent(
+/// Reorders one board column; ids not listed keep their relative order after the listed ones.
+async fn db_reorder_tasks(
     pool: &sqlx::SqlitePool,
-    week_start: &str,
-    week_end: &str,
-    wrong_question_id: &str,
-    title_snapshot: &str,
-    carried_from_week: Option<&str>,
-) -> Result<(), String> {
-    let existing = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM weekly_review_items WHERE week_start = ? AND wrong_question_id = ?",
-    )
-    .bind(week_start)
-    .bind(wrong_question_id)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to check weekly review item duplication: {}", e))?;
+    body: &ReorderTasksBody,
+) -> Result<Vec<Task>, String> {
+    let date = parse_filter_date("date", Some(&body.date))?
+        .ok_or_else(|| "date 不能为空".to_string())?;
+    let status = normalize_task_status_key(&body.status)?;
 
-    if existing > 0 {
-        return Ok(());
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin reorder tasks tx: {}", e))?;
+
+    let current = db_load_task_column_ids(&mut tx, &date, &status).await?;
+    let mut ordered: Vec<String> = Vec::new();
+    for id in &body.task_ids {
+        if !current.contains(id) {
+            return Err(format!("任务 {} 不在该看板列中", id));
+        }
+        if !ordered.contains(id) {
+            ordered.push(id.clone());
+        }
+    }
+    for id in current {
+        if !ordered.contains(&id) {
+            ordered.push(id);
+        }
     }
+    db_write_task_column_positions(&mut tx, &ordered).await?;
 
-    let now = now_iso();
-    sqlx::query(
-        "INSERT INTO weekly_review_items (id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at)
-         VALUES (?, ?, ?, ?, ?, 'pending', ?, NULL, ?, ?)",
+    let tasks = sqlx::query_as::<_, Task>(
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at FROM tasks WHERE date = ? AND status = ? ORDER BY position ASC",
     )
-    .bind(gen_focus_id("weekly-item"))
-    .bind(week_start)
-    .bind(week_end)
-    .bind(wrong_question_id)
-    .bind(title_snapshot)
-    .bind(carried_from_week)
-    .bind(&now)
-    .bind(&now)
-    .execute(pool)
+    .bind(&date)
+    .bind(&status)
+    .fetch_all(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to create weekly review item: {}", e))?;
+    .map_err(|e| format!("Failed to fetch reordered tasks: {}", e))?;
 
-    Ok(())
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit reorder tasks tx: {}", e))?;
+    Ok(tasks)
 }
 
-async fn db_get_weekly_review_items(
+async fn db_get_task_board(
     pool: &sqlx::SqlitePool,
-    week_start: &str,
-) -> Result<Vec<WeeklyReviewItem>, String> {
-    let (start, end) = parse_week_start_monday(week_start)?;
-
-    let start_date = chrono::NaiveDate::parse_from_str(&start, "%Y-%m-%d")
-        .map_err(|e| format!("Invalid week_start: {}", e))?;
-    let prev_start = (start_date - chrono::Duration::days(7)).format("%Y-%m-%d").to_string();
+    start_date: &str,
+    end_date: Option<&str>,
+) -> Result<Vec<TaskBoardColumn>, String> {
+    let start = parse_filter_date("start_date", Some(start_date))?
+        .ok_or_else(|| "start_date 不能为空".to_string())?;
+    let end = parse_filter_date("end_date", end_date)?.unwrap_or_else(|| start.clone());
+    if start > end {
+        return Err("start_date 不能晚于 end_date".to_string());
+    }
 
-    let prev_pending = sqlx::query_as::<_, WeeklyReviewItem>(
-        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
-         FROM weekly_review_items
-         WHERE week_start = ?
-           AND status = 'pending'
-           AND wrong_question_id IN (SELECT id FROM wrong_questions WHERE is_archived = 0)",
+    let statuses = db_get_task_statuses(pool).await?;
+    let tasks = sqlx::query_as::<_, Task>(
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at
+         FROM tasks
+         WHERE date BETWEEN ? AND ?
+         ORDER BY date ASC, position ASC, start_time ASC",
     )
-    .bind(&prev_start)
+    .bind(&start)
+    .bind(&end)
     .fetch_all(pool)
     .await
-    .map_err(|e| format!("Failed to fetch previous week pending items: {}", e))?;
+    .map_err(|e| format!("Failed to fetch board tasks: {}", e))?;
 
-    for item in prev_pending {
-        db_create_weekly_review_item_if_absent(
-            pool,
-            &start,
-            &end,
-            &item.wrong_question_id,
-            &item.title_snapshot,
-            Some(&prev_start),
-        )
-        .await?;
+    let mut columns: Vec<TaskBoardColumn> = statuses
+        .into_iter()
+        .map(|status| TaskBoardColumn {
+            status,
+            tasks: Vec::new(),
+        })
+        .collect();
+    for task in tasks {
+        match columns.iter_mut().find(|c| c.status.key == task.status) {
+            Some(column) => column.tasks.push(task),
+            None => {
+                // Tasks whose status column was never defined still get a column.
+                let sort_order = columns.len() as i64;
+                columns.push(TaskBoardColumn {
+                    status: TaskStatusColumn {
+                        key: task.status.clone(),
+                        label: task.status.clone(),
+                        color: String::new(),
+                        is_done: false,
+                        sort_order,
+                        created_at: String::new(),
+                        updated_at: String::new(),
+                    },
+                    tasks: vec![task],
+                });
+            }
+        }
     }
+    Ok(columns)
+}
 
-    sqlx::query_as::<_, WeeklyReviewItem>(
-        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
-         FROM weekly_review_items
-         WHERE week_start = ?
-           AND wrong_question_id IN (SELECT id FROM wrong_questions WHERE is_archived = 0)
-         ORDER BY status ASC, created_at ASC",
+/// One-time conversion of legacy `focus_sessions`: check-in/out times become attendance records and
+/// focus time not already covered by completed runs on the same day becomes a `legacy_session` run.
+async fn migrate_legacy_focus_sessions(pool: &sqlx::SqlitePool) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin focus session migration tx: {}", e))?;
+
+    let sessions = sqlx::query_as::<_, FocusSession>(
+        "SELECT id, date, checked_in_at, checked_out_at, total_focus_seconds, active_task_id FROM focus_sessions ORDER BY date ASC",
     )
-    .bind(start)
-    .fetch_all(pool)
+    .fetch_all(&mut *tx)
     .await
-    .map_err(|e| format!("Failed to fetch weekly review items: {}", e))
-}
+    .map_err(|e| format!("Failed to load legacy focus sessions: {}", e))?;
 
-async fn db_toggle_weekly_review_item_done(
-    pool: &sqlx::SqlitePool,
-    item_id: &str,
-    done: bool,
-) -> Result<WeeklyReviewItem, String> {
-    let previous = sqlx::query_as::<_, WeeklyReviewItem>(
-        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
-         FROM weekly_review_items WHERE id = ?",
-    )
-    .bind(item_id)
-    .fetch_optional(pool)
-    .await
-    .map_err(|e| format!("Failed to read weekly review item before toggle: {}", e))?
-    .ok_or_else(|| "Weekly review item not found".to_string())?;
-
-    let status = if done { "done" } else { "pending" };
-    let completed_at = if done { Some(now_iso()) } else { None };
-    let result = sqlx::query(
-        "UPDATE weekly_review_items SET status = ?, completed_at = ?, updated_at = ? WHERE id = ?",
-    )
-    .bind(status)
-    .bind(&completed_at)
-    .bind(now_iso())
-    .bind(item_id)
-    .execute(pool)
-    .await
-    .map_err(|e| format!("Failed to toggle weekly review item: {}", e))?;
-
-    if result.rows_affected() == 0 {
-        return Err("Weekly review item not found".to_string());
-    }
+    let now = now_iso();
+    for session in sessions {
+        db_upsert_attendance_record(&mut tx, &session).await?;
 
-    if done && previous.status != "done" {
-        let review_count = sqlx::query_scalar::<_, i64>(
-            "SELECT review_count FROM wrong_questions WHERE id = ?",
+        let run_seconds = sqlx::query_scalar::<_, i64>(
+            "SELECT COALESCE(SUM(actual_seconds), 0) FROM focus_runs WHERE date = ? AND status = 'completed'",
         )
-        .bind(&previous.wrong_question_id)
-        .fetch_optional(pool)
+        .bind(&session.date)
+        .fetch_one(&mut *tx)
         .await
-        .map_err(|e| format!("Failed to read wrong question review_count: {}", e))?
-        .unwrap_or(0)
-        + 1;
-
-        let next_mastery = if review_count >= 3 { 3 } else { review_count as i32 };
-        let auto_archive = review_count >= 4;
-        let now = now_iso();
+        .map_err(|e| format!("Failed to aggregate runs for legacy session: {}", e))?;
+        let legacy_seconds = session.total_focus_seconds - run_seconds;
+        if legacy_seconds < 60 {
+            continue;
+        }
 
+        // Without a check-in only the day is known; local midnight keeps the run on that day.
+        let started_at = session.checked_in_at.clone().unwrap_or_else(|| {
+            chrono::NaiveDate::parse_from_str(&session.date, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .and_then(|d| d.and_local_timezone(Local).earliest())
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_else(|| format!("{}T00:00:00+00:00", session.date))
+        });
         sqlx::query(
-            "UPDATE wrong_questions
-             SET review_count = ?,
-                 mastery_level = ?,
-                 last_review_date = ?,
-                 is_archived = CASE WHEN ? THEN 1 ELSE is_archived END,
-                 updated_at = ?
-             WHERE id = ?",
+            "INSERT OR IGNORE INTO focus_runs (id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, created_at, updated_at)
+             VALUES (?, 'legacy_session', NULL, ?, 'legacy', ?, ?, 'completed', ?, ?, ?, '[]', '由旧版专注记录迁移', ?, ?)",
         )
-        .bind(review_count)
-        .bind(next_mastery)
+        .bind(format!("focus-run-legacy-{}", session.id))
+        .bind(&session.active_task_id)
+        .bind(((legacy_seconds + 59) / 60) as i32)
+        .bind(legacy_seconds)
+        .bind(&started_at)
+        .bind(&session.checked_out_at)
+        .bind(&session.date)
         .bind(&now)
-        .bind(auto_archive)
         .bind(&now)
-        .bind(&previous.wrong_question_id)
-        .execute(pool)
+        .execute(&mut *tx)
         .await
-        .map_err(|e| format!("Failed to update wrong question review progress: {}", e))?;
-
-        if auto_archive {
-            sqlx::query("DELETE FROM weekly_review_items WHERE wrong_question_id = ? AND id <> ?")
-                .bind(&previous.wrong_question_id)
-                .bind(item_id)
-                .execute(pool)
-                .await
-                .map_err(|e| format!("Failed to cleanup weekly items after auto archive: {}", e))?;
-        }
+        .map_err(|e| format!("Failed to convert legacy focus session: {}", e))?;
     }
 
-    sqlx::query_as::<_, WeeklyReviewItem>(
-        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
-         FROM weekly_review_items WHERE id = ?",
-    )
-    .bind(item_id)
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to refetch weekly review item: {}", e))
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit focus session migration tx: {}", e))
 }
 
-async fn db_carry_weekly_review_items_to_next_week(
-    pool: &sqlx::SqlitePool,
-    from_week_start: &str,
-    item_ids: &[String],
+async fn db_upsert_attendance_record(
+    conn: &mut sqlx::SqliteConnection,
+    session: &FocusSession,
 ) -> Result<(), String> {
-    if item_ids.is_empty() {
-        return Ok(());
-    }
-
-    let (from_start, _) = parse_week_start_monday(from_week_start)?;
-    let from_date = chrono::NaiveDate::parse_from_str(&from_start, "%Y-%m-%d")
-        .map_err(|e| format!("Invalid from_week_start: {}", e))?;
-    let next_start = (from_date + chrono::Duration::days(7)).format("%Y-%m-%d").to_string();
-    let (_, next_end) = parse_week_start_monday(&next_start)?;
-
-    for item_id in item_ids {
-        let row = sqlx::query_as::<_, WeeklyReviewItem>(
-            "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
-             FROM weekly_review_items
-             WHERE id = ? AND week_start = ?",
-        )
-        .bind(item_id)
-        .bind(&from_start)
-        .fetch_optional(pool)
-        .await
-        .map_err(|e| format!("Failed to read source weekly review item: {}", e))?;
-
-        if let Some(item) = row {
-            db_create_weekly_review_item_if_absent(
-                pool,
-                &next_start,
-                &next_end,
-                &item.wrong_question_id,
-                &item.title_snapshot,
-                Some(&from_start),
-            )
-            .await?;
-        }
-    }
-
+    let now = now_iso();
+    sqlx::query(
+        "INSERT INTO attendance_records (id, date, checked_in_at, checked_out_at, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT(id) DO UPDATE SET date = excluded.date, checked_in_at = excluded.checked_in_at, checked_out_at = excluded.checked_out_at, updated_at = excluded.updated_at",
+    )
+    .bind(&session.id)
+    .bind(&session.date)
+    .bind(&session.checked_in_at)
+    .bind(&session.checked_out_at)
+    .bind(&now)
+    .bind(&now)
+    .execute(conn)
+    .await
+    .map_err(|e| format!("Failed to upsert attendance record: {}", e))?;
     Ok(())
 }
 
-async fn db_get_wrong_question_stats(pool: &sqlx::SqlitePool) -> Result<WrongQuestionStats, String> {
-    let total_count = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM wrong_questions WHERE is_archived = 0",
+async fn db_get_attendance_records(
+    pool: &sqlx::SqlitePool,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<Vec<AttendanceRecord>, String> {
+    let (start, end) = resolve_focus_range(start_date, end_date)?;
+    sqlx::query_as::<_, AttendanceRecord>(
+        "SELECT id, date, checked_in_at, checked_out_at, created_at, updated_at FROM attendance_records WHERE date BETWEEN ? AND ? ORDER BY date DESC",
     )
-    .fetch_one(pool)
+    .bind(&start)
+    .bind(&end)
+    .fetch_all(pool)
     .await
-    .map_err(|e| format!("Failed to count wrong questions: {}", e))?;
+    .map_err(|e| format!("Failed to fetch attendance records: {}", e))
+}
 
-    let unmastered_count = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM wrong_questions WHERE is_archived = 0 AND mastery_level = 0",
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to count unmastered wrong questions: {}", e))?;
+async fn db_get_weekly_stats(pool: &sqlx::SqlitePool, end_date: &str) -> Result<WeeklyStats, String> {
+    let (start_date, end_date) = resolve_week_window(end_date)?;
 
-    let current_week_start = current_week_start_str();
-    let weekly_pending_count = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM weekly_review_items w
-         JOIN wrong_questions q ON q.id = w.wrong_question_id
-         WHERE w.week_start = ? AND w.status = 'pending' AND q.is_archived = 0",
+    // Same validity rule as db_get_focus_stats so the dashboard and focus page agree.
+    let total_focus_seconds = sqlx::query_scalar::<_, i64>(
+        "SELECT COALESCE(SUM(actual_seconds), 0) FROM focus_runs WHERE date BETWEEN ? AND ? AND status = 'completed' AND actual_seconds >= 60",
     )
-    .bind(&current_week_start)
+    .bind(&start_date)
+    .bind(&end_date)
     .fetch_one(pool)
     .await
-    .map_err(|e| format!("Failed to count weekly pending items: {}", e))?;
+    .map_err(|e| format!("Failed to aggregate focus runs: {}", e))?;
 
-    let weekly_done_count = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM weekly_review_items w
-         JOIN wrong_questions q ON q.id = w.wrong_question_id
-         WHERE w.week_start = ? AND w.status = 'done' AND q.is_archived = 0",
+    let task_done_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COALESCE(SUM(CASE WHEN status IN (SELECT key FROM task_statuses WHERE is_done = 1) THEN 1 ELSE 0 END), 0) FROM tasks WHERE date BETWEEN ? AND ?",
     )
-    .bind(&current_week_start)
+    .bind(&start_date)
+    .bind(&end_date)
     .fetch_one(pool)
     .await
-    .map_err(|e| format!("Failed to count weekly done items: {}", e))?;
+    .map_err(|e| format!("Failed to aggregate done tasks: {}", e))?;
 
-    let this_week_new = sqlx::query_scalar::<_, i64>(
-        "SELECT COUNT(*) FROM wrong_questions WHERE is_archived = 0 AND date(created_at) >= date('now','localtime','weekday 0','-6 days')",
+    let task_total_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM tasks WHERE date BETWEEN ? AND ?",
     )
+    .bind(&start_date)
+    .bind(&end_date)
     .fetch_one(pool)
     .await
-    .map_err(|e| format!("Failed to count this week new wrong questions: {}", e))?;
+    .map_err(|e| format!("Failed to aggregate total tasks: {}", e))?;
 
-    let by_subject = sqlx::query_as::<_, SubjectStat>(
+    let (s408, smath, senglish, spolitics) = sqlx::query_as::<_, (i64, i64, i64, i64)>(
         "SELECT
-            subject AS subject,
-            COUNT(*) AS count,
-            SUM(CASE WHEN mastery_level = 0 THEN 1 ELSE 0 END) AS unmastered
-         FROM wrong_questions
-         WHERE is_archived = 0
-         GROUP BY subject
-         ORDER BY count DESC",
+            COALESCE(SUM(CASE WHEN (title LIKE '%408%' OR tags LIKE '%408%') THEN 1 ELSE 0 END), 0) AS s408,
+            COALESCE(SUM(CASE WHEN (title LIKE '%数学%' OR title LIKE '%数一%' OR tags LIKE '%数学%' OR tags LIKE '%数一%') THEN 1 ELSE 0 END), 0) AS smath,
+            COALESCE(SUM(CASE WHEN (title LIKE '%英语%' OR title LIKE '%英一%' OR tags LIKE '%英语%' OR tags LIKE '%英一%') THEN 1 ELSE 0 END), 0) AS senglish,
+            COALESCE(SUM(CASE WHEN (title LIKE '%政治%' OR tags LIKE '%政治%') THEN 1 ELSE 0 END), 0) AS spolitics
+         FROM tasks
+         WHERE date BETWEEN ? AND ?",
     )
-    .fetch_all(pool)
+    .bind(&start_date)
+    .bind(&end_date)
+    .fetch_one(pool)
     .await
-    .map_err(|e| format!("Failed to aggregate wrong questions by subject: {}", e))?;
+    .map_err(|e| format!("Failed to aggregate subject distribution: {}", e))?;
 
-    Ok(WrongQuestionStats {
-        total_count,
-        unmastered_count,
-        weekly_pending_count,
-        weekly_done_count,
-        this_week_new,
-        by_subject,
+    let total_focus_minutes = total_focus_seconds / 60;
+    let completion_rate = if task_total_count > 0 {
+        (task_done_count as f64 / task_total_count as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    let total_for_distribution = task_total_count.max(1) as f64;
+    let mut subject_distribution = HashMap::new();
+    subject_distribution.insert("408".to_string(), (s408 as f64 / total_for_distribution) * 100.0);
+    subject_distribution.insert("数学".to_string(), (smath as f64 / total_for_distribution) * 100.0);
+    subject_distribution.insert("英语".to_string(), (senglish as f64 / total_for_distribution) * 100.0);
+    subject_distribution.insert("政治".to_string(), (spolitics as f64 / total_for_distribution) * 100.0);
+
+    Ok(WeeklyStats {
+        total_focus_minutes,
+        completion_rate,
+        subject_distribution,
     })
 }
 
-async fn save_chat_image_to_workspace(root: &str, image_data: &[u8], ext: &str) -> Result<String, String> {
-    let now = Local::now();
-    let month_dir = now.format("%Y-%m").to_string();
-    let clean_ext = ext.trim().trim_start_matches('.').to_lowercase();
-    let final_ext = if clean_ext.is_empty() { "png".to_string() } else { clean_ext };
-    let image_id = gen_focus_id("img");
-    let relative = format!("ErrorImages/{}/{}.{}", month_dir, image_id, final_ext);
-
-    let abs_path = PathBuf::from(root).join(&relative);
-    if let Some(parent) = abs_path.parent() {
-        fs::create_dir_all(parent)
-            .await
-            .map_err(|e| format!("Failed to create image dir: {}", e))?;
+fn normalize_focus_dimension(raw: Option<&str>) -> String {
+    match raw.unwrap_or("tag").trim().to_lowercase().as_str() {
+        "template" => "template".to_string(),
+        "timer_type" => "timer_type".to_string(),
+        _ => "tag".to_string(),
     }
+}
 
-    fs::write(&abs_path, image_data)
-        .await
-        .map_err(|e| format!("Failed to save image file: {}", e))?;
+fn resolve_focus_range(
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<(String, String), String> {
+    let resolved_end = if let Some(end) = end_date {
+        chrono::NaiveDate::parse_from_str(end, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid end_date, expected YYYY-MM-DD: {}", e))?
+    } else {
+        Local::now().date_naive()
+    };
 
-    Ok(relative)
-}
+    let resolved_start = if let Some(start) = start_date {
+        chrono::NaiveDate::parse_from_str(start, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid start_date, expected YYYY-MM-DD: {}", e))?
+    } else {
+        resolved_end - chrono::Duration::days(6)
+    };
 
-async fn db_get_resources_rows(pool: &sqlx::SqlitePool) -> Result<Vec<Resource>, String> {
-    let rows = sqlx::query_as::<_, Resource>(
-        "SELECT id, name, path, file_type, subject, size_bytes, created_at FROM resources ORDER BY created_at DESC",
-    )
-    .fetch_all(pool)
-    .await
-    .map_err(|e| format!("Failed to fetch resources: {}", e))?;
-    Ok(rows)
+    if resolved_start > resolved_end {
+        return Err("start_date 不能晚于 end_date".to_string());
+    }
+
+    Ok((
+        resolved_start.format("%Y-%m-%d").to_string(),
+        resolved_end.format("%Y-%m-%d").to_string(),
+    ))
 }
 
-async fn db_fetch_due_questions(
-    pool: &sqlx::SqlitePool,
-    subject: Option<&str>,
-) -> Result<Vec<Question>, String> {
-    let rows = if let Some(subject_value) = subject {
-        sqlx::query_as::<_, Question>(
-            "SELECT id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval
-             FROM questions
-             WHERE review_count > 0
-               AND datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) <= datetime('now')
-               AND subject = ?
-             ORDER BY datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) ASC",
-        )
-        .bind(subject_value)
-        .fetch_all(pool)
-        .await
-        .map_err(|e| format!("Failed to fetch due questions by subject: {}", e))?
-    } else {
-        sqlx::query_as::<_, Question>(
-            "SELECT id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval
-             FROM questions
-             WHERE review_count > 0
-               AND datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) <= datetime('now')
-             ORDER BY datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) ASC",
-        )
-        .fetch_all(pool)
-        .await
-        .map_err(|e| format!("Failed to fetch due questions: {}", e))?
-    };
+fn parse_focus_tags(tags_json: &str) -> Vec<String> {
+    let parsed = serde_json::from_str::<Vec<String>>(tags_json).unwrap_or_default();
+    let mut clean = Vec::<String>::new();
+    for tag in parsed {
+        let normalized = tag.trim();
+        if normalized.is_empty() {
+            continue;
+        }
+        if clean.iter().any(|existing| existing == normalized) {
+            continue;
+        }
+        clean.push(normalized.to_string());
+    }
 
-    Ok(rows)
+    if clean.is_empty() {
+        clean.push("未分类".to_string());
+    }
+    clean
 }
 
-async fn api_quiz_all_handler(
-    AxumState(state): AxumState<LanAppState>,
-) -> Result<Json<Vec<Question>>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let rows = db_fetch_all_questions(&db.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    Ok(Json(rows))
+fn gen_focus_id(prefix: &str) -> String {
+    format!("{}-{}", prefix, Utc::now().timestamp_micros())
 }
 
-async fn api_quiz_due_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Query(params): Query<QuizDueQuery>,
-) -> Result<Json<Vec<Question>>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let rows = db_fetch_due_questions(&db.db, params.subject.as_deref())
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    Ok(Json(rows))
+/// Like `gen_focus_id`, but safe to call repeatedly inside a tight loop.
+fn gen_unique_id(prefix: &str) -> String {
+    format!("{}-{}-{}", prefix, Utc::now().timestamp_micros(), uuid_short())
 }
 
-async fn api_tasks_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Query(params): Query<TasksQuery>,
-) -> Result<Json<Vec<Task>>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let rows = if let Some(date) = params.date.as_deref() {
-        db_get_tasks_by_date(&db.db, date).await
-    } else {
-        db_get_tasks(&db.db).await
-    }
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+#[derive(Debug, sqlx::FromRow)]
+struct FocusSummaryRow {
+    total_seconds: i64,
+    completed_runs: i64,
+    all_runs: i64,
+    interrupted_runs: i64,
+    break_seconds: i64,
+}
 
-    Ok(Json(rows))
+#[derive(Debug, sqlx::FromRow)]
+struct FocusGroupedRow {
+    key_name: String,
+    total_seconds: i64,
+    run_count: i64,
 }
 
-async fn api_create_task_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Json(task): Json<Task>,
-) -> Result<Json<Task>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let created = db_create_task(&db.db, &task)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_TASKS");
-    Ok(Json(created))
+#[derive(Debug, sqlx::FromRow)]
+struct FocusTagRow {
+    tags_json: String,
+    actual_seconds: i64,
 }
 
-async fn api_update_task_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-    Json(mut task): Json<Task>,
-) -> Result<Json<Task>, (StatusCode, String)> {
-    task.id = id;
-    let db = state.db.lock().await;
-    let updated = db_update_task(&db.db, &task)
+async fn db_get_focus_templates(
+    pool: &sqlx::SqlitePool,
+    include_archived: bool,
+) -> Result<Vec<FocusTemplate>, String> {
+    let rows = if include_archived {
+        sqlx::query_as::<_, FocusTemplate>(
+            "SELECT id, name, timer_type, duration_minutes, tags_json, linked_task_title, color_token, is_archived, short_break_minutes, long_break_minutes, cycles_before_long_break, created_at, updated_at FROM focus_templates ORDER BY is_archived ASC, updated_at DESC",
+        )
+        .fetch_all(pool)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_TASKS");
-    Ok(Json(updated))
+        .map_err(|e| format!("Failed to fetch focus templates: {}", e))?
+    } else {
+        sqlx::query_as::<_, FocusTemplate>(
+            "SELECT id, name, timer_type, duration_minutes, tags_json, linked_task_title, color_token, is_archived, short_break_minutes, long_break_minutes, cycles_before_long_break, created_at, updated_at FROM focus_templates WHERE is_archived = 0 ORDER BY updated_at DESC",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch active focus templates: {}", e))?
+    };
+    Ok(rows)
 }
 
-async fn api_delete_task_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    db_delete_task(&db.db, &id)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_TASKS");
-    Ok(StatusCode::NO_CONTENT)
+fn validate_pomodoro_cycle(template: &FocusTemplate) -> Result<(), String> {
+    if template.short_break_minutes <= 0 || template.long_break_minutes <= 0 {
+        return Err("休息时长必须大于 0".to_string());
+    }
+    if template.cycles_before_long_break < 1 {
+        return Err("cycles_before_long_break 至少为 1".to_string());
+    }
+    Ok(())
 }
 
-async fn api_resources_handler(
-    AxumState(state): AxumState<LanAppState>,
-) -> Result<Json<Vec<Resource>>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let rows = db_get_resources_rows(&db.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    Ok(Json(rows))
+async fn db_create_focus_template(
+    pool: &sqlx::SqlitePool,
+    template: &FocusTemplate,
+) -> Result<FocusTemplate, String> {
+    validate_pomodoro_cycle(template)?;
+    sqlx::query(
+        "INSERT INTO focus_templates (id, name, timer_type, duration_minutes, tags_json, linked_task_title, color_token, is_archived, short_break_minutes, long_break_minutes, cycles_before_long_break, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&template.id)
+    .bind(&template.name)
+    .bind(&template.timer_type)
+    .bind(template.duration_minutes)
+    .bind(&template.tags_json)
+    .bind(&template.linked_task_title)
+    .bind(&template.color_token)
+    .bind(template.is_archived)
+    .bind(template.short_break_minutes)
+    .bind(template.long_break_minutes)
+    .bind(template.cycles_before_long_break)
+    .bind(&template.created_at)
+    .bind(&template.updated_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create focus template: {}", e))?;
+
+    Ok(template.clone())
 }
 
-async fn api_weekly_stats_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Query(params): Query<WeeklyStatsQuery>,
-) -> Result<Json<WeeklyStats>, (StatusCode, String)> {
-    let end_date = params
-        .end_date
-        .unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
+async fn db_update_focus_template(
+    pool: &sqlx::SqlitePool,
+    template: &FocusTemplate,
+) -> Result<FocusTemplate, String> {
+    validate_pomodoro_cycle(template)?;
+    let result = sqlx::query(
+        "UPDATE focus_templates SET name = ?, timer_type = ?, duration_minutes = ?, tags_json = ?, linked_task_title = ?, color_token = ?, is_archived = ?, short_break_minutes = ?, long_break_minutes = ?, cycles_before_long_break = ?, updated_at = ? WHERE id = ?",
+    )
+    .bind(&template.name)
+    .bind(&template.timer_type)
+    .bind(template.duration_minutes)
+    .bind(&template.tags_json)
+    .bind(&template.linked_task_title)
+    .bind(&template.color_token)
+    .bind(template.is_archived)
+    .bind(template.short_break_minutes)
+    .bind(template.long_break_minutes)
+    .bind(template.cycles_before_long_break)
+    .bind(&template.updated_at)
+    .bind(&template.id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update focus template: {}", e))?;
 
-    let db = state.db.lock().await;
-    let stats = db_get_weekly_stats(&db.db, &end_date)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    if result.rows_affected() == 0 {
+        return Err("Focus template not found".to_string());
+    }
 
-    Ok(Json(stats))
+    Ok(template.clone())
 }
 
-async fn api_focus_templates_handler(
-    AxumState(state): AxumState<LanAppState>,
-) -> Result<Json<Vec<FocusTemplate>>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let rows = db_get_focus_templates(&db.db, false)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    Ok(Json(rows))
-}
+async fn db_archive_focus_template(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
+    let result = sqlx::query(
+        "UPDATE focus_templates SET is_archived = 1, updated_at = ? WHERE id = ?",
+    )
+    .bind(now_iso())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to archive focus template: {}", e))?;
 
-async fn api_create_focus_template_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Json(mut template): Json<FocusTemplate>,
-) -> Result<Json<FocusTemplate>, (StatusCode, String)> {
-    if template.name.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "template.name 不能为空".to_string()));
-    }
-    if template.duration_minutes <= 0 {
-        return Err((StatusCode::BAD_REQUEST, "template.duration_minutes 必须大于 0".to_string()));
+    if result.rows_affected() == 0 {
+        return Err("Focus template not found".to_string());
     }
 
-    if template.id.trim().is_empty() {
-        template.id = gen_focus_id("focus-template");
+    Ok(())
+}
+
+/// Starts a run; with `take_over`, runs active elsewhere are aborted in the same transaction and
+/// their ids returned, otherwise their presence rejects the start.
+async fn db_start_focus_run(
+    pool: &sqlx::SqlitePool,
+    payload: &StartFocusRunPayload,
+) -> Result<(FocusRun, Vec<String>), String> {
+    if payload.source.trim().is_empty() {
+        return Err("source 不能为空".to_string());
     }
-    if template.timer_type.trim().is_empty() {
-        template.timer_type = "pomodoro".to_string();
+    if payload.planned_minutes <= 0 {
+        return Err("planned_minutes 必须大于 0".to_string());
     }
-    if template.tags_json.trim().is_empty() {
-        template.tags_json = "[]".to_string();
+    if payload.timer_type.trim().is_empty() {
+        return Err("timer_type 不能为空".to_string());
     }
 
     let now = now_iso();
-    if template.created_at.trim().is_empty() {
-        template.created_at = now.clone();
-    }
-    template.updated_at = now;
-
-    let db = state.db.lock().await;
-    let created = db_create_focus_template(&db.db, &template)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_TEMPLATES");
-    Ok(Json(created))
-}
+    let run = FocusRun {
+        id: gen_focus_id("focus-run"),
+        source: payload.source.trim().to_string(),
+        template_id: payload.template_id.clone(),
+        task_id: payload.task_id.clone(),
+        timer_type: payload.timer_type.trim().to_string(),
+        planned_minutes: payload.planned_minutes,
+        actual_seconds: 0,
+        status: "running".to_string(),
+        started_at: now.clone(),
+        ended_at: None,
+        date: payload
+            .date
+            .clone()
+            .unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string()),
+        tags_json: payload
+            .tags_json
+            .clone()
+            .unwrap_or_else(|| "[]".to_string()),
+        note: payload.note.clone(),
+        cycle: 1,
+        break_seconds: 0,
+        created_at: now.clone(),
+        updated_at: now,
+    };
 
-async fn api_update_focus_template_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-    Json(mut template): Json<FocusTemplate>,
-) -> Result<Json<FocusTemplate>, (StatusCode, String)> {
-    if template.name.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "template.name 不能为空".to_string()));
-    }
-    if template.duration_minutes <= 0 {
-        return Err((StatusCode::BAD_REQUEST, "template.duration_minutes 必须大于 0".to_string()));
-    }
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin start focus run tx: {}", e))?;
 
-    template.id = id;
-    if template.timer_type.trim().is_empty() {
-        template.timer_type = "pomodoro".to_string();
+    // Only one run may be active across desktop and phone.
+    let active_ids = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM focus_runs WHERE status IN ('running', 'paused', 'break')",
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to check active focus run: {}", e))?;
+    if let Some(active_id) = active_ids.first() {
+        if !payload.take_over.unwrap_or(false) {
+            return Err(format!("已有进行中的专注: {}", active_id));
+        }
     }
-    if template.tags_json.trim().is_empty() {
-        template.tags_json = "[]".to_string();
+    let aborted = FinishFocusRunPayload {
+        status: "aborted".to_string(),
+        ..FinishFocusRunPayload::completed()
+    };
+    for active_id in &active_ids {
+        db_finish_focus_run_in(&mut tx, active_id, &aborted).await?;
     }
 
-    template.updated_at = now_iso();
-    if template.created_at.trim().is_empty() {
-        template.created_at = template.updated_at.clone();
-    }
+    sqlx::query(
+        "INSERT INTO focus_runs (id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&run.id)
+    .bind(&run.source)
+    .bind(&run.template_id)
+    .bind(&run.task_id)
+    .bind(&run.timer_type)
+    .bind(run.planned_minutes)
+    .bind(run.actual_seconds)
+    .bind(&run.status)
+    .bind(&run.started_at)
+    .bind(&run.ended_at)
+    .bind(&run.date)
+    .bind(&run.tags_json)
+    .bind(&run.note)
+    .bind(&run.created_at)
+    .bind(&run.updated_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to start focus run: {}", e))?;
 
-    let db = state.db.lock().await;
-    let updated = db_update_focus_template(&db.db, &template)
+    db_open_focus_segment(&mut tx, &run.id, "work", 1, 0, &run.started_at).await?;
+    tx.commit()
         .await
-        .map_err(|e| {
-            if e.contains("not found") {
-                (StatusCode::NOT_FOUND, e)
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            }
-        })?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_TEMPLATES");
-    Ok(Json(updated))
-}
+        .map_err(|e| format!("Failed to commit start focus run tx: {}", e))?;
 
-async fn api_archive_focus_template_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    db_archive_focus_template(&db.db, &id)
-        .await
-        .map_err(|e| {
-            if e.contains("not found") {
-                (StatusCode::NOT_FOUND, e)
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            }
-        })?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_TEMPLATES");
-    Ok(StatusCode::NO_CONTENT)
+    Ok((run, active_ids))
 }
 
-async fn api_start_focus_run_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Json(payload): Json<StartFocusRunPayload>,
-) -> Result<Json<FocusRun>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let started = db_start_focus_run(&db.db, &payload)
+async fn db_finish_focus_run(
+    pool: &sqlx::SqlitePool,
+    run_id: &str,
+    payload: &FinishFocusRunPayload,
+) -> Result<FocusRun, String> {
+    let mut tx = pool
+        .begin()
         .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_RUNS");
-    Ok(Json(started))
-}
-
-async fn api_finish_focus_run_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-    Json(payload): Json<FinishFocusRunPayload>,
-) -> Result<Json<FocusRun>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let finished = db_finish_focus_run(&db.db, &id, &payload)
+        .map_err(|e| format!("Failed to begin finish focus run tx: {}", e))?;
+    let run = db_finish_focus_run_in(&mut tx, run_id, payload).await?;
+    tx.commit()
         .await
-        .map_err(|e| {
-            if e.contains("not found") {
-                (StatusCode::NOT_FOUND, e)
-            } else {
-                (StatusCode::BAD_REQUEST, e)
-            }
-        })?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_RUNS");
-    Ok(Json(finished))
+        .map_err(|e| format!("Failed to commit finish focus run tx: {}", e))?;
+    Ok(run)
 }
 
-async fn api_focus_stats_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Query(params): Query<FocusStatsQuery>,
-) -> Result<Json<FocusStatsResult>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let stats = db_get_focus_stats(
-        &db.db,
-        params.start_date.as_deref(),
-        params.end_date.as_deref(),
-        params.dimension.as_deref(),
+async fn db_finish_focus_run_in(
+    conn: &mut sqlx::SqliteConnection,
+    run_id: &str,
+    payload: &FinishFocusRunPayload,
+) -> Result<FocusRun, String> {
+    let next_status = payload.status.trim().to_lowercase();
+    if next_status != "completed" && next_status != "aborted" {
+        return Err("status 仅支持 completed 或 aborted".to_string());
+    }
+
+    let run = db_fetch_focus_run(conn, run_id)
+        .await?
+        .ok_or_else(|| "Focus run not found".to_string())?;
+    if !FOCUS_RUN_ACTIVE_STATUSES.contains(&run.status.as_str()) {
+        return Err(illegal_focus_transition(&run.status, "finish"));
+    }
+
+    let updated_at = now_iso();
+    let segments = db_fetch_focus_run_segments(conn, run_id).await?;
+    // Focus time is always server-computed: from segments, or for runs started before segments
+    // existed, from the wall clock minus recorded breaks.
+    let (actual_seconds, break_seconds) = if segments.is_empty() {
+        let wall_clock = match (
+            chrono::DateTime::parse_from_rfc3339(&run.started_at),
+            chrono::DateTime::parse_from_rfc3339(&updated_at),
+        ) {
+            (Ok(start), Ok(end)) => (end - start).num_seconds().max(0),
+            _ => 0,
+        };
+        ((wall_clock - run.break_seconds).max(0), run.break_seconds)
+    } else {
+        db_close_focus_segments(conn, run_id, &updated_at).await?;
+        let segments = db_fetch_focus_run_segments(conn, run_id).await?;
+        let now_ts = Utc::now().timestamp();
+        (
+            sum_focus_segments(&segments, "focus", now_ts),
+            sum_focus_segments(&segments, "break", now_ts),
+        )
+    };
+
+    sqlx::query(
+        "UPDATE focus_runs SET actual_seconds = ?, break_seconds = ?, status = ?, ended_at = ?, tags_json = COALESCE(?, tags_json), note = COALESCE(?, note), updated_at = ? WHERE id = ?",
     )
+    .bind(actual_seconds)
+    .bind(break_seconds)
+    .bind(&next_status)
+    .bind(&updated_at)
+    .bind(&payload.tags_json)
+    .bind(&payload.note)
+    .bind(&updated_at)
+    .bind(run_id)
+    .execute(&mut *conn)
     .await
-    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    .map_err(|e| format!("Failed to finish focus run: {}", e))?;
 
-    Ok(Json(stats))
+    db_fetch_focus_run(conn, run_id)
+        .await?
+        .ok_or_else(|| "Focus run not found".to_string())
 }
 
-async fn api_wrong_questions_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Query(params): Query<WrongQuestionsQuery>,
-) -> Result<Json<Vec<WrongQuestion>>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let rows = db_get_wrong_questions(
-        &db.db,
-        &WrongQuestionFilter {
-            subject: params.subject,
-            mastery_level: params.mastery_level,
-            search_keyword: params.search_keyword,
-            is_archived: params.is_archived.or(Some(0)),
-        },
-    )
-    .await
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    Ok(Json(rows))
+const FOCUS_RUN_ACTIVE_STATUSES: [&str; 3] = ["running", "paused", "break"];
+
+#[derive(Debug, Clone, Copy)]
+enum FocusRunAction {
+    Pause,
+    Resume,
+    Extend(i32),
+    Break(Option<i32>), // None: short/long break length from the run's template
+    SkipBreak,
 }
 
-async fn api_create_wrong_question_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Json(mut req): Json<CreateWrongQuestionRequest>,
-) -> Result<Json<WrongQuestion>, (StatusCode, String)> {
-    if req.question.question_content.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "question_content 不能为空".to_string()));
-    }
-    if req.question.ai_solution.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "ai_solution 不能为空".to_string()));
+impl FocusRunAction {
+    fn name(&self) -> &'static str {
+        match self {
+            FocusRunAction::Pause => "pause",
+            FocusRunAction::Resume => "resume",
+            FocusRunAction::Extend(_) => "extend",
+            FocusRunAction::Break(_) => "break",
+            FocusRunAction::SkipBreak => "skip-break",
+        }
     }
 
-    if req.question.id.trim().is_empty() {
-        req.question.id = gen_focus_id("wrong-question");
-    }
-    if req.question.subject.trim().is_empty() {
-        req.question.subject = "其他".to_string();
-    }
-    if req.question.tags_json.trim().is_empty() {
-        req.question.tags_json = "[]".to_string();
-    }
-    if req.question.source.trim().is_empty() {
-        req.question.source = "ai_chat".to_string();
-    }
-    let now = now_iso();
-    if req.question.created_at.trim().is_empty() {
-        req.question.created_at = now.clone();
+    fn allowed_from(&self, status: &str) -> bool {
+        match self {
+            FocusRunAction::Pause => status == "running",
+            FocusRunAction::Resume => status == "paused" || status == "break",
+            FocusRunAction::Extend(_) => FOCUS_RUN_ACTIVE_STATUSES.contains(&status),
+            FocusRunAction::Break(_) => status == "running" || status == "paused",
+            FocusRunAction::SkipBreak => status == "break",
+        }
     }
-    req.question.updated_at = now;
+}
 
-    let db = state.db.lock().await;
-    let created = db_create_wrong_question(&db.db, &req.question)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+fn illegal_focus_transition(status: &str, action: &str) -> String {
+    format!("非法的状态转换: {} 状态下不能执行 {}", status, action)
+}
 
-    if req.add_to_current_week.unwrap_or(false) {
-        let week_start = current_week_start_str();
-        let (_, week_end) = parse_week_start_monday(&week_start)
-            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-        db_create_weekly_review_item_if_absent(
-            &db.db,
-            &week_start,
-            &week_end,
-            &created.id,
-            &make_title_snapshot(&created.question_content, &created.ai_solution),
-            None,
-        )
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    }
-    drop(db);
+fn focus_timestamp_secs(ts: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(ts)
+        .ok()
+        .map(|dt| dt.timestamp())
+}
 
-    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
-    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
-    Ok(Json(created))
+fn focus_segment_seconds(segment: &FocusRunSegment, now: i64) -> i64 {
+    let start = focus_timestamp_secs(&segment.started_at).unwrap_or(now);
+    let end = segment
+        .ended_at
+        .as_deref()
+        .and_then(focus_timestamp_secs)
+        .unwrap_or(now);
+    (end - start).max(0)
 }
 
-async fn api_update_wrong_question_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-    Json(mut question): Json<WrongQuestion>,
-) -> Result<Json<WrongQuestion>, (StatusCode, String)> {
-    question.id = id;
-    question.updated_at = now_iso();
-    let db = state.db.lock().await;
-    let updated = db_update_wrong_question(&db.db, &question)
-        .await
-        .map_err(|e| {
-            if e.contains("not found") {
-                (StatusCode::NOT_FOUND, e)
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            }
-        })?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
-    Ok(Json(updated))
+fn sum_focus_segments(segments: &[FocusRunSegment], kind: &str, now: i64) -> i64 {
+    segments
+        .iter()
+        .filter(|s| s.kind == kind)
+        .map(|s| focus_segment_seconds(s, now))
+        .sum()
 }
 
-async fn api_archive_wrong_question_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    db_archive_wrong_question(&db.db, &id)
-        .await
-        .map_err(|e| {
-            if e.contains("not found") {
-                (StatusCode::NOT_FOUND, e)
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            }
-        })?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
-    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
-    Ok(StatusCode::NO_CONTENT)
-}
+fn build_focus_run_state(run: FocusRun, segments: Vec<FocusRunSegment>) -> FocusRunState {
+    let now = Utc::now().timestamp();
+    let elapsed_seconds = if segments.is_empty() {
+        run.actual_seconds
+    } else {
+        sum_focus_segments(&segments, "focus", now)
+    };
+    let remaining_seconds = match run.status.as_str() {
+        "break" => segments
+            .iter()
+            .rev()
+            .find(|s| s.kind == "break" && s.ended_at.is_none())
+            .map(|s| s.planned_seconds - focus_segment_seconds(s, now))
+            .unwrap_or(0),
+        "running" | "paused" if segments.is_empty() => {
+            run.planned_minutes as i64 * 60 - elapsed_seconds
+        }
+        // The planned length applies to each work cycle.
+        "running" | "paused" => {
+            let cycle_seconds: i64 = segments
+                .iter()
+                .filter(|s| s.kind == "focus" && s.cycle == run.cycle.max(1))
+                .map(|s| focus_segment_seconds(s, now))
+                .sum();
+            run.planned_minutes as i64 * 60 - cycle_seconds
+        }
+        _ => 0,
+    }
+    .max(0);
+    let phase = match run.status.as_str() {
+        "running" => "focus".to_string(),
+        "break" => segments
+            .iter()
+            .rev()
+            .find(|s| s.kind == "break" && s.ended_at.is_none())
+            .map(|s| s.phase.clone())
+            .unwrap_or_else(|| "break".to_string()),
+        other => other.to_string(),
+    };
 
-async fn api_delete_wrong_question_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    db_delete_wrong_question(&db.db, &id)
-        .await
-        .map_err(|e| {
-            if e.contains("not found") {
-                (StatusCode::NOT_FOUND, e)
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            }
-        })?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
-    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
-    Ok(StatusCode::NO_CONTENT)
+    FocusRunState {
+        run,
+        phase,
+        elapsed_seconds,
+        remaining_seconds,
+        segments,
+        server_time: now_iso(),
+    }
 }
 
-async fn api_wrong_question_stats_handler(
-    AxumState(state): AxumState<LanAppState>,
-) -> Result<Json<WrongQuestionStats>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let stats = db_get_wrong_question_stats(&db.db)
-        .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    Ok(Json(stats))
+async fn db_fetch_focus_run(
+    conn: &mut sqlx::SqliteConnection,
+    run_id: &str,
+) -> Result<Option<FocusRun>, String> {
+    sqlx::query_as::<_, FocusRun>(
+        "SELECT id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, cycle, break_seconds, created_at, updated_at FROM focus_runs WHERE id = ?",
+    )
+    .bind(run_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(|e| format!("Failed to fetch focus run: {}", e))
 }
 
-async fn api_weekly_review_items_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Query(params): Query<WeeklyReviewItemsQuery>,
-) -> Result<Json<Vec<WeeklyReviewItem>>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let rows = db_get_weekly_review_items(&db.db, &params.week_start)
-        .await
-        .map_err(|e| {
-            if e.contains("week_start") {
-                (StatusCode::BAD_REQUEST, e)
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            }
-        })?;
-    Ok(Json(rows))
+async fn db_fetch_focus_run_segments(
+    conn: &mut sqlx::SqliteConnection,
+    run_id: &str,
+) -> Result<Vec<FocusRunSegment>, String> {
+    sqlx::query_as::<_, FocusRunSegment>(
+        "SELECT id, run_id, kind, phase, cycle, started_at, ended_at, planned_seconds FROM focus_run_segments WHERE run_id = ? ORDER BY started_at ASC, id ASC",
+    )
+    .bind(run_id)
+    .fetch_all(conn)
+    .await
+    .map_err(|e| format!("Failed to fetch focus run segments: {}", e))
 }
 
-async fn api_weekly_review_toggle_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(id): AxumPath<String>,
-    Json(body): Json<WeeklyReviewToggleBody>,
-) -> Result<Json<WeeklyReviewItem>, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    let row = db_toggle_weekly_review_item_done(&db.db, &id, body.done)
-        .await
-        .map_err(|e| {
-            if e.contains("not found") {
-                (StatusCode::NOT_FOUND, e)
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            }
-        })?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
-    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
-    Ok(Json(row))
+/// Opens a segment for `phase` ("work" | "short_break" | "long_break") in the given pomodoro cycle.
+async fn db_open_focus_segment(
+    conn: &mut sqlx::SqliteConnection,
+    run_id: &str,
+    phase: &str,
+    cycle: i32,
+    planned_seconds: i64,
+    started_at: &str,
+) -> Result<(), String> {
+    let kind = if phase == "work" { "focus" } else { "break" };
+    sqlx::query(
+        "INSERT INTO focus_run_segments (id, run_id, kind, phase, cycle, started_at, ended_at, planned_seconds) VALUES (?, ?, ?, ?, ?, ?, NULL, ?)",
+    )
+    .bind(gen_unique_id("focus-seg"))
+    .bind(run_id)
+    .bind(kind)
+    .bind(phase)
+    .bind(cycle)
+    .bind(started_at)
+    .bind(planned_seconds)
+    .execute(conn)
+    .await
+    .map_err(|e| format!("Failed to open focus segment: {}", e))?;
+    Ok(())
 }
 
-async fn api_weekly_review_carry_handler(
-    AxumState(state): AxumState<LanAppState>,
-    Json(body): Json<CarryNextWeekBody>,
-) -> Result<StatusCode, (StatusCode, String)> {
-    let db = state.db.lock().await;
-    db_carry_weekly_review_items_to_next_week(&db.db, &body.from_week_start, &body.item_ids)
+async fn db_close_focus_segments(
+    conn: &mut sqlx::SqliteConnection,
+    run_id: &str,
+    ended_at: &str,
+) -> Result<(), String> {
+    sqlx::query("UPDATE focus_run_segments SET ended_at = ? WHERE run_id = ? AND ended_at IS NULL")
+        .bind(ended_at)
+        .bind(run_id)
+        .execute(conn)
         .await
-        .map_err(|e| {
-            if e.contains("week_start") {
-                (StatusCode::BAD_REQUEST, e)
-            } else {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            }
-        })?;
-    drop(db);
-    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
-    Ok(StatusCode::NO_CONTENT)
+        .map_err(|e| format!("Failed to close focus segment: {}", e))?;
+    Ok(())
 }
 
-async fn api_image_upload_handler(
-    AxumState(state): AxumState<LanAppState>,
-    mut multipart: Multipart,
-) -> Result<Json<ImageUploadResponse>, (StatusCode, String)> {
-    while let Some(field) = multipart
-        .next_field()
+async fn db_get_focus_run_state(
+    pool: &sqlx::SqlitePool,
+    run_id: &str,
+) -> Result<FocusRunState, String> {
+    let mut conn = pool
+        .acquire()
         .await
-        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid multipart field: {}", e)))?
-    {
-        let name = field.name().unwrap_or("").to_string();
-        if name != "file" {
-            continue;
-        }
+        .map_err(|e| format!("Failed to acquire connection: {}", e))?;
+    let run = db_fetch_focus_run(&mut conn, run_id)
+        .await?
+        .ok_or_else(|| "Focus run not found".to_string())?;
+    let segments = db_fetch_focus_run_segments(&mut conn, run_id).await?;
+    Ok(build_focus_run_state(run, segments))
+}
 
-        let filename = field.file_name().unwrap_or("upload.png").to_string();
-        let ext = Path::new(&filename)
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("png")
-            .to_string();
+async fn db_get_active_focus_run(
+    pool: &sqlx::SqlitePool,
+) -> Result<Option<FocusRunState>, String> {
+    let run_id = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM focus_runs WHERE status IN ('running', 'paused', 'break') ORDER BY started_at DESC LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch active focus run: {}", e))?;
 
-        let data = field
-            .bytes()
-            .await
-            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Failed to read file field: {}", e)))?;
+    match run_id {
+        Some(id) => db_get_focus_run_state(pool, &id).await.map(Some),
+        None => Ok(None),
+    }
+}
 
-        if data.is_empty() {
-            return Err((StatusCode::BAD_REQUEST, "上传文件为空".to_string()));
-        }
+/// Startup recovery: any run still active when the app boots was orphaned by a crash or kill.
+/// Open segments are left open so the user can later resume, finalize or abort the run.
+async fn db_mark_interrupted_focus_runs(pool: &sqlx::SqlitePool) -> Result<u64, String> {
+    let result = sqlx::query(
+        "UPDATE focus_runs
+         SET status = 'interrupted',
+             last_seen_at = MAX(COALESCE(last_seen_at, updated_at), updated_at),
+             updated_at = ?
+         WHERE status IN ('running', 'paused', 'break')",
+    )
+    .bind(now_iso())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to mark interrupted focus runs: {}", e))?;
+    Ok(result.rows_affected())
+}
 
-        let relative = save_chat_image_to_workspace(&state.workspace_root, &data, &ext)
-            .await
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+async fn db_get_interrupted_focus_runs(
+    pool: &sqlx::SqlitePool,
+) -> Result<Vec<FocusRunState>, String> {
+    let ids = sqlx::query_scalar::<_, String>(
+        "SELECT id FROM focus_runs WHERE status = 'interrupted' ORDER BY started_at DESC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch interrupted focus runs: {}", e))?;
 
-        return Ok(Json(ImageUploadResponse { path: relative }));
+    let mut states = Vec::with_capacity(ids.len());
+    for id in ids {
+        states.push(db_get_focus_run_state(pool, &id).await?);
     }
-
-    Err((StatusCode::BAD_REQUEST, "缺少 file 字段".to_string()))
+    Ok(states)
 }
 
-async fn api_image_file_handler(
-    AxumState(state): AxumState<LanAppState>,
-    AxumPath(path): AxumPath<String>,
-) -> Result<Response<Body>, (StatusCode, String)> {
-    if path.trim().is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "path 不能为空".to_string()));
+/// Records that a client still has the run on screen; crash recovery credits time only up to here.
+async fn db_touch_focus_run(pool: &sqlx::SqlitePool, run_id: &str) -> Result<(), String> {
+    let result = sqlx::query(
+        "UPDATE focus_runs SET last_seen_at = ? WHERE id = ? AND status IN ('running', 'paused', 'break')",
+    )
+    .bind(now_iso())
+    .bind(run_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record focus run heartbeat: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err("Focus run not found or not active".to_string());
     }
+    Ok(())
+}
 
-    let sanitized = sanitize_relative_path(&path)
-        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-    let full = PathBuf::from(&state.workspace_root).join(&sanitized);
-
-    let bytes = fs::read(&full)
-        .await
-        .map_err(|e| (StatusCode::NOT_FOUND, format!("Failed to read image: {}", e)))?;
+/// Closes segments left open by a crash at the run's last sign of life (`last_seen`), never
+/// crediting more focus than `cap_seconds` in total nor more break than the break's planned length.
+async fn db_close_interrupted_segments(
+    conn: &mut sqlx::SqliteConnection,
+    run_id: &str,
+    cap_seconds: i64,
+    last_seen: Option<chrono::DateTime<Utc>>,
+) -> Result<Option<String>, String> {
+    let segments = db_fetch_focus_run_segments(&mut *conn, run_id).await?;
+    let now = Utc::now();
+    // Marking a run interrupted sets `last_seen_at` to at least its last state change, so without
+    // heartbeats open segments are credited up to that change; `None` (an unreadable timestamp)
+    // credits nothing.
+    let until = last_seen.map(|seen| seen.min(now));
+    let closed_focus: i64 = segments
+        .iter()
+        .filter(|s| s.kind == "focus" && s.ended_at.is_some())
+        .map(|s| focus_segment_seconds(s, now.timestamp()))
+        .sum();
 
-    let mime = from_path(&full).first_or_octet_stream();
-    Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, mime.as_ref())
-        .body(Body::from(bytes))
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to build image response: {}", e)))
+    let mut last_end: Option<String> = segments.iter().filter_map(|s| s.ended_at.clone()).max();
+    for segment in segments.iter().filter(|s| s.ended_at.is_none()) {
+        let Ok(started) = chrono::DateTime::parse_from_rfc3339(&segment.started_at) else {
+            continue;
+        };
+        let allowed = if segment.kind == "break" {
+            segment.planned_seconds
+        } else {
+            (cap_seconds - closed_focus).max(0)
+        };
+        let until_ts = until.map(|u| u.timestamp()).unwrap_or_else(|| started.timestamp());
+        let elapsed = (until_ts - started.timestamp()).clamp(0, allowed);
+        let ended_at = (started + chrono::Duration::seconds(elapsed))
+            .with_timezone(&Utc)
+            .to_rfc3339();
+        sqlx::query("UPDATE focus_run_segments SET ended_at = ? WHERE id = ?")
+            .bind(&ended_at)
+            .bind(&segment.id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| format!("Failed to close interrupted segment: {}", e))?;
+        if last_end.as_deref().map(|l| ended_at.as_str() > l).unwrap_or(true) {
+            last_end = Some(ended_at);
+        }
+    }
+    Ok(last_end)
 }
 
-async fn api_ws_handler(
-    AxumState(state): AxumState<LanAppState>,
-    ws: WebSocketUpgrade,
-) -> impl IntoResponse {
-    let tx = state.sync_hub.tx.clone();
-    ws.on_upgrade(move |socket| async move {
-        let rx = tx.subscribe();
-        ws_client_loop(socket, rx).await;
-    })
-}
+/// Resolves an interrupted run: `resume` continues it, `finalize` completes it with elapsed time up to
+/// the cap (default: planned length), `abort` discards it.
+async fn db_recover_focus_run(
+    pool: &sqlx::SqlitePool,
+    run_id: &str,
+    action: &str,
+    cap_minutes: Option<i32>,
+) -> Result<FocusRunState, String> {
+    let action = action.trim().to_lowercase();
+    if !matches!(action.as_str(), "resume" | "finalize" | "abort") {
+        return Err("action 仅支持 resume / finalize / abort".to_string());
+    }
+    if matches!(cap_minutes, Some(m) if m < 0) {
+        return Err("cap_minutes 不能为负数".to_string());
+    }
 
-async fn load_notes_tree(app: &tauri::AppHandle) -> Result<Vec<NotesFsNode>, String> {
-    let root = PathBuf::from(ensure_workspace_dirs(app).await?);
-    let notes_root = root.join("Notes");
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin recover focus run tx: {}", e))?;
 
-    fs::create_dir_all(&notes_root).await.map_err(|e| {
-        format!(
-            "Failed to ensure notes directory {}: {}",
-            notes_root.to_string_lossy(),
-            e
+    let run = db_fetch_focus_run(&mut tx, run_id)
+        .await?
+        .ok_or_else(|| "Focus run not found".to_string())?;
+    if run.status != "interrupted" {
+        return Err(illegal_focus_transition(&run.status, &action));
+    }
+    if action == "resume" {
+        let active_id = sqlx::query_scalar::<_, String>(
+            "SELECT id FROM focus_runs WHERE status IN ('running', 'paused', 'break') AND id != ? LIMIT 1",
         )
-    })?;
-
-    scan_notes_directory_sync(&notes_root)
-}
-
-async fn api_notes_tree_handler(
-    app: tauri::AppHandle,
-) -> Result<Json<Vec<NotesFsNode>>, (StatusCode, String)> {
-    let rows = load_notes_tree(&app)
+        .bind(run_id)
+        .fetch_optional(&mut *tx)
         .await
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    Ok(Json(rows))
-}
+        .map_err(|e| format!("Failed to check active focus run: {}", e))?;
+        if let Some(active_id) = active_id {
+            return Err(format!("已有进行中的专注: {}", active_id));
+        }
+    }
 
-async fn api_notes_file_handler(
-    app: tauri::AppHandle,
-    Query(params): Query<NotesFileQuery>,
-) -> Result<String, (StatusCode, String)> {
-    let path = params.path.trim();
-    if path.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "path 参数不能为空".to_string()));
+    if db_fetch_focus_run_segments(&mut tx, run_id).await?.is_empty() {
+        db_open_focus_segment(&mut tx, run_id, "work", run.cycle.max(1), 0, &run.started_at)
+            .await?;
     }
+    let cap_seconds = cap_minutes.unwrap_or(run.planned_minutes) as i64 * 60;
+    let last_seen = sqlx::query_scalar::<_, Option<String>>("SELECT last_seen_at FROM focus_runs WHERE id = ?")
+        .bind(run_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to fetch focus run last seen time: {}", e))?
+        .and_then(|raw| chrono::DateTime::parse_from_rfc3339(&raw).ok())
+        .map(|seen| seen.with_timezone(&Utc));
+    let last_end = db_close_interrupted_segments(&mut tx, run_id, cap_seconds, last_seen).await?;
 
-    let full_path = {
-        let as_path = PathBuf::from(path);
-        if as_path.is_absolute() {
-            as_path
-        } else {
-            let root = PathBuf::from(ensure_workspace_dirs(&app).await.map_err(|e| {
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            })?);
-            let notes_root = root.join("Notes");
-            let sanitized = sanitize_relative_path(path)
-                .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
-            notes_root.join(sanitized)
+    let now = now_iso();
+    let (next_status, ended_at) = match action.as_str() {
+        "resume" => {
+            db_open_focus_segment(&mut tx, run_id, "work", run.cycle.max(1), 0, &now).await?;
+            ("running", None)
         }
+        "finalize" => ("completed", Some(last_end.unwrap_or_else(|| now.clone()))),
+        _ => ("aborted", Some(last_end.unwrap_or_else(|| now.clone()))),
     };
 
-    let content = fs::read_to_string(&full_path)
+    let segments = db_fetch_focus_run_segments(&mut tx, run_id).await?;
+    let now_ts = Utc::now().timestamp();
+    sqlx::query(
+        "UPDATE focus_runs SET status = ?, actual_seconds = ?, break_seconds = ?, ended_at = ?, updated_at = ? WHERE id = ?",
+    )
+    .bind(next_status)
+    .bind(sum_focus_segments(&segments, "focus", now_ts))
+    .bind(sum_focus_segments(&segments, "break", now_ts))
+    .bind(&ended_at)
+    .bind(&now)
+    .bind(run_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to recover focus run: {}", e))?;
+
+    let run = db_fetch_focus_run(&mut tx, run_id)
+        .await?
+        .ok_or_else(|| "Focus run not found".to_string())?;
+    tx.commit()
         .await
-        .map_err(|e| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to read notes file {}: {}", full_path.to_string_lossy(), e),
-            )
-        })?;
-    Ok(content)
+        .map_err(|e| format!("Failed to commit recover focus run tx: {}", e))?;
+    Ok(build_focus_run_state(run, segments))
 }
 
-#[tauri::command]
-fn get_local_ip() -> String {
-    match local_ip_address::local_ip() {
-        Ok(IpAddr::V4(ipv4)) => ipv4.to_string(),
-        _ => "127.0.0.1".to_string(),
-    }
+#[derive(Debug, sqlx::FromRow)]
+struct FocusPomodoroConfig {
+    short_break_minutes: i32,
+    long_break_minutes: i32,
+    cycles_before_long_break: i32,
+}
+
+/// Break lengths of the run's template, falling back to the classic 5/15 min every 4 cycles.
+async fn db_focus_run_pomodoro_config(
+    conn: &mut sqlx::SqliteConnection,
+    template_id: Option<&str>,
+) -> Result<FocusPomodoroConfig, String> {
+    let config = match template_id {
+        Some(id) => sqlx::query_as::<_, FocusPomodoroConfig>(
+            "SELECT short_break_minutes, long_break_minutes, cycles_before_long_break FROM focus_templates WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(conn)
+        .await
+        .map_err(|e| format!("Failed to fetch pomodoro config: {}", e))?,
+        None => None,
+    };
+    Ok(config.unwrap_or(FocusPomodoroConfig {
+        short_break_minutes: default_short_break_minutes(),
+        long_break_minutes: default_long_break_minutes(),
+        cycles_before_long_break: default_cycles_before_long_break(),
+    }))
 }
 
-#[tauri::command]
-async fn toggle_local_server(
-    enable: bool,
-    port: u16,
-    app: tauri::AppHandle,
-    server_state: State<'_, Arc<Mutex<LocalServerState>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<String, String> {
-    if port == 0 {
-        return Err("端口必须大于 0".to_string());
+/// Applies a timer transition; segment bookkeeping and `actual_seconds` are owned here, not by clients.
+async fn db_transition_focus_run(
+    pool: &sqlx::SqlitePool,
+    run_id: &str,
+    action: FocusRunAction,
+) -> Result<FocusRunState, String> {
+    if let FocusRunAction::Extend(minutes) | FocusRunAction::Break(Some(minutes)) = action {
+        if minutes <= 0 {
+            return Err("minutes 必须大于 0".to_string());
+        }
     }
 
-    let previous_runtime = {
-        let mut state = server_state.lock().await;
-        state.runtime.take()
-    };
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin focus transition tx: {}", e))?;
 
-    if let Some(mut runtime) = previous_runtime {
-        if let Some(shutdown_tx) = runtime.shutdown_tx.take() {
-            let _ = shutdown_tx.send(());
-        }
-        let _ = runtime.handle.await;
+    let run = db_fetch_focus_run(&mut tx, run_id)
+        .await?
+        .ok_or_else(|| "Focus run not found".to_string())?;
+    if !action.allowed_from(&run.status) {
+        return Err(illegal_focus_transition(&run.status, action.name()));
     }
 
-    if !enable {
-        return Ok("局域网共享服务已关闭".to_string());
+    // Runs started before segments existed are treated as one open focus segment since start.
+    if db_fetch_focus_run_segments(&mut tx, run_id).await?.is_empty() {
+        db_open_focus_segment(&mut tx, run_id, "work", run.cycle.max(1), 0, &run.started_at)
+            .await?;
     }
 
-    let db_state = app
-        .try_state::<Arc<Mutex<AppDb>>>()
-        .ok_or_else(|| "数据库尚未就绪，请稍后重试。".to_string())?;
-    let shared_db = db_state.inner().clone();
-    let workspace_root = ensure_workspace_dirs(&app).await?;
-    let lan_state = LanAppState {
-        db: shared_db,
-        sync_hub: sync_hub.inner().clone(),
-        workspace_root,
-    };
+    let now = now_iso();
+    let mut planned_minutes = run.planned_minutes;
+    let mut cycle = run.cycle.max(1);
+    let next_status = match action {
+        FocusRunAction::Pause => {
+            db_close_focus_segments(&mut tx, run_id, &now).await?;
+            "paused"
+        }
+        FocusRunAction::Resume => {
+            // Resuming from a break ends it early, like skip-break.
+            if run.status == "break" {
+                cycle += 1;
+                db_close_focus_segments(&mut tx, run_id, &now).await?;
+            }
+            db_open_focus_segment(&mut tx, run_id, "work", cycle, 0, &now).await?;
+            "running"
+        }
+        FocusRunAction::Extend(minutes) => {
+            if run.status == "break" {
+                sqlx::query(
+                    "UPDATE focus_run_segments SET planned_seconds = planned_seconds + ? WHERE run_id = ? AND kind = 'break' AND ended_at IS NULL",
+                )
+                .bind(minutes as i64 * 60)
+                .bind(run_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to extend break: {}", e))?;
+            } else {
+                planned_minutes += minutes;
+            }
+            run.status.as_str()
+        }
+        FocusRunAction::Break(minutes) => {
+            let config = db_focus_run_pomodoro_config(&mut tx, run.template_id.as_deref()).await?;
+            let (phase, default_minutes) = if cycle % config.cycles_before_long_break.max(1) == 0 {
+                ("long_break", config.long_break_minutes)
+            } else {
+                ("short_break", config.short_break_minutes)
+            };
+            let planned_seconds = minutes.unwrap_or(default_minutes) as i64 * 60;
+            db_close_focus_segments(&mut tx, run_id, &now).await?;
+            db_open_focus_segment(&mut tx, run_id, phase, cycle, planned_seconds, &now).await?;
+            "break"
+        }
+        FocusRunAction::SkipBreak => {
+            // Leaving a break starts the next work cycle.
+            cycle += 1;
+            db_close_focus_segments(&mut tx, run_id, &now).await?;
+            db_open_focus_segment(&mut tx, run_id, "work", cycle, 0, &now).await?;
+            "running"
+        }
+    }
+    .to_string();
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    let listener = tokio::net::TcpListener::bind(addr)
-        .await
-        .map_err(|e| format!("启动局域网服务失败（端口 {}）: {}", port, e))?;
+    let segments = db_fetch_focus_run_segments(&mut tx, run_id).await?;
+    let now_ts = Utc::now().timestamp();
+    sqlx::query(
+        "UPDATE focus_runs SET status = ?, planned_minutes = ?, actual_seconds = ?, break_seconds = ?, cycle = ?, updated_at = ? WHERE id = ?",
+    )
+    .bind(&next_status)
+    .bind(planned_minutes)
+    .bind(sum_focus_segments(&segments, "focus", now_ts))
+    .bind(sum_focus_segments(&segments, "break", now_ts))
+    .bind(cycle)
+    .bind(&now)
+    .bind(run_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to update focus run state: {}", e))?;
 
-    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
-    let app_handle_for_notes_tree = app.clone();
-    let app_handle_for_notes_file = app.clone();
+    let run = db_fetch_focus_run(&mut tx, run_id)
+        .await?
+        .ok_or_else(|| "Focus run not found".to_string())?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit focus transition tx: {}", e))?;
+    Ok(build_focus_run_state(run, segments))
+}
 
-    let router = Router::new()
-        .route("/api/ping", get(local_ping_handler))
-        .route("/api/ws", get(api_ws_handler))
-        .route("/api/tasks", get(api_tasks_handler).post(api_create_task_handler))
-        .route("/api/tasks/{id}", put(api_update_task_handler).delete(api_delete_task_handler))
-        .route(
-            "/api/focus/templates",
-            get(api_focus_templates_handler).post(api_create_focus_template_handler),
-        )
-        .route(
-            "/api/focus/templates/{id}",
-            put(api_update_focus_template_handler).delete(api_archive_focus_template_handler),
-        )
-        .route("/api/focus/runs/start", axum::routing::post(api_start_focus_run_handler))
-        .route("/api/focus/runs/{id}/finish", axum::routing::post(api_finish_focus_run_handler))
-        .route("/api/focus/stats", get(api_focus_stats_handler))
-        .route(
-            "/api/wrong-questions",
-            get(api_wrong_questions_handler).post(api_create_wrong_question_handler),
-        )
-        .route(
-            "/api/wrong-questions/{id}",
-            put(api_update_wrong_question_handler).delete(api_archive_wrong_question_handler),
-        )
-        .route(
-            "/api/wrong-questions/{id}/hard-delete",
-            axum::routing::delete(api_delete_wrong_question_handler),
-        )
-        .route("/api/wrong-questions/stats", get(api_wrong_question_stats_handler))
-        .route("/api/weekly-review/items", get(api_weekly_review_items_handler))
-        .route(
-            "/api/weekly-review/items/{id}/toggle",
-            axum::routing::post(api_weekly_review_toggle_handler),
-        )
-        .route(
-            "/api/weekly-review/carry-next-week",
-            axum::routing::post(api_weekly_review_carry_handler),
-        )
-        .route("/api/images/upload", axum::routing::post(api_image_upload_handler))
-        .route("/api/images/{*path}", get(api_image_file_handler))
-        .route("/api/stats/weekly", get(api_weekly_stats_handler))
-        .route("/api/resources", get(api_resources_handler))
-        .route(
-            "/api/notes/tree",
-            get(move || {
-                let app = app_handle_for_notes_tree.clone();
-                async move { api_notes_tree_handler(app).await }
-            }),
+async fn db_get_focus_runs(
+    pool: &sqlx::SqlitePool,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+    status: Option<&str>,
+) -> Result<Vec<FocusRun>, String> {
+    let (start, end) = resolve_focus_range(start_date, end_date)?;
+    let rows = if let Some(status_value) = status {
+        sqlx::query_as::<_, FocusRun>(
+            "SELECT id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, cycle, break_seconds, created_at, updated_at FROM focus_runs WHERE date BETWEEN ? AND ? AND status = ? ORDER BY started_at DESC",
         )
-        .route(
-            "/api/notes/file",
-            get(move |query: Query<NotesFileQuery>| {
-                let app = app_handle_for_notes_file.clone();
-                async move { api_notes_file_handler(app, query).await }
-            }),
+        .bind(&start)
+        .bind(&end)
+        .bind(status_value)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch focus runs by status: {}", e))?
+    } else {
+        sqlx::query_as::<_, FocusRun>(
+            "SELECT id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, cycle, break_seconds, created_at, updated_at FROM focus_runs WHERE date BETWEEN ? AND ? ORDER BY started_at DESC",
         )
-        .route("/api/quiz/all", get(api_quiz_all_handler))
-        .route("/api/quiz/due", get(api_quiz_due_handler))
-        .fallback(embedded_static_handler)
-        .layer(CorsLayer::permissive())
-        .with_state(lan_state);
-
-    let server = axum::serve(listener, router).with_graceful_shutdown(async move {
-        let _ = shutdown_rx.await;
-    });
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch focus runs: {}", e))?
+    };
+    Ok(rows)
+}
 
-    let handle = tauri::async_runtime::spawn(async move {
-        if let Err(err) = server.await {
-            log::error!("Local LAN server stopped with error: {}", err);
-        }
-    });
+async fn db_get_focus_stats(
+    pool: &sqlx::SqlitePool,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+    dimension: Option<&str>,
+) -> Result<FocusStatsResult, String> {
+    let (start, end) = resolve_focus_range(start_date, end_date)?;
+    let dim = normalize_focus_dimension(dimension);
 
-    {
-        let mut state = server_state.lock().await;
-        state.runtime = Some(LocalServerRuntime {
-            handle,
-            shutdown_tx: Some(shutdown_tx),
-        });
-    }
+    let summary_row = sqlx::query_as::<_, FocusSummaryRow>(
+        "SELECT
+            COALESCE(SUM(CASE WHEN status = 'completed' AND actual_seconds >= 60 THEN actual_seconds ELSE 0 END), 0) AS total_seconds,
+            COALESCE(SUM(CASE WHEN status = 'completed' AND actual_seconds >= 60 THEN 1 ELSE 0 END), 0) AS completed_runs,
+            COALESCE(SUM(CASE WHEN status != 'interrupted' THEN 1 ELSE 0 END), 0) AS all_runs,
+            COALESCE(SUM(CASE WHEN status = 'interrupted' THEN 1 ELSE 0 END), 0) AS interrupted_runs,
+            COALESCE(SUM(CASE WHEN status = 'completed' AND actual_seconds >= 60 THEN break_seconds ELSE 0 END), 0) AS break_seconds
+         FROM focus_runs
+         WHERE date BETWEEN ? AND ?",
+    )
+    .bind(&start)
+    .bind(&end)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to aggregate focus summary: {}", e))?;
 
-    Ok(format!("局域网共享服务已启动，端口 {}", port))
-}
+    let total_focus_minutes = summary_row.total_seconds / 60;
+    let completion_rate = if summary_row.all_runs > 0 {
+        (summary_row.completed_runs as f64 / summary_row.all_runs as f64) * 100.0
+    } else {
+        0.0
+    };
 
-// ═══════════════════════════════════════════════════════════
-// Database Initialization
-// ═══════════════════════════════════════════════════════════
+    let summary = FocusStatsSummary {
+        total_focus_minutes,
+        completed_runs: summary_row.completed_runs,
+        completion_rate,
+        interrupted_runs: summary_row.interrupted_runs,
+        total_break_minutes: summary_row.break_seconds / 60,
+    };
 
-async fn init_db(db_path: &str) -> Result<sqlx::SqlitePool, String> {
-    // Ensure parent directory exists
-    if let Some(parent) = std::path::Path::new(db_path).parent() {
-        std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create db directory: {}", e))?;
-    }
+    let slices = if dim == "template" {
+        let rows = sqlx::query_as::<_, FocusGroupedRow>(
+            "SELECT
+                COALESCE(NULLIF(ft.name, ''), '未命名模板') AS key_name,
+                COALESCE(SUM(fr.actual_seconds), 0) AS total_seconds,
+                COUNT(*) AS run_count
+             FROM focus_runs fr
+             LEFT JOIN focus_templates ft ON fr.template_id = ft.id
+             WHERE fr.date BETWEEN ? AND ?
+               AND fr.status = 'completed'
+               AND fr.actual_seconds >= 60
+             GROUP BY COALESCE(NULLIF(ft.name, ''), '未命名模板')
+             ORDER BY total_seconds DESC",
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to aggregate focus template slices: {}", e))?;
 
-    let db_url = format!("sqlite:{}?mode=rwc", db_path);
-    let pool = sqlx::sqlite::SqlitePoolOptions::new()
-        .max_connections(5)
-        .connect(&db_url)
+        rows.into_iter()
+            .map(|row| {
+                let minutes = row.total_seconds / 60;
+                let percent = if total_focus_minutes > 0 {
+                    (minutes as f64 / total_focus_minutes as f64) * 100.0
+                } else {
+                    0.0
+                };
+                FocusStatsSlice {
+                    key: row.key_name,
+                    minutes,
+                    percent,
+                    runs: row.run_count,
+                }
+            })
+            .collect::<Vec<FocusStatsSlice>>()
+    } else if dim == "timer_type" {
+        let rows = sqlx::query_as::<_, FocusGroupedRow>(
+            "SELECT
+                COALESCE(NULLIF(timer_type, ''), 'unknown') AS key_name,
+                COALESCE(SUM(actual_seconds), 0) AS total_seconds,
+                COUNT(*) AS run_count
+             FROM focus_runs
+             WHERE date BETWEEN ? AND ?
+               AND status = 'completed'
+               AND actual_seconds >= 60
+             GROUP BY COALESCE(NULLIF(timer_type, ''), 'unknown')
+             ORDER BY total_seconds DESC",
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(pool)
         .await
-        .map_err(|e| format!("Failed to connect to database: {}", e))?;
+        .map_err(|e| format!("Failed to aggregate focus timer_type slices: {}", e))?;
 
-    sqlx::query("PRAGMA foreign_keys = ON")
-        .execute(&pool)
+        rows.into_iter()
+            .map(|row| {
+                let minutes = row.total_seconds / 60;
+                let percent = if total_focus_minutes > 0 {
+                    (minutes as f64 / total_focus_minutes as f64) * 100.0
+                } else {
+                    0.0
+                };
+                FocusStatsSlice {
+                    key: row.key_name,
+                    minutes,
+                    percent,
+                    runs: row.run_count,
+                }
+            })
+            .collect::<Vec<FocusStatsSlice>>()
+    } else {
+        let rows = sqlx::query_as::<_, FocusTagRow>(
+            "SELECT tags_json, actual_seconds
+             FROM focus_runs
+             WHERE date BETWEEN ? AND ?
+               AND status = 'completed'
+               AND actual_seconds >= 60",
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(pool)
         .await
-        .map_err(|e| format!("Failed to enable foreign keys: {}", e))?;
+        .map_err(|e| format!("Failed to fetch focus run tags for aggregation: {}", e))?;
 
-    // Create tables
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS tasks (
-            id TEXT PRIMARY KEY,
-            title TEXT NOT NULL,
-            description TEXT DEFAULT '',
-            status TEXT DEFAULT 'todo',
-            priority TEXT DEFAULT 'medium',
-            date TEXT NOT NULL,
-            start_time TEXT DEFAULT '09:00',
-            duration REAL DEFAULT 1.0,
-            tags TEXT DEFAULT '',
-            repeat_type TEXT DEFAULT 'none',
-            timer_type TEXT DEFAULT 'none',
-            timer_duration INTEGER DEFAULT 25,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
-        )",
-    )
-    .execute(&pool)
-    .await
-    .map_err(|e| format!("Failed to create tasks table: {}", e))?;
+        let mut tag_map: HashMap<String, (i64, i64)> = HashMap::new();
+        for row in rows {
+            let minutes = row.actual_seconds / 60;
+            if minutes <= 0 {
+                continue;
+            }
+            let tags = parse_focus_tags(&row.tags_json);
+            for tag in tags {
+                let entry = tag_map.entry(tag).or_insert((0, 0));
+                entry.0 += minutes;
+                entry.1 += 1;
+            }
+        }
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS focus_sessions (
-            id TEXT PRIMARY KEY,
-            date TEXT NOT NULL,
-            checked_in_at TEXT,
-            checked_out_at TEXT,
-            total_focus_seconds INTEGER DEFAULT 0,
-            active_task_id TEXT
-        )",
-    )
-    .execute(&pool)
-    .await
-    .map_err(|e| format!("Failed to create focus_sessions table: {}", e))?;
+        let mut slices_vec = tag_map
+            .into_iter()
+            .map(|(key, (minutes, runs))| {
+                let percent = if total_focus_minutes > 0 {
+                    (minutes as f64 / total_focus_minutes as f64) * 100.0
+                } else {
+                    0.0
+                };
+                FocusStatsSlice {
+                    key,
+                    minutes,
+                    percent,
+                    runs,
+                }
+            })
+            .collect::<Vec<FocusStatsSlice>>();
+        slices_vec.sort_by(|a, b| b.minutes.cmp(&a.minutes));
+        slices_vec
+    };
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS focus_templates (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            timer_type TEXT NOT NULL DEFAULT 'pomodoro',
-            duration_minutes INTEGER NOT NULL DEFAULT 25,
-            tags_json TEXT NOT NULL DEFAULT '[]',
-            linked_task_title TEXT,
-            color_token TEXT,
-            is_archived INTEGER NOT NULL DEFAULT 0,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
-        )",
-    )
-    .execute(&pool)
-    .await
-    .map_err(|e| format!("Failed to create focus_templates table: {}", e))?;
+    let cycles = db_get_focus_cycle_stats(pool, &start, &end).await?;
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS focus_runs (
-            id TEXT PRIMARY KEY,
-            source TEXT NOT NULL,
-            template_id TEXT,
-            task_id TEXT,
-            timer_type TEXT NOT NULL,
-            planned_minutes INTEGER NOT NULL,
-            actual_seconds INTEGER NOT NULL DEFAULT 0,
-            status TEXT NOT NULL DEFAULT 'running',
-            started_at TEXT NOT NULL,
-            ended_at TEXT,
-            date TEXT NOT NULL,
-            tags_json TEXT NOT NULL DEFAULT '[]',
-            note TEXT,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            FOREIGN KEY(template_id) REFERENCES focus_templates(id) ON DELETE SET NULL
-        )",
-    )
-    .execute(&pool)
-    .await
-    .map_err(|e| format!("Failed to create focus_runs table: {}", e))?;
+    Ok(FocusStatsResult {
+        start_date: start,
+        end_date: end,
+        dimension: dim,
+        summary,
+        slices,
+        cycles,
+    })
+}
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS video_bookmarks (
-            id TEXT PRIMARY KEY,
-            bvid TEXT NOT NULL,
-            title TEXT NOT NULL,
-            pic TEXT DEFAULT '',
-            owner_name TEXT DEFAULT '',
-            duration INTEGER DEFAULT 0,
-            created_at TEXT NOT NULL
-        )",
+/// Splits completed runs' segment time into focus vs break minutes per pomodoro cycle.
+async fn db_get_focus_cycle_stats(
+    pool: &sqlx::SqlitePool,
+    start: &str,
+    end: &str,
+) -> Result<Vec<FocusCycleStat>, String> {
+    let segments = sqlx::query_as::<_, FocusRunSegment>(
+        "SELECT s.id, s.run_id, s.kind, s.phase, s.cycle, s.started_at, s.ended_at, s.planned_seconds
+         FROM focus_run_segments s
+         JOIN focus_runs fr ON fr.id = s.run_id
+         WHERE fr.date BETWEEN ? AND ?
+           AND fr.status = 'completed'
+           AND fr.actual_seconds >= 60
+           AND s.ended_at IS NOT NULL",
     )
-    .execute(&pool)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
     .await
-    .map_err(|e| format!("Failed to create video_bookmarks table: {}", e))?;
+    .map_err(|e| format!("Failed to fetch focus segments for cycle stats: {}", e))?;
+
+    let now = Utc::now().timestamp();
+    let mut by_cycle =
+        std::collections::BTreeMap::<i32, (i64, i64, std::collections::HashSet<String>)>::new();
+    for segment in &segments {
+        let entry = by_cycle.entry(segment.cycle.max(1)).or_default();
+        let seconds = focus_segment_seconds(segment, now);
+        if segment.kind == "break" {
+            entry.1 += seconds;
+        } else {
+            entry.0 += seconds;
+        }
+        entry.2.insert(segment.run_id.clone());
+    }
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS resources (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            path TEXT NOT NULL,
-            file_type TEXT DEFAULT 'file',
-            subject TEXT DEFAULT '',
-            size_bytes INTEGER DEFAULT 0,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        )",
+    Ok(by_cycle
+        .into_iter()
+        .map(|(cycle, (focus_seconds, break_seconds, runs))| FocusCycleStat {
+            cycle,
+            focus_minutes: focus_seconds / 60,
+            break_minutes: break_seconds / 60,
+            runs: runs.len() as i64,
+        })
+        .collect())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct FocusSeriesRow {
+    date: String,
+    source: String,
+    started_at: String,
+    actual_seconds: i64,
+    tags_json: String,
+    template_name: String,
+    timer_type: String,
+}
+
+fn focus_series_row_matches(row: &FocusSeriesRow, dimension: &str, key: Option<&str>) -> bool {
+    let Some(key) = key else {
+        return true;
+    };
+    match dimension {
+        "template" => row.template_name == key,
+        "timer_type" => row.timer_type == key,
+        _ => parse_focus_tags(&row.tags_json).iter().any(|tag| tag == key),
+    }
+}
+
+/// Completed runs of at least a minute dated `from..=to`, keyed the way `db_get_focus_stats` slices them.
+async fn db_fetch_focus_series_rows(
+    pool: &sqlx::SqlitePool,
+    from: &str,
+    to: &str,
+) -> Result<Vec<FocusSeriesRow>, String> {
+    sqlx::query_as::<_, FocusSeriesRow>(
+        "SELECT
+            fr.date AS date,
+            fr.source AS source,
+            fr.started_at AS started_at,
+            fr.actual_seconds AS actual_seconds,
+            fr.tags_json AS tags_json,
+            COALESCE(NULLIF(ft.name, ''), '未命名模板') AS template_name,
+            COALESCE(NULLIF(fr.timer_type, ''), 'unknown') AS timer_type
+         FROM focus_runs fr
+         LEFT JOIN focus_templates ft ON fr.template_id = ft.id
+         WHERE fr.status = 'completed'
+           AND fr.actual_seconds >= 60
+           AND fr.date >= ?
+           AND fr.date <= ?",
     )
-    .execute(&pool)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
     .await
-    .map_err(|e| format!("Failed to create resources table: {}", e))?;
+    .map_err(|e| format!("Failed to fetch focus runs for series: {}", e))
+}
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS questions (
-            id TEXT PRIMARY KEY,
-            subject TEXT NOT NULL,
-            type TEXT NOT NULL DEFAULT 'choice',
-            stem TEXT NOT NULL,
-            options TEXT,
-            answer TEXT NOT NULL,
-            explanation TEXT DEFAULT '',
-            source_files TEXT DEFAULT '[]',
-            difficulty INTEGER DEFAULT 2,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            next_review DATETIME DEFAULT CURRENT_TIMESTAMP,
-            review_count INTEGER DEFAULT 0,
-            correct_count INTEGER DEFAULT 0,
-            ease_factor REAL DEFAULT 2.5,
-            interval INTEGER DEFAULT 0
-        )",
-    )
-    .execute(&pool)
-    .await
-    .map_err(|e| format!("Failed to create questions table: {}", e))?;
+/// Spreads a run's focus seconds over local hours of day, starting from when it began.
+fn spread_focus_by_hour(started_at: &str, seconds: i64, hours: &mut [i64; 24]) {
+    use chrono::Timelike;
+    let Ok(start) = chrono::DateTime::parse_from_rfc3339(started_at) else {
+        return;
+    };
+    let mut cursor = start.with_timezone(&Local);
+    let mut remaining = seconds;
+    while remaining > 0 {
+        let into_hour = i64::from(cursor.minute() * 60 + cursor.second());
+        let chunk = (3600 - into_hour).min(remaining);
+        hours[cursor.hour() as usize] += chunk;
+        remaining -= chunk;
+        cursor += chrono::Duration::seconds(chunk);
+    }
+}
+
+fn focus_day_points(
+    by_day: &HashMap<String, (i64, i64)>,
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+) -> Vec<FocusDayPoint> {
+    let mut points = Vec::new();
+    let mut day = start;
+    while day <= end {
+        let date = day.format("%Y-%m-%d").to_string();
+        let (seconds, runs) = by_day.get(&date).copied().unwrap_or((0, 0));
+        points.push(FocusDayPoint {
+            date,
+            minutes: seconds / 60,
+            runs,
+        });
+        day += chrono::Duration::days(1);
+    }
+    points
+}
 
-    let question_columns: Vec<String> = sqlx::query_scalar::<_, String>(
-        "SELECT name FROM pragma_table_info('questions')",
-    )
-    .fetch_all(&pool)
-    .await
-    .map_err(|e| format!("Failed to inspect questions columns: {}", e))?;
+fn sum_focus_days(
+    by_day: &HashMap<String, (i64, i64)>,
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+) -> i64 {
+    focus_day_points(by_day, start, end)
+        .iter()
+        .map(|p| p.minutes)
+        .sum()
+}
 
-    if !question_columns.iter().any(|col| col == "interval") {
-        sqlx::query("ALTER TABLE questions ADD COLUMN interval INTEGER DEFAULT 0")
-            .execute(&pool)
-            .await
-            .map_err(|e| format!("Failed to add questions.interval column: {}", e))?;
+// How far back before end_date streaks are looked for; bounds the timeseries query.
+const FOCUS_STREAK_LOOKBACK_DAYS: i64 = 366;
+
+fn compute_focus_streak(
+    by_day: &HashMap<String, (i64, i64)>,
+    end: chrono::NaiveDate,
+    threshold_minutes: i64,
+) -> FocusStreak {
+    let earliest = end - chrono::Duration::days(FOCUS_STREAK_LOOKBACK_DAYS);
+    let mut qualifying: Vec<chrono::NaiveDate> = by_day
+        .iter()
+        .filter(|(_, (seconds, _))| seconds / 60 >= threshold_minutes)
+        .filter_map(|(date, _)| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .filter(|date| *date >= earliest && *date <= end)
+        .collect();
+    qualifying.sort();
+    let qualifying_set: std::collections::HashSet<chrono::NaiveDate> = qualifying.iter().copied().collect();
+
+    let mut longest_days = 0i64;
+    let mut longest_range: Option<(chrono::NaiveDate, chrono::NaiveDate)> = None;
+    let mut run_start: Option<chrono::NaiveDate> = None;
+    let mut prev: Option<chrono::NaiveDate> = None;
+    for day in &qualifying {
+        let continues = prev.map(|p| *day - p == chrono::Duration::days(1)).unwrap_or(false);
+        if !continues {
+            run_start = Some(*day);
+        }
+        let start = run_start.unwrap_or(*day);
+        let length = (*day - start).num_days() + 1;
+        if length > longest_days {
+            longest_days = length;
+            longest_range = Some((start, *day));
+        }
+        prev = Some(*day);
     }
 
-    if !question_columns.iter().any(|col| col == "next_review") {
-        sqlx::query("ALTER TABLE questions ADD COLUMN next_review DATETIME DEFAULT CURRENT_TIMESTAMP")
-            .execute(&pool)
-            .await
-            .map_err(|e| format!("Failed to add questions.next_review column: {}", e))?;
+    // Today may still be in progress, so a streak ending yesterday is still current.
+    let mut cursor = end;
+    if !qualifying_set.contains(&cursor) {
+        cursor -= chrono::Duration::days(1);
+    }
+    let mut current_days = 0i64;
+    while qualifying_set.contains(&cursor) {
+        current_days += 1;
+        cursor -= chrono::Duration::days(1);
     }
 
-    sqlx::query("UPDATE questions SET next_review = CURRENT_TIMESTAMP WHERE next_review IS NULL")
-        .execute(&pool)
+    FocusStreak {
+        threshold_minutes,
+        current_days,
+        longest_days,
+        longest_start: longest_range.map(|(s, _)| s.format("%Y-%m-%d").to_string()),
+        longest_end: longest_range.map(|(_, e)| e.format("%Y-%m-%d").to_string()),
+    }
+}
+
+async fn db_get_focus_timeseries(
+    pool: &sqlx::SqlitePool,
+    query: &FocusTimeseriesQuery,
+) -> Result<FocusTimeseriesResult, String> {
+    let (start, end) = resolve_focus_range(query.start_date.as_deref(), query.end_date.as_deref())?;
+    let dim = normalize_focus_dimension(query.dimension.as_deref());
+    let key = query
+        .key
+        .as_deref()
+        .map(|k| k.trim())
+        .filter(|k| !k.is_empty())
+        .map(|k| k.to_string());
+    let threshold_minutes = query.threshold_minutes.unwrap_or(25).max(1);
+
+    let start_day = chrono::NaiveDate::parse_from_str(&start, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid start_date: {}", e))?;
+    let end_day = chrono::NaiveDate::parse_from_str(&end, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid end_date: {}", e))?;
+    let year = query.year.unwrap_or_else(|| end_day.year());
+    let year_start = chrono::NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| format!("Invalid year: {}", year))?;
+    let year_end = chrono::NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| format!("Invalid year: {}", year))?;
+    let fetch_until = end_day.max(year_end).format("%Y-%m-%d").to_string();
+    // Range, heatmap year, the previous week for week-over-week, and the streak look-back.
+    let fetch_from = [
+        start_day,
+        year_start,
+        week_bounds(end_day).0 - chrono::Duration::days(7),
+        end_day - chrono::Duration::days(FOCUS_STREAK_LOOKBACK_DAYS),
+    ]
+    .into_iter()
+    .min()
+    .unwrap_or(start_day)
+    .format("%Y-%m-%d")
+    .to_string();
+
+    let rows = db_fetch_focus_series_rows(pool, &fetch_from, &fetch_until).await?;
+
+    let mut by_day: HashMap<String, (i64, i64)> = HashMap::new();
+    let mut hour_seconds = [0i64; 24];
+    for row in rows
+        .iter()
+        .filter(|row| focus_series_row_matches(row, &dim, key.as_deref()))
+    {
+        let entry = by_day.entry(row.date.clone()).or_insert((0, 0));
+        entry.0 += row.actual_seconds;
+        entry.1 += 1;
+        // Migrated sessions only know a check-in time (or just the day), not when the focus happened.
+        if row.date >= start && row.date <= end && row.source != "legacy_session" {
+            spread_focus_by_hour(&row.started_at, row.actual_seconds, &mut hour_seconds);
+        }
+    }
+
+    let hourly: Vec<FocusHourPoint> = hour_seconds
+        .iter()
+        .enumerate()
+        .map(|(hour, seconds)| FocusHourPoint {
+            hour: hour as u32,
+            minutes: seconds / 60,
+        })
+        .collect();
+    let mut best_hours: Vec<FocusHourPoint> =
+        hourly.iter().filter(|h| h.minutes > 0).cloned().collect();
+    best_hours.sort_by(|a, b| b.minutes.cmp(&a.minutes).then(a.hour.cmp(&b.hour)));
+    best_hours.truncate(3);
+
+    let (week_start, _) = week_bounds(end_day);
+    let previous_start = week_start - chrono::Duration::days(7);
+    let previous_end = end_day - chrono::Duration::days(7);
+    let minutes = sum_focus_days(&by_day, week_start, end_day);
+    let previous_minutes = sum_focus_days(&by_day, previous_start, previous_end);
+    let week_over_week = FocusWeekComparison {
+        week_start: week_start.format("%Y-%m-%d").to_string(),
+        week_end: end.clone(),
+        minutes,
+        previous_week_start: previous_start.format("%Y-%m-%d").to_string(),
+        previous_week_end: previous_end.format("%Y-%m-%d").to_string(),
+        previous_minutes,
+        change_percent: if previous_minutes > 0 {
+            Some((minutes - previous_minutes) as f64 / previous_minutes as f64 * 100.0)
+        } else {
+            None
+        },
+    };
+
+    Ok(FocusTimeseriesResult {
+        daily: focus_day_points(&by_day, start_day, end_day),
+        heatmap: focus_day_points(&by_day, year_start, year_end),
+        streak: compute_focus_streak(&by_day, end_day, threshold_minutes),
+        start_date: start,
+        end_date: end,
+        dimension: dim,
+        key,
+        hourly,
+        heatmap_year: year,
+        best_hours,
+        week_over_week,
+    })
+}
+
+fn normalize_focus_goal_payload(payload: &FocusGoalPayload) -> Result<(String, String, String), String> {
+    let target_key = payload.target_key.trim();
+    if target_key.is_empty() {
+        return Err("target_key 不能为空".to_string());
+    }
+    let period = payload.period.trim().to_lowercase();
+    if period != "day" && period != "week" {
+        return Err("period 仅支持 day 或 week".to_string());
+    }
+    if payload.target_minutes <= 0 {
+        return Err("target_minutes 必须大于 0".to_string());
+    }
+    Ok((
+        normalize_focus_dimension(payload.dimension.as_deref()),
+        target_key.to_string(),
+        period,
+    ))
+}
+
+async fn db_get_focus_goals(
+    pool: &sqlx::SqlitePool,
+    include_inactive: bool,
+) -> Result<Vec<FocusGoal>, String> {
+    let sql = if include_inactive {
+        "SELECT id, dimension, target_key, period, target_minutes, is_active, last_reached_period, created_at, updated_at FROM focus_goals ORDER BY created_at ASC"
+    } else {
+        "SELECT id, dimension, target_key, period, target_minutes, is_active, last_reached_period, created_at, updated_at FROM focus_goals WHERE is_active = 1 ORDER BY created_at ASC"
+    };
+    sqlx::query_as::<_, FocusGoal>(sql)
+        .fetch_all(pool)
         .await
-        .ok();
+        .map_err(|e| format!("Failed to fetch focus goals: {}", e))
+}
 
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS ai_sessions (
-            id TEXT PRIMARY KEY,
-            title TEXT NOT NULL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
-        )",
+async fn db_get_focus_goal(pool: &sqlx::SqlitePool, id: &str) -> Result<FocusGoal, String> {
+    sqlx::query_as::<_, FocusGoal>(
+        "SELECT id, dimension, target_key, period, target_minutes, is_active, last_reached_period, created_at, updated_at FROM focus_goals WHERE id = ?",
     )
-    .execute(&pool)
+    .bind(id)
+    .fetch_optional(pool)
     .await
-    .map_err(|e| format!("Failed to create ai_sessions table: {}", e))?;
+    .map_err(|e| format!("Failed to fetch focus goal: {}", e))?
+    .ok_or_else(|| "Focus goal not found".to_string())
+}
 
+async fn db_create_focus_goal(
+    pool: &sqlx::SqlitePool,
+    payload: &FocusGoalPayload,
+) -> Result<FocusGoal, String> {
+    let (dimension, target_key, period) = normalize_focus_goal_payload(payload)?;
+    let id = gen_focus_id("focus-goal");
+    let now = now_iso();
     sqlx::query(
-        "CREATE TABLE IF NOT EXISTS ai_messages (
-            id TEXT PRIMARY KEY,
-            session_id TEXT NOT NULL,
-            role TEXT NOT NULL,
-            content TEXT NOT NULL,
-            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-            FOREIGN KEY(session_id) REFERENCES ai_sessions(id) ON DELETE CASCADE
-        )",
+        "INSERT INTO focus_goals (id, dimension, target_key, period, target_minutes, is_active, last_reached_period, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, NULL, ?, ?)",
     )
-    .execute(&pool)
+    .bind(&id)
+    .bind(&dimension)
+    .bind(&target_key)
+    .bind(&period)
+    .bind(payload.target_minutes)
+    .bind(payload.is_active.unwrap_or(true))
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
     .await
-    .map_err(|e| format!("Failed to create ai_messages table: {}", e))?;
+    .map_err(|e| format!("Failed to create focus goal: {}", e))?;
+    db_get_focus_goal(pool, &id).await
+}
 
+async fn db_update_focus_goal(
+    pool: &sqlx::SqlitePool,
+    id: &str,
+    payload: &FocusGoalPayload,
+) -> Result<FocusGoal, String> {
+    let (dimension, target_key, period) = normalize_focus_goal_payload(payload)?;
+    let previous = db_get_focus_goal(pool, id).await?;
+    // A changed target may not be reached yet for this period, so allow a fresh reached event.
+    let target_changed = previous.dimension != dimension
+        || previous.target_key != target_key
+        || previous.period != period
+        || previous.target_minutes != payload.target_minutes;
     sqlx::query(
-        "CREATE TABLE IF NOT EXISTS wrong_questions (
-            id TEXT PRIMARY KEY,
-            subject TEXT NOT NULL DEFAULT '其他',
-            tags_json TEXT NOT NULL DEFAULT '[]',
-            question_content TEXT NOT NULL,
-            question_image_path TEXT,
-            ai_solution TEXT NOT NULL,
-            user_note TEXT,
-            source TEXT NOT NULL DEFAULT 'ai_chat',
-            ai_session_id TEXT,
-            ai_message_ids_json TEXT,
-            difficulty INTEGER NOT NULL DEFAULT 3,
-            mastery_level INTEGER NOT NULL DEFAULT 0,
-            review_count INTEGER NOT NULL DEFAULT 0,
-            next_review_date TEXT,
-            last_review_date TEXT,
-            ease_factor REAL NOT NULL DEFAULT 2.5,
-            interval_days INTEGER NOT NULL DEFAULT 1,
-            is_archived INTEGER NOT NULL DEFAULT 0,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            FOREIGN KEY(ai_session_id) REFERENCES ai_sessions(id) ON DELETE SET NULL
-        )",
+        "UPDATE focus_goals SET dimension = ?, target_key = ?, period = ?, target_minutes = ?, is_active = ?, last_reached_period = ?, updated_at = ? WHERE id = ?",
     )
-    .execute(&pool)
+    .bind(&dimension)
+    .bind(&target_key)
+    .bind(&period)
+    .bind(payload.target_minutes)
+    .bind(payload.is_active.unwrap_or(previous.is_active))
+    .bind(if target_changed { None } else { previous.last_reached_period.clone() })
+    .bind(now_iso())
+    .bind(id)
+    .execute(pool)
     .await
-    .map_err(|e| format!("Failed to create wrong_questions table: {}", e))?;
+    .map_err(|e| format!("Failed to update focus goal: {}", e))?;
+    db_get_focus_goal(pool, id).await
+}
+
+async fn db_delete_focus_goal(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
+    let result = sqlx::query("DELETE FROM focus_goals WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete focus goal: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err("Focus goal not found".to_string());
+    }
+    Ok(())
+}
+
+fn focus_goal_period_bounds(
+    period: &str,
+    date: chrono::NaiveDate,
+) -> (chrono::NaiveDate, chrono::NaiveDate) {
+    if period == "week" {
+        week_bounds(date)
+    } else {
+        (date, date)
+    }
+}
+
+// Past periods scored for a goal's hit rate: (days per period, periods).
+fn focus_goal_history(period: &str) -> (i64, usize) {
+    if period == "week" {
+        (7, 8)
+    } else {
+        (1, 14)
+    }
+}
+
+/// Minutes for the goal's key in a period, rounded the same way as `db_get_focus_stats` slices:
+/// per run for tags, over the summed seconds for templates and timer types.
+fn focus_goal_minutes(
+    rows: &[FocusSeriesRow],
+    goal: &FocusGoal,
+    start: chrono::NaiveDate,
+    end: chrono::NaiveDate,
+) -> i64 {
+    let start = start.format("%Y-%m-%d").to_string();
+    let end = end.format("%Y-%m-%d").to_string();
+    let matching = rows.iter().filter(|row| {
+        row.date >= start
+            && row.date <= end
+            && focus_series_row_matches(row, &goal.dimension, Some(&goal.target_key))
+    });
+    if goal.dimension == "template" || goal.dimension == "timer_type" {
+        matching.map(|row| row.actual_seconds).sum::<i64>() / 60
+    } else {
+        matching.map(|row| row.actual_seconds / 60).sum()
+    }
+}
+
+fn compute_focus_goal_progress(
+    rows: &[FocusSeriesRow],
+    goal: FocusGoal,
+    date: chrono::NaiveDate,
+) -> FocusGoalProgress {
+    let (start, end) = focus_goal_period_bounds(&goal.period, date);
+    let achieved_minutes = focus_goal_minutes(rows, &goal, start, end);
+
+    let created = chrono::DateTime::parse_from_rfc3339(&goal.created_at)
+        .map(|dt| dt.with_timezone(&Local).date_naive())
+        .unwrap_or(start);
+    let (step, max_periods) = focus_goal_history(&goal.period);
+    let mut hits = 0i64;
+    let mut evaluated_periods = 0i64;
+    let mut cursor = start - chrono::Duration::days(step);
+    for _ in 0..max_periods {
+        let (past_start, past_end) = focus_goal_period_bounds(&goal.period, cursor);
+        if past_end < created {
+            break;
+        }
+        evaluated_periods += 1;
+        if focus_goal_minutes(rows, &goal, past_start, past_end) >= goal.target_minutes {
+            hits += 1;
+        }
+        cursor -= chrono::Duration::days(step);
+    }
+
+    FocusGoalProgress {
+        period_start: start.format("%Y-%m-%d").to_string(),
+        period_end: end.format("%Y-%m-%d").to_string(),
+        achieved_minutes,
+        percent: achieved_minutes as f64 / goal.target_minutes as f64 * 100.0,
+        remaining_minutes: (goal.target_minutes - achieved_minutes).max(0),
+        reached: achieved_minutes >= goal.target_minutes,
+        hit_rate: if evaluated_periods > 0 {
+            Some(hits as f64 / evaluated_periods as f64)
+        } else {
+            None
+        },
+        evaluated_periods,
+        goal,
+    }
+}
+
+async fn db_get_focus_goal_progress(
+    pool: &sqlx::SqlitePool,
+    date: Option<&str>,
+) -> Result<Vec<FocusGoalProgress>, String> {
+    let date = match parse_filter_date("date", date)? {
+        Some(d) => chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date: {}", e))?,
+        None => Local::now().date_naive(),
+    };
+    let goals = db_get_focus_goals(pool, false).await?;
+    let Some(from) = goals
+        .iter()
+        .map(|goal| {
+            let (step, max_periods) = focus_goal_history(&goal.period);
+            focus_goal_period_bounds(&goal.period, date).0 - chrono::Duration::days(step * max_periods as i64)
+        })
+        .min()
+    else {
+        return Ok(Vec::new());
+    };
+    let to = goals
+        .iter()
+        .map(|goal| focus_goal_period_bounds(&goal.period, date).1)
+        .max()
+        .unwrap_or(date);
+    // One read covers every goal's current period and the history behind its hit rate.
+    let rows = db_fetch_focus_series_rows(
+        pool,
+        &from.format("%Y-%m-%d").to_string(),
+        &to.format("%Y-%m-%d").to_string(),
+    )
+    .await?;
+    Ok(goals
+        .into_iter()
+        .map(|goal| compute_focus_goal_progress(&rows, goal, date))
+        .collect())
+}
+
+/// Active goals that became reached in the current period and have not been announced yet.
+async fn db_collect_reached_focus_goals(
+    pool: &sqlx::SqlitePool,
+) -> Result<Vec<FocusGoalProgress>, String> {
+    let mut reached = Vec::new();
+    for progress in db_get_focus_goal_progress(pool, None).await? {
+        if !progress.reached
+            || progress.goal.last_reached_period.as_deref() == Some(progress.period_start.as_str())
+        {
+            continue;
+        }
+        sqlx::query("UPDATE focus_goals SET last_reached_period = ? WHERE id = ?")
+            .bind(&progress.period_start)
+            .bind(&progress.goal.id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to mark focus goal reached: {}", e))?;
+        reached.push(progress);
+    }
+    Ok(reached)
+}
+
+fn emit_focus_goals_reached(sync_hub: &SyncHub, reached: &[FocusGoalProgress]) {
+    for progress in reached {
+        emit_sync_event(
+            sync_hub,
+            "FOCUS_GOAL_REACHED",
+            serde_json::to_value(progress).unwrap_or_default(),
+        );
+    }
+}
+
+const FOCUS_INTERRUPTION_CATEGORIES: [&str; 4] = ["phone", "person", "self", "other"];
+
+fn normalize_interruption_category(category: Option<&str>) -> Result<String, String> {
+    let category = category
+        .map(|c| c.trim().to_lowercase())
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| "other".to_string());
+    if !FOCUS_INTERRUPTION_CATEGORIES.contains(&category.as_str()) {
+        return Err("category 仅支持 phone / person / self / other".to_string());
+    }
+    Ok(category)
+}
 
+/// Logs an interruption against `run_id`, or against the active run when no id is given
+/// so the phone can record one with a single tap.
+async fn db_log_focus_interruption(
+    pool: &sqlx::SqlitePool,
+    run_id: Option<&str>,
+    payload: &LogFocusInterruptionPayload,
+) -> Result<FocusInterruption, String> {
+    let category = normalize_interruption_category(payload.category.as_deref())?;
+    let occurred_at = match payload.occurred_at.as_deref().map(str::trim) {
+        Some(ts) if !ts.is_empty() => {
+            chrono::DateTime::parse_from_rfc3339(ts)
+                .map_err(|e| format!("Invalid occurred_at, expected RFC3339: {}", e))?;
+            ts.to_string()
+        }
+        _ => now_iso(),
+    };
+
+    let run_id = match run_id {
+        Some(id) => sqlx::query_scalar::<_, String>("SELECT id FROM focus_runs WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to fetch focus run: {}", e))?
+            .ok_or_else(|| "Focus run not found".to_string())?,
+        None => db_get_active_focus_run(pool)
+            .await?
+            .map(|state| state.run.id)
+            .ok_or_else(|| "Active focus run not found".to_string())?,
+    };
+
+    let interruption = FocusInterruption {
+        id: gen_unique_id("focus-int"),
+        run_id,
+        category,
+        note: payload
+            .note
+            .as_deref()
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(str::to_string),
+        occurred_at,
+        created_at: now_iso(),
+    };
     sqlx::query(
-        "CREATE TABLE IF NOT EXISTS weekly_review_items (
-            id TEXT PRIMARY KEY,
-            week_start TEXT NOT NULL,
-            week_end TEXT NOT NULL,
-            wrong_question_id TEXT NOT NULL,
-            title_snapshot TEXT NOT NULL,
-            status TEXT NOT NULL DEFAULT 'pending',
-            carried_from_week TEXT,
-            completed_at TEXT,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            FOREIGN KEY(wrong_question_id) REFERENCES wrong_questions(id) ON DELETE CASCADE
-        )",
+        "INSERT INTO focus_interruptions (id, run_id, category, note, occurred_at, created_at) VALUES (?, ?, ?, ?, ?, ?)",
     )
-    .execute(&pool)
+    .bind(&interruption.id)
+    .bind(&interruption.run_id)
+    .bind(&interruption.category)
+    .bind(&interruption.note)
+    .bind(&interruption.occurred_at)
+    .bind(&interruption.created_at)
+    .execute(pool)
     .await
-    .map_err(|e| format!("Failed to create weekly_review_items table: {}", e))?;
+    .map_err(|e| format!("Failed to log focus interruption: {}", e))?;
+    Ok(interruption)
+}
 
-    sqlx::query("ALTER TABLE ai_messages ADD COLUMN image_path TEXT")
-        .execute(&pool)
-        .await
-        .ok();
+async fn db_get_focus_interruptions(
+    pool: &sqlx::SqlitePool,
+    run_id: &str,
+) -> Result<Vec<FocusInterruption>, String> {
+    sqlx::query_as::<_, FocusInterruption>(
+        "SELECT id, run_id, category, note, occurred_at, created_at FROM focus_interruptions WHERE run_id = ? ORDER BY occurred_at ASC",
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch focus interruptions: {}", e))
+}
 
-    // Create index for date-based queries
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tasks_date ON tasks(date)")
-        .execute(&pool)
-        .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_sessions_date ON focus_sessions(date)")
-        .execute(&pool)
-        .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_templates_archived ON focus_templates(is_archived)")
-        .execute(&pool)
-        .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_templates_updated_at ON focus_templates(updated_at DESC)")
-        .execute(&pool)
-        .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_runs_date ON focus_runs(date)")
-        .execute(&pool)
-        .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_runs_status ON focus_runs(status)")
-        .execute(&pool)
+async fn db_delete_focus_interruption(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
+    let result = sqlx::query("DELETE FROM focus_interruptions WHERE id = ?")
+        .bind(id)
+        .execute(pool)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_runs_template_id ON focus_runs(template_id)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to delete focus interruption: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err("Focus interruption not found".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct FocusInterruptionRow {
+    run_id: String,
+    category: String,
+    occurred_at: String,
+    tags_json: String,
+}
+
+fn sorted_interruption_counts(counts: HashMap<String, i64>) -> Vec<FocusInterruptionCount> {
+    let mut rows = counts
+        .into_iter()
+        .map(|(key, count)| FocusInterruptionCount { key, count })
+        .collect::<Vec<_>>();
+    rows.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.key.cmp(&b.key)));
+    rows
+}
+
+/// Interruption counts by local hour of day, run tag and category for runs dated in the range.
+async fn db_get_focus_interruption_stats(
+    pool: &sqlx::SqlitePool,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<FocusInterruptionStats, String> {
+    use chrono::Timelike;
+    let (start, end) = resolve_focus_range(start_date, end_date)?;
+    let rows = sqlx::query_as::<_, FocusInterruptionRow>(
+        "SELECT fi.run_id, fi.category, fi.occurred_at, fr.tags_json
+         FROM focus_interruptions fi
+         JOIN focus_runs fr ON fr.id = fi.run_id
+         WHERE fr.date BETWEEN ? AND ?",
+    )
+    .bind(&start)
+    .bind(&end)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch focus interruptions for stats: {}", e))?;
+
+    let mut hours = [0i64; 24];
+    let mut by_tag: HashMap<String, i64> = HashMap::new();
+    let mut by_category: HashMap<String, i64> = HashMap::new();
+    let mut runs = std::collections::HashSet::new();
+    for row in &rows {
+        if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(&row.occurred_at) {
+            hours[ts.with_timezone(&Local).hour() as usize] += 1;
+        }
+        for tag in parse_focus_tags(&row.tags_json) {
+            *by_tag.entry(tag).or_insert(0) += 1;
+        }
+        *by_category.entry(row.category.clone()).or_insert(0) += 1;
+        runs.insert(row.run_id.as_str());
+    }
+
+    Ok(FocusInterruptionStats {
+        start_date: start,
+        end_date: end,
+        total: rows.len() as i64,
+        interrupted_run_count: runs.len() as i64,
+        by_hour: hours
+            .iter()
+            .enumerate()
+            .map(|(hour, count)| FocusInterruptionHourCount {
+                hour: hour as u32,
+                count: *count,
+            })
+            .collect(),
+        by_tag: sorted_interruption_counts(by_tag),
+        by_category: sorted_interruption_counts(by_category),
+    })
+}
+
+fn make_title_snapshot(content: &str, ai_solution: &str) -> String {
+    let summary_line = ai_solution
+        .lines()
+        .map(|line| line.trim())
+        .find(|line| {
+            !line.is_empty()
+                && (line.starts_with("题型总结:")
+                    || line.starts_with("题型总结：")
+                    || line.starts_with("题型:")
+                    || line.starts_with("题型：")
+                    || line.starts_with("知识点:")
+                    || line.starts_with("知识点："))
+        })
+        .map(|line| {
+            line.trim_start_matches("题型总结:")
+                .trim_start_matches("题型总结：")
+                .trim_start_matches("题型:")
+                .trim_start_matches("题型：")
+                .trim_start_matches("知识点:")
+                .trim_start_matches("知识点：")
+                .trim()
+                .to_string()
+        })
+        .filter(|line| !line.is_empty());
+
+    let first_line = summary_line.unwrap_or_else(|| {
+        content
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("未命名错题")
+            .trim()
+            .to_string()
+    });
+
+    let mut title = first_line
+        .replace("[公式]", " ")
+        .replace("\\(", " ")
+        .replace("\\)", " ")
+        .replace("\\[", " ")
+        .replace("\\]", " ")
+        .replace("$$", " ")
+        .replace('$', " ");
+
+    if let Some(idx) = title.find("选项") {
+        title = title[..idx].trim().to_string();
+    }
+
+    title = title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if title.chars().count() > 50 {
+        title = title.chars().take(50).collect::<String>();
+    }
+
+    if title.is_empty() {
+        title = "未命名错题".to_string();
+    }
+    title
+}
+
+const WRONG_QUESTION_ERROR_CAUSES: [&str; 5] =
+    ["concept_gap", "careless", "calculation", "misread", "time_pressure"];
+
+/// Validates the cause keys and returns them as a de-duplicated JSON array.
+fn normalize_error_causes_json(raw: &str) -> Result<String, String> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok("[]".to_string());
+    }
+    let causes = serde_json::from_str::<Vec<String>>(raw)
+        .map_err(|_| "error_causes_json 必须是字符串数组".to_string())?;
+    let mut normalized: Vec<String> = Vec::new();
+    for cause in causes.iter().map(|c| c.trim()) {
+        if !WRONG_QUESTION_ERROR_CAUSES.contains(&cause) {
+            return Err(format!(
+                "未知错因: {}（可选 {}）",
+                cause,
+                WRONG_QUESTION_ERROR_CAUSES.join(" / ")
+            ));
+        }
+        if !normalized.iter().any(|c| c == cause) {
+            normalized.push(cause.to_string());
+        }
+    }
+    Ok(serde_json::to_string(&normalized).unwrap_or_else(|_| "[]".to_string()))
+}
+
+async fn db_get_wrong_questions(
+    pool: &sqlx::SqlitePool,
+    filter: &WrongQuestionFilter,
+) -> Result<Vec<WrongQuestion>, String> {
+    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+        "SELECT id, subject, tags_json, question_content, question_image_path, ai_solution, user_note, source, ai_session_id, ai_message_ids_json, difficulty, mastery_level, review_count, next_review_date, last_review_date, ease_factor, interval_days, is_archived, created_at, updated_at, error_causes_json FROM wrong_questions WHERE 1=1",
+    );
+
+    builder.push(" AND is_archived = ");
+    builder.push_bind(filter.is_archived.unwrap_or(0));
+
+    if let Some(subject) = filter.subject.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        builder.push(" AND subject = ");
+        builder.push_bind(subject);
+    }
+
+    if let Some(level) = filter.mastery_level {
+        builder.push(" AND mastery_level = ");
+        builder.push_bind(level);
+    }
+
+    if let Some(cause) = filter.error_cause.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        builder.push(" AND EXISTS (SELECT 1 FROM json_each(CASE WHEN json_valid(error_causes_json) THEN error_causes_json ELSE '[]' END) WHERE value = ");
+        builder.push_bind(cause);
+        builder.push(")");
+    }
+
+    if let Some(keyword) = filter.search_keyword.as_ref().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let like = format!("%{}%", keyword);
+        builder.push(" AND (question_content LIKE ");
+        builder.push_bind(like.clone());
+        builder.push(" OR ai_solution LIKE ");
+        builder.push_bind(like.clone());
+        builder.push(" OR COALESCE(user_note,'') LIKE ");
+        builder.push_bind(like);
+        builder.push(")");
+    }
+
+    builder.push(" ORDER BY created_at DESC");
+
+    builder
+        .build_query_as::<WrongQuestion>()
+        .fetch_all(pool)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_video_bookmarks_created_at ON video_bookmarks(created_at DESC)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to fetch wrong questions: {}", e))
+}
+
+/// Checks for likely duplicates first and only saves when there are none or
+/// `allow_duplicate` is set; otherwise the result carries the duplicates and no question.
+async fn db_create_wrong_question(
+    pool: &sqlx::SqlitePool,
+    input: &WrongQuestion,
+    allow_duplicate: bool,
+) -> Result<WrongQuestionCreateResult, String> {
+    let mut input = input.clone();
+    input.error_causes_json = Some(normalize_error_causes_json(input.error_causes_json.as_deref().unwrap_or(""))?);
+    let duplicates = db_find_duplicate_wrong_questions(
+        pool,
+        &input.question_content,
+        Some(&input.id),
+        Some(&input.subject),
+    )
+    .await?;
+    if !duplicates.is_empty() && !allow_duplicate {
+        return Ok(WrongQuestionCreateResult { question: None, duplicates });
+    }
+
+    sqlx::query(
+        "INSERT INTO wrong_questions (id, subject, tags_json, question_content, question_image_path, ai_solution, user_note, source, ai_session_id, ai_message_ids_json, difficulty, mastery_level, review_count, next_review_date, last_review_date, ease_factor, interval_days, is_archived, created_at, updated_at, content_fingerprint, error_causes_json)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&input.id)
+    .bind(&input.subject)
+    .bind(&input.tags_json)
+    .bind(&input.question_content)
+    .bind(&input.question_image_path)
+    .bind(&input.ai_solution)
+    .bind(&input.user_note)
+    .bind(&input.source)
+    .bind(&input.ai_session_id)
+    .bind(&input.ai_message_ids_json)
+    .bind(input.difficulty)
+    .bind(input.mastery_level)
+    .bind(input.review_count)
+    .bind(&input.next_review_date)
+    .bind(&input.last_review_date)
+    .bind(input.ease_factor)
+    .bind(input.interval_days)
+    .bind(input.is_archived)
+    .bind(&input.created_at)
+    .bind(&input.updated_at)
+    .bind(question_fingerprint(&input.question_content))
+    .bind(&input.error_causes_json)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create wrong question: {}", e))?;
+
+    Ok(WrongQuestionCreateResult { question: Some(input), duplicates })
+}
+
+async fn db_update_wrong_question(
+    pool: &sqlx::SqlitePool,
+    input: &WrongQuestion,
+) -> Result<WrongQuestion, String> {
+    let error_causes_json = input
+        .error_causes_json
+        .as_deref()
+        .map(normalize_error_causes_json)
+        .transpose()?;
+
+    let result = sqlx::query(
+        "UPDATE wrong_questions SET
+           subject = ?,
+           tags_json = ?,
+           question_content = ?,
+           question_image_path = ?,
+           ai_solution = ?,
+           user_note = ?,
+           source = ?,
+           ai_session_id = ?,
+           ai_message_ids_json = ?,
+           difficulty = ?,
+           mastery_level = ?,
+           review_count = ?,
+           next_review_date = ?,
+           last_review_date = ?,
+           ease_factor = ?,
+           interval_days = ?,
+           is_archived = ?,
+           updated_at = ?,
+           content_fingerprint = ?,
+           error_causes_json = COALESCE(?, error_causes_json)
+         WHERE id = ?",
+    )
+    .bind(&input.subject)
+    .bind(&input.tags_json)
+    .bind(&input.question_content)
+    .bind(&input.question_image_path)
+    .bind(&input.ai_solution)
+    .bind(&input.user_note)
+    .bind(&input.source)
+    .bind(&input.ai_session_id)
+    .bind(&input.ai_message_ids_json)
+    .bind(input.difficulty)
+    .bind(input.mastery_level)
+    .bind(input.review_count)
+    .bind(&input.next_review_date)
+    .bind(&input.last_review_date)
+    .bind(input.ease_factor)
+    .bind(input.interval_days)
+    .bind(input.is_archived)
+    .bind(&input.updated_at)
+    .bind(question_fingerprint(&input.question_content))
+    .bind(&error_causes_json)
+    .bind(&input.id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update wrong question: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err("Wrong question not found".to_string());
+    }
+
+    db_get_wrong_question(pool, &input.id).await
+}
+
+async fn db_archive_wrong_question(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
+    let result = sqlx::query(
+        "UPDATE wrong_questions SET is_archived = 1, updated_at = ? WHERE id = ?",
+    )
+    .bind(now_iso())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to archive wrong question: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err("Wrong question not found".to_string());
+    }
+
+    sqlx::query("DELETE FROM weekly_review_items WHERE wrong_question_id = ?")
+        .bind(id)
+        .execute(pool)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_resources_subject ON resources(subject)")
-        .execute(&pool)
-        .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_questions_subject ON questions(subject)")
-        .execute(&pool)
-        .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_questions_next_review ON questions(next_review)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to cleanup weekly review items on archive: {}", e))?;
+
+    Ok(())
+}
+
+async fn db_delete_wrong_question(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
+    let result = sqlx::query("DELETE FROM wrong_questions WHERE id = ?")
+        .bind(id)
+        .execute(pool)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_ai_sessions_updated_at ON ai_sessions(updated_at DESC)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to delete wrong question: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err("Wrong question not found".to_string());
+    }
+
+    sqlx::query("DELETE FROM knowledge_node_items WHERE item_type = 'wrong_question' AND item_id = ?")
+        .bind(id)
+        .execute(pool)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_ai_messages_session_id ON ai_messages(session_id)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to cleanup knowledge assignments: {}", e))?;
+
+    Ok(())
+}
+
+// Candidates at or above this Jaccard similarity are reported as likely duplicates.
+const WRONG_QUESTION_DUPLICATE_THRESHOLD: f64 = 0.6;
+const WRONG_QUESTION_DUPLICATE_LIMIT: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WrongQuestionDuplicate {
+    pub question: WrongQuestion,
+    pub similarity: f64, // 0–1, Jaccard over character trigrams
+    pub exact: bool,     // same normalized fingerprint
+}
+
+/// Outcome of a create: `question` is `None` when the save was held back because of
+/// `duplicates`; resubmitting with `allow_duplicate` saves it anyway.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WrongQuestionCreateResult {
+    pub question: Option<WrongQuestion>,
+    pub duplicates: Vec<WrongQuestionDuplicate>,
+}
+
+/// Lowercased letters and digits only (CJK included), so whitespace, punctuation and
+/// formatting differences between AI chats don't matter.
+fn normalize_question_text(content: &str) -> String {
+    content
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn question_fingerprint(content: &str) -> String {
+    use sha1::{Digest, Sha1};
+    Sha1::digest(normalize_question_text(content).as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn question_shingles(normalized: &str) -> std::collections::HashSet<String> {
+    let chars: Vec<char> = normalized.chars().collect();
+    if chars.len() < 3 {
+        return std::iter::once(normalized.to_string()).filter(|s| !s.is_empty()).collect();
+    }
+    chars.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+fn shingle_similarity(a: &std::collections::HashSet<String>, b: &std::collections::HashSet<String>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// Existing wrong questions (archived included) that look like `content`, most similar first.
+/// Content with no letters or digits (e.g. only an image placeholder) never matches.
+/// With a `subject`, only exact fingerprint matches and questions of that subject are
+/// compared, so a create doesn't re-shingle the whole table.
+async fn db_find_duplicate_wrong_questions(
+    pool: &sqlx::SqlitePool,
+    content: &str,
+    exclude_id: Option<&str>,
+    subject: Option<&str>,
+) -> Result<Vec<WrongQuestionDuplicate>, String> {
+    let normalized = normalize_question_text(content);
+    if normalized.is_empty() {
+        return Ok(Vec::new());
+    }
+    let fingerprint = question_fingerprint(content);
+    let shingles = question_shingles(&normalized);
+    let rows = sqlx::query_as::<_, (String, String, Option<String>)>(
+        "SELECT id, question_content, content_fingerprint FROM wrong_questions
+         WHERE id <> ? AND (? IS NULL OR content_fingerprint = ? OR subject = ?)",
+    )
+    .bind(exclude_id.unwrap_or(""))
+    .bind(subject)
+    .bind(&fingerprint)
+    .bind(subject)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to scan wrong questions for duplicates: {}", e))?;
+
+    let mut scored: Vec<(String, f64, bool)> = rows
+        .into_iter()
+        .filter_map(|(id, existing, existing_fingerprint)| {
+            if existing_fingerprint.as_deref() == Some(fingerprint.as_str()) {
+                return Some((id, 1.0, true));
+            }
+            let similarity = shingle_similarity(&shingles, &question_shingles(&normalize_question_text(&existing)));
+            (similarity >= WRONG_QUESTION_DUPLICATE_THRESHOLD).then_some((id, similarity, false))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    scored.truncate(WRONG_QUESTION_DUPLICATE_LIMIT);
+
+    let mut duplicates = Vec::with_capacity(scored.len());
+    for (id, similarity, exact) in scored {
+        duplicates.push(WrongQuestionDuplicate {
+            question: db_get_wrong_question(pool, &id).await?,
+            similarity: (similarity * 1000.0).round() / 1000.0,
+            exact,
+        });
+    }
+    Ok(duplicates)
+}
+
+async fn db_backfill_wrong_question_fingerprints(pool: &sqlx::SqlitePool) -> Result<(), String> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        "SELECT id, question_content FROM wrong_questions WHERE content_fingerprint IS NULL",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read wrong questions for fingerprinting: {}", e))?;
+    for (id, content) in rows {
+        sqlx::query("UPDATE wrong_questions SET content_fingerprint = ? WHERE id = ?")
+            .bind(question_fingerprint(&content))
+            .bind(&id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to backfill wrong question fingerprint: {}", e))?;
+    }
+    Ok(())
+}
+
+fn merge_json_string_lists(lists: &[Option<&str>]) -> String {
+    let mut merged: Vec<String> = Vec::new();
+    for raw in lists.iter().flatten() {
+        for item in serde_json::from_str::<Vec<String>>(raw).unwrap_or_default() {
+            if !merged.contains(&item) {
+                merged.push(item);
+            }
+        }
+    }
+    serde_json::to_string(&merged).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Folds `source_ids` into `target_id`: tags, notes and AI message ids are combined, review
+/// counts add up, the earliest due date and latest review date win, and review log entries and
+/// weekly review items move to the target. The sources are deleted.
+async fn db_merge_wrong_questions(
+    pool: &sqlx::SqlitePool,
+    target_id: &str,
+    source_ids: &[String],
+) -> Result<WrongQuestion, String> {
+    let source_ids: Vec<&String> = source_ids.iter().filter(|id| id.as_str() != target_id).collect();
+    if source_ids.is_empty() {
+        return Err("source_ids 不能为空".to_string());
+    }
+    let target = db_get_wrong_question(pool, target_id).await?;
+    let mut sources = Vec::with_capacity(source_ids.len());
+    for id in &source_ids {
+        sources.push(db_get_wrong_question(pool, id).await?);
+    }
+    let all: Vec<&WrongQuestion> = std::iter::once(&target).chain(sources.iter()).collect();
+
+    let tags_json = merge_json_string_lists(&all.iter().map(|q| Some(q.tags_json.as_str())).collect::<Vec<_>>());
+    let error_causes_json =
+        merge_json_string_lists(&all.iter().map(|q| q.error_causes_json.as_deref()).collect::<Vec<_>>());
+    let ai_message_ids_json =
+        merge_json_string_lists(&all.iter().map(|q| q.ai_message_ids_json.as_deref()).collect::<Vec<_>>());
+    let mut notes: Vec<&str> = Vec::new();
+    for note in all.iter().filter_map(|q| q.user_note.as_deref()).map(str::trim) {
+        if !note.is_empty() && !notes.contains(&note) {
+            notes.push(note);
+        }
+    }
+    let user_note = (!notes.is_empty()).then(|| notes.join("\n\n---\n\n"));
+    let question_image_path = all.iter().find_map(|q| q.question_image_path.clone().filter(|p| !p.trim().is_empty()));
+    let ai_solution = all
+        .iter()
+        .map(|q| q.ai_solution.as_str())
+        .find(|s| !s.trim().is_empty())
+        .unwrap_or("")
+        .to_string();
+    let review_count: i32 = all.iter().map(|q| q.review_count).sum();
+    let next_review_date = all.iter().filter_map(|q| q.next_review_date.clone()).min();
+    let last_review_date = all.iter().filter_map(|q| q.last_review_date.clone()).max();
+    let mastery_level = all.iter().map(|q| q.mastery_level).min().unwrap_or(0);
+    let difficulty = all.iter().map(|q| q.difficulty).max().unwrap_or(target.difficulty);
+
+    let mut tx = pool
+        .begin()
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_subject ON wrong_questions(subject)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to begin wrong question merge tx: {}", e))?;
+    sqlx::query(
+        "UPDATE wrong_questions
+         SET tags_json = ?, error_causes_json = ?, ai_message_ids_json = ?, user_note = ?, question_image_path = ?, ai_solution = ?,
+             review_count = ?, next_review_date = ?, last_review_date = ?, mastery_level = ?, difficulty = ?, updated_at = ?
+         WHERE id = ?",
+    )
+    .bind(&tags_json)
+    .bind(&error_causes_json)
+    .bind(&ai_message_ids_json)
+    .bind(&user_note)
+    .bind(&question_image_path)
+    .bind(&ai_solution)
+    .bind(review_count)
+    .bind(&next_review_date)
+    .bind(&last_review_date)
+    .bind(mastery_level)
+    .bind(difficulty)
+    .bind(now_iso())
+    .bind(target_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to update merged wrong question: {}", e))?;
+
+    for source_id in &source_ids {
+        sqlx::query("UPDATE review_log SET item_id = ? WHERE item_type = 'wrong_question' AND item_id = ?")
+            .bind(target_id)
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to move review history: {}", e))?;
+        sqlx::query(
+            "UPDATE weekly_review_items SET wrong_question_id = ?, updated_at = ?
+             WHERE wrong_question_id = ?
+               AND week_start NOT IN (SELECT week_start FROM weekly_review_items WHERE wrong_question_id = ?)",
+        )
+        .bind(target_id)
+        .bind(now_iso())
+        .bind(source_id)
+        .bind(target_id)
+        .execute(&mut *tx)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_mastery ON wrong_questions(mastery_level)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to move weekly review items: {}", e))?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO knowledge_node_items (node_id, item_type, item_id, created_at)
+             SELECT node_id, item_type, ?, created_at FROM knowledge_node_items
+             WHERE item_type = 'wrong_question' AND item_id = ?",
+        )
+        .bind(target_id)
+        .bind(source_id)
+        .execute(&mut *tx)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_next_review ON wrong_questions(next_review_date)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to move knowledge assignments: {}", e))?;
+        sqlx::query("DELETE FROM knowledge_node_items WHERE item_type = 'wrong_question' AND item_id = ?")
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to cleanup knowledge assignments: {}", e))?;
+        sqlx::query("DELETE FROM wrong_questions WHERE id = ?")
+            .bind(source_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to delete merged wrong question: {}", e))?;
+    }
+    tx.commit()
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_archived ON wrong_questions(is_archived)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to commit wrong question merge tx: {}", e))?;
+
+    db_get_wrong_question(pool, target_id).await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReviewGrade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+impl ReviewGrade {
+    fn parse(raw: &str) -> Result<Self, String> {
+        match raw.trim().to_lowercase().as_str() {
+            "again" | "1" => Ok(ReviewGrade::Again),
+            "hard" | "2" => Ok(ReviewGrade::Hard),
+            "good" | "3" => Ok(ReviewGrade::Good),
+            "easy" | "4" => Ok(ReviewGrade::Easy),
+            other => Err(format!("grade 仅支持 again / hard / good / easy，收到: {}", other)),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReviewGrade::Again => "again",
+            ReviewGrade::Hard => "hard",
+            ReviewGrade::Good => "good",
+            ReviewGrade::Easy => "easy",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ReviewSchedule {
+    ease_factor: f64,
+    interval_days: i32,
+}
+
+/// SM-2 with Anki-style grades: `again` relearns tomorrow, `hard` grows the interval slowly,
+/// `good` follows 1 → 6 → interval × ease, `easy` adds a bonus. Ease stays within [1.3, 3.0].
+fn sm2_schedule(ease_factor: f64, interval_days: i32, grade: ReviewGrade) -> ReviewSchedule {
+    let ease = if ease_factor > 0.0 { ease_factor } else { 2.5 };
+    let interval = interval_days.max(0);
+    let good_interval = match interval {
+        0 => 1,
+        1..=5 => 6,
+        _ => (interval as f64 * ease).round() as i32,
+    };
+    let (ease, next) = match grade {
+        ReviewGrade::Again => (ease - 0.2, 1),
+        ReviewGrade::Hard => (ease - 0.15, ((interval as f64) * 1.2).round().max(1.0) as i32),
+        ReviewGrade::Good => (ease, good_interval),
+        ReviewGrade::Easy => (ease + 0.15, ((good_interval as f64) * 1.3).round().max(4.0) as i32),
+    };
+    let next = if matches!(grade, ReviewGrade::Good | ReviewGrade::Easy) {
+        next.max(interval + 1)
+    } else {
+        next
+    };
+    ReviewSchedule {
+        ease_factor: (ease.clamp(1.3, 3.0) * 100.0).round() / 100.0,
+        interval_days: next.min(3650),
+    }
+}
+
+fn review_due_date(interval_days: i32) -> String {
+    (Local::now().date_naive() + chrono::Duration::days(interval_days as i64))
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+// ═══════════════════════════════════════════════════════════
+// Review Scheduling (SM-2 / FSRS)
+// ═══════════════════════════════════════════════════════════
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReviewItemKind {
+    Question,
+    WrongQuestion,
+}
+
+impl ReviewItemKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ReviewItemKind::Question => "question",
+            ReviewItemKind::WrongQuestion => "wrong_question",
+        }
+    }
+}
+
+/// Scheduling state shared by both algorithms; each one reads and updates its own fields.
+#[derive(Debug, Clone, Copy)]
+struct ReviewCard {
+    ease_factor: f64,
+    interval_days: i32,
+    stability: f64,
+    difficulty: f64,
+    reps: i32,         // reviews before this one
+    elapsed_days: f64, // since the previous review
+}
+
+trait ReviewScheduler: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Card state after answering with `grade`; `interval_days` is the gap until the next review.
+    fn next(&self, card: &ReviewCard, grade: ReviewGrade) -> ReviewCard;
+}
+
+struct Sm2Scheduler;
+
+impl ReviewScheduler for Sm2Scheduler {
+    fn name(&self) -> &'static str {
+        "sm2"
+    }
+
+    fn next(&self, card: &ReviewCard, grade: ReviewGrade) -> ReviewCard {
+        let interval = if card.reps == 0 { 0 } else { card.interval_days };
+        let schedule = sm2_schedule(card.ease_factor, interval, grade);
+        ReviewCard {
+            ease_factor: schedule.ease_factor,
+            interval_days: schedule.interval_days,
+            ..*card
+        }
+    }
+}
+
+const FSRS_DEFAULT_WEIGHTS: [f64; 17] = [
+    0.4872, 1.4003, 3.7145, 13.8206, 5.1618, 1.2298, 0.8975, 0.031, 1.6474, 0.1367, 1.0461,
+    2.1072, 0.0793, 0.3246, 1.587, 0.2272, 2.8755,
+];
+
+// Clamps used while optimizing, following the reference FSRS optimizer.
+const FSRS_WEIGHT_BOUNDS: [(f64, f64); 17] = [
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (0.1, 100.0),
+    (1.0, 10.0),
+    (0.1, 5.0),
+    (0.1, 5.0),
+    (0.0, 0.8),
+    (0.0, 6.0),
+    (0.0, 0.8),
+    (0.01, 5.0),
+    (0.2, 6.0),
+    (0.01, 0.4),
+    (0.01, 0.9),
+    (0.01, 4.0),
+    (0.0, 1.0),
+    (1.0, 6.0),
+];
+
+const FSRS_DECAY: f64 = -0.5;
+const FSRS_FACTOR: f64 = 19.0 / 81.0;
+
+/// FSRS-4.5: models each card's memory stability and difficulty and schedules the next review
+/// when predicted recall drops to `desired_retention`.
+#[derive(Debug, Clone)]
+struct FsrsScheduler {
+    weights: [f64; 17],
+    desired_retention: f64,
+}
+
+impl FsrsScheduler {
+    fn grade_value(grade: ReviewGrade) -> f64 {
+        match grade {
+            ReviewGrade::Again => 1.0,
+            ReviewGrade::Hard => 2.0,
+            ReviewGrade::Good => 3.0,
+            ReviewGrade::Easy => 4.0,
+        }
+    }
+
+    fn retrievability(elapsed_days: f64, stability: f64) -> f64 {
+        (1.0 + FSRS_FACTOR * elapsed_days.max(0.0) / stability.max(0.1)).powf(FSRS_DECAY)
+    }
+
+    fn initial_difficulty(&self, grade_value: f64) -> f64 {
+        (self.weights[4] - (grade_value - 3.0) * self.weights[5]).clamp(1.0, 10.0)
+    }
+
+    /// Returns (stability, difficulty) after a review; `first` uses the initial-state formulas.
+    fn next_memory(
+        &self,
+        stability: f64,
+        difficulty: f64,
+        elapsed_days: f64,
+        grade: ReviewGrade,
+        first: bool,
+    ) -> (f64, f64) {
+        let w = &self.weights;
+        let g = Self::grade_value(grade);
+        if first {
+            return (w[g as usize - 1].max(0.1), self.initial_difficulty(g));
+        }
+
+        let r = Self::retrievability(elapsed_days, stability);
+        let next_difficulty = {
+            let d = difficulty - w[6] * (g - 3.0);
+            (w[7] * self.initial_difficulty(3.0) + (1.0 - w[7]) * d).clamp(1.0, 10.0)
+        };
+        let next_stability = if grade == ReviewGrade::Again {
+            w[11]
+                * difficulty.powf(-w[12])
+                * ((stability + 1.0).powf(w[13]) - 1.0)
+                * (w[14] * (1.0 - r)).exp()
+        } else {
+            let hard_penalty = if grade == ReviewGrade::Hard { w[15] } else { 1.0 };
+            let easy_bonus = if grade == ReviewGrade::Easy { w[16] } else { 1.0 };
+            stability
+                * (w[8].exp()
+                    * (11.0 - difficulty)
+                    * stability.powf(-w[9])
+                    * ((w[10] * (1.0 - r)).exp() - 1.0)
+                    * hard_penalty
+                    * easy_bonus
+                    + 1.0)
+        };
+        (next_stability.clamp(0.1, 36500.0), next_difficulty)
+    }
+
+    fn interval_for(&self, stability: f64) -> i32 {
+        let days = stability / FSRS_FACTOR * (self.desired_retention.powf(1.0 / FSRS_DECAY) - 1.0);
+        (days.round() as i32).clamp(1, 36500)
+    }
+}
+
+impl ReviewScheduler for FsrsScheduler {
+    fn name(&self) -> &'static str {
+        "fsrs"
+    }
+
+    fn next(&self, card: &ReviewCard, grade: ReviewGrade) -> ReviewCard {
+        let first = card.reps == 0;
+        // Cards reviewed before FSRS was enabled start from their SM-2 interval.
+        let (stability, difficulty) = if !first && card.stability <= 0.0 {
+            (card.interval_days.max(1) as f64, 5.0)
+        } else {
+            (card.stability, card.difficulty)
+        };
+        let (stability, difficulty) =
+            self.next_memory(stability, difficulty, card.elapsed_days, grade, first);
+        let interval_days = if grade == ReviewGrade::Again {
+            1
+        } else {
+            self.interval_for(stability)
+        };
+        ReviewCard {
+            stability,
+            difficulty,
+            interval_days,
+            ..*card
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewSchedulerSettings {
+    pub algorithm: String, // "sm2" | "fsrs"
+    pub desired_retention: f64,
+    pub fsrs_weights: Vec<f64>,
+    pub optimized_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReviewSchedulerSettingsPayload {
+    pub algorithm: Option<String>,
+    pub desired_retention: Option<f64>,
+    pub fsrs_weights: Option<Vec<f64>>,
+    pub reset_weights: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FsrsOptimizationResult {
+    pub item_count: i64,
+    pub review_count: i64, // reviews with a prior review, i.e. ones that test a prediction
+    pub log_loss_before: f64,
+    pub log_loss_after: f64,
+    pub applied: bool,
+    pub weights: Vec<f64>,
+}
+
+async fn db_get_setting(pool: &sqlx::SqlitePool, key: &str) -> Result<Option<String>, String> {
+    sqlx::query_scalar::<_, String>("SELECT value FROM app_settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_created ON wrong_questions(created_at DESC)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to read setting {}: {}", key, e))
+}
+
+async fn db_set_setting(pool: &sqlx::SqlitePool, key: &str, value: &str) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?, ?, ?)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+    )
+    .bind(key)
+    .bind(value)
+    .bind(now_iso())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+    Ok(())
+}
+
+fn parse_fsrs_weights(weights: &[f64]) -> Result<[f64; 17], String> {
+    let weights: [f64; 17] = weights
+        .try_into()
+        .map_err(|_| "fsrs_weights 必须包含 17 个参数".to_string())?;
+    if weights.iter().any(|w| !w.is_finite()) {
+        return Err("fsrs_weights 包含非法数值".to_string());
+    }
+    Ok(weights)
+}
+
+async fn db_get_review_scheduler_settings(
+    pool: &sqlx::SqlitePool,
+) -> Result<ReviewSchedulerSettings, String> {
+    let algorithm = db_get_setting(pool, "review.scheduler")
+        .await?
+        .unwrap_or_else(|| "sm2".to_string());
+    let desired_retention = db_get_setting(pool, "review.desired_retention")
+        .await?
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(0.9);
+    let fsrs_weights = db_get_setting(pool, "review.fsrs_weights")
+        .await?
+        .and_then(|v| serde_json::from_str::<Vec<f64>>(&v).ok())
+        .filter(|w| parse_fsrs_weights(w).is_ok())
+        .unwrap_or_else(|| FSRS_DEFAULT_WEIGHTS.to_vec());
+    Ok(ReviewSchedulerSettings {
+        algorithm,
+        desired_retention,
+        fsrs_weights,
+        optimized_at: db_get_setting(pool, "review.fsrs_optimized_at").await?,
+    })
+}
+
+async fn db_update_review_scheduler_settings(
+    pool: &sqlx::SqlitePool,
+    payload: &ReviewSchedulerSettingsPayload,
+) -> Result<ReviewSchedulerSettings, String> {
+    if let Some(algorithm) = &payload.algorithm {
+        let algorithm = algorithm.trim().to_lowercase();
+        if algorithm != "sm2" && algorithm != "fsrs" {
+            return Err("algorithm 仅支持 sm2 或 fsrs".to_string());
+        }
+        db_set_setting(pool, "review.scheduler", &algorithm).await?;
+    }
+    if let Some(retention) = payload.desired_retention {
+        if !(0.7..=0.97).contains(&retention) {
+            return Err("desired_retention 需在 0.7 到 0.97 之间".to_string());
+        }
+        db_set_setting(pool, "review.desired_retention", &retention.to_string()).await?;
+    }
+    if payload.reset_weights.unwrap_or(false) {
+        sqlx::query("DELETE FROM app_settings WHERE key IN ('review.fsrs_weights', 'review.fsrs_optimized_at')")
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to reset FSRS weights: {}", e))?;
+    } else if let Some(weights) = &payload.fsrs_weights {
+        parse_fsrs_weights(weights)?;
+        let json = serde_json::to_string(weights).map_err(|e| e.to_string())?;
+        db_set_setting(pool, "review.fsrs_weights", &json).await?;
+    }
+    db_get_review_scheduler_settings(pool).await
+}
+
+fn scheduler_from_settings(settings: &ReviewSchedulerSettings) -> Box<dyn ReviewScheduler> {
+    match settings.algorithm.as_str() {
+        "fsrs" => Box::new(FsrsScheduler {
+            weights: parse_fsrs_weights(&settings.fsrs_weights).unwrap_or(FSRS_DEFAULT_WEIGHTS),
+            desired_retention: settings.desired_retention,
+        }),
+        _ => Box::new(Sm2Scheduler),
+    }
+}
+
+/// Accepts RFC 3339, SQLite `datetime()` output (UTC) or a plain date.
+fn parse_review_timestamp(raw: &str) -> Option<chrono::DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.with_timezone(&Utc));
+    }
+    if let Ok(naive) = chrono::NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S") {
+        return Some(naive.and_utc());
+    }
+    chrono::NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|naive| naive.and_utc())
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct ReviewCardRow {
+    ease_factor: f64,
+    interval_days: i32,
+    stability: f64,
+    fsrs_difficulty: f64,
+    review_count: i32,
+    last_review: Option<String>,
+}
+
+/// Runs the configured scheduler for one answer, persists the new schedule and logs the review.
+/// Counters such as `review_count` and mastery stay with the caller.
+/// Runs on the caller's connection so it can share a transaction with the caller's own counters.
+async fn db_apply_review(
+    conn: &mut sqlx::SqliteConnection,
+    settings: &ReviewSchedulerSettings,
+    kind: ReviewItemKind,
+    id: &str,
+    grade: ReviewGrade,
+    response_ms: Option<i64>,
+) -> Result<ReviewCard, String> {
+    let select_sql = match kind {
+        ReviewItemKind::Question => {
+            "SELECT COALESCE(ease_factor, 2.5) AS ease_factor, COALESCE(interval, 0) AS interval_days, stability, fsrs_difficulty, COALESCE(review_count, 0) AS review_count, last_review FROM questions WHERE id = ?"
+        }
+        ReviewItemKind::WrongQuestion => {
+            "SELECT ease_factor, interval_days, stability, fsrs_difficulty, review_count, last_review_date AS last_review FROM wrong_questions WHERE id = ?"
+        }
+    };
+    let row = sqlx::query_as::<_, ReviewCardRow>(select_sql)
+        .bind(id)
+        .fetch_optional(&mut *conn)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_weekly_review_items_week ON weekly_review_items(week_start, week_end)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to read review state: {}", e))?
+        .ok_or_else(|| match kind {
+            ReviewItemKind::Question => "Question not found".to_string(),
+            ReviewItemKind::WrongQuestion => "Wrong question not found".to_string(),
+        })?;
+
+    let now = Utc::now();
+    let elapsed_days = row
+        .last_review
+        .as_deref()
+        .and_then(parse_review_timestamp)
+        .map(|last| (now - last).num_seconds() as f64 / 86400.0)
+        .unwrap_or(row.interval_days as f64)
+        .max(0.0);
+    let card = ReviewCard {
+        ease_factor: row.ease_factor,
+        interval_days: row.interval_days,
+        stability: row.stability,
+        difficulty: row.fsrs_difficulty,
+        reps: row.review_count,
+        elapsed_days,
+    };
+
+    let scheduler = scheduler_from_settings(settings);
+    let next = scheduler.next(&card, grade);
+    let reviewed_at = now.to_rfc3339();
+
+    match kind {
+        ReviewItemKind::Question => sqlx::query(
+            "UPDATE questions
+             SET ease_factor = ?, interval = ?, stability = ?, fsrs_difficulty = ?, last_review = ?,
+                 next_review = datetime('now', '+' || ? || ' day')
+             WHERE id = ?",
+        )
+        .bind(next.ease_factor)
+        .bind(next.interval_days)
+        .bind(next.stability)
+        .bind(next.difficulty)
+        .bind(&reviewed_at)
+        .bind(next.interval_days)
+        .bind(id),
+        ReviewItemKind::WrongQuestion => sqlx::query(
+            "UPDATE wrong_questions
+             SET ease_factor = ?, interval_days = ?, stability = ?, fsrs_difficulty = ?, last_review_date = ?,
+                 next_review_date = ?, updated_at = ?
+             WHERE id = ?",
+        )
+        .bind(next.ease_factor)
+        .bind(next.interval_days)
+        .bind(next.stability)
+        .bind(next.difficulty)
+        .bind(&reviewed_at)
+        .bind(review_due_date(next.interval_days))
+        .bind(&reviewed_at)
+        .bind(id),
+    }
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to save review schedule: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO review_log (id, item_type, item_id, grade, reviewed_at, elapsed_days, response_ms, prev_interval, new_interval, scheduler)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(gen_unique_id("review"))
+    .bind(kind.as_str())
+    .bind(id)
+    .bind(grade.as_str())
+    .bind(&reviewed_at)
+    .bind(elapsed_days)
+    .bind(response_ms)
+    .bind(card.interval_days)
+    .bind(next.interval_days)
+    .bind(scheduler.name())
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| format!("Failed to write review log: {}", e))?;
+
+    Ok(next)
+}
+
+/// Mean log loss of FSRS recall predictions over every review that had a previous review.
+fn fsrs_log_loss(weights: &[f64; 17], histories: &[Vec<(f64, ReviewGrade)>]) -> (f64, i64) {
+    let scheduler = FsrsScheduler {
+        weights: *weights,
+        desired_retention: 0.9,
+    };
+    let mut loss = 0.0;
+    let mut count = 0;
+    for history in histories {
+        let mut memory: Option<(f64, f64)> = None;
+        for &(elapsed_days, grade) in history {
+            memory = Some(match memory {
+                None => scheduler.next_memory(0.0, 0.0, 0.0, grade, true),
+                Some((stability, difficulty)) => {
+                    let r = FsrsScheduler::retrievability(elapsed_days, stability).clamp(1e-4, 1.0 - 1e-4);
+                    loss -= if grade == ReviewGrade::Again { (1.0 - r).ln() } else { r.ln() };
+                    count += 1;
+                    scheduler.next_memory(stability, difficulty, elapsed_days, grade, false)
+                }
+            });
+        }
+    }
+    (if count > 0 { loss / count as f64 } else { 0.0 }, count)
+}
+
+/// Finite-difference gradient descent on the log loss, starting from the current weights.
+fn optimize_fsrs_weights(start: [f64; 17], histories: &[Vec<(f64, ReviewGrade)>]) -> ([f64; 17], f64) {
+    let mut weights = start;
+    let (mut best, _) = fsrs_log_loss(&weights, histories);
+    let mut learning_rate = 0.05;
+    for _ in 0..300 {
+        let mut gradient = [0.0; 17];
+        for i in 0..17 {
+            let (lo, hi) = FSRS_WEIGHT_BOUNDS[i];
+            let eps = (hi - lo) * 1e-4;
+            let mut plus = weights;
+            plus[i] = (plus[i] + eps).min(hi);
+            let mut minus = weights;
+            minus[i] = (minus[i] - eps).max(lo);
+            if plus[i] > minus[i] {
+                gradient[i] = (fsrs_log_loss(&plus, histories).0 - fsrs_log_loss(&minus, histories).0)
+                    / (plus[i] - minus[i]);
+            }
+        }
+        let norm = gradient.iter().map(|g| g * g).sum::<f64>().sqrt();
+        if norm < 1e-9 {
+            break;
+        }
+        let mut candidate = weights;
+        for i in 0..17 {
+            let (lo, hi) = FSRS_WEIGHT_BOUNDS[i];
+            candidate[i] = (candidate[i] - learning_rate * (hi - lo) * gradient[i] / norm).clamp(lo, hi);
+        }
+        let (loss, _) = fsrs_log_loss(&candidate, histories);
+        if loss < best {
+            weights = candidate;
+            best = loss;
+            learning_rate *= 1.2;
+        } else {
+            learning_rate *= 0.5;
+            if learning_rate < 1e-5 {
+                break;
+            }
+        }
+    }
+    (weights, best)
+}
+
+const FSRS_MIN_OPTIMIZATION_REVIEWS: i64 = 32;
+
+/// One entry per reviewed item: (days since the previous review, grade) in review order.
+type FsrsHistories = Vec<Vec<(f64, ReviewGrade)>>;
+
+/// Per-item review histories plus the weights currently in use, the input to `optimize_fsrs`.
+async fn db_load_fsrs_training_set(
+    pool: &sqlx::SqlitePool,
+) -> Result<(FsrsHistories, [f64; 17]), String> {
+    let rows = sqlx::query_as::<_, (String, String, String, f64)>(
+        "SELECT item_type, item_id, grade, elapsed_days FROM review_log ORDER BY item_type, item_id, reviewed_at ASC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch review history: {}", e))?;
+
+    let mut histories: FsrsHistories = Vec::new();
+    let mut current_key: Option<(String, String)> = None;
+    for (item_type, item_id, grade, elapsed_days) in rows {
+        let Ok(grade) = ReviewGrade::parse(&grade) else {
+            continue;
+        };
+        let key = (item_type, item_id);
+        if current_key.as_ref() != Some(&key) {
+            histories.push(Vec::new());
+            current_key = Some(key);
+        }
+        if let Some(history) = histories.last_mut() {
+            history.push((elapsed_days, grade));
+        }
+    }
+
+    let settings = db_get_review_scheduler_settings(pool).await?;
+    let start = parse_fsrs_weights(&settings.fsrs_weights).unwrap_or(FSRS_DEFAULT_WEIGHTS);
+    Ok((histories, start))
+}
+
+/// Fits FSRS weights to this user's review history and reports whether they predict better than
+/// `start`; saving them is left to the caller (`db_save_fsrs_weights`). Runs the fit off the
+/// async runtime and holds no database handle, so callers must not hold the `AppDb` lock across it.
+async fn optimize_fsrs(
+    start: [f64; 17],
+    histories: FsrsHistories,
+) -> Result<FsrsOptimizationResult, String> {
+    let (log_loss_before, review_count) = fsrs_log_loss(&start, &histories);
+    if review_count < FSRS_MIN_OPTIMIZATION_REVIEWS {
+        return Err(format!(
+            "复习记录不足：需要至少 {} 次重复复习，当前 {} 次",
+            FSRS_MIN_OPTIMIZATION_REVIEWS, review_count
+        ));
+    }
+
+    let item_count = histories.len() as i64;
+    let (weights, log_loss_after) =
+        tokio::task::spawn_blocking(move || optimize_fsrs_weights(start, &histories))
+            .await
+            .map_err(|e| format!("FSRS optimization failed: {}", e))?;
+
+    let applied = log_loss_after < log_loss_before;
+    Ok(FsrsOptimizationResult {
+        item_count,
+        review_count,
+        log_loss_before,
+        log_loss_after: if applied { log_loss_after } else { log_loss_before },
+        applied,
+        weights: if applied { weights.to_vec() } else { start.to_vec() },
+    })
+}
+
+async fn db_save_fsrs_weights(pool: &sqlx::SqlitePool, weights: &[f64]) -> Result<(), String> {
+    let json = serde_json::to_string(weights).map_err(|e| e.to_string())?;
+    db_set_setting(pool, "review.fsrs_weights", &json).await?;
+    db_set_setting(pool, "review.fsrs_optimized_at", &now_iso()).await
+}
+
+async fn db_answer_question(
+    pool: &sqlx::SqlitePool,
+    id: &str,
+    grade: ReviewGrade,
+    response_ms: Option<i64>,
+) -> Result<(), String> {
+    let settings = db_get_review_scheduler_settings(pool).await?;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin answer question tx: {}", e))?;
+    db_apply_review(&mut tx, &settings, ReviewItemKind::Question, id, grade, response_ms).await?;
+    sqlx::query(
+        "UPDATE questions
+         SET review_count = COALESCE(review_count, 0) + 1,
+             correct_count = COALESCE(correct_count, 0) + ?
+         WHERE id = ?",
+    )
+    .bind(i32::from(grade != ReviewGrade::Again))
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to update question answer stats: {}", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit answer question tx: {}", e))?;
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct ReviewLogEntry {
+    pub id: String,
+    pub item_type: String, // "question" | "wrong_question"
+    pub item_id: String,
+    pub grade: String,
+    pub reviewed_at: String,
+    pub elapsed_days: f64,
+    pub response_ms: Option<i64>,
+    pub prev_interval: i32,
+    pub new_interval: i32,
+    pub scheduler: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewRetentionPoint {
+    pub label: String,
+    pub min_days: f64,
+    pub max_days: Option<f64>,
+    pub reviews: i64,
+    pub recalled: i64,
+    pub retention: f64, // percent
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewAccuracyPoint {
+    pub date: String,
+    pub reviews: i64,
+    pub correct: i64,
+    pub accuracy: f64, // percent
+    pub avg_response_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReviewCurves {
+    pub start_date: String,
+    pub end_date: String,
+    pub item_type: Option<String>,
+    pub total_reviews: i64,
+    pub accuracy: f64,
+    pub retention_curve: Vec<ReviewRetentionPoint>,
+    pub accuracy_curve: Vec<ReviewAccuracyPoint>,
+}
+
+fn parse_review_item_type(raw: &str) -> Result<ReviewItemKind, String> {
+    match raw.trim() {
+        "question" => Ok(ReviewItemKind::Question),
+        "wrong_question" => Ok(ReviewItemKind::WrongQuestion),
+        other => Err(format!("item_type 仅支持 question / wrong_question，收到: {}", other)),
+    }
+}
+
+fn validate_response_ms(response_ms: Option<i64>) -> Result<Option<i64>, String> {
+    match response_ms {
+        Some(ms) if ms < 0 => Err("response_ms 不能为负数".to_string()),
+        other => Ok(other),
+    }
+}
+
+/// Newest first.
+async fn db_get_review_history(
+    pool: &sqlx::SqlitePool,
+    kind: ReviewItemKind,
+    item_id: &str,
+    limit: Option<i64>,
+) -> Result<Vec<ReviewLogEntry>, String> {
+    sqlx::query_as::<_, ReviewLogEntry>(
+        "SELECT id, item_type, item_id, grade, reviewed_at, elapsed_days, response_ms, prev_interval, new_interval, scheduler
+         FROM review_log WHERE item_type = ? AND item_id = ?
+         ORDER BY reviewed_at DESC LIMIT ?",
+    )
+    .bind(kind.as_str())
+    .bind(item_id)
+    .bind(limit.unwrap_or(200).clamp(1, 1000))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch review history: {}", e))
+}
+
+// Elapsed-day buckets for the retention curve: [min, max).
+const REVIEW_RETENTION_BUCKETS: [(&str, f64, Option<f64>); 7] = [
+    ("<1d", 0.0, Some(1.0)),
+    ("1-2d", 1.0, Some(3.0)),
+    ("3-6d", 3.0, Some(7.0)),
+    ("7-13d", 7.0, Some(14.0)),
+    ("14-29d", 14.0, Some(30.0)),
+    ("30-89d", 30.0, Some(90.0)),
+    ("90d+", 90.0, None),
+];
+
+fn review_rate_percent(part: i64, total: i64) -> f64 {
+    if total > 0 {
+        (part as f64 / total as f64) * 100.0
+    } else {
+        0.0
+    }
+}
+
+/// Daily accuracy over the range, plus recall rate by time since the previous review.
+/// Only reviews that follow an earlier review of the same item enter the retention curve.
+async fn db_get_review_curves(
+    pool: &sqlx::SqlitePool,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+    item_type: Option<&str>,
+) -> Result<ReviewCurves, String> {
+    let (start_date, end_date) = resolve_focus_range(start_date, end_date)?;
+    let kind = item_type
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(parse_review_item_type)
+        .transpose()?;
+
+    // reviewed_at is UTC; widen by a day on each side and filter on the local date below.
+    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+        "SELECT l.grade, l.reviewed_at, l.elapsed_days, l.response_ms,
+                EXISTS (SELECT 1 FROM review_log p WHERE p.item_type = l.item_type AND p.item_id = l.item_id AND p.reviewed_at < l.reviewed_at) AS has_prior
+         FROM review_log l WHERE date(l.reviewed_at) BETWEEN date(",
+    );
+    builder.push_bind(&start_date);
+    builder.push(", '-1 day') AND date(");
+    builder.push_bind(&end_date);
+    builder.push(", '+1 day')");
+    if let Some(kind) = kind {
+        builder.push(" AND l.item_type = ");
+        builder.push_bind(kind.as_str());
+    }
+    let rows = builder
+        .build_query_as::<(String, String, f64, Option<i64>, bool)>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch review curves: {}", e))?;
+
+    let mut retention: Vec<(i64, i64)> = vec![(0, 0); REVIEW_RETENTION_BUCKETS.len()];
+    // date -> (reviews, correct, response_ms sum, responses timed)
+    let mut by_day = std::collections::BTreeMap::<String, (i64, i64, i64, i64)>::new();
+    let (mut total, mut correct_total) = (0, 0);
+    for (grade, reviewed_at, elapsed_days, response_ms, has_prior) in rows {
+        let Some(reviewed_at) = parse_review_timestamp(&reviewed_at) else {
+            continue;
+        };
+        let day = reviewed_at.with_timezone(&Local).format("%Y-%m-%d").to_string();
+        if day < start_date || day > end_date {
+            continue;
+        }
+        let correct = i64::from(grade != ReviewGrade::Again.as_str());
+        total += 1;
+        correct_total += correct;
+
+        let entry = by_day.entry(day).or_insert((0, 0, 0, 0));
+        entry.0 += 1;
+        entry.1 += correct;
+        if let Some(ms) = response_ms {
+            entry.2 += ms;
+            entry.3 += 1;
+        }
+
+        if has_prior {
+            if let Some(index) = REVIEW_RETENTION_BUCKETS
+                .iter()
+                .position(|(_, min, max)| elapsed_days >= *min && max.map_or(true, |max| elapsed_days < max))
+            {
+                retention[index].0 += 1;
+                retention[index].1 += correct;
+            }
+        }
+    }
+
+    Ok(ReviewCurves {
+        start_date,
+        end_date,
+        item_type: kind.map(|k| k.as_str().to_string()),
+        total_reviews: total,
+        accuracy: review_rate_percent(correct_total, total),
+        retention_curve: REVIEW_RETENTION_BUCKETS
+            .iter()
+            .zip(retention)
+            .map(|((label, min_days, max_days), (reviews, recalled))| ReviewRetentionPoint {
+                label: label.to_string(),
+                min_days: *min_days,
+                max_days: *max_days,
+                reviews,
+                recalled,
+                retention: review_rate_percent(recalled, reviews),
+            })
+            .collect(),
+        accuracy_curve: by_day
+            .into_iter()
+            .map(|(date, (reviews, correct, response_sum, response_count))| ReviewAccuracyPoint {
+                date,
+                reviews,
+                correct,
+                accuracy: review_rate_percent(correct, reviews),
+                avg_response_ms: (response_count > 0).then(|| response_sum as f64 / response_count as f64),
+            })
+            .collect(),
+    })
+}
+
+async fn db_get_wrong_question(pool: &sqlx::SqlitePool, id: &str) -> Result<WrongQuestion, String> {
+    sqlx::query_as::<_, WrongQuestion>(
+        "SELECT id, subject, tags_json, question_content, question_image_path, ai_solution, user_note, source, ai_session_id, ai_message_ids_json, difficulty, mastery_level, review_count, next_review_date, last_review_date, ease_factor, interval_days, is_archived, created_at, updated_at, error_causes_json FROM wrong_questions WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch wrong question: {}", e))?
+    .ok_or_else(|| "Wrong question not found".to_string())
+}
+
+// ═══════════════════════════════════════════════════════════
+// Wrong Question Mastery Rules
+// ═══════════════════════════════════════════════════════════
+
+/// Subject key of the rule used when a subject has none of its own.
+const DEFAULT_MASTERY_RULE_SUBJECT: &str = "*";
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct MasteryRule {
+    pub subject: String,
+    pub reviews_to_master: i32,
+    pub min_spacing_days: i32, // reviews sooner than this after the previous one don't count
+    pub auto_archive: bool,
+    pub archive_after_reviews: i32,
+    pub updated_at: Option<String>,
+}
+
+impl MasteryRule {
+    fn builtin(subject: &str) -> Self {
+        MasteryRule {
+            subject: subject.to_string(),
+            reviews_to_master: 3,
+            min_spacing_days: 0,
+            auto_archive: true,
+            archive_after_reviews: 4,
+            updated_at: None,
+        }
+    }
+
+    /// Scales counted reviews onto mastery levels 0–3; a failed recall drops back to 0.
+    fn mastery_level(&self, review_count: i64, grade: ReviewGrade) -> i32 {
+        if grade == ReviewGrade::Again {
+            return 0;
+        }
+        (review_count * 3 / self.reviews_to_master.max(1) as i64).clamp(0, 3) as i32
+    }
+
+    fn should_archive(&self, review_count: i64, grade: ReviewGrade) -> bool {
+        self.auto_archive && grade != ReviewGrade::Again && review_count >= self.archive_after_reviews as i64
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MasteryRulePayload {
+    pub subject: Option<String>, // omitted or "*" edits the default rule
+    pub reviews_to_master: Option<i32>,
+    pub min_spacing_days: Option<i32>,
+    pub auto_archive: Option<bool>,
+    pub archive_after_reviews: Option<i32>,
+}
+
+fn normalize_mastery_rule_subject(subject: Option<&str>) -> String {
+    match subject.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(s) => s.to_string(),
+        None => DEFAULT_MASTERY_RULE_SUBJECT.to_string(),
+    }
+}
+
+fn validate_mastery_rule(rule: &MasteryRule) -> Result<(), String> {
+    if !(1..=20).contains(&rule.reviews_to_master) {
+        return Err("reviews_to_master 需在 1 到 20 之间".to_string());
+    }
+    if !(0..=365).contains(&rule.min_spacing_days) {
+        return Err("min_spacing_days 需在 0 到 365 之间".to_string());
+    }
+    if rule.archive_after_reviews < rule.reviews_to_master || rule.archive_after_reviews > 50 {
+        return Err("archive_after_reviews 需不小于 reviews_to_master 且不超过 50".to_string());
+    }
+    Ok(())
+}
+
+async fn db_find_mastery_rule(pool: &sqlx::SqlitePool, subject: &str) -> Result<Option<MasteryRule>, String> {
+    sqlx::query_as::<_, MasteryRule>(
+        "SELECT subject, reviews_to_master, min_spacing_days, auto_archive, archive_after_reviews, updated_at
+         FROM wrong_question_mastery_rules WHERE subject = ?",
+    )
+    .bind(subject)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch mastery rule: {}", e))
+}
+
+/// The subject's own rule, else the stored default, else the built-in default.
+async fn db_resolve_mastery_rule(pool: &sqlx::SqlitePool, subject: &str) -> Result<MasteryRule, String> {
+    if let Some(rule) = db_find_mastery_rule(pool, subject).await? {
+        return Ok(rule);
+    }
+    Ok(db_find_mastery_rule(pool, DEFAULT_MASTERY_RULE_SUBJECT)
+        .await?
+        .unwrap_or_else(|| MasteryRule::builtin(DEFAULT_MASTERY_RULE_SUBJECT)))
+}
+
+/// All stored rules; the default rule is always first, even if never saved.
+async fn db_get_mastery_rules(pool: &sqlx::SqlitePool) -> Result<Vec<MasteryRule>, String> {
+    let mut rules = sqlx::query_as::<_, MasteryRule>(
+        "SELECT subject, reviews_to_master, min_spacing_days, auto_archive, archive_after_reviews, updated_at
+         FROM wrong_question_mastery_rules ORDER BY subject ASC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch mastery rules: {}", e))?;
+    if let Some(index) = rules.iter().position(|r| r.subject == DEFAULT_MASTERY_RULE_SUBJECT) {
+        let default_rule = rules.remove(index);
+        rules.insert(0, default_rule);
+    } else {
+        rules.insert(0, MasteryRule::builtin(DEFAULT_MASTERY_RULE_SUBJECT));
+    }
+    Ok(rules)
+}
+
+/// Recomputes stored mastery levels after a rule change. Levels reset to 0 by a failed recall stay at 0.
+async fn db_recompute_mastery_levels(pool: &sqlx::SqlitePool, subject: &str) -> Result<(), String> {
+    let subjects: Vec<String> = if subject == DEFAULT_MASTERY_RULE_SUBJECT {
+        sqlx::query_scalar::<_, String>(
+            "SELECT DISTINCT subject FROM wrong_questions
+             WHERE subject NOT IN (SELECT subject FROM wrong_question_mastery_rules)",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch subjects for mastery recompute: {}", e))?
+    } else {
+        vec![subject.to_string()]
+    };
+
+    for subject in subjects {
+        let rule = db_resolve_mastery_rule(pool, &subject).await?;
+        sqlx::query(
+            "UPDATE wrong_questions
+             SET mastery_level = MIN(3, MAX(1, review_count * 3 / ?))
+             WHERE subject = ? AND mastery_level > 0",
+        )
+        .bind(rule.reviews_to_master.max(1))
+        .bind(&subject)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to recompute mastery levels: {}", e))?;
+    }
+    Ok(())
+}
+
+async fn db_upsert_mastery_rule(
+    pool: &sqlx::SqlitePool,
+    payload: &MasteryRulePayload,
+) -> Result<MasteryRule, String> {
+    let subject = normalize_mastery_rule_subject(payload.subject.as_deref());
+    let current = db_resolve_mastery_rule(pool, &subject).await?;
+    let rule = MasteryRule {
+        subject: subject.clone(),
+        reviews_to_master: payload.reviews_to_master.unwrap_or(current.reviews_to_master),
+        min_spacing_days: payload.min_spacing_days.unwrap_or(current.min_spacing_days),
+        auto_archive: payload.auto_archive.unwrap_or(current.auto_archive),
+        archive_after_reviews: payload.archive_after_reviews.unwrap_or(current.archive_after_reviews),
+        updated_at: Some(now_iso()),
+    };
+    validate_mastery_rule(&rule)?;
+
+    sqlx::query(
+        "INSERT INTO wrong_question_mastery_rules (subject, reviews_to_master, min_spacing_days, auto_archive, archive_after_reviews, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT(subject) DO UPDATE SET
+            reviews_to_master = excluded.reviews_to_master,
+            min_spacing_days = excluded.min_spacing_days,
+            auto_archive = excluded.auto_archive,
+            archive_after_reviews = excluded.archive_after_reviews,
+            updated_at = excluded.updated_at",
+    )
+    .bind(&rule.subject)
+    .bind(rule.reviews_to_master)
+    .bind(rule.min_spacing_days)
+    .bind(rule.auto_archive)
+    .bind(rule.archive_after_reviews)
+    .bind(&rule.updated_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save mastery rule: {}", e))?;
+
+    db_recompute_mastery_levels(pool, &subject).await?;
+    Ok(rule)
+}
+
+/// Drops a subject's rule so it falls back to the default; deleting the default restores the built-in one.
+async fn db_delete_mastery_rule(pool: &sqlx::SqlitePool, subject: &str) -> Result<(), String> {
+    let subject = normalize_mastery_rule_subject(Some(subject));
+    let result = sqlx::query("DELETE FROM wrong_question_mastery_rules WHERE subject = ?")
+        .bind(&subject)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete mastery rule: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err("Mastery rule not found".to_string());
+    }
+    db_recompute_mastery_levels(pool, &subject).await
+}
+
+/// Reschedules a wrong question and updates its review count, mastery and archive state
+/// according to its subject's mastery rule. A review closer than `min_spacing_days` to the
+/// previous one is still scheduled but not counted. Weekly items other than `keep_item_id`
+/// are removed when the question gets archived.
+async fn db_record_wrong_question_review(
+    pool: &sqlx::SqlitePool,
+    id: &str,
+    grade: ReviewGrade,
+    response_ms: Option<i64>,
+    keep_item_id: Option<&str>,
+) -> Result<(), String> {
+    let (subject, review_count, mastery_level, last_counted_review_at) =
+        sqlx::query_as::<_, (String, i64, i32, Option<String>)>(
+            "SELECT subject, review_count, mastery_level, last_counted_review_at FROM wrong_questions WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to read wrong question review progress: {}", e))?
+        .ok_or_else(|| "Wrong question not found".to_string())?;
+    let rule = db_resolve_mastery_rule(pool, &subject).await?;
+    let settings = db_get_review_scheduler_settings(pool).await?;
+
+    // Spacing runs from the last review that counted, so early extra reviews don't push it back.
+    let too_soon = rule.min_spacing_days > 0
+        && last_counted_review_at
+            .as_deref()
+            .and_then(parse_review_timestamp)
+            .is_some_and(|last| Utc::now() - last < chrono::Duration::days(rule.min_spacing_days as i64));
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin wrong question review tx: {}", e))?;
+    db_apply_review(&mut tx, &settings, ReviewItemKind::WrongQuestion, id, grade, response_ms).await?;
+
+    let (review_count, mastery_level, archive) = if too_soon {
+        let mastery_level = if grade == ReviewGrade::Again { 0 } else { mastery_level };
+        (review_count, mastery_level, false)
+    } else {
+        let review_count = review_count + 1;
+        (
+            review_count,
+            rule.mastery_level(review_count, grade),
+            rule.should_archive(review_count, grade),
+        )
+    };
+
+    let now = now_iso();
+    sqlx::query(
+        "UPDATE wrong_questions
+         SET review_count = ?,
+             mastery_level = ?,
+             is_archived = CASE WHEN ? THEN 1 ELSE is_archived END,
+             last_counted_review_at = CASE WHEN ? THEN last_counted_review_at ELSE ? END,
+             updated_at = ?
+         WHERE id = ?",
+    )
+    .bind(review_count)
+    .bind(mastery_level)
+    .bind(archive)
+    .bind(too_soon)
+    .bind(&now)
+    .bind(&now)
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to update wrong question review progress: {}", e))?;
+
+    if archive {
+        sqlx::query("DELETE FROM weekly_review_items WHERE wrong_question_id = ? AND id <> ?")
+            .bind(id)
+            .bind(keep_item_id.unwrap_or(""))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to cleanup weekly items after auto archive: {}", e))?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit wrong question review tx: {}", e))?;
+    Ok(())
+}
+
+/// Records a graded recall and reschedules the question.
+async fn db_review_wrong_question(
+    pool: &sqlx::SqlitePool,
+    id: &str,
+    grade: ReviewGrade,
+    response_ms: Option<i64>,
+) -> Result<WrongQuestion, String> {
+    db_record_wrong_question_review(pool, id, grade, response_ms, None).await?;
+    db_get_wrong_question(pool, id).await
+}
+
+/// Active wrong questions due on or before `date` (default today); never-reviewed ones count as due.
+async fn db_get_due_wrong_questions(
+    pool: &sqlx::SqlitePool,
+    date: Option<&str>,
+    subject: Option<&str>,
+    limit: Option<i64>,
+) -> Result<Vec<WrongQuestion>, String> {
+    let date = match date.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(d) if is_plain_iso_date(d) => d.to_string(),
+        Some(d) => return Err(format!("非法的日期: {}", d)),
+        None => Local::now().format("%Y-%m-%d").to_string(),
+    };
+    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+        "SELECT id, subject, tags_json, question_content, question_image_path, ai_solution, user_note, source, ai_session_id, ai_message_ids_json, difficulty, mastery_level, review_count, next_review_date, last_review_date, ease_factor, interval_days, is_archived, created_at, updated_at, error_causes_json FROM wrong_questions WHERE is_archived = 0 AND (next_review_date IS NULL OR date(next_review_date) <= ",
+    );
+    builder.push_bind(date);
+    builder.push(")");
+    if let Some(subject) = subject.map(str::trim).filter(|s| !s.is_empty()) {
+        builder.push(" AND subject = ");
+        builder.push_bind(subject.to_string());
+    }
+    builder.push(" ORDER BY next_review_date IS NOT NULL, next_review_date ASC, difficulty DESC, created_at ASC");
+    if let Some(limit) = limit.filter(|l| *l > 0) {
+        builder.push(" LIMIT ");
+        builder.push_bind(limit);
+    }
+
+    builder
+        .build_query_as::<WrongQuestion>()
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch due wrong questions: {}", e))
+}
+
+async fn db_create_weekly_review_item_if_absent(
+    pool: &sqlx::SqlitePool,
+    week_start: &str,
+    week_end: &str,
+    wrong_question_id: &str,
+    title_snapshot: &str,
+    carried_from_week: Option<&str>,
+) -> Result<(), String> {
+    let existing = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM weekly_review_items WHERE week_start = ? AND wrong_question_id = ?",
+    )
+    .bind(week_start)
+    .bind(wrong_question_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to check weekly review item duplication: {}", e))?;
+
+    if existing > 0 {
+        return Ok(());
+    }
+
+    let now = now_iso();
+    sqlx::query(
+        "INSERT INTO weekly_review_items (id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, 'pending', ?, NULL, ?, ?)",
+    )
+    .bind(gen_focus_id("weekly-item"))
+    .bind(week_start)
+    .bind(week_end)
+    .bind(wrong_question_id)
+    .bind(title_snapshot)
+    .bind(carried_from_week)
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create weekly review item: {}", e))?;
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WeeklyReviewSettings {
+    pub capacity: i64,
+    pub auto_populate: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WeeklyReviewSettingsPayload {
+    pub capacity: Option<i64>,
+    pub auto_populate: Option<bool>,
+}
+
+const DEFAULT_WEEKLY_REVIEW_CAPACITY: i64 = 20;
+
+async fn db_get_weekly_review_settings(pool: &sqlx::SqlitePool) -> Result<WeeklyReviewSettings, String> {
+    let capacity = db_get_setting(pool, "weekly_review.capacity")
+        .await?
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_WEEKLY_REVIEW_CAPACITY);
+    let auto_populate = db_get_setting(pool, "weekly_review.auto_populate")
+        .await?
+        .map(|v| v == "true")
+        .unwrap_or(false);
+    Ok(WeeklyReviewSettings {
+        capacity,
+        auto_populate,
+    })
+}
+
+async fn db_update_weekly_review_settings(
+    pool: &sqlx::SqlitePool,
+    payload: &WeeklyReviewSettingsPayload,
+) -> Result<WeeklyReviewSettings, String> {
+    if let Some(capacity) = payload.capacity {
+        if !(1..=500).contains(&capacity) {
+            return Err("capacity 需在 1 到 500 之间".to_string());
+        }
+        db_set_setting(pool, "weekly_review.capacity", &capacity.to_string()).await?;
+    }
+    if let Some(auto_populate) = payload.auto_populate {
+        db_set_setting(pool, "weekly_review.auto_populate", &auto_populate.to_string()).await?;
+    }
+    db_get_weekly_review_settings(pool).await
+}
+
+/// Fills the week up to its capacity with wrong questions due by the end of the week
+/// (never-reviewed ones included), skipping questions removed from that week by hand and
+/// questions still pending in any week, so unfinished items are carried rather than duplicated.
+/// Higher difficulty, lower mastery and older entries go first. Returns how many were added.
+async fn db_auto_populate_weekly_review_items(
+    pool: &sqlx::SqlitePool,
+    week_start: &str,
+    week_end: &str,
+    capacity: i64,
+) -> Result<i64, String> {
+    let planned = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM weekly_review_items w
+         JOIN wrong_questions q ON q.id = w.wrong_question_id
+         WHERE w.week_start = ? AND q.is_archived = 0",
+    )
+    .bind(week_start)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count weekly review items: {}", e))?;
+
+    let remaining = capacity - planned;
+    if remaining <= 0 {
+        return Ok(0);
+    }
+
+    let candidates = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT q.id, q.question_content, q.ai_solution FROM wrong_questions q
+         WHERE q.is_archived = 0
+           AND (q.next_review_date IS NULL OR date(q.next_review_date) <= ?)
+           AND q.id NOT IN (SELECT wrong_question_id FROM weekly_review_items WHERE week_start = ? OR status = 'pending')
+           AND q.id NOT IN (SELECT wrong_question_id FROM weekly_review_exclusions WHERE week_start = ?)
+         ORDER BY q.difficulty * 2 + (3 - q.mastery_level) * 3
+                  + MIN(julianday(?) - julianday(date(q.created_at)), 56) / 14.0 DESC,
+                  q.created_at ASC
+         LIMIT ?",
+    )
+    .bind(week_end)
+    .bind(week_start)
+    .bind(week_start)
+    .bind(week_end)
+    .bind(remaining)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch weekly review candidates: {}", e))?;
+
+    let added = candidates.len() as i64;
+    for (id, content, ai_solution) in candidates {
+        db_create_weekly_review_item_if_absent(
+            pool,
+            week_start,
+            week_end,
+            &id,
+            &make_title_snapshot(&content, &ai_solution),
+            None,
+        )
+        .await?;
+    }
+    Ok(added)
+}
+
+/// Manually plans a wrong question into a week; capacity does not apply.
+async fn db_add_weekly_review_item(
+    pool: &sqlx::SqlitePool,
+    week_start: &str,
+    wrong_question_id: &str,
+) -> Result<WeeklyReviewItem, String> {
+    let (start, end) = parse_week_start_monday(week_start)?;
+    let question = db_get_wrong_question(pool, wrong_question_id).await?;
+    if question.is_archived != 0 {
+        return Err("错题已归档，无法加入复习周".to_string());
+    }
+
+    sqlx::query("DELETE FROM weekly_review_exclusions WHERE week_start = ? AND wrong_question_id = ?")
+        .bind(&start)
+        .bind(wrong_question_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to clear weekly review exclusion: {}", e))?;
+
+    db_create_weekly_review_item_if_absent(
+        pool,
+        &start,
+        &end,
+        wrong_question_id,
+        &make_title_snapshot(&question.question_content, &question.ai_solution),
+        None,
+    )
+    .await?;
+
+    sqlx::query_as::<_, WeeklyReviewItem>(
+        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
+         FROM weekly_review_items WHERE week_start = ? AND wrong_question_id = ?",
+    )
+    .bind(&start)
+    .bind(wrong_question_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to read weekly review item: {}", e))
+}
+
+/// Removes an item from its week and keeps auto-planning from adding it back to that week.
+async fn db_remove_weekly_review_item(pool: &sqlx::SqlitePool, item_id: &str) -> Result<(), String> {
+    let (week_start, wrong_question_id) = sqlx::query_as::<_, (String, String)>(
+        "SELECT week_start, wrong_question_id FROM weekly_review_items WHERE id = ?",
+    )
+    .bind(item_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to read weekly review item: {}", e))?
+    .ok_or_else(|| "Weekly review item not found".to_string())?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin weekly review removal tx: {}", e))?;
+    sqlx::query("DELETE FROM weekly_review_items WHERE id = ?")
+        .bind(item_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to remove weekly review item: {}", e))?;
+    sqlx::query(
+        "INSERT OR IGNORE INTO weekly_review_exclusions (week_start, wrong_question_id, created_at) VALUES (?, ?, ?)",
+    )
+    .bind(&week_start)
+    .bind(&wrong_question_id)
+    .bind(now_iso())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to record weekly review exclusion: {}", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit weekly review removal tx: {}", e))?;
+    Ok(())
+}
+
+/// `auto_populate` overrides the stored setting; past weeks are never auto-populated.
+/// Also returns how many due questions auto-populate added, so callers can broadcast the change.
+async fn db_get_weekly_review_items(
+    pool: &sqlx::SqlitePool,
+    week_start: &str,
+    auto_populate: Option<bool>,
+) -> Result<(Vec<WeeklyReviewItem>, i64), String> {
+    let (start, end) = parse_week_start_monday(week_start)?;
+
+    let start_date = chrono::NaiveDate::parse_from_str(&start, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid week_start: {}", e))?;
+    let prev_start = (start_date - chrono::Duration::days(7)).format("%Y-%m-%d").to_string();
+
+    let prev_pending = sqlx::query_as::<_, WeeklyReviewItem>(
+        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
+         FROM weekly_review_items
+         WHERE week_start = ?
+           AND status = 'pending'
+           AND wrong_question_id IN (SELECT id FROM wrong_questions WHERE is_archived = 0)",
+    )
+    .bind(&prev_start)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch previous week pending items: {}", e))?;
+
+    for item in prev_pending {
+        db_create_weekly_review_item_if_absent(
+            pool,
+            &start,
+            &end,
+            &item.wrong_question_id,
+            &item.title_snapshot,
+            Some(&prev_start),
+        )
+        .await?;
+    }
+
+    let settings = db_get_weekly_review_settings(pool).await?;
+    let is_past_week = end < Local::now().format("%Y-%m-%d").to_string();
+    let added = if auto_populate.unwrap_or(settings.auto_populate) && !is_past_week {
+        db_auto_populate_weekly_review_items(pool, &start, &end, settings.capacity).await?
+    } else {
+        0
+    };
+
+    let items = sqlx::query_as::<_, WeeklyReviewItem>(
+        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
+         FROM weekly_review_items
+         WHERE week_start = ?
+           AND wrong_question_id IN (SELECT id FROM wrong_questions WHERE is_archived = 0)
+         ORDER BY status ASC, created_at ASC",
+    )
+    .bind(start)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch weekly review items: {}", e))?;
+    Ok((items, added))
+}
+
+async fn db_toggle_weekly_review_item_done(
+    pool: &sqlx::SqlitePool,
+    item_id: &str,
+    done: bool,
+) -> Result<WeeklyReviewItem, String> {
+    let previous = sqlx::query_as::<_, WeeklyReviewItem>(
+        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
+         FROM weekly_review_items WHERE id = ?",
+    )
+    .bind(item_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to read weekly review item before toggle: {}", e))?
+    .ok_or_else(|| "Weekly review item not found".to_string())?;
+
+    let status = if done { "done" } else { "pending" };
+    let completed_at = if done { Some(now_iso()) } else { None };
+    let result = sqlx::query(
+        "UPDATE weekly_review_items SET status = ?, completed_at = ?, updated_at = ? WHERE id = ?",
+    )
+    .bind(status)
+    .bind(&completed_at)
+    .bind(now_iso())
+    .bind(item_id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to toggle weekly review item: {}", e))?;
+
+    if result.rows_affected() == 0 {
+        return Err("Weekly review item not found".to_string());
+    }
+
+    if done && previous.status != "done" {
+        // Finishing a weekly item counts as a successful ("good") recall.
+        db_record_wrong_question_review(
+            pool,
+            &previous.wrong_question_id,
+            ReviewGrade::Good,
+            None,
+            Some(item_id),
+        )
+        .await?;
+    }
+
+    sqlx::query_as::<_, WeeklyReviewItem>(
+        "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
+         FROM weekly_review_items WHERE id = ?",
+    )
+    .bind(item_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to refetch weekly review item: {}", e))
+}
+
+async fn db_carry_weekly_review_items_to_next_week(
+    pool: &sqlx::SqlitePool,
+    from_week_start: &str,
+    item_ids: &[String],
+) -> Result<(), String> {
+    if item_ids.is_empty() {
+        return Ok(());
+    }
+
+    let (from_start, _) = parse_week_start_monday(from_week_start)?;
+    let from_date = chrono::NaiveDate::parse_from_str(&from_start, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid from_week_start: {}", e))?;
+    let next_start = (from_date + chrono::Duration::days(7)).format("%Y-%m-%d").to_string();
+    let (_, next_end) = parse_week_start_monday(&next_start)?;
+
+    for item_id in item_ids {
+        let row = sqlx::query_as::<_, WeeklyReviewItem>(
+            "SELECT id, week_start, week_end, wrong_question_id, title_snapshot, status, carried_from_week, completed_at, created_at, updated_at
+             FROM weekly_review_items
+             WHERE id = ? AND week_start = ?",
+        )
+        .bind(item_id)
+        .bind(&from_start)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to read source weekly review item: {}", e))?;
+
+        if let Some(item) = row {
+            db_create_weekly_review_item_if_absent(
+                pool,
+                &next_start,
+                &next_end,
+                &item.wrong_question_id,
+                &item.title_snapshot,
+                Some(&from_start),
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn db_get_wrong_question_stats(pool: &sqlx::SqlitePool) -> Result<WrongQuestionStats, String> {
+    let total_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM wrong_questions WHERE is_archived = 0",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count wrong questions: {}", e))?;
+
+    let unmastered_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM wrong_questions WHERE is_archived = 0 AND mastery_level = 0",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count unmastered wrong questions: {}", e))?;
+
+    // Mastery levels follow each subject's rule, so level 3 means the rule's review count was reached.
+    let mastered_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM wrong_questions WHERE is_archived = 0 AND mastery_level >= 3",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count mastered wrong questions: {}", e))?;
+
+    let current_week_start = current_week_start_str();
+    let weekly_pending_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM weekly_review_items w
+         JOIN wrong_questions q ON q.id = w.wrong_question_id
+         WHERE w.week_start = ? AND w.status = 'pending' AND q.is_archived = 0",
+    )
+    .bind(&current_week_start)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count weekly pending items: {}", e))?;
+
+    let weekly_done_count = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM weekly_review_items w
+         JOIN wrong_questions q ON q.id = w.wrong_question_id
+         WHERE w.week_start = ? AND w.status = 'done' AND q.is_archived = 0",
+    )
+    .bind(&current_week_start)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count weekly done items: {}", e))?;
+
+    let this_week_new = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM wrong_questions WHERE is_archived = 0 AND date(created_at) >= date('now','localtime','weekday 0','-6 days')",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count this week new wrong questions: {}", e))?;
+
+    let by_subject = sqlx::query_as::<_, SubjectStat>(
+        "SELECT
+            subject AS subject,
+            COUNT(*) AS count,
+            SUM(CASE WHEN mastery_level = 0 THEN 1 ELSE 0 END) AS unmastered,
+            SUM(CASE WHEN mastery_level >= 3 THEN 1 ELSE 0 END) AS mastered
+         FROM wrong_questions
+         WHERE is_archived = 0
+         GROUP BY subject
+         ORDER BY count DESC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to aggregate wrong questions by subject: {}", e))?;
+
+    let by_cause = sqlx::query_as::<_, ErrorCauseStat>(
+        "SELECT
+            c.value AS cause,
+            COUNT(*) AS count,
+            SUM(CASE WHEN q.mastery_level = 0 THEN 1 ELSE 0 END) AS unmastered
+         FROM wrong_questions q,
+              json_each(CASE WHEN json_valid(q.error_causes_json) THEN q.error_causes_json ELSE '[]' END) c
+         WHERE q.is_archived = 0
+         GROUP BY c.value
+         ORDER BY count DESC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to aggregate wrong questions by cause: {}", e))?;
+
+    // Weekly buckets (Monday start) over the last 12 weeks, keyed by when the question was recorded.
+    let cause_trend = sqlx::query_as::<_, ErrorCauseTrendPoint>(
+        "SELECT
+            date(q.created_at, 'localtime', 'weekday 0', '-6 days') AS week_start,
+            q.subject AS subject,
+            c.value AS cause,
+            COUNT(*) AS count
+         FROM wrong_questions q,
+              json_each(CASE WHEN json_valid(q.error_causes_json) THEN q.error_causes_json ELSE '[]' END) c
+         WHERE q.is_archived = 0
+           AND date(q.created_at, 'localtime') >= date('now', 'localtime', 'weekday 0', '-6 days', '-77 days')
+         GROUP BY week_start, q.subject, c.value
+         ORDER BY week_start ASC, count DESC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to aggregate wrong question cause trend: {}", e))?;
+
+    Ok(WrongQuestionStats {
+        total_count,
+        unmastered_count,
+        mastered_count,
+        weekly_pending_count,
+        weekly_done_count,
+        this_week_new,
+        by_subject,
+        by_cause,
+        cause_trend,
+    })
+}
+
+// ═══════════════════════════════════════════════════════════
+// Knowledge Point Tree
+// ═══════════════════════════════════════════════════════════
+
+const KNOWLEDGE_LEVELS: [&str; 3] = ["subject", "chapter", "point"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct KnowledgeNode {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+    pub level: String, // "subject" | "chapter" | "point"
+    pub position: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct KnowledgeNodePayload {
+    pub parent_id: Option<String>,
+    pub name: Option<String>,
+    pub position: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KnowledgeNodeStats {
+    pub id: String,
+    pub parent_id: Option<String>,
+    pub name: String,
+    pub level: String,
+    pub position: i32,
+    // Rolled up over the subtree; an item assigned to several descendants counts once.
+    pub wrong_question_count: i64,
+    pub unmastered_count: i64,
+    pub question_count: i64,
+    pub due_count: i64,
+    pub recent_reviews: i64,
+    pub recent_errors: i64,
+    pub recent_error_rate: f64, // percent
+    pub children: Vec<KnowledgeNodeStats>,
+}
+
+async fn db_get_knowledge_node(pool: &sqlx::SqlitePool, id: &str) -> Result<KnowledgeNode, String> {
+    sqlx::query_as::<_, KnowledgeNode>(
+        "SELECT id, parent_id, name, level, position, created_at, updated_at FROM knowledge_nodes WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch knowledge node: {}", e))?
+    .ok_or_else(|| "Knowledge node not found".to_string())
+}
+
+async fn db_get_knowledge_nodes(pool: &sqlx::SqlitePool) -> Result<Vec<KnowledgeNode>, String> {
+    sqlx::query_as::<_, KnowledgeNode>(
+        "SELECT id, parent_id, name, level, position, created_at, updated_at FROM knowledge_nodes ORDER BY position ASC, created_at ASC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch knowledge nodes: {}", e))
+}
+
+/// The level is implied by the parent: none → subject, subject → chapter, chapter → point.
+async fn db_create_knowledge_node(
+    pool: &sqlx::SqlitePool,
+    payload: &KnowledgeNodePayload,
+) -> Result<KnowledgeNode, String> {
+    let name = payload
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .ok_or_else(|| "name 不能为空".to_string())?;
+    let parent_id = payload.parent_id.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    let level = match parent_id {
+        None => KNOWLEDGE_LEVELS[0],
+        Some(parent_id) => {
+            let parent = db_get_knowledge_node(pool, parent_id).await?;
+            match KNOWLEDGE_LEVELS.iter().position(|l| *l == parent.level) {
+                Some(index) if index + 1 < KNOWLEDGE_LEVELS.len() => KNOWLEDGE_LEVELS[index + 1],
+                _ => return Err("知识点下不能再添加子节点".to_string()),
+            }
+        }
+    };
+
+    let duplicate = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM knowledge_nodes WHERE COALESCE(parent_id, '') = ? AND name = ?",
+    )
+    .bind(parent_id.unwrap_or(""))
+    .bind(name)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to check knowledge node name: {}", e))?;
+    if duplicate > 0 {
+        return Err(format!("同级已存在同名节点: {}", name));
+    }
+
+    let position = match payload.position {
+        Some(position) => position,
+        None => sqlx::query_scalar::<_, i32>(
+            "SELECT COALESCE(MAX(position), -1) + 1 FROM knowledge_nodes WHERE COALESCE(parent_id, '') = ?",
+        )
+        .bind(parent_id.unwrap_or(""))
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to compute knowledge node position: {}", e))?,
+    };
+
+    let id = gen_unique_id("knowledge");
+    let now = now_iso();
+    sqlx::query(
+        "INSERT INTO knowledge_nodes (id, parent_id, name, level, position, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(parent_id)
+    .bind(name)
+    .bind(level)
+    .bind(position)
+    .bind(&now)
+    .bind(&now)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create knowledge node: {}", e))?;
+    db_get_knowledge_node(pool, &id).await
+}
+
+/// Renames or reorders a node; moving between parents is not supported.
+async fn db_update_knowledge_node(
+    pool: &sqlx::SqlitePool,
+    id: &str,
+    payload: &KnowledgeNodePayload,
+) -> Result<KnowledgeNode, String> {
+    let current = db_get_knowledge_node(pool, id).await?;
+    let name = match payload.name.as_deref().map(str::trim) {
+        Some("") => return Err("name 不能为空".to_string()),
+        Some(name) => name.to_string(),
+        None => current.name.clone(),
+    };
+    sqlx::query("UPDATE knowledge_nodes SET name = ?, position = ?, updated_at = ? WHERE id = ?")
+        .bind(&name)
+        .bind(payload.position.unwrap_or(current.position))
+        .bind(now_iso())
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update knowledge node: {}", e))?;
+    db_get_knowledge_node(pool, id).await
+}
+
+/// Deletes the node with its subtree and their assignments.
+async fn db_delete_knowledge_node(pool: &sqlx::SqlitePool, id: &str) -> Result<(), String> {
+    let result = sqlx::query(
+        "WITH RECURSIVE subtree(id) AS (
+            SELECT id FROM knowledge_nodes WHERE id = ?
+            UNION ALL
+            SELECT n.id FROM knowledge_nodes n JOIN subtree s ON n.parent_id = s.id
+         )
+         DELETE FROM knowledge_nodes WHERE id IN (SELECT id FROM subtree)",
+    )
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to delete knowledge node: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err("Knowledge node not found".to_string());
+    }
+    sqlx::query("DELETE FROM knowledge_node_items WHERE node_id NOT IN (SELECT id FROM knowledge_nodes)")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to cleanup knowledge assignments: {}", e))?;
+    Ok(())
+}
+
+async fn db_get_item_knowledge_nodes(
+    pool: &sqlx::SqlitePool,
+    kind: ReviewItemKind,
+    item_id: &str,
+) -> Result<Vec<KnowledgeNode>, String> {
+    sqlx::query_as::<_, KnowledgeNode>(
+        "SELECT n.id, n.parent_id, n.name, n.level, n.position, n.created_at, n.updated_at
+         FROM knowledge_nodes n JOIN knowledge_node_items i ON i.node_id = n.id
+         WHERE i.item_type = ? AND i.item_id = ?
+         ORDER BY n.position ASC",
+    )
+    .bind(kind.as_str())
+    .bind(item_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch item knowledge nodes: {}", e))
+}
+
+/// Replaces the nodes a question or wrong question is filed under.
+async fn db_assign_knowledge_nodes(
+    pool: &sqlx::SqlitePool,
+    kind: ReviewItemKind,
+    item_id: &str,
+    node_ids: &[String],
+) -> Result<Vec<KnowledgeNode>, String> {
+    let table = match kind {
+        ReviewItemKind::Question => "questions",
+        ReviewItemKind::WrongQuestion => "wrong_questions",
+    };
+    let exists = sqlx::query_scalar::<_, i64>(&format!("SELECT COUNT(*) FROM {} WHERE id = ?", table))
+        .bind(item_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to check {} row: {}", table, e))?;
+    if exists == 0 {
+        return Err(match kind {
+            ReviewItemKind::Question => "Question not found".to_string(),
+            ReviewItemKind::WrongQuestion => "Wrong question not found".to_string(),
+        });
+    }
+    for node_id in node_ids {
+        db_get_knowledge_node(pool, node_id).await?;
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin knowledge assignment tx: {}", e))?;
+    sqlx::query("DELETE FROM knowledge_node_items WHERE item_type = ? AND item_id = ?")
+        .bind(kind.as_str())
+        .bind(item_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to clear knowledge assignments: {}", e))?;
+    for node_id in node_ids {
+        sqlx::query(
+            "INSERT OR IGNORE INTO knowledge_node_items (node_id, item_type, item_id, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(node_id)
+        .bind(kind.as_str())
+        .bind(item_id)
+        .bind(now_iso())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to assign knowledge node: {}", e))?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit knowledge assignment tx: {}", e))?;
+    db_get_item_knowledge_nodes(pool, kind, item_id).await
+}
+
+#[derive(Default, Clone, Copy)]
+struct KnowledgeItemMetrics {
+    is_wrong_question: bool,
+    unmastered: bool,
+    due: bool,
+    recent_reviews: i64,
+    recent_errors: i64,
+}
+
+fn build_knowledge_stats(
+    node: &KnowledgeNode,
+    children: &HashMap<Option<String>, Vec<&KnowledgeNode>>,
+    assignments: &HashMap<String, Vec<(String, String)>>,
+    metrics: &HashMap<(String, String), KnowledgeItemMetrics>,
+) -> (KnowledgeNodeStats, std::collections::HashSet<(String, String)>) {
+    let mut items: std::collections::HashSet<(String, String)> = assignments
+        .get(&node.id)
+        .map(|list| list.iter().filter(|key| metrics.contains_key(*key)).cloned().collect())
+        .unwrap_or_default();
+    let mut child_stats = Vec::new();
+    for child in children.get(&Some(node.id.clone())).into_iter().flatten() {
+        let (stats, child_items) = build_knowledge_stats(child, children, assignments, metrics);
+        items.extend(child_items);
+        child_stats.push(stats);
+    }
+
+    let mut stats = KnowledgeNodeStats {
+        id: node.id.clone(),
+        parent_id: node.parent_id.clone(),
+        name: node.name.clone(),
+        level: node.level.clone(),
+        position: node.position,
+        wrong_question_count: 0,
+        unmastered_count: 0,
+        question_count: 0,
+        due_count: 0,
+        recent_reviews: 0,
+        recent_errors: 0,
+        recent_error_rate: 0.0,
+        children: child_stats,
+    };
+    for m in items.iter().filter_map(|key| metrics.get(key)) {
+        if m.is_wrong_question {
+            stats.wrong_question_count += 1;
+        } else {
+            stats.question_count += 1;
+        }
+        stats.unmastered_count += i64::from(m.unmastered);
+        stats.due_count += i64::from(m.due);
+        stats.recent_reviews += m.recent_reviews;
+        stats.recent_errors += m.recent_errors;
+    }
+    stats.recent_error_rate = review_rate_percent(stats.recent_errors, stats.recent_reviews);
+    (stats, items)
+}
+
+/// The whole tree with per-node figures rolled up from descendants. Archived wrong questions are
+/// left out; "recent" covers the last `recent_days` days (default 30) of the review log.
+async fn db_get_knowledge_tree(
+    pool: &sqlx::SqlitePool,
+    recent_days: Option<i64>,
+) -> Result<Vec<KnowledgeNodeStats>, String> {
+    let nodes = db_get_knowledge_nodes(pool).await?;
+    let assignment_rows = sqlx::query_as::<_, (String, String, String)>(
+        "SELECT node_id, item_type, item_id FROM knowledge_node_items",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch knowledge assignments: {}", e))?;
+
+    let mut metrics = HashMap::<(String, String), KnowledgeItemMetrics>::new();
+    let wrong_rows = sqlx::query_as::<_, (String, i32, bool)>(
+        "SELECT id, mastery_level,
+                (next_review_date IS NULL OR date(next_review_date) <= date('now', 'localtime')) AS due
+         FROM wrong_questions
+         WHERE is_archived = 0
+           AND id IN (SELECT item_id FROM knowledge_node_items WHERE item_type = 'wrong_question')",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch wrong question metrics: {}", e))?;
+    for (id, mastery_level, due) in wrong_rows {
+        metrics.insert(
+            (ReviewItemKind::WrongQuestion.as_str().to_string(), id),
+            KnowledgeItemMetrics {
+                is_wrong_question: true,
+                unmastered: mastery_level == 0,
+                due,
+                ..Default::default()
+            },
+        );
+    }
+    let question_rows = sqlx::query_as::<_, (String, bool)>(
+        "SELECT id,
+                (COALESCE(review_count, 0) > 0 AND datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) <= datetime('now')) AS due
+         FROM questions
+         WHERE id IN (SELECT item_id FROM knowledge_node_items WHERE item_type = 'question')",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch question metrics: {}", e))?;
+    for (id, due) in question_rows {
+        metrics.insert(
+            (ReviewItemKind::Question.as_str().to_string(), id),
+            KnowledgeItemMetrics {
+                due,
+                ..Default::default()
+            },
+        );
+    }
+
+    let recent_days = recent_days.unwrap_or(30).clamp(1, 3650);
+    let since = (Utc::now() - chrono::Duration::days(recent_days)).to_rfc3339();
+    let review_rows = sqlx::query_as::<_, (String, String, i64, i64)>(
+        "SELECT item_type, item_id, COUNT(*), SUM(CASE WHEN grade = 'again' THEN 1 ELSE 0 END)
+         FROM review_log WHERE reviewed_at >= ? GROUP BY item_type, item_id",
+    )
+    .bind(&since)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch recent reviews: {}", e))?;
+    for (item_type, item_id, reviews, errors) in review_rows {
+        if let Some(m) = metrics.get_mut(&(item_type, item_id)) {
+            m.recent_reviews = reviews;
+            m.recent_errors = errors;
+        }
+    }
+
+    let mut assignments = HashMap::<String, Vec<(String, String)>>::new();
+    for (node_id, item_type, item_id) in assignment_rows {
+        assignments.entry(node_id).or_default().push((item_type, item_id));
+    }
+    let mut children = HashMap::<Option<String>, Vec<&KnowledgeNode>>::new();
+    for node in &nodes {
+        children.entry(node.parent_id.clone()).or_default().push(node);
+    }
+
+    Ok(children
+        .get(&None)
+        .into_iter()
+        .flatten()
+        .map(|root| build_knowledge_stats(root, &children, &assignments, &metrics).0)
+        .collect())
+}
+
+// ═══════════════════════════════════════════════════════════
+// Wrong Question Booklet Export
+// ═══════════════════════════════════════════════════════════
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct WrongQuestionBookletPayload {
+    pub filter: Option<WrongQuestionFilter>,
+    pub ids: Option<Vec<String>>, // restricts the filtered set to these questions
+    pub format: Option<String>,   // "html" (default) | "markdown"
+    pub title: Option<String>,
+    pub hide_answers: Option<bool>, // moves solutions and notes into an answer key at the end
+    pub include_notes: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WrongQuestionBookletResult {
+    pub path: String, // relative to the workspace root
+    pub format: String,
+    pub question_count: usize,
+    pub missing_images: Vec<String>,
+}
+
+const BOOKLET_UNTAGGED: &str = "未分类";
+
+struct BookletEntry {
+    number: usize,
+    question: WrongQuestion,
+    image: Option<String>, // data URI
+}
+
+/// (subject, [(tag, entries)])
+type BookletGroups = Vec<(String, Vec<(String, Vec<BookletEntry>)>)>;
+
+/// Same rewrite as the frontend's `normalizeMathDelimiters`: `\[..\]` and `\(..\)` become `$$`/`$`.
+fn normalize_math_delimiters(input: &str) -> String {
+    fn replace_pairs(text: &str, open: &str, close: &str, multiline: bool, wrap: impl Fn(&str) -> String) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find(open) {
+            let after = &rest[start + open.len()..];
+            match after.find(close) {
+                Some(end) if end > 0 && (multiline || !after[..end].contains('\n')) => {
+                    out.push_str(&rest[..start]);
+                    out.push_str(&wrap(after[..end].trim()));
+                    rest = &after[end + close.len()..];
+                }
+                _ => {
+                    out.push_str(&rest[..start + open.len()]);
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    let text = input.replace("\r\n", "\n");
+    let text = replace_pairs(&text, "\\[", "\\]", true, |inner| format!("\n$$\n{}\n$$\n", inner));
+    replace_pairs(&text, "\\(", "\\)", false, |inner| format!("${}$", inner))
+}
+
+/// Typesets TeX as MathML, which browsers render without a stylesheet, fonts or network access.
+#[cfg(any(unix, all(windows, target_env = "gnu")))]
+fn render_math_html(tex: &str, display: bool) -> String {
+    let rendered = katex::Opts::builder()
+        .display_mode(display)
+        .output_type(katex::OutputType::Mathml)
+        .throw_on_error(false)
+        .build()
+        .ok()
+        .and_then(|opts| katex::render_with_opts(tex, &opts).ok());
+    rendered.unwrap_or_else(|| math_source_html(tex, display))
+}
+
+/// No KaTeX engine on this target; the booklet shows the TeX source.
+#[cfg(not(any(unix, all(windows, target_env = "gnu"))))]
+fn render_math_html(tex: &str, display: bool) -> String {
+    math_source_html(tex, display)
+}
+
+fn math_source_html(tex: &str, display: bool) -> String {
+    let class = if display { "math math-display" } else { "math math-inline" };
+    format!("<span class=\"{}\">{}</span>", class, html_escape(tex))
+}
+
+/// Renders question Markdown the way the app shows it. Raw HTML is printed as text, and math is
+/// typeset up front so the file needs nothing from the network.
+fn render_markdown_html(markdown: &str) -> String {
+    use pulldown_cmark::{html, Event, Options, Parser};
+
+    let normalized = normalize_math_delimiters(markdown);
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_MATH;
+    let parser = Parser::new_ext(&normalized, options).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::InlineMath(tex) => Event::InlineHtml(render_math_html(&tex, false).into()),
+        Event::DisplayMath(tex) => Event::InlineHtml(render_math_html(&tex, true).into()),
+        other => other,
+    });
+    let mut out = String::new();
+    html::push_html(&mut out, parser);
+    out
+}
+
+fn html_escape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for ch in raw.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Subject → tags → questions, in subject/tag name order. A question with several tags is filed
+/// under the combination (e.g. "函数 · 导数"), so each question still appears once.
+fn group_booklet_entries(entries: Vec<BookletEntry>) -> BookletGroups {
+    let mut grouped = std::collections::BTreeMap::<String, std::collections::BTreeMap<String, Vec<BookletEntry>>>::new();
+    for entry in entries {
+        let mut tags = parse_focus_tags(&entry.question.tags_json);
+        tags.sort();
+        tags.dedup();
+        let tag = if tags.is_empty() {
+            BOOKLET_UNTAGGED.to_string()
+        } else {
+            tags.join(" · ")
+        };
+        grouped
+            .entry(entry.question.subject.clone())
+            .or_default()
+            .entry(tag)
+            .or_default()
+            .push(entry);
+    }
+    grouped
+        .into_iter()
+        .map(|(subject, tags)| (subject, tags.into_iter().collect()))
+        .collect()
+}
+
+fn render_booklet_markdown(
+    title: &str,
+    groups: &BookletGroups,
+    hide_answers: bool,
+    include_notes: bool,
+) -> String {
+    let mut out = format!("# {}\n\n> 导出时间：{}\n\n", title, Local::now().format("%Y-%m-%d %H:%M"));
+    let mut answer_key = String::new();
+    for (subject, tags) in groups {
+        out.push_str(&format!("## {}\n\n", subject));
+        for (tag, entries) in tags {
+            out.push_str(&format!("### {}\n\n", tag));
+            for entry in entries {
+                let q = &entry.question;
+                out.push_str(&format!(
+                    "#### 第 {} 题\n\n{}\n\n",
+                    entry.number,
+                    normalize_math_delimiters(q.question_content.trim())
+                ));
+                if let Some(image) = &entry.image {
+                    out.push_str(&format!("![第 {} 题配图]({})\n\n", entry.number, image));
+                }
+                let mut answer = format!("**解析**\n\n{}\n\n", normalize_math_delimiters(q.ai_solution.trim()));
+                if let Some(note) = q.user_note.as_deref().map(str::trim).filter(|n| include_notes && !n.is_empty()) {
+                    answer.push_str(&format!("**笔记**\n\n{}\n\n", note));
+                }
+                if hide_answers {
+                    answer_key.push_str(&format!("### 第 {} 题\n\n{}", entry.number, answer));
+                } else {
+                    out.push_str(&answer);
+                }
+                out.push_str("---\n\n");
+            }
+        }
+    }
+    if hide_answers && !answer_key.is_empty() {
+        out.push_str("## 答案\n\n");
+        out.push_str(&answer_key);
+    }
+    out
+}
+
+fn render_booklet_html(
+    title: &str,
+    groups: &BookletGroups,
+    hide_answers: bool,
+    include_notes: bool,
+) -> String {
+    let mut body = String::new();
+    let mut answer_key = String::new();
+    for (subject, tags) in groups {
+        body.push_str(&format!("<section class=\"subject\"><h2>{}</h2>\n", html_escape(subject)));
+        for (tag, entries) in tags {
+            body.push_str(&format!("<h3>{}</h3>\n", html_escape(tag)));
+            for entry in entries {
+                let q = &entry.question;
+                body.push_str(&format!(
+                    "<article class=\"question\"><h4>第 {} 题</h4>\n<div class=\"text\">{}</div>\n",
+                    entry.number,
+                    render_markdown_html(q.question_content.trim())
+                ));
+                if let Some(image) = &entry.image {
+                    body.push_str(&format!("<img src=\"{}\" alt=\"第 {} 题配图\">\n", image, entry.number));
+                }
+                let mut answer = format!(
+                    "<div class=\"answer\"><h5>解析</h5><div class=\"text\">{}</div>",
+                    render_markdown_html(q.ai_solution.trim())
+                );
+                if let Some(note) = q.user_note.as_deref().map(str::trim).filter(|n| include_notes && !n.is_empty()) {
+                    answer.push_str(&format!("<h5>笔记</h5><div class=\"text note\">{}</div>", render_markdown_html(note)));
+                }
+                answer.push_str("</div>\n");
+                if hide_answers {
+                    answer_key.push_str(&format!("<h4>第 {} 题</h4>\n{}", entry.number, answer));
+                } else {
+                    body.push_str(&answer);
+                }
+                body.push_str("</article>\n");
+            }
+        }
+        body.push_str("</section>\n");
+    }
+    if hide_answers && !answer_key.is_empty() {
+        body.push_str("<section class=\"answer-key\"><h2>答案</h2>\n");
+        body.push_str(&answer_key);
+        body.push_str("</section>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"zh-CN\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+<style>\n\
+body {{ font-family: \"Noto Serif SC\", \"Songti SC\", serif; max-width: 820px; margin: 2em auto; line-height: 1.7; color: #222; }}\n\
+h1 {{ text-align: center; }}\n\
+.meta {{ text-align: center; color: #777; }}\n\
+.question {{ border-bottom: 1px dashed #bbb; padding: 0.5em 0 1em; page-break-inside: avoid; }}\n\
+.text p {{ margin: 0.4em 0; }}\n\
+.math-display, math[display=\"block\"] {{ display: block; text-align: center; margin: 0.6em 0; }}\n\
+table {{ border-collapse: collapse; }}\n\
+td, th {{ border: 1px solid #bbb; padding: 0.2em 0.5em; }}\n\
+.note {{ color: #555; }}\n\
+img {{ max-width: 100%; margin: 0.5em 0; }}\n\
+.subject, .answer-key {{ page-break-before: always; }}\n\
+.subject:first-of-type {{ page-break-before: auto; }}\n\
+</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p class=\"meta\">导出时间：{exported}</p>\n{body}</body>\n</html>\n",
+        title = html_escape(title),
+        exported = Local::now().format("%Y-%m-%d %H:%M"),
+        body = body,
+    )
+}
+
+/// Reads a workspace image into a data URI; `None` when the path is unsafe or unreadable.
+async fn booklet_image_data_uri(root: &Path, path: &str) -> Option<String> {
+    use base64::Engine;
+
+    let relative = sanitize_relative_path(path).ok().filter(|p| !p.as_os_str().is_empty())?;
+    let bytes = fs::read(root.join(&relative)).await.ok()?;
+    Some(format!(
+        "data:{};base64,{}",
+        from_path(&relative).first_or_octet_stream(),
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// Rewrites Markdown images that point into the workspace (`ErrorImages/a.png`, `/api/images/...`)
+/// to data URIs; remote and data URLs are kept, unreadable paths are pushed onto `missing`.
+async fn inline_markdown_images(root: &Path, markdown: &str, missing: &mut Vec<String>) -> String {
+    use pulldown_cmark::{Event, Options, Parser, Tag};
+
+    let mut targets = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_MATH).into_offset_iter() {
+        if let Event::Start(Tag::Image { dest_url, .. }) = event {
+            let url = dest_url.trim().to_string();
+            let lower = url.to_ascii_lowercase();
+            if url.is_empty() || ["http://", "https://", "data:"].iter().any(|p| lower.starts_with(p)) {
+                continue;
+            }
+            // Reference-style images keep their URL elsewhere in the text; those are left alone.
+            if let Some(pos) = markdown[range.clone()].rfind(url.as_str()) {
+                targets.push((range.start + pos, url));
+            }
+        }
+    }
+
+    let mut out = markdown.to_string();
+    for (start, url) in targets.into_iter().rev() {
+        let path = url.trim_start_matches('/');
+        let path = path.strip_prefix("api/images/").unwrap_or(path);
+        match booklet_image_data_uri(root, path).await {
+            Some(uri) => out.replace_range(start..start + url.len(), &uri),
+            None => missing.push(url),
+        }
+    }
+    out
+}
+
+/// Renders the selected wrong questions into a booklet under `Exports/` in the workspace. Images,
+/// including those referenced from the Markdown, are inlined as data URIs and HTML math is
+/// typeset as MathML, so the file opens offline.
+async fn write_wrong_question_booklet(
+    pool: &sqlx::SqlitePool,
+    workspace_root: &str,
+    payload: &WrongQuestionBookletPayload,
+) -> Result<WrongQuestionBookletResult, String> {
+    let format = match payload.format.as_deref().map(|f| f.trim().to_lowercase()) {
+        None => "html".to_string(),
+        Some(f) if f.is_empty() || f == "html" => "html".to_string(),
+        Some(f) if f == "markdown" || f == "md" => "markdown".to_string(),
+        Some(f) => return Err(format!("format 仅支持 html 或 markdown，收到: {}", f)),
+    };
+    let filter = payload.filter.clone().unwrap_or(WrongQuestionFilter {
+        subject: None,
+        mastery_level: None,
+        search_keyword: None,
+        is_archived: Some(0),
+        error_cause: None,
+    });
+    let mut questions = db_get_wrong_questions(pool, &filter).await?;
+    if let Some(ids) = payload.ids.as_ref().filter(|ids| !ids.is_empty()) {
+        questions.retain(|q| ids.contains(&q.id));
+    }
+    if questions.is_empty() {
+        return Err("没有符合条件的错题".to_string());
+    }
+    questions.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+    let root = PathBuf::from(workspace_root);
+    let mut missing_images = Vec::new();
+    let mut entries = Vec::with_capacity(questions.len());
+    for mut question in questions {
+        let image = match question.question_image_path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+            Some(path) => {
+                let uri = booklet_image_data_uri(&root, path).await;
+                if uri.is_none() {
+                    missing_images.push(path.to_string());
+                }
+                uri
+            }
+            None => None,
+        };
+        question.question_content = inline_markdown_images(&root, &question.question_content, &mut missing_images).await;
+        question.ai_solution = inline_markdown_images(&root, &question.ai_solution, &mut missing_images).await;
+        if let Some(note) = question.user_note.take() {
+            question.user_note = Some(inline_markdown_images(&root, &note, &mut missing_images).await);
+        }
+        entries.push(BookletEntry {
+            number: 0,
+            question,
+            image,
+        });
+    }
+
+    let question_count = entries.len();
+    let mut groups = group_booklet_entries(entries);
+    let mut number = 0;
+    for (_, tags) in groups.iter_mut() {
+        for (_, entries) in tags.iter_mut() {
+            for entry in entries.iter_mut() {
+                number += 1;
+                entry.number = number;
+            }
+        }
+    }
+
+    let title = payload
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or("错题本")
+        .to_string();
+    let hide_answers = payload.hide_answers.unwrap_or(false);
+    let include_notes = payload.include_notes.unwrap_or(true);
+    let (content, ext) = if format == "html" {
+        (render_booklet_html(&title, &groups, hide_answers, include_notes), "html")
+    } else {
+        (render_booklet_markdown(&title, &groups, hide_answers, include_notes), "md")
+    };
+
+    let exports_dir = root.join("Exports");
+    fs::create_dir_all(&exports_dir)
+        .await
+        .map_err(|e| format!("Failed to create exports dir: {}", e))?;
+    let file_name = format!("错题本-{}.{}", Local::now().format("%Y%m%d-%H%M%S"), ext);
+    fs::write(exports_dir.join(&file_name), content)
+        .await
+        .map_err(|e| format!("Failed to write booklet: {}", e))?;
+
+    Ok(WrongQuestionBookletResult {
+        path: format!("Exports/{}", file_name),
+        format,
+        question_count,
+        missing_images,
+    })
+}
+
+// ═══════════════════════════════════════════════════════════
+// Anki Package Export & Import
+// ═══════════════════════════════════════════════════════════
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AnkiExportPayload {
+    pub deck_name: Option<String>, // root deck, default "EVA"
+    pub include_questions: Option<bool>,
+    pub include_wrong_questions: Option<bool>,
+    pub question_subject: Option<String>,
+    pub wrong_question_filter: Option<WrongQuestionFilter>,
+    pub include_scheduling: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnkiExportResult {
+    pub path: String, // relative to the workspace root
+    pub note_count: usize,
+    pub media_count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AnkiImportPayload {
+    pub file_path: String,       // absolute, or relative to the workspace root
+    pub subject: Option<String>, // one of QUIZ_SUBJECTS; overrides the subject matched from the deck name
+    pub keep_scheduling: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnkiImportReport {
+    pub total: usize,
+    pub created: usize,
+    pub skipped: usize, // already imported or same stem and answer
+    pub media_copied: usize,
+    pub errors: Vec<String>,
+}
+
+const ANKI_FIELD_SEPARATOR: char = '\u{1f}';
+const ANKI_MEDIA_DIR: &str = "ErrorImages/Anki";
+// Subjects the Quiz page lists; imported cards must land in one of them to be practised.
+const QUIZ_SUBJECTS: [&str; 4] = ["408", "数一", "英一", "政治"];
+
+struct AnkiCardDraft {
+    guid: String,
+    deck: String,
+    front: String,
+    back: String,
+    tags: Vec<String>,
+    // (ease_factor, interval_days, reps, lapses, next review date); None = new card
+    schedule: Option<(f64, i32, i32, i32, chrono::NaiveDate)>,
+}
+
+fn anki_text_to_html(text: &str) -> String {
+    html_escape(text.trim()).replace('\n', "<br>")
+}
+
+fn anki_strip_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for ch in html.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(ch),
+            _ => {}
+        }
+    }
+    out.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Anki's duplicate checksum: the first 8 hex digits of SHA-1 over the stripped sort field.
+fn anki_field_checksum(field: &str) -> i64 {
+    use sha1::{Digest, Sha1};
+    let digest = Sha1::digest(anki_strip_html(field).as_bytes());
+    i64::from(u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]))
+}
+
+fn anki_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// Renders quiz options stored as a JSON array or object; anything else is shown verbatim.
+fn quiz_options_text(options: Option<&str>) -> String {
+    let Some(raw) = options.map(str::trim).filter(|o| !o.is_empty()) else {
+        return String::new();
+    };
+    match serde_json::from_str::<serde_json::Value>(raw) {
+        Ok(serde_json::Value::Array(items)) => items
+            .iter()
+            .map(|v| v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Ok(serde_json::Value::Object(map)) => map
+            .iter()
+            .map(|(k, v)| format!("{}. {}", k, v.as_str().map(str::to_string).unwrap_or_else(|| v.to_string())))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => raw.to_string(),
+    }
+}
+
+/// `col` JSON blobs: (conf, models, decks, dconf) for a single basic note type.
+fn anki_collection_json(model_id: i64, decks: &[(i64, String)]) -> (String, String, String, String) {
+    let now = Utc::now().timestamp();
+    let conf = json!({
+        "nextPos": 1, "estTimes": true, "activeDecks": [1], "sortType": "noteFld", "timeLim": 0,
+        "sortBackwards": false, "addToCur": true, "curDeck": 1, "newBury": true, "newSpread": 0,
+        "dueCounts": true, "curModel": model_id.to_string(), "collapseTime": 1200
+    });
+    let field = |name: &str, ord: i32| {
+        json!({ "name": name, "ord": ord, "sticky": false, "rtl": false, "font": "Arial", "size": 20, "media": [] })
+    };
+    let models = json!({
+        model_id.to_string(): {
+            "id": model_id, "name": "EVA 问答", "type": 0, "mod": now, "usn": -1, "sortf": 0,
+            "did": decks.first().map(|(id, _)| *id).unwrap_or(1),
+            "tmpls": [{
+                "name": "Card 1", "ord": 0, "qfmt": "{{Front}}",
+                "afmt": "{{FrontSide}}\n\n<hr id=answer>\n\n{{Back}}",
+                "did": null, "bqfmt": "", "bafmt": ""
+            }],
+            "flds": [field("Front", 0), field("Back", 1)],
+            "css": ".card { font-family: sans-serif; font-size: 18px; text-align: left; color: black; background-color: white; }\nimg { max-width: 100%; }",
+            "latexPre": "\\documentclass[12pt]{article}\n\\special{papersize=3in,5in}\n\\usepackage[utf8]{inputenc}\n\\usepackage{amssymb,amsmath}\n\\pagestyle{empty}\n\\setlength{\\parindent}{0in}\n\\begin{document}\n",
+            "latexPost": "\\end{document}",
+            "tags": [], "vers": [], "req": [[0, "any", [0]]]
+        }
+    });
+    let deck = |id: i64, name: &str| {
+        json!({
+            "id": id, "name": name, "mod": now, "usn": -1, "desc": "", "dyn": 0, "conf": 1,
+            "collapsed": false, "extendNew": 10, "extendRev": 50,
+            "lrnToday": [0, 0], "revToday": [0, 0], "newToday": [0, 0], "timeToday": [0, 0]
+        })
+    };
+    let mut deck_map = serde_json::Map::new();
+    deck_map.insert("1".to_string(), deck(1, "Default"));
+    for (id, name) in decks {
+        deck_map.insert(id.to_string(), deck(*id, name));
+    }
+    let dconf = json!({
+        "1": {
+            "id": 1, "name": "Default", "mod": 0, "usn": 0, "maxTaken": 60, "autoplay": true,
+            "timer": 0, "replayq": true, "dyn": false,
+            "new": { "delays": [1, 10], "ints": [1, 4, 7], "initialFactor": 2500, "order": 1, "perDay": 20, "bury": true, "separate": true },
+            "rev": { "perDay": 200, "ease4": 1.3, "fuzz": 0.05, "ivlFct": 1, "maxIvl": 36500, "bury": true, "minSpace": 1 },
+            "lapse": { "delays": [10], "mult": 0, "minInt": 1, "leechFails": 8, "leechAction": 0 }
+        }
+    });
+    (
+        conf.to_string(),
+        models.to_string(),
+        serde_json::Value::Object(deck_map).to_string(),
+        dconf.to_string(),
+    )
+}
+
+const ANKI_SCHEMA: [&str; 9] = [
+    "CREATE TABLE col (id integer primary key, crt integer not null, mod integer not null, scm integer not null, ver integer not null, dty integer not null, usn integer not null, ls integer not null, conf text not null, models text not null, decks text not null, dconf text not null, tags text not null)",
+    "CREATE TABLE notes (id integer primary key, guid text not null, mid integer not null, mod integer not null, usn integer not null, tags text not null, flds text not null, sfld integer not null, csum integer not null, flags integer not null, data text not null)",
+    "CREATE TABLE cards (id integer primary key, nid integer not null, did integer not null, ord integer not null, mod integer not null, usn integer not null, type integer not null, queue integer not null, due integer not null, ivl integer not null, factor integer not null, reps integer not null, lapses integer not null, left integer not null, odue integer not null, odid integer not null, flags integer not null, data text not null)",
+    "CREATE TABLE revlog (id integer primary key, cid integer not null, usn integer not null, ease integer not null, ivl integer not null, lastIvl integer not null, factor integer not null, time integer not null, type integer not null)",
+    "CREATE TABLE graves (usn integer not null, oid integer not null, type integer not null)",
+    "CREATE INDEX ix_notes_usn on notes (usn)",
+    "CREATE INDEX ix_cards_usn on cards (usn)",
+    "CREATE INDEX ix_cards_nid on cards (nid)",
+    "CREATE INDEX ix_cards_sched on cards (did, queue, due)",
+];
+
+fn anki_temp_path(ext: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}.{}", gen_unique_id("eva-anki"), ext))
+}
+
+async fn anki_open_collection(path: &Path, create: bool) -> Result<sqlx::SqliteConnection, String> {
+    use sqlx::Connection;
+    let options = sqlx::sqlite::SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(create);
+    sqlx::SqliteConnection::connect_with(&options)
+        .await
+        .map_err(|e| format!("Failed to open Anki collection: {}", e))
+}
+
+/// Writes a schema-11 collection with one basic note and card per draft.
+async fn write_anki_collection(path: &Path, root_deck: &str, drafts: &[AnkiCardDraft]) -> Result<(), String> {
+    use sqlx::Connection;
+    let mut conn = anki_open_collection(path, true).await?;
+    for statement in ANKI_SCHEMA {
+        sqlx::query(statement)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to create Anki schema: {}", e))?;
+    }
+
+    let now_ms = Utc::now().timestamp_millis();
+    let today = Local::now().date_naive();
+    let crt = today
+        .and_hms_opt(0, 0, 0)
+        .and_then(|dt| dt.and_local_timezone(Local).single())
+        .map(|dt| dt.timestamp())
+        .unwrap_or(now_ms / 1000);
+    let model_id = now_ms;
+
+    let mut deck_ids = HashMap::<String, i64>::new();
+    for draft in drafts {
+        let next_id = now_ms + 1 + deck_ids.len() as i64;
+        deck_ids.entry(draft.deck.clone()).or_insert(next_id);
+    }
+    let mut decks: Vec<(i64, String)> = deck_ids.iter().map(|(name, id)| (*id, name.clone())).collect();
+    decks.sort();
+    if !deck_ids.contains_key(root_deck) {
+        decks.insert(0, (now_ms, root_deck.to_string()));
+    }
+    let (conf, models, decks_json, dconf) = anki_collection_json(model_id, &decks);
+
+    sqlx::query("INSERT INTO col VALUES (1, ?, ?, ?, 11, 0, 0, 0, ?, ?, ?, ?, '{}')")
+        .bind(crt)
+        .bind(now_ms)
+        .bind(now_ms)
+        .bind(conf)
+        .bind(models)
+        .bind(decks_json)
+        .bind(dconf)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to write Anki collection: {}", e))?;
+
+    let mut tx = conn
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin Anki export tx: {}", e))?;
+    let base_id = now_ms + 1000;
+    for (index, draft) in drafts.iter().enumerate() {
+        let note_id = base_id + index as i64;
+        let tags = if draft.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" {} ", draft.tags.join(" "))
+        };
+        sqlx::query("INSERT INTO notes VALUES (?, ?, ?, ?, -1, ?, ?, ?, ?, 0, '')")
+            .bind(note_id)
+            .bind(&draft.guid)
+            .bind(model_id)
+            .bind(now_ms / 1000)
+            .bind(tags)
+            .bind(format!("{}{}{}", draft.front, ANKI_FIELD_SEPARATOR, draft.back))
+            .bind(anki_strip_html(&draft.front))
+            .bind(anki_field_checksum(&draft.front))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to write Anki note: {}", e))?;
+
+        let (card_type, due, ivl, factor, reps, lapses) = match draft.schedule {
+            Some((ease, interval, reps, lapses, next)) => (
+                2,
+                (next - today).num_days().max(0),
+                interval.max(1),
+                (ease * 1000.0).round() as i64,
+                reps,
+                lapses,
+            ),
+            None => (0, index as i64 + 1, 0, 0, 0, 0),
+        };
+        sqlx::query("INSERT INTO cards VALUES (?, ?, ?, 0, ?, -1, ?, ?, ?, ?, ?, ?, ?, 0, 0, 0, 0, '')")
+            .bind(note_id)
+            .bind(note_id)
+            .bind(deck_ids.get(&draft.deck).copied().unwrap_or(1))
+            .bind(now_ms / 1000)
+            .bind(card_type)
+            .bind(card_type)
+            .bind(due)
+            .bind(ivl)
+            .bind(factor)
+            .bind(reps)
+            .bind(lapses)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to write Anki card: {}", e))?;
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit Anki export tx: {}", e))?;
+    conn.close()
+        .await
+        .map_err(|e| format!("Failed to close Anki collection: {}", e))
+}
+
+fn build_apkg(collection: Vec<u8>, media: &[(String, Vec<u8>)]) -> Result<Vec<u8>, String> {
+    use std::io::Write;
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let zip_err = |e: zip::result::ZipError| format!("Failed to build apkg: {}", e);
+    let io_err = |e: std::io::Error| format!("Failed to build apkg: {}", e);
+
+    writer.start_file("collection.anki2", options).map_err(zip_err)?;
+    writer.write_all(&collection).map_err(io_err)?;
+
+    let mut media_map = serde_json::Map::new();
+    for (index, (name, bytes)) in media.iter().enumerate() {
+        media_map.insert(index.to_string(), json!(name));
+        writer.start_file(index.to_string(), options).map_err(zip_err)?;
+        writer.write_all(bytes).map_err(io_err)?;
+    }
+    writer.start_file("media", options).map_err(zip_err)?;
+    writer
+        .write_all(serde_json::Value::Object(media_map).to_string().as_bytes())
+        .map_err(io_err)?;
+
+    writer
+        .finish()
+        .map(|cursor| cursor.into_inner())
+        .map_err(zip_err)
+}
+
+/// Exports quiz and wrong questions into `Exports/*.apkg`, one subdeck per source and subject.
+async fn write_anki_package(
+    pool: &sqlx::SqlitePool,
+    workspace_root: &str,
+    payload: &AnkiExportPayload,
+) -> Result<AnkiExportResult, String> {
+    let root_deck = payload
+        .deck_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .unwrap_or("EVA")
+        .to_string();
+    let with_scheduling = payload.include_scheduling.unwrap_or(true);
+    let root = PathBuf::from(workspace_root);
+    let mut drafts = Vec::new();
+    let mut media: Vec<(String, Vec<u8>)> = Vec::new();
+
+    if payload.include_questions.unwrap_or(true) {
+        let subject = payload.question_subject.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        for q in db_fetch_all_questions(pool).await? {
+            if subject.is_some_and(|s| s != q.subject) {
+                continue;
+            }
+            let options = quiz_options_text(q.options.as_deref());
+            let front = if options.is_empty() {
+                anki_text_to_html(&q.stem)
+            } else {
+                format!("{}<br><br>{}", anki_text_to_html(&q.stem), anki_text_to_html(&options))
+            };
+            let back = if q.explanation.trim().is_empty() {
+                anki_text_to_html(&q.answer)
+            } else {
+                format!("{}<br><br>{}", anki_text_to_html(&q.answer), anki_text_to_html(&q.explanation))
+            };
+            let schedule = q
+                .next_review
+                .as_deref()
+                .and_then(parse_review_timestamp)
+                .filter(|_| with_scheduling && q.review_count > 0 && q.r#interval > 0)
+                .map(|next| {
+                    (
+                        q.ease_factor,
+                        q.r#interval,
+                        q.review_count,
+                        (q.review_count - q.correct_count).max(0),
+                        next.with_timezone(&Local).date_naive(),
+                    )
+                });
+            let mut tags: Vec<String> = parse_focus_tags(&q.tags_json).iter().map(|t| anki_tag(t)).collect();
+            tags.push("eva_quiz".to_string());
+            drafts.push(AnkiCardDraft {
+                guid: q.id.clone(),
+                deck: format!("{}::题库::{}", root_deck, q.subject),
+                front,
+                back,
+                tags,
+                schedule,
+            });
+        }
+    }
+
+    if payload.include_wrong_questions.unwrap_or(true) {
+        let filter = payload.wrong_question_filter.clone().unwrap_or(WrongQuestionFilter {
+            subject: None,
+            mastery_level: None,
+            search_keyword: None,
+            is_archived: Some(0),
+            error_cause: None,
+        });
+        for q in db_get_wrong_questions(pool, &filter).await? {
+            let mut front = anki_text_to_html(&q.question_content);
+            if let Some(path) = q.question_image_path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+                if let Ok(relative) = sanitize_relative_path(path) {
+                    if let Ok(bytes) = fs::read(root.join(&relative)).await {
+                        let base = relative
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or_else(|| "image.png".to_string());
+                        let name = format!("{}-{}", media.len(), base);
+                        front.push_str(&format!("<br><img src=\"{}\">", html_escape(&name)));
+                        media.push((name, bytes));
+                    }
+                }
+            }
+            let mut back = anki_text_to_html(&q.ai_solution);
+            if let Some(note) = q.user_note.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+                back.push_str(&format!("<br><br><b>笔记</b><br>{}", anki_text_to_html(note)));
+            }
+            let schedule = q
+                .next_review_date
+                .as_deref()
+                .and_then(parse_review_timestamp)
+                .filter(|_| with_scheduling && q.review_count > 0)
+                .map(|next| (q.ease_factor, q.interval_days, q.review_count, 0, next.date_naive()));
+            let mut tags: Vec<String> = parse_focus_tags(&q.tags_json).iter().map(|t| anki_tag(t)).collect();
+            tags.push("eva_wrong_question".to_string());
+            drafts.push(AnkiCardDraft {
+                guid: q.id.clone(),
+                deck: format!("{}::错题本::{}", root_deck, q.subject),
+                front,
+                back,
+                tags,
+                schedule,
+            });
+        }
+    }
+
+    if drafts.is_empty() {
+        return Err("没有可导出的题目".to_string());
+    }
+
+    let collection_path = anki_temp_path("anki2");
+    let written = write_anki_collection(&collection_path, &root_deck, &drafts).await;
+    let collection = match written {
+        Ok(()) => fs::read(&collection_path)
+            .await
+            .map_err(|e| format!("Failed to read Anki collection: {}", e)),
+        Err(e) => Err(e),
+    };
+    fs::remove_file(&collection_path).await.ok();
+    let package = build_apkg(collection?, &media)?;
+
+    let exports_dir = root.join("Exports");
+    fs::create_dir_all(&exports_dir)
+        .await
+        .map_err(|e| format!("Failed to create exports dir: {}", e))?;
+    let file_name = format!("{}-{}.apkg", root_deck.replace(['/', '\\', ':'], "_"), Local::now().format("%Y%m%d-%H%M%S"));
+    fs::write(exports_dir.join(&file_name), package)
+        .await
+        .map_err(|e| format!("Failed to write apkg: {}", e))?;
+
+    Ok(AnkiExportResult {
+        path: format!("Exports/{}", file_name),
+        note_count: drafts.len(),
+        media_count: media.len(),
+    })
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct AnkiNoteRow {
+    guid: String,
+    tags: String,
+    flds: String,
+    card_id: Option<i64>,
+    did: Option<i64>,
+    queue: Option<i64>,
+    due: Option<i64>,
+    ivl: Option<i64>,
+    factor: Option<i64>,
+    reps: Option<i64>,
+    lapses: Option<i64>,
+}
+
+enum AnkiDue {
+    Day(i64),       // days since the collection was created
+    Timestamp(i64), // unix seconds
+}
+
+/// Interprets `cards.due`, whose unit depends on the queue the card sits in:
+/// intraday learning (1) stores a timestamp, review (2) and interday learning (3)
+/// store a day number. Suspended/buried cards (< 0) keep the value from their old
+/// queue, so fall back to Anki's own magnitude check. New cards (0) have no due date.
+fn anki_due_kind(queue: Option<i64>, due: Option<i64>) -> Option<AnkiDue> {
+    let due = due?;
+    match queue? {
+        1 => Some(AnkiDue::Timestamp(due)),
+        2 | 3 => Some(AnkiDue::Day(due)),
+        q if q < 0 && due > 1_000_000_000 => Some(AnkiDue::Timestamp(due)),
+        q if q < 0 && due > 0 => Some(AnkiDue::Day(due)),
+        _ => None,
+    }
+}
+
+/// Maps a deck path such as `考研::数一::高数` onto a Quiz subject.
+fn anki_deck_subject(deck_name: &str) -> Option<&'static str> {
+    deck_name
+        .rsplit("::")
+        .find_map(|part| QUIZ_SUBJECTS.iter().copied().find(|subject| part.trim() == *subject))
+}
+
+/// Collects `<img src="...">` file names referenced by a field.
+fn anki_image_refs(html: &str) -> Vec<String> {
+    let mut refs = Vec::new();
+    // ASCII lowering keeps byte offsets aligned with `html`.
+    let lower = html.to_ascii_lowercase();
+    let mut cursor = 0;
+    while let Some(pos) = lower[cursor..].find("<img") {
+        let start = cursor + pos;
+        let end = lower[start..].find('>').map(|e| start + e).unwrap_or(lower.len());
+        let tag = &html[start..end];
+        if let Some(src_pos) = lower[start..end].find("src=") {
+            let rest = &tag[src_pos + 4..];
+            let value = match rest.chars().next() {
+                Some(quote @ ('"' | '\'')) => rest[1..].split(quote).next().unwrap_or(""),
+                _ => rest.split_whitespace().next().unwrap_or(""),
+            };
+            if !value.is_empty() {
+                refs.push(value.to_string());
+            }
+        }
+        cursor = end;
+    }
+    refs
+}
+
+/// Field HTML to the plain text stored on questions; images become Markdown links into the workspace.
+fn anki_field_to_text(html: &str, media_paths: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(pos) = rest.to_ascii_lowercase().find("<img") {
+        out.push_str(&rest[..pos]);
+        let end = rest[pos..].find('>').map(|e| pos + e + 1).unwrap_or(rest.len());
+        if let Some(src) = anki_image_refs(&rest[pos..end]).into_iter().next() {
+            let path = media_paths.get(&src).unwrap_or(&src);
+            out.push_str(&format!("\n![]({})\n", path));
+        }
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    let text = out
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n")
+        .replace("</div>", "\n")
+        .replace("</p>", "\n");
+    anki_strip_html(&text).trim().to_string()
+}
+
+/// Imports the notes of an `.apkg` as quiz questions: first field → stem, second → answer,
+/// remaining fields → explanation, deck → subject, tags → tags_json. Review cards keep their
+/// interval, ease and due date unless `keep_scheduling` is false.
+async fn db_import_anki_package(
+    pool: &sqlx::SqlitePool,
+    workspace_root: &str,
+    payload: &AnkiImportPayload,
+) -> Result<AnkiImportReport, String> {
+    use std::io::Read;
+    use sqlx::Connection;
+
+    let raw_path = payload.file_path.trim();
+    if raw_path.is_empty() {
+        return Err("file_path 不能为空".to_string());
+    }
+    let input = PathBuf::from(raw_path);
+    let package_path = if input.is_absolute() {
+        input
+    } else {
+        PathBuf::from(workspace_root).join(sanitize_relative_path(raw_path)?)
+    };
+    let package = fs::read(&package_path)
+        .await
+        .map_err(|e| format!("Failed to read apkg {}: {}", package_path.to_string_lossy(), e))?;
+
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(package))
+        .map_err(|e| format!("无法解析 apkg 文件: {}", e))?;
+    let collection_name = ["collection.anki21", "collection.anki2"]
+        .into_iter()
+        .find(|name| archive.index_for_name(name).is_some())
+        .ok_or_else(|| {
+            if archive.index_for_name("collection.anki21b").is_some() {
+                "暂不支持新版 Anki 格式，请在 Anki 导出时勾选“支持旧版 Anki”".to_string()
+            } else {
+                "apkg 中缺少 collection.anki2".to_string()
+            }
+        })?;
+    let mut collection = Vec::new();
+    archive
+        .by_name(collection_name)
+        .map_err(|e| format!("Failed to read Anki collection: {}", e))?
+        .read_to_end(&mut collection)
+        .map_err(|e| format!("Failed to read Anki collection: {}", e))?;
+    // name -> zip entry
+    let media_index: HashMap<String, String> = match archive.by_name("media") {
+        Ok(mut entry) => {
+            let mut raw = String::new();
+            entry.read_to_string(&mut raw).ok();
+            serde_json::from_str::<HashMap<String, String>>(&raw)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(index, name)| (name, index))
+                .collect()
+        }
+        Err(_) => HashMap::new(),
+    };
+
+    let collection_path = anki_temp_path("anki2");
+    fs::write(&collection_path, collection)
+        .await
+        .map_err(|e| format!("Failed to stage Anki collection: {}", e))?;
+    let loaded = async {
+        let mut conn = anki_open_collection(&collection_path, false).await?;
+        let (crt, decks) = sqlx::query_as::<_, (i64, String)>("SELECT crt, decks FROM col")
+            .fetch_one(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read Anki collection: {}", e))?;
+        let notes = sqlx::query_as::<_, AnkiNoteRow>(
+            "SELECT n.guid, n.tags, n.flds, c.id AS card_id, c.did, c.queue, c.due, c.ivl, c.factor, c.reps, c.lapses
+             FROM notes n
+             LEFT JOIN cards c ON c.id = (SELECT id FROM cards WHERE nid = n.id ORDER BY ord LIMIT 1)
+             ORDER BY n.id",
+        )
+        .fetch_all(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to read Anki notes: {}", e))?;
+        let last_reviews = sqlx::query_as::<_, (i64, i64)>("SELECT cid, MAX(id) FROM revlog GROUP BY cid")
+            .fetch_all(&mut conn)
+            .await
+            .map_err(|e| format!("Failed to read Anki review log: {}", e))?;
+        conn.close().await.ok();
+        Ok::<_, String>((crt, decks, notes, last_reviews.into_iter().collect::<HashMap<i64, i64>>()))
+    }
+    .await;
+    fs::remove_file(&collection_path).await.ok();
+    let (crt, decks, notes, last_reviews) = loaded?;
+
+    let decks: serde_json::Value = serde_json::from_str(&decks).unwrap_or_default();
+    let crt_date = chrono::DateTime::from_timestamp(crt, 0)
+        .map(|dt| dt.with_timezone(&Local).date_naive())
+        .unwrap_or_else(|| Local::now().date_naive());
+
+    // Copy referenced images into the workspace before converting fields.
+    let media_dir = PathBuf::from(workspace_root).join(ANKI_MEDIA_DIR);
+    let mut media_paths = HashMap::<String, String>::new();
+    let mut errors = Vec::new();
+    for name in notes.iter().flat_map(|n| anki_image_refs(&n.flds)) {
+        if media_paths.contains_key(&name) {
+            continue;
+        }
+        let Some(index) = media_index.get(&name) else {
+            continue;
+        };
+        let safe_name: String = name
+            .chars()
+            .map(|c| if c == '/' || c == '\\' || c == ':' { '_' } else { c })
+            .collect();
+        let mut bytes = Vec::new();
+        match archive.by_name(index) {
+            Ok(mut entry) => {
+                if let Err(e) = entry.read_to_end(&mut bytes) {
+                    errors.push(format!("媒体 {} 读取失败: {}", name, e));
+                    continue;
+                }
+            }
+            Err(e) => {
+                errors.push(format!("媒体 {} 缺失: {}", name, e));
+                continue;
+            }
+        }
+        fs::create_dir_all(&media_dir)
+            .await
+            .map_err(|e| format!("Failed to create Anki media dir: {}", e))?;
+        fs::write(media_dir.join(&safe_name), bytes)
+            .await
+            .map_err(|e| format!("Failed to write Anki media: {}", e))?;
+        media_paths.insert(name, format!("{}/{}", ANKI_MEDIA_DIR, safe_name));
+    }
+
+    let keep_scheduling = payload.keep_scheduling.unwrap_or(true);
+    let subject_override = payload.subject.as_deref().map(str::trim).filter(|s| !s.is_empty());
+    if let Some(subject) = subject_override {
+        if !QUIZ_SUBJECTS.contains(&subject) {
+            return Err(format!("subject 仅支持 {}", QUIZ_SUBJECTS.join(" / ")));
+        }
+    }
+    // All-or-nothing: a failure midway must not leave half a deck behind.
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin Anki import tx: {}", e))?;
+    let total = notes.len();
+    let (mut created, mut skipped) = (0, 0);
+    for note in notes {
+        let fields: Vec<String> = note
+            .flds
+            .split(ANKI_FIELD_SEPARATOR)
+            .map(|f| anki_field_to_text(f, &media_paths))
+            .collect();
+        let stem = fields.first().cloned().unwrap_or_default();
+        if stem.is_empty() {
+            errors.push(format!("笔记 {} 的正面为空，已跳过", note.guid));
+            continue;
+        }
+        let answer = fields.get(1).cloned().unwrap_or_default();
+        let explanation = fields
+            .iter()
+            .skip(2)
+            .filter(|f| !f.is_empty())
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let deck_name = note
+            .did
+            .and_then(|did| decks.get(did.to_string()))
+            .and_then(|deck| deck.get("name"))
+            .and_then(|name| name.as_str())
+            .unwrap_or("Default");
+        let Some(subject) = subject_override.or_else(|| anki_deck_subject(deck_name)) else {
+            errors.push(format!(
+                "笔记 {} 的牌组 {} 无法对应科目，请指定 subject（{}）",
+                note.guid,
+                deck_name,
+                QUIZ_SUBJECTS.join(" / ")
+            ));
+            continue;
+        };
+
+        let id = format!("anki-{}", note.guid);
+        let exists = sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM questions WHERE id IN (?, ?) OR (stem = ? AND answer = ?)",
+        )
+        .bind(&note.guid)
+        .bind(&id)
+        .bind(&stem)
+        .bind(&answer)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to check duplicate question: {}", e))?;
+        if exists > 0 {
+            skipped += 1;
+            continue;
+        }
+
+        let tags: Vec<&str> = note.tags.split_whitespace().collect();
+
+        let now = Utc::now();
+        let (next_review, interval, ease, review_count, correct_count) = match anki_due_kind(note.queue, note.due) {
+            Some(AnkiDue::Day(day)) if keep_scheduling => {
+                let next = crt_date + chrono::Duration::days(day);
+                let reps = note.reps.unwrap_or(0) as i32;
+                (
+                    format!("{} 00:00:00", next.format("%Y-%m-%d")),
+                    note.ivl.unwrap_or(1).max(1) as i32,
+                    note.factor.filter(|f| *f > 0).map(|f| f as f64 / 1000.0).unwrap_or(2.5),
+                    reps,
+                    (reps - note.lapses.unwrap_or(0) as i32).max(0),
+                )
+            }
+            Some(AnkiDue::Timestamp(secs)) if keep_scheduling => {
+                let next = chrono::DateTime::from_timestamp(secs, 0).unwrap_or(now);
+                let reps = note.reps.unwrap_or(0) as i32;
+                (
+                    next.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    note.ivl.unwrap_or(0).max(0) as i32,
+                    note.factor.filter(|f| *f > 0).map(|f| f as f64 / 1000.0).unwrap_or(2.5),
+                    reps,
+                    (reps - note.lapses.unwrap_or(0) as i32).max(0),
+                )
+            }
+            _ => (now.format("%Y-%m-%d %H:%M:%S").to_string(), 0, 2.5, 0, 0),
+        };
+        let last_review = note
+            .card_id
+            .filter(|_| keep_scheduling && review_count > 0)
+            .and_then(|cid| last_reviews.get(&cid))
+            .and_then(|ms| chrono::DateTime::from_timestamp_millis(*ms))
+            .map(|dt| dt.to_rfc3339());
+
+        sqlx::query(
+            "INSERT INTO questions (id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval, tags_json, last_review)
+             VALUES (?, ?, 'flashcard', ?, NULL, ?, ?, '[]', 2, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(subject)
+        .bind(&stem)
+        .bind(&answer)
+        .bind(&explanation)
+        .bind(now.to_rfc3339())
+        .bind(&next_review)
+        .bind(review_count)
+        .bind(correct_count)
+        .bind(ease)
+        .bind(interval)
+        .bind(serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_string()))
+        .bind(&last_review)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to import Anki note {}: {}", note.guid, e))?;
+        created += 1;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit Anki import tx: {}", e))?;
+
+    Ok(AnkiImportReport {
+        total,
+        created,
+        skipped,
+        media_copied: media_paths.len(),
+        errors,
+    })
+}
+
+async fn save_chat_image_to_workspace(root: &str, image_data: &[u8], ext: &str) -> Result<String, String> {
+    let now = Local::now();
+    let month_dir = now.format("%Y-%m").to_string();
+    let clean_ext = ext.trim().trim_start_matches('.').to_lowercase();
+    let final_ext = if clean_ext.is_empty() { "png".to_string() } else { clean_ext };
+    let image_id = gen_focus_id("img");
+    let relative = format!("ErrorImages/{}/{}.{}", month_dir, image_id, final_ext);
+
+    let abs_path = PathBuf::from(root).join(&relative);
+    if let Some(parent) = abs_path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create image dir: {}", e))?;
+    }
+
+    fs::write(&abs_path, image_data)
+        .await
+        .map_err(|e| format!("Failed to save image file: {}", e))?;
+
+    Ok(relative)
+}
+
+async fn db_get_resources_rows(pool: &sqlx::SqlitePool) -> Result<Vec<Resource>, String> {
+    let rows = sqlx::query_as::<_, Resource>(
+        "SELECT id, name, path, file_type, subject, size_bytes, created_at FROM resources ORDER BY created_at DESC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch resources: {}", e))?;
+    Ok(rows)
+}
+
+async fn db_fetch_due_questions(
+    pool: &sqlx::SqlitePool,
+    subject: Option<&str>,
+) -> Result<Vec<Question>, String> {
+    let rows = if let Some(subject_value) = subject {
+        sqlx::query_as::<_, Question>(
+            "SELECT id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval, tags_json
+             FROM questions
+             WHERE review_count > 0
+               AND datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) <= datetime('now')
+               AND subject = ?
+             ORDER BY datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) ASC",
+        )
+        .bind(subject_value)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch due questions by subject: {}", e))?
+    } else {
+        sqlx::query_as::<_, Question>(
+            "SELECT id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval, tags_json
+             FROM questions
+             WHERE review_count > 0
+               AND datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) <= datetime('now')
+             ORDER BY datetime(COALESCE(next_review, CURRENT_TIMESTAMP)) ASC",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch due questions: {}", e))?
+    };
+
+    Ok(rows)
+}
+
+async fn api_quiz_all_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<Vec<Question>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_fetch_all_questions(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_quiz_due_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<QuizDueQuery>,
+) -> Result<Json<Vec<Question>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_fetch_due_questions(&db.db, params.subject.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+fn task_query_from_params(params: TaskQueryParams) -> TaskQuery {
+    let date = params.date.filter(|d| !d.trim().is_empty());
+    TaskQuery {
+        filter: TaskFilter {
+            start_date: params.start_date.or_else(|| date.clone()),
+            end_date: params.end_date.or(date),
+            statuses: split_query_list(params.status),
+            priorities: split_query_list(params.priority),
+            tags: split_query_list(params.tags),
+            keyword: params.keyword,
+        },
+        sort_by: params.sort_by,
+        sort_order: params.sort_order,
+        cursor: params.cursor,
+        limit: params.limit,
+    }
+}
+
+/// Without filter or paging parameters this still returns the plain task list. With any of them
+/// it returns one filtered page; the next cursor and total travel in `X-Next-Cursor` /
+/// `X-Total-Count` so the body stays a task array for older clients.
+async fn api_tasks_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<TaskQueryParams>,
+) -> Result<Response, (StatusCode, String)> {
+    let query = task_query_from_params(params);
+    let db = state.db.lock().await;
+    if query.cursor.is_none() && query.limit.is_none() && query.sort_by.is_none() {
+        let only_date = TaskFilter {
+            start_date: None,
+            end_date: None,
+            ..query.filter.clone()
+        };
+        if task_filter_is_empty(&only_date) && query.filter.start_date == query.filter.end_date {
+            let rows = match query.filter.start_date.as_deref() {
+                Some(date) => db_get_tasks_by_date(&db.db, date).await,
+                None => db_get_tasks(&db.db).await,
+            }
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+            return Ok(Json(rows).into_response());
+        }
+    }
+
+    let page = db_query_tasks(&db.db, &query)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    drop(db);
+    let mut response = Json(page.items).into_response();
+    let headers = response.headers_mut();
+    if let Ok(total) = header::HeaderValue::from_str(&page.total.to_string()) {
+        headers.insert("x-total-count", total);
+    }
+    if let Some(cursor) = page.next_cursor.and_then(|c| header::HeaderValue::from_str(&c).ok()) {
+        headers.insert("x-next-cursor", cursor);
+    }
+    Ok(response)
+}
+
+async fn api_query_tasks_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<TaskQueryParams>,
+) -> Result<Json<TaskQueryPage>, (StatusCode, String)> {
+    let query = task_query_from_params(params);
+
+    let db = state.db.lock().await;
+    let page = db_query_tasks(&db.db, &query)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(page))
+}
+
+async fn api_task_statuses_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<Vec<TaskStatusColumn>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_get_task_statuses(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_task_board_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<TaskBoardQuery>,
+) -> Result<Json<Vec<TaskBoardColumn>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let start = params.start_date.unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
+    let columns = db_get_task_board(&db.db, &start, params.end_date.as_deref())
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(columns))
+}
+
+async fn api_move_task_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(payload): Json<MoveTaskPayload>,
+) -> Result<Json<Task>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let task = db_move_task(&db.db, &id, &payload).await.map_err(|e| {
+        if e.contains("not found") {
+            (StatusCode::NOT_FOUND, e)
+        } else {
+            (StatusCode::BAD_REQUEST, e)
+        }
+    })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_TASKS");
+    Ok(Json(task))
+}
+
+async fn api_reorder_tasks_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(body): Json<ReorderTasksBody>,
+) -> Result<Json<Vec<Task>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let tasks = db_reorder_tasks(&db.db, &body)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_TASKS");
+    Ok(Json(tasks))
+}
+
+async fn api_bulk_update_tasks_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(body): Json<TaskBulkUpdateBody>,
+) -> Result<Json<TaskBulkResult>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let result = db_bulk_update_tasks(&db.db, &body.filter, &body.patch)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    drop(db);
+    if result.affected > 0 {
+        emit_sync_action(&state.sync_hub, "SYNC_TASKS");
+    }
+    Ok(Json(result))
+}
+
+async fn api_bulk_delete_tasks_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(body): Json<TaskBulkDeleteBody>,
+) -> Result<Json<TaskBulkResult>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let result = db_bulk_delete_tasks(&db.db, &body.filter)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    drop(db);
+    if result.affected > 0 {
+        emit_sync_action(&state.sync_hub, "SYNC_TASKS");
+    }
+    Ok(Json(result))
+}
+
+async fn api_create_task_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(task): Json<Task>,
+) -> Result<Json<Task>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let created = db_create_task(&db.db, &task)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_TASKS");
+    Ok(Json(created))
+}
+
+async fn api_update_task_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(mut task): Json<Task>,
+) -> Result<Json<Task>, (StatusCode, String)> {
+    task.id = id;
+    let db = state.db.lock().await;
+    let updated = db_update_task(&db.db, &task)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_TASKS");
+    Ok(Json(updated))
+}
+
+async fn api_delete_task_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_delete_task(&db.db, &id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_TASKS");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_resources_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<Vec<Resource>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_get_resources_rows(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_weekly_stats_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<WeeklyStatsQuery>,
+) -> Result<Json<WeeklyStats>, (StatusCode, String)> {
+    let end_date = params
+        .end_date
+        .unwrap_or_else(|| Local::now().format("%Y-%m-%d").to_string());
+
+    let db = state.db.lock().await;
+    let stats = db_get_weekly_stats(&db.db, &end_date)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    Ok(Json(stats))
+}
+
+async fn api_focus_templates_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<Vec<FocusTemplate>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_get_focus_templates(&db.db, false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_create_focus_template_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(mut template): Json<FocusTemplate>,
+) -> Result<Json<FocusTemplate>, (StatusCode, String)> {
+    if template.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "template.name 不能为空".to_string()));
+    }
+    if template.duration_minutes <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "template.duration_minutes 必须大于 0".to_string()));
+    }
+
+    if template.id.trim().is_empty() {
+        template.id = gen_focus_id("focus-template");
+    }
+    if template.timer_type.trim().is_empty() {
+        template.timer_type = "pomodoro".to_string();
+    }
+    if template.tags_json.trim().is_empty() {
+        template.tags_json = "[]".to_string();
+    }
+
+    let now = now_iso();
+    if template.created_at.trim().is_empty() {
+        template.created_at = now.clone();
+    }
+    template.updated_at = now;
+
+    let db = state.db.lock().await;
+    let created = db_create_focus_template(&db.db, &template)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_TEMPLATES");
+    Ok(Json(created))
+}
+
+async fn api_update_focus_template_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(mut template): Json<FocusTemplate>,
+) -> Result<Json<FocusTemplate>, (StatusCode, String)> {
+    if template.name.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "template.name 不能为空".to_string()));
+    }
+    if template.duration_minutes <= 0 {
+        return Err((StatusCode::BAD_REQUEST, "template.duration_minutes 必须大于 0".to_string()));
+    }
+
+    template.id = id;
+    if template.timer_type.trim().is_empty() {
+        template.timer_type = "pomodoro".to_string();
+    }
+    if template.tags_json.trim().is_empty() {
+        template.tags_json = "[]".to_string();
+    }
+
+    template.updated_at = now_iso();
+    if template.created_at.trim().is_empty() {
+        template.created_at = template.updated_at.clone();
+    }
+
+    let db = state.db.lock().await;
+    let updated = db_update_focus_template(&db.db, &template)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_TEMPLATES");
+    Ok(Json(updated))
+}
+
+async fn api_archive_focus_template_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_archive_focus_template(&db.db, &id)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_TEMPLATES");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_start_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(payload): Json<StartFocusRunPayload>,
+) -> Result<Json<FocusRun>, (StatusCode, String)> {
+    let started = start_focus_run_with_sync(&state.db, &state.sync_hub, &payload)
+        .await
+        .map_err(|e| {
+            if e.starts_with("已有进行中的专注") {
+                (StatusCode::CONFLICT, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    Ok(Json(started))
+}
+
+async fn api_finish_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(payload): Json<FinishFocusRunPayload>,
+) -> Result<Json<FocusRun>, (StatusCode, String)> {
+    let finished = finish_focus_run_with_sync(&state.db, &state.sync_hub, &id, &payload)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    Ok(Json(finished))
+}
+
+async fn api_active_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<Option<FocusRunState>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let active = db_get_active_focus_run(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(active))
+}
+
+async fn api_transition_focus_run(
+    state: LanAppState,
+    id: String,
+    action: FocusRunAction,
+) -> Result<Json<FocusRunState>, (StatusCode, String)> {
+    let run_state = transition_focus_run(&id, action, &state.db, &state.sync_hub)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else if e.starts_with("非法的状态转换") {
+                (StatusCode::CONFLICT, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    Ok(Json(run_state))
+}
+
+async fn api_interrupted_focus_runs_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<Vec<FocusRunState>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let runs = db_get_interrupted_focus_runs(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(runs))
+}
+
+async fn api_recover_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Query(params): Query<FocusRunRecoverQuery>,
+) -> Result<Json<FocusRunState>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let run_state = db_recover_focus_run(&db.db, &id, &params.action, params.cap_minutes)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else if e.starts_with("非法的状态转换") || e.starts_with("已有进行中的专注") {
+                (StatusCode::CONFLICT, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    let reached = db_collect_reached_focus_goals(&db.db).await.unwrap_or_default();
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_RUNS");
+    emit_focus_run_event(&state.sync_hub, "recover", &run_state);
+    emit_focus_goals_reached(&state.sync_hub, &reached);
+    Ok(Json(run_state))
+}
+
+async fn api_heartbeat_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_touch_focus_run(&db.db, &id)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_pause_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<FocusRunState>, (StatusCode, String)> {
+    api_transition_focus_run(state, id, FocusRunAction::Pause).await
+}
+
+async fn api_resume_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<FocusRunState>, (StatusCode, String)> {
+    api_transition_focus_run(state, id, FocusRunAction::Resume).await
+}
+
+async fn api_extend_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Query(params): Query<FocusRunTransitionQuery>,
+) -> Result<Json<FocusRunState>, (StatusCode, String)> {
+    let minutes = params.minutes.unwrap_or(5);
+    api_transition_focus_run(state, id, FocusRunAction::Extend(minutes)).await
+}
+
+async fn api_break_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Query(params): Query<FocusRunTransitionQuery>,
+) -> Result<Json<FocusRunState>, (StatusCode, String)> {
+    api_transition_focus_run(state, id, FocusRunAction::Break(params.minutes)).await
+}
+
+async fn api_skip_break_focus_run_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<FocusRunState>, (StatusCode, String)> {
+    api_transition_focus_run(state, id, FocusRunAction::SkipBreak).await
+}
+
+async fn api_export_focus_runs_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<FocusExportQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    let format = params.format.unwrap_or_else(|| "csv".to_string());
+    let db = state.db.lock().await;
+    let body = db_export_focus_runs(
+        &db.db,
+        &format,
+        params.start_date.as_deref(),
+        params.end_date.as_deref(),
+    )
+    .await
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let content_type = if format.trim().eq_ignore_ascii_case("json") {
+        "application/json; charset=utf-8"
+    } else {
+        "text/csv; charset=utf-8"
+    };
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to build export response: {}", e)))
+}
+
+async fn api_import_focus_runs_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(payload): Json<FocusCsvImportPayload>,
+) -> Result<Json<FocusImportReport>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let report = db_import_focus_runs_csv(&db.db, &payload)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    drop(db);
+    if report.created > 0 {
+        emit_sync_action(&state.sync_hub, "SYNC_FOCUS_RUNS");
+    }
+    Ok(Json(report))
+}
+
+async fn api_focus_stats_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<FocusStatsQuery>,
+) -> Result<Json<FocusStatsResult>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let stats = db_get_focus_stats(
+        &db.db,
+        params.start_date.as_deref(),
+        params.end_date.as_deref(),
+        params.dimension.as_deref(),
+    )
+    .await
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    Ok(Json(stats))
+}
+
+async fn api_focus_timeseries_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<FocusTimeseriesQuery>,
+) -> Result<Json<FocusTimeseriesResult>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let series = db_get_focus_timeseries(&db.db, &params)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(series))
+}
+
+async fn api_focus_goals_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<Vec<FocusGoal>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_get_focus_goals(&db.db, true)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_create_focus_goal_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(payload): Json<FocusGoalPayload>,
+) -> Result<Json<FocusGoal>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let goal = db_create_focus_goal(&db.db, &payload)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let reached = db_collect_reached_focus_goals(&db.db).await.unwrap_or_default();
+    let goal = db_get_focus_goal(&db.db, &goal.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_GOALS");
+    emit_focus_goals_reached(&state.sync_hub, &reached);
+    Ok(Json(goal))
+}
+
+async fn api_update_focus_goal_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(payload): Json<FocusGoalPayload>,
+) -> Result<Json<FocusGoal>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let goal = db_update_focus_goal(&db.db, &id, &payload)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    let reached = db_collect_reached_focus_goals(&db.db).await.unwrap_or_default();
+    let goal = db_get_focus_goal(&db.db, &goal.id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_GOALS");
+    emit_focus_goals_reached(&state.sync_hub, &reached);
+    Ok(Json(goal))
+}
+
+async fn api_delete_focus_goal_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_delete_focus_goal(&db.db, &id)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_GOALS");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_focus_goal_progress_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<FocusGoalProgressQuery>,
+) -> Result<Json<Vec<FocusGoalProgress>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let progress = db_get_focus_goal_progress(&db.db, params.date.as_deref())
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(progress))
+}
+
+async fn api_log_focus_interruption(
+    state: LanAppState,
+    run_id: Option<&str>,
+    payload: LogFocusInterruptionPayload,
+) -> Result<Json<FocusInterruption>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let interruption = db_log_focus_interruption(&db.db, run_id, &payload)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_INTERRUPTIONS");
+    Ok(Json(interruption))
+}
+
+async fn api_log_focus_interruption_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    payload: Option<Json<LogFocusInterruptionPayload>>,
+) -> Result<Json<FocusInterruption>, (StatusCode, String)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    api_log_focus_interruption(state, Some(&id), payload).await
+}
+
+async fn api_log_active_focus_interruption_handler(
+    AxumState(state): AxumState<LanAppState>,
+    payload: Option<Json<LogFocusInterruptionPayload>>,
+) -> Result<Json<FocusInterruption>, (StatusCode, String)> {
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    api_log_focus_interruption(state, None, payload).await
+}
+
+async fn api_focus_interruptions_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<Json<Vec<FocusInterruption>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_get_focus_interruptions(&db.db, &id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_delete_focus_interruption_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_delete_focus_interruption(&db.db, &id)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_FOCUS_INTERRUPTIONS");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_focus_interruption_stats_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<FocusInterruptionStatsQuery>,
+) -> Result<Json<FocusInterruptionStats>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let stats = db_get_focus_interruption_stats(
+        &db.db,
+        params.start_date.as_deref(),
+        params.end_date.as_deref(),
+    )
+    .await
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(stats))
+}
+
+async fn api_wrong_questions_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<WrongQuestionsQuery>,
+) -> Result<Json<Vec<WrongQuestion>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_get_wrong_questions(
+        &db.db,
+        &WrongQuestionFilter {
+            subject: params.subject,
+            mastery_level: params.mastery_level,
+            search_keyword: params.search_keyword,
+            is_archived: params.is_archived.or(Some(0)),
+            error_cause: params.error_cause,
+        },
+    )
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_create_wrong_question_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(mut req): Json<CreateWrongQuestionRequest>,
+) -> Result<(StatusCode, Json<WrongQuestionCreateResult>), (StatusCode, String)> {
+    if req.question.question_content.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "question_content 不能为空".to_string()));
+    }
+    if req.question.ai_solution.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "ai_solution 不能为空".to_string()));
+    }
+
+    if req.question.id.trim().is_empty() {
+        req.question.id = gen_focus_id("wrong-question");
+    }
+    if req.question.subject.trim().is_empty() {
+        req.question.subject = "其他".to_string();
+    }
+    if req.question.tags_json.trim().is_empty() {
+        req.question.tags_json = "[]".to_string();
+    }
+    if req.question.source.trim().is_empty() {
+        req.question.source = "ai_chat".to_string();
+    }
+    let now = now_iso();
+    if req.question.created_at.trim().is_empty() {
+        req.question.created_at = now.clone();
+    }
+    req.question.updated_at = now;
+
+    let db = state.db.lock().await;
+    let result = db_create_wrong_question(&db.db, &req.question, req.allow_duplicate.unwrap_or(false))
+        .await
+        .map_err(|e| {
+            if e.starts_with("Failed") {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    let Some(created) = result.question.as_ref() else {
+        return Ok((StatusCode::CONFLICT, Json(result)));
+    };
+
+    if req.add_to_current_week.unwrap_or(false) {
+        let week_start = current_week_start_str();
+        let (_, week_end) = parse_week_start_monday(&week_start)
+            .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+        db_create_weekly_review_item_if_absent(
+            &db.db,
+            &week_start,
+            &week_end,
+            &created.id,
+            &make_title_snapshot(&created.question_content, &created.ai_solution),
+            None,
+        )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    }
+    drop(db);
+
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok((StatusCode::CREATED, Json(result)))
+}
+
+async fn api_update_wrong_question_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(mut question): Json<WrongQuestion>,
+) -> Result<Json<WrongQuestion>, (StatusCode, String)> {
+    question.id = id;
+    question.updated_at = now_iso();
+    let db = state.db.lock().await;
+    let updated = db_update_wrong_question(&db.db, &question)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else if e.starts_with("Failed") {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    Ok(Json(updated))
+}
+
+async fn api_archive_wrong_question_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_archive_wrong_question(&db.db, &id)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_delete_wrong_question_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_delete_wrong_question(&db.db, &id)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_review_wrong_question_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(body): Json<ReviewWrongQuestionBody>,
+) -> Result<Json<WrongQuestion>, (StatusCode, String)> {
+    let grade = ReviewGrade::parse(&body.grade).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let response_ms = validate_response_ms(body.response_ms).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let db = state.db.lock().await;
+    let reviewed = db_review_wrong_question(&db.db, &id, grade, response_ms)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    Ok(Json(reviewed))
+}
+
+async fn api_due_wrong_questions_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<DueWrongQuestionsQuery>,
+) -> Result<Json<Vec<WrongQuestion>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rows = db_get_due_wrong_questions(
+        &db.db,
+        params.date.as_deref(),
+        params.subject.as_deref(),
+        params.limit,
+    )
+    .await
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_review_history_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<ReviewHistoryQuery>,
+) -> Result<Json<Vec<ReviewLogEntry>>, (StatusCode, String)> {
+    let kind = parse_review_item_type(&params.item_type).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let db = state.db.lock().await;
+    let rows = db_get_review_history(&db.db, kind, &params.item_id, params.limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_review_curves_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<ReviewCurvesQuery>,
+) -> Result<Json<ReviewCurves>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let curves = db_get_review_curves(
+        &db.db,
+        params.start_date.as_deref(),
+        params.end_date.as_deref(),
+        params.item_type.as_deref(),
+    )
+    .await
+    .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(curves))
+}
+
+async fn api_get_review_scheduler_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<ReviewSchedulerSettings>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let settings = db_get_review_scheduler_settings(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(settings))
+}
+
+async fn api_update_review_scheduler_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(payload): Json<ReviewSchedulerSettingsPayload>,
+) -> Result<Json<ReviewSchedulerSettings>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let settings = db_update_review_scheduler_settings(&db.db, &payload)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(settings))
+}
+
+async fn api_wrong_question_stats_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<WrongQuestionStats>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let stats = db_get_wrong_question_stats(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(stats))
+}
+
+async fn api_weekly_review_items_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<WeeklyReviewItemsQuery>,
+) -> Result<Json<Vec<WeeklyReviewItem>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let (rows, added) = db_get_weekly_review_items(&db.db, &params.week_start, params.auto_populate)
+        .await
+        .map_err(|e| {
+            if e.contains("week_start") {
+                (StatusCode::BAD_REQUEST, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    if added > 0 {
+        emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    }
+    Ok(Json(rows))
+}
+
+async fn api_weekly_review_toggle_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(body): Json<WeeklyReviewToggleBody>,
+) -> Result<Json<WeeklyReviewItem>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let row = db_toggle_weekly_review_item_done(&db.db, &id, body.done)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    Ok(Json(row))
+}
+
+async fn api_weekly_review_carry_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(body): Json<CarryNextWeekBody>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_carry_weekly_review_items_to_next_week(&db.db, &body.from_week_start, &body.item_ids)
+        .await
+        .map_err(|e| {
+            if e.contains("week_start") {
+                (StatusCode::BAD_REQUEST, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_add_weekly_review_item_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(body): Json<AddWeeklyReviewItemBody>,
+) -> Result<Json<WeeklyReviewItem>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let item = db_add_weekly_review_item(&db.db, &body.week_start, &body.wrong_question_id)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(Json(item))
+}
+
+async fn api_remove_weekly_review_item_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_remove_weekly_review_item(&db.db, &id)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_wrong_question_duplicates_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(body): Json<WrongQuestionDuplicatesBody>,
+) -> Result<Json<Vec<WrongQuestionDuplicate>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let duplicates = db_find_duplicate_wrong_questions(
+        &db.db,
+        &body.question_content,
+        body.exclude_id.as_deref(),
+        body.subject.as_deref(),
+    )
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(duplicates))
+}
+
+async fn api_merge_wrong_questions_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(body): Json<MergeWrongQuestionsBody>,
+) -> Result<Json<WrongQuestion>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let merged = db_merge_wrong_questions(&db.db, &id, &body.source_ids)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else if e.starts_with("Failed") {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    emit_sync_action(&state.sync_hub, "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(Json(merged))
+}
+
+async fn api_wrong_question_booklet_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(payload): Json<WrongQuestionBookletPayload>,
+) -> Result<Json<WrongQuestionBookletResult>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let result = write_wrong_question_booklet(&db.db, &state.workspace_root, &payload)
+        .await
+        .map_err(|e| {
+            if e.starts_with("Failed") {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            } else {
+                (StatusCode::BAD_REQUEST, e)
+            }
+        })?;
+    Ok(Json(result))
+}
+
+async fn api_get_mastery_rules_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<Vec<MasteryRule>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rules = db_get_mastery_rules(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rules))
+}
+
+async fn api_save_mastery_rule_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(payload): Json<MasteryRulePayload>,
+) -> Result<Json<MasteryRule>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let rule = db_upsert_mastery_rule(&db.db, &payload)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    Ok(Json(rule))
+}
+
+async fn api_delete_mastery_rule_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(subject): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_delete_mastery_rule(&db.db, &subject)
+        .await
+        .map_err(|e| {
+            if e.contains("not found") {
+                (StatusCode::NOT_FOUND, e)
+            } else {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            }
+        })?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_WRONG_QUESTIONS");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn knowledge_error_status(e: String) -> (StatusCode, String) {
+    if e.contains("not found") {
+        (StatusCode::NOT_FOUND, e)
+    } else if e.starts_with("Failed") {
+        (StatusCode::INTERNAL_SERVER_ERROR, e)
+    } else {
+        (StatusCode::BAD_REQUEST, e)
+    }
+}
+
+async fn api_knowledge_tree_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<KnowledgeTreeQuery>,
+) -> Result<Json<Vec<KnowledgeNodeStats>>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let tree = db_get_knowledge_tree(&db.db, params.recent_days)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(tree))
+}
+
+async fn api_create_knowledge_node_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(payload): Json<KnowledgeNodePayload>,
+) -> Result<Json<KnowledgeNode>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let node = db_create_knowledge_node(&db.db, &payload)
+        .await
+        .map_err(knowledge_error_status)?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_KNOWLEDGE_TREE");
+    Ok(Json(node))
+}
+
+async fn api_update_knowledge_node_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+    Json(payload): Json<KnowledgeNodePayload>,
+) -> Result<Json<KnowledgeNode>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let node = db_update_knowledge_node(&db.db, &id, &payload)
+        .await
+        .map_err(knowledge_error_status)?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_KNOWLEDGE_TREE");
+    Ok(Json(node))
+}
+
+async fn api_delete_knowledge_node_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(id): AxumPath<String>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    db_delete_knowledge_node(&db.db, &id)
+        .await
+        .map_err(knowledge_error_status)?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_KNOWLEDGE_TREE");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn api_get_knowledge_assignments_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Query(params): Query<KnowledgeItemQuery>,
+) -> Result<Json<Vec<KnowledgeNode>>, (StatusCode, String)> {
+    let kind = parse_review_item_type(&params.item_type).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let db = state.db.lock().await;
+    let nodes = db_get_item_knowledge_nodes(&db.db, kind, &params.item_id)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(nodes))
+}
+
+async fn api_assign_knowledge_nodes_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(body): Json<KnowledgeAssignmentBody>,
+) -> Result<Json<Vec<KnowledgeNode>>, (StatusCode, String)> {
+    let kind = parse_review_item_type(&body.item_type).map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let db = state.db.lock().await;
+    let nodes = db_assign_knowledge_nodes(&db.db, kind, &body.item_id, &body.node_ids)
+        .await
+        .map_err(knowledge_error_status)?;
+    drop(db);
+    emit_sync_action(&state.sync_hub, "SYNC_KNOWLEDGE_TREE");
+    Ok(Json(nodes))
+}
+
+async fn api_get_weekly_review_settings_handler(
+    AxumState(state): AxumState<LanAppState>,
+) -> Result<Json<WeeklyReviewSettings>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let settings = db_get_weekly_review_settings(&db.db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(settings))
+}
+
+async fn api_update_weekly_review_settings_handler(
+    AxumState(state): AxumState<LanAppState>,
+    Json(payload): Json<WeeklyReviewSettingsPayload>,
+) -> Result<Json<WeeklyReviewSettings>, (StatusCode, String)> {
+    let db = state.db.lock().await;
+    let settings = db_update_weekly_review_settings(&db.db, &payload)
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    Ok(Json(settings))
+}
+
+async fn api_image_upload_handler(
+    AxumState(state): AxumState<LanAppState>,
+    mut multipart: Multipart,
+) -> Result<Json<ImageUploadResponse>, (StatusCode, String)> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid multipart field: {}", e)))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+        if name != "file" {
+            continue;
+        }
+
+        let filename = field.file_name().unwrap_or("upload.png").to_string();
+        let ext = Path::new(&filename)
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("png")
+            .to_string();
+
+        let data = field
+            .bytes()
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Failed to read file field: {}", e)))?;
+
+        if data.is_empty() {
+            return Err((StatusCode::BAD_REQUEST, "上传文件为空".to_string()));
+        }
+
+        let relative = save_chat_image_to_workspace(&state.workspace_root, &data, &ext)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+        return Ok(Json(ImageUploadResponse { path: relative }));
+    }
+
+    Err((StatusCode::BAD_REQUEST, "缺少 file 字段".to_string()))
+}
+
+async fn api_image_file_handler(
+    AxumState(state): AxumState<LanAppState>,
+    AxumPath(path): AxumPath<String>,
+) -> Result<Response<Body>, (StatusCode, String)> {
+    if path.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "path 不能为空".to_string()));
+    }
+
+    let sanitized = sanitize_relative_path(&path)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    let full = PathBuf::from(&state.workspace_root).join(&sanitized);
+
+    let bytes = fs::read(&full)
+        .await
+        .map_err(|e| (StatusCode::NOT_FOUND, format!("Failed to read image: {}", e)))?;
+
+    let mime = from_path(&full).first_or_octet_stream();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, mime.as_ref())
+        .body(Body::from(bytes))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to build image response: {}", e)))
+}
+
+async fn api_ws_handler(
+    AxumState(state): AxumState<LanAppState>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let rx = state.sync_hub.tx.subscribe();
+    ws.on_upgrade(move |socket| async move {
+        ws_client_loop(socket, rx, state).await;
+    })
+}
+
+async fn load_notes_tree(app: &tauri::AppHandle) -> Result<Vec<NotesFsNode>, String> {
+    let root = PathBuf::from(ensure_workspace_dirs(app).await?);
+    let notes_root = root.join("Notes");
+
+    fs::create_dir_all(&notes_root).await.map_err(|e| {
+        format!(
+            "Failed to ensure notes directory {}: {}",
+            notes_root.to_string_lossy(),
+            e
+        )
+    })?;
+
+    scan_notes_directory_sync(&notes_root)
+}
+
+async fn api_notes_tree_handler(
+    app: tauri::AppHandle,
+) -> Result<Json<Vec<NotesFsNode>>, (StatusCode, String)> {
+    let rows = load_notes_tree(&app)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(rows))
+}
+
+async fn api_notes_file_handler(
+    app: tauri::AppHandle,
+    Query(params): Query<NotesFileQuery>,
+) -> Result<String, (StatusCode, String)> {
+    let path = params.path.trim();
+    if path.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "path 参数不能为空".to_string()));
+    }
+
+    let full_path = {
+        let as_path = PathBuf::from(path);
+        if as_path.is_absolute() {
+            as_path
+        } else {
+            let root = PathBuf::from(ensure_workspace_dirs(&app).await.map_err(|e| {
+                (StatusCode::INTERNAL_SERVER_ERROR, e)
+            })?);
+            let notes_root = root.join("Notes");
+            let sanitized = sanitize_relative_path(path)
+                .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+            notes_root.join(sanitized)
+        }
+    };
+
+    let content = fs::read_to_string(&full_path)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to read notes file {}: {}", full_path.to_string_lossy(), e),
+            )
+        })?;
+    Ok(content)
+}
+
+#[tauri::command]
+fn get_local_ip() -> String {
+    match local_ip_address::local_ip() {
+        Ok(IpAddr::V4(ipv4)) => ipv4.to_string(),
+        _ => "127.0.0.1".to_string(),
+    }
+}
+
+#[tauri::command]
+async fn toggle_local_server(
+    enable: bool,
+    port: u16,
+    app: tauri::AppHandle,
+    server_state: State<'_, Arc<Mutex<LocalServerState>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<String, String> {
+    if port == 0 {
+        return Err("端口必须大于 0".to_string());
+    }
+
+    let previous_runtime = {
+        let mut state = server_state.lock().await;
+        state.runtime.take()
+    };
+
+    if let Some(mut runtime) = previous_runtime {
+        if let Some(shutdown_tx) = runtime.shutdown_tx.take() {
+            let _ = shutdown_tx.send(());
+        }
+        let _ = runtime.handle.await;
+    }
+
+    if !enable {
+        return Ok("局域网共享服务已关闭".to_string());
+    }
+
+    let db_state = app
+        .try_state::<Arc<Mutex<AppDb>>>()
+        .ok_or_else(|| "数据库尚未就绪，请稍后重试。".to_string())?;
+    let shared_db = db_state.inner().clone();
+    let workspace_root = ensure_workspace_dirs(&app).await?;
+    let lan_state = LanAppState {
+        db: shared_db,
+        sync_hub: sync_hub.inner().clone(),
+        workspace_root,
+    };
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("启动局域网服务失败（端口 {}）: {}", port, e))?;
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+    let app_handle_for_notes_tree = app.clone();
+    let app_handle_for_notes_file = app.clone();
+
+    let router = Router::new()
+        .route("/api/ping", get(local_ping_handler))
+        .route("/api/ws", get(api_ws_handler))
+        .route("/api/tasks", get(api_tasks_handler).post(api_create_task_handler))
+        .route("/api/tasks/query", get(api_query_tasks_handler))
+        .route("/api/tasks/bulk-update", axum::routing::post(api_bulk_update_tasks_handler))
+        .route("/api/tasks/bulk-delete", axum::routing::post(api_bulk_delete_tasks_handler))
+        .route("/api/tasks/statuses", get(api_task_statuses_handler))
+        .route("/api/tasks/board", get(api_task_board_handler))
+        .route("/api/tasks/reorder", axum::routing::post(api_reorder_tasks_handler))
+        .route("/api/tasks/{id}/move", axum::routing::post(api_move_task_handler))
+        .route("/api/tasks/{id}", put(api_update_task_handler).delete(api_delete_task_handler))
+        .route(
+            "/api/focus/templates",
+            get(api_focus_templates_handler).post(api_create_focus_template_handler),
+        )
+        .route(
+            "/api/focus/templates/{id}",
+            put(api_update_focus_template_handler).delete(api_archive_focus_template_handler),
+        )
+        .route("/api/focus/runs/start", axum::routing::post(api_start_focus_run_handler))
+        .route("/api/focus/runs/{id}/finish", axum::routing::post(api_finish_focus_run_handler))
+        .route("/api/focus/runs/active", get(api_active_focus_run_handler))
+        .route("/api/focus/runs/export", get(api_export_focus_runs_handler))
+        .route("/api/focus/runs/import", axum::routing::post(api_import_focus_runs_handler))
+        .route("/api/focus/runs/interrupted", get(api_interrupted_focus_runs_handler))
+        .route("/api/focus/runs/{id}/recover", axum::routing::post(api_recover_focus_run_handler))
+        .route("/api/focus/runs/{id}/heartbeat", axum::routing::post(api_heartbeat_focus_run_handler))
+        .route("/api/focus/runs/{id}/pause", axum::routing::post(api_pause_focus_run_handler))
+        .route("/api/focus/runs/{id}/resume", axum::routing::post(api_resume_focus_run_handler))
+        .route("/api/focus/runs/{id}/extend", axum::routing::post(api_extend_focus_run_handler))
+        .route("/api/focus/runs/{id}/break", axum::routing::post(api_break_focus_run_handler))
+        .route("/api/focus/runs/{id}/skip-break", axum::routing::post(api_skip_break_focus_run_handler))
+        .route(
+            "/api/focus/runs/active/interruptions",
+            axum::routing::post(api_log_active_focus_interruption_handler),
+        )
+        .route(
+            "/api/focus/runs/{id}/interruptions",
+            get(api_focus_interruptions_handler).post(api_log_focus_interruption_handler),
+        )
+        .route("/api/focus/interruptions/stats", get(api_focus_interruption_stats_handler))
+        .route(
+            "/api/focus/interruptions/{id}",
+            axum::routing::delete(api_delete_focus_interruption_handler),
+        )
+        .route("/api/focus/stats", get(api_focus_stats_handler))
+        .route("/api/focus/timeseries", get(api_focus_timeseries_handler))
+        .route(
+            "/api/focus/goals",
+            get(api_focus_goals_handler).post(api_create_focus_goal_handler),
+        )
+        .route("/api/focus/goals/progress", get(api_focus_goal_progress_handler))
+        .route(
+            "/api/focus/goals/{id}",
+            put(api_update_focus_goal_handler).delete(api_delete_focus_goal_handler),
+        )
+        .route(
+            "/api/wrong-questions",
+            get(api_wrong_questions_handler).post(api_create_wrong_question_handler),
+        )
+        .route(
+            "/api/wrong-questions/{id}",
+            put(api_update_wrong_question_handler).delete(api_archive_wrong_question_handler),
+        )
+        .route(
+            "/api/wrong-questions/{id}/hard-delete",
+            axum::routing::delete(api_delete_wrong_question_handler),
+        )
+        .route("/api/wrong-questions/stats", get(api_wrong_question_stats_handler))
+        .route("/api/wrong-questions/due", get(api_due_wrong_questions_handler))
+        .route(
+            "/api/wrong-questions/duplicates",
+            axum::routing::post(api_wrong_question_duplicates_handler),
+        )
+        .route(
+            "/api/wrong-questions/{id}/merge",
+            axum::routing::post(api_merge_wrong_questions_handler),
+        )
+        .route(
+            "/api/wrong-questions/booklet",
+            axum::routing::post(api_wrong_question_booklet_handler),
+        )
+        .route(
+            "/api/wrong-questions/mastery-rules",
+            get(api_get_mastery_rules_handler).put(api_save_mastery_rule_handler),
+        )
+        .route(
+            "/api/wrong-questions/mastery-rules/{subject}",
+            axum::routing::delete(api_delete_mastery_rule_handler),
+        )
+        .route("/api/knowledge/tree", get(api_knowledge_tree_handler))
+        .route(
+            "/api/knowledge/nodes",
+            axum::routing::post(api_create_knowledge_node_handler),
+        )
+        .route(
+            "/api/knowledge/nodes/{id}",
+            axum::routing::put(api_update_knowledge_node_handler)
+                .delete(api_delete_knowledge_node_handler),
+        )
+        .route(
+            "/api/knowledge/assignments",
+            get(api_get_knowledge_assignments_handler).put(api_assign_knowledge_nodes_handler),
+        )
+        .route("/api/review/history", get(api_review_history_handler))
+        .route("/api/review/curves", get(api_review_curves_handler))
+        .route(
+            "/api/review/scheduler",
+            get(api_get_review_scheduler_handler).put(api_update_review_scheduler_handler),
+        )
+        .route(
+            "/api/wrong-questions/{id}/review",
+            axum::routing::post(api_review_wrong_question_handler),
+        )
+        .route(
+            "/api/weekly-review/items",
+            get(api_weekly_review_items_handler).post(api_add_weekly_review_item_handler),
+        )
+        .route(
+            "/api/weekly-review/items/{id}",
+            axum::routing::delete(api_remove_weekly_review_item_handler),
+        )
+        .route(
+            "/api/weekly-review/settings",
+            get(api_get_weekly_review_settings_handler).put(api_update_weekly_review_settings_handler),
+        )
+        .route(
+            "/api/weekly-review/items/{id}/toggle",
+            axum::routing::post(api_weekly_review_toggle_handler),
+        )
+        .route(
+            "/api/weekly-review/carry-next-week",
+            axum::routing::post(api_weekly_review_carry_handler),
+        )
+        .route("/api/images/upload", axum::routing::post(api_image_upload_handler))
+        .route("/api/images/{*path}", get(api_image_file_handler))
+        .route("/api/stats/weekly", get(api_weekly_stats_handler))
+        .route("/api/resources", get(api_resources_handler))
+        .route(
+            "/api/notes/tree",
+            get(move || {
+                let app = app_handle_for_notes_tree.clone();
+                async move { api_notes_tree_handler(app).await }
+            }),
+        )
+        .route(
+            "/api/notes/file",
+            get(move |query: Query<NotesFileQuery>| {
+                let app = app_handle_for_notes_file.clone();
+                async move { api_notes_file_handler(app, query).await }
+            }),
+        )
+        .route("/api/quiz/all", get(api_quiz_all_handler))
+        .route("/api/quiz/due", get(api_quiz_due_handler))
+        .fallback(embedded_static_handler)
+        .layer(CorsLayer::permissive())
+        .with_state(lan_state);
+
+    let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+        let _ = shutdown_rx.await;
+    });
+
+    let handle = tauri::async_runtime::spawn(async move {
+        if let Err(err) = server.await {
+            log::error!("Local LAN server stopped with error: {}", err);
+        }
+    });
+
+    {
+        let mut state = server_state.lock().await;
+        state.runtime = Some(LocalServerRuntime {
+            handle,
+            shutdown_tx: Some(shutdown_tx),
+        });
+    }
+
+    Ok(format!("局域网共享服务已启动，端口 {}", port))
+}
+
+// ═══════════════════════════════════════════════════════════
+// Database Initialization
+// ═══════════════════════════════════════════════════════════
+
+async fn init_db(db_path: &str) -> Result<sqlx::SqlitePool, String> {
+    // Ensure parent directory exists
+    if let Some(parent) = std::path::Path::new(db_path).parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create db directory: {}", e))?;
+    }
+
+    let db_url = format!("sqlite:{}?mode=rwc", db_path);
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(5)
+        .connect(&db_url)
+        .await
+        .map_err(|e| format!("Failed to connect to database: {}", e))?;
+
+    sqlx::query("PRAGMA foreign_keys = ON")
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to enable foreign keys: {}", e))?;
+
+    // Create tables
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS tasks (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            description TEXT DEFAULT '',
+            status TEXT DEFAULT 'todo',
+            priority TEXT DEFAULT 'medium',
+            date TEXT NOT NULL,
+            start_time TEXT DEFAULT '09:00',
+            duration REAL DEFAULT 1.0,
+            tags TEXT DEFAULT '',
+            repeat_type TEXT DEFAULT 'none',
+            timer_type TEXT DEFAULT 'none',
+            timer_duration INTEGER DEFAULT 25,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create tasks table: {}", e))?;
+
+    let task_columns: Vec<String> = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM pragma_table_info('tasks')",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("Failed to inspect tasks columns: {}", e))?;
+
+    if !task_columns.iter().any(|col| col == "position") {
+        sqlx::query("ALTER TABLE tasks ADD COLUMN position INTEGER NOT NULL DEFAULT 0")
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to add tasks.position column: {}", e))?;
+        // Seed board order from the existing timeline order of each (date, status) column.
+        sqlx::query(
+            "UPDATE tasks SET position = (
+                SELECT COUNT(*) FROM tasks t2
+                WHERE t2.date = tasks.date AND t2.status = tasks.status
+                  AND (COALESCE(t2.start_time, '') < COALESCE(tasks.start_time, '')
+                       OR (COALESCE(t2.start_time, '') = COALESCE(tasks.start_time, '') AND t2.id < tasks.id))
+            )",
+        )
+        .execute(&pool)
+        .await
+        .map_err(|e| format!("Failed to backfill tasks.position: {}", e))?;
+    }
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS task_statuses (
+            key TEXT PRIMARY KEY,
+            label TEXT NOT NULL,
+            color TEXT NOT NULL DEFAULT '',
+            is_done INTEGER NOT NULL DEFAULT 0,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create task_statuses table: {}", e))?;
+
+    let status_count = sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM task_statuses")
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| format!("Failed to count task statuses: {}", e))?;
+    if status_count == 0 {
+        let now = now_iso();
+        for (idx, (key, label, color, is_done)) in [
+            ("todo", "待办", "#94a3b8", false),
+            ("in-progress", "进行中", "#f59e0b", false),
+            ("done", "已完成", "#22c55e", true),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            sqlx::query(
+                "INSERT OR IGNORE INTO task_statuses (key, label, color, is_done, sort_order, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(key)
+            .bind(label)
+            .bind(color)
+            .bind(is_done)
+            .bind(idx as i64)
+            .bind(&now)
+            .bind(&now)
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to seed task statuses: {}", e))?;
+        }
+    }
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS task_plan_templates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT '',
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create task_plan_templates table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS task_plan_template_items (
+            id TEXT PRIMARY KEY,
+            template_id TEXT NOT NULL,
+            weekday_offset INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT NOT NULL DEFAULT '',
+            priority TEXT NOT NULL DEFAULT 'medium',
+            start_time TEXT NOT NULL DEFAULT '09:00',
+            duration REAL NOT NULL DEFAULT 1.0,
+            tags TEXT NOT NULL DEFAULT '',
+            timer_type TEXT NOT NULL DEFAULT 'none',
+            timer_duration INTEGER NOT NULL DEFAULT 25,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY(template_id) REFERENCES task_plan_templates(id) ON DELETE CASCADE
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create task_plan_template_items table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS focus_sessions (
+            id TEXT PRIMARY KEY,
+            date TEXT NOT NULL,
+            checked_in_at TEXT,
+            checked_out_at TEXT,
+            total_focus_seconds INTEGER DEFAULT 0,
+            active_task_id TEXT
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create focus_sessions table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS focus_templates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            timer_type TEXT NOT NULL DEFAULT 'pomodoro',
+            duration_minutes INTEGER NOT NULL DEFAULT 25,
+            tags_json TEXT NOT NULL DEFAULT '[]',
+            linked_task_title TEXT,
+            color_token TEXT,
+            is_archived INTEGER NOT NULL DEFAULT 0,
+            short_break_minutes INTEGER NOT NULL DEFAULT 5,
+            long_break_minutes INTEGER NOT NULL DEFAULT 15,
+            cycles_before_long_break INTEGER NOT NULL DEFAULT 4,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create focus_templates table: {}", e))?;
+
+    let focus_template_columns: Vec<String> = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM pragma_table_info('focus_templates')",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("Failed to inspect focus_templates columns: {}", e))?;
+
+    for (column, definition) in [
+        ("short_break_minutes", "INTEGER NOT NULL DEFAULT 5"),
+        ("long_break_minutes", "INTEGER NOT NULL DEFAULT 15"),
+        ("cycles_before_long_break", "INTEGER NOT NULL DEFAULT 4"),
+    ] {
+        if !focus_template_columns.iter().any(|col| col == column) {
+            sqlx::query(&format!("ALTER TABLE focus_templates ADD COLUMN {} {}", column, definition))
+                .execute(&pool)
+                .await
+                .map_err(|e| format!("Failed to add focus_templates.{} column: {}", column, e))?;
+        }
+    }
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS focus_runs (
+            id TEXT PRIMARY KEY,
+            source TEXT NOT NULL,
+            template_id TEXT,
+            task_id TEXT,
+            timer_type TEXT NOT NULL,
+            planned_minutes INTEGER NOT NULL,
+            actual_seconds INTEGER NOT NULL DEFAULT 0,
+            status TEXT NOT NULL DEFAULT 'running',
+            started_at TEXT NOT NULL,
+            ended_at TEXT,
+            date TEXT NOT NULL,
+            tags_json TEXT NOT NULL DEFAULT '[]',
+            note TEXT,
+            cycle INTEGER NOT NULL DEFAULT 1,
+            break_seconds INTEGER NOT NULL DEFAULT 0,
+            last_seen_at TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY(template_id) REFERENCES focus_templates(id) ON DELETE SET NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create focus_runs table: {}", e))?;
+
+    let focus_run_columns: Vec<String> = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM pragma_table_info('focus_runs')",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("Failed to inspect focus_runs columns: {}", e))?;
+
+    for (column, definition) in [
+        ("cycle", "INTEGER NOT NULL DEFAULT 1"),
+        ("break_seconds", "INTEGER NOT NULL DEFAULT 0"),
+        ("last_seen_at", "TEXT"),
+    ] {
+        if !focus_run_columns.iter().any(|col| col == column) {
+            sqlx::query(&format!("ALTER TABLE focus_runs ADD COLUMN {} {}", column, definition))
+                .execute(&pool)
+                .await
+                .map_err(|e| format!("Failed to add focus_runs.{} column: {}", column, e))?;
+        }
+    }
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS focus_run_segments (
+            id TEXT PRIMARY KEY,
+            run_id TEXT NOT NULL,
+            kind TEXT NOT NULL DEFAULT 'focus',
+            phase TEXT NOT NULL DEFAULT 'work',
+            cycle INTEGER NOT NULL DEFAULT 1,
+            started_at TEXT NOT NULL,
+            ended_at TEXT,
+            planned_seconds INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY(run_id) REFERENCES focus_runs(id) ON DELETE CASCADE
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create focus_run_segments table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS focus_interruptions (
+            id TEXT PRIMARY KEY,
+            run_id TEXT NOT NULL,
+            category TEXT NOT NULL DEFAULT 'other',
+            note TEXT,
+            occurred_at TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY(run_id) REFERENCES focus_runs(id) ON DELETE CASCADE
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create focus_interruptions table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS focus_goals (
+            id TEXT PRIMARY KEY,
+            dimension TEXT NOT NULL DEFAULT 'tag',
+            target_key TEXT NOT NULL,
+            period TEXT NOT NULL DEFAULT 'day',
+            target_minutes INTEGER NOT NULL,
+            is_active INTEGER NOT NULL DEFAULT 1,
+            last_reached_period TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create focus_goals table: {}", e))?;
+
+    let has_attendance_table = sqlx::query_scalar::<_, i64>(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'attendance_records'",
+    )
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| format!("Failed to inspect attendance_records table: {}", e))?
+        > 0;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS attendance_records (
+            id TEXT PRIMARY KEY,
+            date TEXT NOT NULL,
+            checked_in_at TEXT,
+            checked_out_at TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create attendance_records table: {}", e))?;
+
+    if !has_attendance_table {
+        migrate_legacy_focus_sessions(&pool).await?;
+    }
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS video_bookmarks (
+            id TEXT PRIMARY KEY,
+            bvid TEXT NOT NULL,
+            title TEXT NOT NULL,
+            pic TEXT DEFAULT '',
+            owner_name TEXT DEFAULT '',
+            duration INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create video_bookmarks table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS resources (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            path TEXT NOT NULL,
+            file_type TEXT DEFAULT 'file',
+            subject TEXT DEFAULT '',
+            size_bytes INTEGER DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create resources table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS questions (
+            id TEXT PRIMARY KEY,
+            subject TEXT NOT NULL,
+            type TEXT NOT NULL DEFAULT 'choice',
+            stem TEXT NOT NULL,
+            options TEXT,
+            answer TEXT NOT NULL,
+            explanation TEXT DEFAULT '',
+            source_files TEXT DEFAULT '[]',
+            difficulty INTEGER DEFAULT 2,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            next_review DATETIME DEFAULT CURRENT_TIMESTAMP,
+            review_count INTEGER DEFAULT 0,
+            correct_count INTEGER DEFAULT 0,
+            ease_factor REAL DEFAULT 2.5,
+            interval INTEGER DEFAULT 0
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create questions table: {}", e))?;
+
+    let question_columns: Vec<String> = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM pragma_table_info('questions')",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("Failed to inspect questions columns: {}", e))?;
+
+    if !question_columns.iter().any(|col| col == "interval") {
+        sqlx::query("ALTER TABLE questions ADD COLUMN interval INTEGER DEFAULT 0")
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to add questions.interval column: {}", e))?;
+    }
+
+    if !question_columns.iter().any(|col| col == "next_review") {
+        sqlx::query("ALTER TABLE questions ADD COLUMN next_review DATETIME DEFAULT CURRENT_TIMESTAMP")
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to add questions.next_review column: {}", e))?;
+    }
+
+    sqlx::query("UPDATE questions SET next_review = CURRENT_TIMESTAMP WHERE next_review IS NULL")
+        .execute(&pool)
+        .await
+        .ok();
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS ai_sessions (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create ai_sessions table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS ai_messages (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(session_id) REFERENCES ai_sessions(id) ON DELETE CASCADE
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create ai_messages table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS wrong_questions (
+            id TEXT PRIMARY KEY,
+            subject TEXT NOT NULL DEFAULT '其他',
+            tags_json TEXT NOT NULL DEFAULT '[]',
+            question_content TEXT NOT NULL,
+            question_image_path TEXT,
+            ai_solution TEXT NOT NULL,
+            user_note TEXT,
+            source TEXT NOT NULL DEFAULT 'ai_chat',
+            ai_session_id TEXT,
+            ai_message_ids_json TEXT,
+            difficulty INTEGER NOT NULL DEFAULT 3,
+            mastery_level INTEGER NOT NULL DEFAULT 0,
+            review_count INTEGER NOT NULL DEFAULT 0,
+            next_review_date TEXT,
+            last_review_date TEXT,
+            ease_factor REAL NOT NULL DEFAULT 2.5,
+            interval_days INTEGER NOT NULL DEFAULT 1,
+            is_archived INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY(ai_session_id) REFERENCES ai_sessions(id) ON DELETE SET NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create wrong_questions table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS weekly_review_items (
+            id TEXT PRIMARY KEY,
+            week_start TEXT NOT NULL,
+            week_end TEXT NOT NULL,
+            wrong_question_id TEXT NOT NULL,
+            title_snapshot TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            carried_from_week TEXT,
+            completed_at TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY(wrong_question_id) REFERENCES wrong_questions(id) ON DELETE CASCADE
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create weekly_review_items table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS weekly_review_exclusions (
+            week_start TEXT NOT NULL,
+            wrong_question_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY(week_start, wrong_question_id),
+            FOREIGN KEY(wrong_question_id) REFERENCES wrong_questions(id) ON DELETE CASCADE
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create weekly_review_exclusions table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS wrong_question_mastery_rules (
+            subject TEXT PRIMARY KEY,
+            reviews_to_master INTEGER NOT NULL DEFAULT 3,
+            min_spacing_days INTEGER NOT NULL DEFAULT 0,
+            auto_archive INTEGER NOT NULL DEFAULT 1,
+            archive_after_reviews INTEGER NOT NULL DEFAULT 4,
+            updated_at TEXT
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create wrong_question_mastery_rules table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS knowledge_nodes (
+            id TEXT PRIMARY KEY,
+            parent_id TEXT,
+            name TEXT NOT NULL,
+            level TEXT NOT NULL DEFAULT 'subject',
+            position INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY(parent_id) REFERENCES knowledge_nodes(id) ON DELETE CASCADE
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create knowledge_nodes table: {}", e))?;
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS knowledge_node_items (
+            node_id TEXT NOT NULL,
+            item_type TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY(node_id, item_type, item_id),
+            FOREIGN KEY(node_id) REFERENCES knowledge_nodes(id) ON DELETE CASCADE
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create knowledge_node_items table: {}", e))?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_knowledge_nodes_parent ON knowledge_nodes(parent_id)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_knowledge_node_items_item ON knowledge_node_items(item_type, item_id)",
+    )
+    .execute(&pool)
+    .await
+    .ok();
+
+    // FSRS memory state lives next to the SM-2 columns so either scheduler can take over a card.
+    let question_columns: Vec<String> = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM pragma_table_info('questions')",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("Failed to inspect questions columns: {}", e))?;
+
+    for (column, definition) in [
+        ("stability", "REAL NOT NULL DEFAULT 0"),
+        ("fsrs_difficulty", "REAL NOT NULL DEFAULT 0"),
+        ("last_review", "TEXT"),
+        ("tags_json", "TEXT NOT NULL DEFAULT '[]'"),
+    ] {
+        if !question_columns.iter().any(|col| col == column) {
+            sqlx::query(&format!("ALTER TABLE questions ADD COLUMN {} {}", column, definition))
+                .execute(&pool)
+                .await
+                .map_err(|e| format!("Failed to add questions.{} column: {}", column, e))?;
+        }
+    }
+
+    let wrong_question_columns: Vec<String> = sqlx::query_scalar::<_, String>(
+        "SELECT name FROM pragma_table_info('wrong_questions')",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(|e| format!("Failed to inspect wrong_questions columns: {}", e))?;
+
+    for (column, definition) in [
+        ("stability", "REAL NOT NULL DEFAULT 0"),
+        ("fsrs_difficulty", "REAL NOT NULL DEFAULT 0"),
+        ("content_fingerprint", "TEXT"),
+        ("error_causes_json", "TEXT NOT NULL DEFAULT '[]'"),
+        ("last_counted_review_at", "TEXT"),
+    ] {
+        if !wrong_question_columns.iter().any(|col| col == column) {
+            sqlx::query(&format!("ALTER TABLE wrong_questions ADD COLUMN {} {}", column, definition))
+                .execute(&pool)
+                .await
+                .map_err(|e| format!("Failed to add wrong_questions.{} column: {}", column, e))?;
+        }
+    }
+
+    if !wrong_question_columns.iter().any(|col| col == "last_counted_review_at") {
+        // Best available guess for rows reviewed before counted reviews were tracked separately.
+        sqlx::query("UPDATE wrong_questions SET last_counted_review_at = last_review_date WHERE review_count > 0")
+            .execute(&pool)
+            .await
+            .map_err(|e| format!("Failed to backfill wrong_questions.last_counted_review_at: {}", e))?;
+    }
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS review_log (
+            id TEXT PRIMARY KEY,
+            item_type TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            grade TEXT NOT NULL,
+            reviewed_at TEXT NOT NULL,
+            elapsed_days REAL NOT NULL DEFAULT 0,
+            response_ms INTEGER,
+            prev_interval INTEGER NOT NULL DEFAULT 0,
+            new_interval INTEGER NOT NULL DEFAULT 0,
+            scheduler TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create review_log table: {}", e))?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_review_log_item ON review_log(item_type, item_id, reviewed_at)")
+        .execute(&pool)
+        .await
+        .ok();
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&pool)
+    .await
+    .map_err(|e| format!("Failed to create app_settings table: {}", e))?;
+
+    sqlx::query("ALTER TABLE ai_messages ADD COLUMN image_path TEXT")
+        .execute(&pool)
+        .await
+        .ok();
+
+    // Create index for date-based queries
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tasks_date ON tasks(date)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tasks_status ON tasks(status)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_tasks_board ON tasks(date, status, position)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_task_plan_template_items_template ON task_plan_template_items(template_id)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_attendance_records_date ON attendance_records(date)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_sessions_date ON focus_sessions(date)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_templates_archived ON focus_templates(is_archived)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_templates_updated_at ON focus_templates(updated_at DESC)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_runs_date ON focus_runs(date)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_run_segments_run_id ON focus_run_segments(run_id)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_interruptions_run_id ON focus_interruptions(run_id)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_runs_status ON focus_runs(status)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_focus_runs_template_id ON focus_runs(template_id)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_video_bookmarks_created_at ON video_bookmarks(created_at DESC)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_resources_subject ON resources(subject)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_questions_subject ON questions(subject)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_questions_next_review ON questions(next_review)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_ai_sessions_updated_at ON ai_sessions(updated_at DESC)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_ai_messages_session_id ON ai_messages(session_id)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_subject ON wrong_questions(subject)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_fingerprint ON wrong_questions(content_fingerprint)")
+        .execute(&pool)
+        .await
+        .ok();
+
+    db_backfill_wrong_question_fingerprints(&pool).await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_mastery ON wrong_questions(mastery_level)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_next_review ON wrong_questions(next_review_date)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_archived ON wrong_questions(is_archived)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_wrong_questions_created ON wrong_questions(created_at DESC)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_weekly_review_items_week ON weekly_review_items(week_start, week_end)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_weekly_review_items_status ON weekly_review_items(status)")
+        .execute(&pool)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_weekly_review_items_question ON weekly_review_items(wrong_question_id)")
+        .execute(&pool)
+        .await
+        .ok();
+
+    Ok(pool)
+}
+
+// ═══════════════════════════════════════════════════════════
+// Task CRUD Commands
+// ═══════════════════════════════════════════════════════════
+
+#[tauri::command]
+async fn get_tasks(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<Task>, String> {
+    let db = db.lock().await;
+    db_get_tasks(&db.db).await
+}
+
+#[tauri::command]
+async fn get_tasks_by_date(
+    date: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<Task>, String> {
+    let db = db.lock().await;
+    db_get_tasks_by_date(&db.db, &date).await
+}
+
+#[tauri::command]
+async fn query_tasks(
+    query: Option<TaskQuery>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<TaskQueryPage, String> {
+    let db = db.lock().await;
+    db_query_tasks(&db.db, &query.unwrap_or_default()).await
+}
+
+#[tauri::command]
+async fn create_task(
+    task: Task,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<Task, String> {
+    let db = db.lock().await;
+    let created = db_create_task(&db.db, &task).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    Ok(created)
+}
+
+#[tauri::command]
+async fn update_task(
+    task: Task,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<Task, String> {
+    let db = db.lock().await;
+    let updated = db_update_task(&db.db, &task).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn delete_task(
+    id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db_delete_task(&db.db, &id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    Ok(())
+}
+
+#[tauri::command]
+async fn batch_create_tasks(
+    tasks: Vec<Task>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<usize, String> {
+    let db = db.lock().await;
+    let count = db_batch_create_tasks(&db.db, &tasks).await?;
+    drop(db);
+    if count > 0 {
+        emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    }
+    Ok(count)
+}
+
+#[tauri::command]
+async fn get_task_statuses(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<TaskStatusColumn>, String> {
+    let db = db.lock().await;
+    db_get_task_statuses(&db.db).await
+}
+
+#[tauri::command]
+async fn save_task_status(
+    payload: SaveTaskStatusPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<TaskStatusColumn, String> {
+    let db = db.lock().await;
+    let saved = db_save_task_status(&db.db, &payload).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASK_STATUSES");
+    Ok(saved)
+}
+
+#[tauri::command]
+async fn delete_task_status(
+    key: String,
+    fallback: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<usize, String> {
+    let db = db.lock().await;
+    let moved = db_delete_task_status(&db.db, &key, fallback.as_deref()).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASK_STATUSES");
+    if moved > 0 {
+        emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    }
+    Ok(moved)
+}
+
+#[tauri::command]
+async fn reorder_task_statuses(
+    keys: Vec<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<Vec<TaskStatusColumn>, String> {
+    let db = db.lock().await;
+    let rows = db_reorder_task_statuses(&db.db, &keys).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASK_STATUSES");
+    Ok(rows)
+}
+
+#[tauri::command]
+async fn get_task_board(
+    start_date: String,
+    end_date: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<TaskBoardColumn>, String> {
+    let db = db.lock().await;
+    db_get_task_board(&db.db, &start_date, end_date.as_deref()).await
+}
+
+#[tauri::command]
+async fn move_task(
+    id: String,
+    payload: MoveTaskPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<Task, String> {
+    let db = db.lock().await;
+    let task = db_move_task(&db.db, &id, &payload).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    Ok(task)
+}
+
+#[tauri::command]
+async fn reorder_tasks(
+    date: String,
+    status: String,
+    task_ids: Vec<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<Vec<Task>, String> {
+    let db = db.lock().await;
+    let body = ReorderTasksBody {
+        date,
+        status,
+        task_ids,
+    };
+    let tasks = db_reorder_tasks(&db.db, &body).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    Ok(tasks)
+}
+
+#[tauri::command]
+async fn bulk_update_tasks(
+    filter: TaskFilter,
+    patch: TaskBulkPatch,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<TaskBulkResult, String> {
+    let db = db.lock().await;
+    let result = db_bulk_update_tasks(&db.db, &filter, &patch).await?;
+    drop(db);
+    if result.affected > 0 {
+        emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+async fn bulk_delete_tasks(
+    filter: TaskFilter,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<TaskBulkResult, String> {
+    let db = db.lock().await;
+    let result = db_bulk_delete_tasks(&db.db, &filter).await?;
+    drop(db);
+    if result.affected > 0 {
+        emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+async fn get_task_plan_templates(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<TaskPlanTemplateDetail>, String> {
+    let db = db.lock().await;
+    db_get_task_plan_templates(&db.db).await
+}
+
+#[tauri::command]
+async fn save_week_as_task_plan_template(
+    name: String,
+    description: Option<String>,
+    week_start: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<TaskPlanTemplateDetail, String> {
+    let db = db.lock().await;
+    let created = db_save_week_as_task_plan_template(
+        &db.db,
+        &name,
+        description.as_deref().unwrap_or(""),
+        &week_start,
+    )
+    .await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASK_PLAN_TEMPLATES");
+    Ok(created)
+}
+
+#[tauri::command]
+async fn update_task_plan_template(
+    id: String,
+    name: String,
+    description: Option<String>,
+    items: Vec<TaskPlanTemplateItem>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<TaskPlanTemplateDetail, String> {
+    let db = db.lock().await;
+    let updated = db_update_task_plan_template(
+        &db.db,
+        &id,
+        &name,
+        description.as_deref().unwrap_or(""),
+        &items,
+    )
+    .await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASK_PLAN_TEMPLATES");
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn delete_task_plan_template(
+    id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db_delete_task_plan_template(&db.db, &id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASK_PLAN_TEMPLATES");
+    Ok(())
+}
+
+#[tauri::command]
+async fn apply_task_plan_template(
+    template_id: String,
+    week_start: String,
+    conflict_strategy: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<ApplyTaskPlanReport, String> {
+    let db = db.lock().await;
+    let report = db_apply_task_plan_template(
+        &db.db,
+        &template_id,
+        &week_start,
+        conflict_strategy.as_deref(),
+    )
+    .await?;
+    drop(db);
+    if report.created > 0 || report.replaced > 0 {
+        emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+async fn get_weekly_stats(
+    end_date: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<WeeklyStats, String> {
+    let db = db.lock().await;
+    db_get_weekly_stats(&db.db, &end_date).await
+}
+
+// ═══════════════════════════════════════════════════════════
+// Daily Log CRUD Commands
+// ═══════════════════════════════════════════════════════════
+
+fn parse_frontmatter_value<'a>(lines: &'a [String], key: &str) -> Option<&'a str> {
+    lines
+        .iter()
+        .find_map(|line| line.strip_prefix(&format!("{}:", key)).map(|v| v.trim()))
+}
+
+fn sanitize_title_for_filename(title: &str) -> String {
+    title
+        .chars()
+        .map(|ch| {
+            if ch.is_alphanumeric() || ch == '-' || ch == '_' {
+                ch
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .trim_matches('-')
+        .chars()
+        .take(24)
+        .collect()
+}
+
+fn normalize_log_date(input: &str) -> String {
+    let trimmed = input.trim();
+    if trimmed.len() >= 10 {
+        let prefix = &trimmed[0..10];
+        if prefix.chars().nth(4) == Some('-') && prefix.chars().nth(7) == Some('-') {
+            return prefix.to_string();
+        }
+    }
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+fn build_log_stem(date: &str, title: &str) -> String {
+    let time_part = Local::now().format("%H-%M-%S").to_string();
+    let slug = sanitize_title_for_filename(title);
+    if slug.is_empty() {
+        format!("{}_{}", date, time_part)
+    } else {
+        format!("{}_{}_{}", date, time_part, slug)
+    }
+}
+
+fn parse_daily_log_markdown(raw: &str, fallback_stem: &str) -> DailyLog {
+    let mut frontmatter: Vec<String> = Vec::new();
+    let mut body = raw.to_string();
+
+    if raw.starts_with("---\n") {
+        let mut parts = raw.splitn(3, "---\n");
+        let _ = parts.next();
+        if let (Some(fm), Some(rest)) = (parts.next(), parts.next()) {
+            frontmatter = fm.lines().map(|s| s.trim().to_string()).collect();
+            body = rest.to_string();
+        }
+    }
+
+    let fallback_date = normalize_log_date(fallback_stem);
+    let id = parse_frontmatter_value(&frontmatter, "id")
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| fallback_stem.to_string());
+    let date = parse_frontmatter_value(&frontmatter, "date")
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| fallback_date.clone());
+    let title = parse_frontmatter_value(&frontmatter, "title")
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| format!("留痕 {}", fallback_date));
+    let mood = parse_frontmatter_value(&frontmatter, "mood")
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| "neutral".to_string());
+    let sync_rate = parse_frontmatter_value(&frontmatter, "sync_rate")
+        .and_then(|v| v.parse::<i32>().ok())
+        .unwrap_or(80);
+    let tags = parse_frontmatter_value(&frontmatter, "tags")
+        .map(|v| v.to_string())
+        .unwrap_or_default();
+    let auto_generated = parse_frontmatter_value(&frontmatter, "auto_generated")
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+    let created_at = parse_frontmatter_value(&frontmatter, "created_at")
+        .map(|v| v.to_string())
+        .unwrap_or_else(now_iso);
+    let updated_at = parse_frontmatter_value(&frontmatter, "updated_at")
+        .map(|v| v.to_string())
+        .unwrap_or_else(now_iso);
+
+    DailyLog {
+        id,
+        date,
+        title,
+        content: body.trim_start_matches('\n').to_string(),
+        mood,
+        sync_rate,
+        tags,
+        auto_generated,
+        created_at,
+        updated_at,
+    }
+}
+
+fn daily_log_to_markdown(log: &DailyLog) -> String {
+    format!(
+        "---\nid: {}\ndate: {}\ntitle: {}\nmood: {}\nsync_rate: {}\ntags: {}\nauto_generated: {}\ncreated_at: {}\nupdated_at: {}\n---\n\n{}\n",
+        log.id,
+        log.date,
+        log.title,
+        log.mood,
+        log.sync_rate,
+        log.tags,
+        if log.auto_generated { "true" } else { "false" },
+        log.created_at,
+        log.updated_at,
+        log.content
+    )
+}
+
+fn now_iso() -> String {
+    Utc::now().to_rfc3339()
+}
+
+async fn resolve_log_path_by_id(logs_dir: &Path, id: &str) -> Result<Option<PathBuf>, String> {
+    let direct = logs_dir.join(format!("{}.md", id));
+    if fs::metadata(&direct).await.is_ok() {
+        return Ok(Some(direct));
+    }
+
+    let mut entries = fs::read_dir(logs_dir).await.map_err(|e| {
+        format!(
+            "Failed to read logs dir {}: {}",
+            logs_dir.to_string_lossy(),
+            e
+        )
+    })?;
+    while let Some(entry) = entries
+        .next_entry()
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_weekly_review_items_status ON weekly_review_items(status)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to iterate logs dir: {}", e))?
+    {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+        let fallback_stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+        let raw = fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read log file {}: {}", path.to_string_lossy(), e))?;
+        let parsed = parse_daily_log_markdown(&raw, &fallback_stem);
+        if parsed.id == id {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+#[tauri::command]
+async fn get_daily_logs(app: tauri::AppHandle) -> Result<Vec<DailyLog>, String> {
+    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
+    let logs_dir = root.join("Logs");
+    fs::create_dir_all(&logs_dir).await.map_err(|e| {
+        format!(
+            "Failed to ensure logs dir {}: {}",
+            logs_dir.to_string_lossy(),
+            e
+        )
+    })?;
+
+    let mut entries = fs::read_dir(&logs_dir).await.map_err(|e| {
+        format!(
+            "Failed to read logs dir {}: {}",
+            logs_dir.to_string_lossy(),
+            e
+        )
+    })?;
+
+    let mut logs: Vec<DailyLog> = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_weekly_review_items_question ON weekly_review_items(wrong_question_id)")
-        .execute(&pool)
+        .map_err(|e| format!("Failed to iterate logs dir: {}", e))?
+    {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+            continue;
+        }
+
+        let fallback_stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+        let raw = fs::read_to_string(&path)
+            .await
+            .map_err(|e| format!("Failed to read log file {}: {}", path.to_string_lossy(), e))?;
+        logs.push(parse_daily_log_markdown(&raw, &fallback_stem));
+    }
+
+    logs.sort_by(|a, b| b.id.cmp(&a.id));
+    Ok(logs)
+}
+
+#[tauri::command]
+async fn create_daily_log(app: tauri::AppHandle, mut log: DailyLog) -> Result<DailyLog, String> {
+    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
+    let logs_dir = root.join("Logs");
+    fs::create_dir_all(&logs_dir).await.map_err(|e| {
+        format!(
+            "Failed to ensure logs dir {}: {}",
+            logs_dir.to_string_lossy(),
+            e
+        )
+    })?;
+
+    let normalized_date = normalize_log_date(&log.date);
+    log.date = normalized_date.clone();
+
+    let mut stem = build_log_stem(&normalized_date, &log.title);
+    let mut file_path = logs_dir.join(format!("{}.md", stem));
+    let mut suffix = 1usize;
+    while fs::metadata(&file_path).await.is_ok() {
+        stem = format!(
+            "{}-{}",
+            build_log_stem(&normalized_date, &log.title),
+            suffix
+        );
+        file_path = logs_dir.join(format!("{}.md", stem));
+        suffix += 1;
+    }
+
+    log.id = stem;
+    if log.created_at.trim().is_empty() {
+        log.created_at = now_iso();
+    }
+    log.updated_at = now_iso();
+
+    let markdown = daily_log_to_markdown(&log);
+    fs::write(&file_path, markdown).await.map_err(|e| {
+        format!(
+            "Failed to write log file {}: {}",
+            file_path.to_string_lossy(),
+            e
+        )
+    })?;
+
+    Ok(log)
+}
+
+#[tauri::command]
+async fn update_daily_log(app: tauri::AppHandle, mut log: DailyLog) -> Result<DailyLog, String> {
+    if log.id.trim().is_empty() {
+        return Err("Daily log id is required for update".to_string());
+    }
+
+    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
+    let logs_dir = root.join("Logs");
+    fs::create_dir_all(&logs_dir).await.map_err(|e| {
+        format!(
+            "Failed to ensure logs dir {}: {}",
+            logs_dir.to_string_lossy(),
+            e
+        )
+    })?;
+
+    let existing_path = resolve_log_path_by_id(&logs_dir, &log.id)
+        .await?
+        .ok_or_else(|| format!("Daily log not found for id {}", log.id))?;
+
+    let existing_raw = fs::read_to_string(&existing_path).await.map_err(|e| {
+        format!(
+            "Failed to read existing log file {}: {}",
+            existing_path.to_string_lossy(),
+            e
+        )
+    })?;
+    let fallback_stem = existing_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_string();
+    let existing_log = parse_daily_log_markdown(&existing_raw, &fallback_stem);
+
+    if log.date.trim().is_empty() {
+        log.date = existing_log.date;
+    } else {
+        log.date = normalize_log_date(&log.date);
+    }
+    if log.created_at.trim().is_empty() {
+        log.created_at = existing_log.created_at;
+    }
+    log.updated_at = now_iso();
+
+    let markdown = daily_log_to_markdown(&log);
+    fs::write(&existing_path, markdown).await.map_err(|e| {
+        format!(
+            "Failed to update log file {}: {}",
+            existing_path.to_string_lossy(),
+            e
+        )
+    })?;
+
+    Ok(log)
+}
+
+#[tauri::command]
+async fn delete_daily_log(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
+    let logs_dir = root.join("Logs");
+    fs::create_dir_all(&logs_dir).await.map_err(|e| {
+        format!(
+            "Failed to ensure logs dir {}: {}",
+            logs_dir.to_string_lossy(),
+            e
+        )
+    })?;
+
+    if let Some(path) = resolve_log_path_by_id(&logs_dir, &id).await? {
+        fs::remove_file(&path).await.map_err(|e| {
+            format!(
+                "Failed to delete log file {}: {}",
+                path.to_string_lossy(),
+                e
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════
+// Focus Session Commands
+// ═══════════════════════════════════════════════════════════
+
+#[tauri::command]
+async fn get_focus_sessions(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<FocusSession>, String> {
+    let db = db.lock().await;
+    let rows = sqlx::query_as::<_, FocusSession>(
+        "SELECT id, date, checked_in_at, checked_out_at, total_focus_seconds, active_task_id FROM focus_sessions ORDER BY date DESC"
+    )
+    .fetch_all(&db.db)
+    .await
+    .map_err(|e| format!("Failed to fetch focus sessions: {}", e))?;
+    Ok(rows)
+}
+
+#[tauri::command]
+async fn upsert_focus_session(
+    session: FocusSession,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<FocusSession, String> {
+    let db = db.lock().await;
+    sqlx::query(
+        "INSERT INTO focus_sessions (id, date, checked_in_at, checked_out_at, total_focus_seconds, active_task_id) VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT(id) DO UPDATE SET checked_in_at=excluded.checked_in_at, checked_out_at=excluded.checked_out_at, total_focus_seconds=excluded.total_focus_seconds, active_task_id=excluded.active_task_id"
+    )
+    .bind(&session.id)
+    .bind(&session.date)
+    .bind(&session.checked_in_at)
+    .bind(&session.checked_out_at)
+    .bind(&session.total_focus_seconds)
+    .bind(&session.active_task_id)
+    .execute(&db.db)
+    .await
+    .map_err(|e| format!("Failed to upsert focus session: {}", e))?;
+
+    let mut conn = db
+        .db
+        .acquire()
         .await
-        .ok();
+        .map_err(|e| format!("Failed to acquire connection: {}", e))?;
+    db_upsert_attendance_record(&mut conn, &session).await?;
+    Ok(session)
+}
 
-    Ok(pool)
+#[tauri::command]
+async fn get_attendance_records(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<AttendanceRecord>, String> {
+    let db = db.lock().await;
+    db_get_attendance_records(&db.db, start_date.as_deref(), end_date.as_deref()).await
+}
+
+#[tauri::command]
+async fn get_focus_templates(
+    include_archived: Option<bool>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<FocusTemplate>, String> {
+    let db = db.lock().await;
+    db_get_focus_templates(&db.db, include_archived.unwrap_or(false)).await
+}
+
+#[tauri::command]
+async fn create_focus_template(
+    mut template: FocusTemplate,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusTemplate, String> {
+    if template.name.trim().is_empty() {
+        return Err("template.name 不能为空".to_string());
+    }
+    if template.duration_minutes <= 0 {
+        return Err("template.duration_minutes 必须大于 0".to_string());
+    }
+    let now = now_iso();
+    if template.id.trim().is_empty() {
+        template.id = gen_focus_id("focus-template");
+    }
+    if template.timer_type.trim().is_empty() {
+        template.timer_type = "pomodoro".to_string();
+    }
+    if template.tags_json.trim().is_empty() {
+        template.tags_json = "[]".to_string();
+    }
+    if template.created_at.trim().is_empty() {
+        template.created_at = now.clone();
+    }
+    template.updated_at = now;
+
+    let db = db.lock().await;
+    let created = db_create_focus_template(&db.db, &template).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_TEMPLATES");
+    Ok(created)
+}
+
+#[tauri::command]
+async fn update_focus_template(
+    id: String,
+    mut template: FocusTemplate,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusTemplate, String> {
+    if template.name.trim().is_empty() {
+        return Err("template.name 不能为空".to_string());
+    }
+    if template.duration_minutes <= 0 {
+        return Err("template.duration_minutes 必须大于 0".to_string());
+    }
+    if template.timer_type.trim().is_empty() {
+        template.timer_type = "pomodoro".to_string();
+    }
+    if template.tags_json.trim().is_empty() {
+        template.tags_json = "[]".to_string();
+    }
+
+    template.id = id;
+    template.updated_at = now_iso();
+    if template.created_at.trim().is_empty() {
+        template.created_at = template.updated_at.clone();
+    }
+
+    let db = db.lock().await;
+    let updated = db_update_focus_template(&db.db, &template).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_TEMPLATES");
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn archive_focus_template(
+    id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db_archive_focus_template(&db.db, &id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_TEMPLATES");
+    Ok(())
+}
+
+#[tauri::command]
+async fn start_focus_run(
+    payload: StartFocusRunPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusRun, String> {
+    start_focus_run_with_sync(db.inner(), sync_hub.inner(), &payload).await
+}
+
+#[tauri::command]
+async fn finish_focus_run(
+    run_id: String,
+    payload: FinishFocusRunPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusRun, String> {
+    finish_focus_run_with_sync(db.inner(), sync_hub.inner(), &run_id, &payload).await
+}
+
+#[tauri::command]
+async fn get_active_focus_run(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Option<FocusRunState>, String> {
+    let db = db.lock().await;
+    db_get_active_focus_run(&db.db).await
+}
+
+/// Shared by Tauri commands, LAN routes and WebSocket commands so every device sees the same events;
+/// runs aborted by an explicit `take_over` are announced with a "finish".
+async fn start_focus_run_with_sync(
+    db: &Arc<Mutex<AppDb>>,
+    sync_hub: &SyncHub,
+    payload: &StartFocusRunPayload,
+) -> Result<FocusRun, String> {
+    let db = db.lock().await;
+    let (started, superseded_ids) = db_start_focus_run(&db.db, payload).await?;
+    let mut superseded = Vec::with_capacity(superseded_ids.len());
+    for id in &superseded_ids {
+        superseded.push(db_get_focus_run_state(&db.db, id).await?);
+    }
+    let run_state = db_get_focus_run_state(&db.db, &started.id).await?;
+    drop(db);
+    emit_sync_action(sync_hub, "SYNC_FOCUS_RUNS");
+    for previous in &superseded {
+        emit_focus_run_event(sync_hub, "finish", previous);
+    }
+    emit_focus_run_event(sync_hub, "start", &run_state);
+    Ok(started)
+}
+
+async fn finish_focus_run_with_sync(
+    db: &Arc<Mutex<AppDb>>,
+    sync_hub: &SyncHub,
+    run_id: &str,
+    payload: &FinishFocusRunPayload,
+) -> Result<FocusRun, String> {
+    let db = db.lock().await;
+    let finished = db_finish_focus_run(&db.db, run_id, payload).await?;
+    let run_state = db_get_focus_run_state(&db.db, run_id).await?;
+    let reached = db_collect_reached_focus_goals(&db.db).await.unwrap_or_default();
+    drop(db);
+    emit_sync_action(sync_hub, "SYNC_FOCUS_RUNS");
+    emit_focus_run_event(sync_hub, "finish", &run_state);
+    emit_focus_goals_reached(sync_hub, &reached);
+    Ok(finished)
+}
+
+async fn transition_focus_run(
+    run_id: &str,
+    action: FocusRunAction,
+    db: &Arc<Mutex<AppDb>>,
+    sync_hub: &SyncHub,
+) -> Result<FocusRunState, String> {
+    let db = db.lock().await;
+    let run_state = db_transition_focus_run(&db.db, run_id, action).await?;
+    drop(db);
+    emit_sync_action(sync_hub, "SYNC_FOCUS_RUNS");
+    emit_focus_run_event(sync_hub, action.name(), &run_state);
+    Ok(run_state)
+}
+
+#[tauri::command]
+async fn heartbeat_focus_run(run_id: String, db: State<'_, Arc<Mutex<AppDb>>>) -> Result<(), String> {
+    let db = db.lock().await;
+    db_touch_focus_run(&db.db, &run_id).await
+}
+
+#[tauri::command]
+async fn pause_focus_run(
+    run_id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusRunState, String> {
+    transition_focus_run(&run_id, FocusRunAction::Pause, db.inner(), sync_hub.inner()).await
+}
+
+#[tauri::command]
+async fn resume_focus_run(
+    run_id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusRunState, String> {
+    transition_focus_run(&run_id, FocusRunAction::Resume, db.inner(), sync_hub.inner()).await
+}
+
+#[tauri::command]
+async fn extend_focus_run(
+    run_id: String,
+    minutes: i32,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusRunState, String> {
+    transition_focus_run(&run_id, FocusRunAction::Extend(minutes), db.inner(), sync_hub.inner()).await
+}
+
+#[tauri::command]
+async fn start_focus_break(
+    run_id: String,
+    minutes: Option<i32>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusRunState, String> {
+    transition_focus_run(&run_id, FocusRunAction::Break(minutes), db.inner(), sync_hub.inner()).await
+}
+
+#[tauri::command]
+async fn skip_focus_break(
+    run_id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusRunState, String> {
+    transition_focus_run(&run_id, FocusRunAction::SkipBreak, db.inner(), sync_hub.inner()).await
+}
+
+#[tauri::command]
+async fn get_interrupted_focus_runs(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<FocusRunState>, String> {
+    let db = db.lock().await;
+    db_get_interrupted_focus_runs(&db.db).await
+}
+
+#[tauri::command]
+async fn recover_focus_run(
+    run_id: String,
+    action: String,
+    cap_minutes: Option<i32>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusRunState, String> {
+    let db = db.lock().await;
+    let run_state = db_recover_focus_run(&db.db, &run_id, &action, cap_minutes).await?;
+    let reached = db_collect_reached_focus_goals(&db.db).await.unwrap_or_default();
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_RUNS");
+    emit_focus_run_event(sync_hub.inner().as_ref(), "recover", &run_state);
+    emit_focus_goals_reached(sync_hub.inner().as_ref(), &reached);
+    Ok(run_state)
+}
+
+#[tauri::command]
+async fn get_focus_runs(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    status: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<FocusRun>, String> {
+    let db = db.lock().await;
+    db_get_focus_runs(
+        &db.db,
+        start_date.as_deref(),
+        end_date.as_deref(),
+        status.as_deref(),
+    )
+    .await
 }
 
-// ═══════════════════════════════════════════════════════════
-// Task CRUD Commands
-// ═══════════════════════════════════════════════════════════
-
 #[tauri::command]
-async fn get_tasks(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<Task>, String> {
+async fn get_focus_timeseries(
+    query: Option<FocusTimeseriesQuery>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<FocusTimeseriesResult, String> {
     let db = db.lock().await;
-    db_get_tasks(&db.db).await
+    db_get_focus_timeseries(&db.db, &query.unwrap_or_default()).await
 }
 
 #[tauri::command]
-async fn get_tasks_by_date(
-    date: String,
+async fn get_focus_goals(
+    include_inactive: Option<bool>,
     db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Vec<Task>, String> {
+) -> Result<Vec<FocusGoal>, String> {
     let db = db.lock().await;
-    db_get_tasks_by_date(&db.db, &date).await
+    db_get_focus_goals(&db.db, include_inactive.unwrap_or(true)).await
 }
 
 #[tauri::command]
-async fn create_task(
-    task: Task,
+async fn create_focus_goal(
+    payload: FocusGoalPayload,
     db: State<'_, Arc<Mutex<AppDb>>>,
     sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<Task, String> {
+) -> Result<FocusGoal, String> {
     let db = db.lock().await;
-    let created = db_create_task(&db.db, &task).await?;
+    let goal = db_create_focus_goal(&db.db, &payload).await?;
+    let reached = db_collect_reached_focus_goals(&db.db).await.unwrap_or_default();
+    let goal = db_get_focus_goal(&db.db, &goal.id).await?;
     drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
-    Ok(created)
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_GOALS");
+    emit_focus_goals_reached(sync_hub.inner().as_ref(), &reached);
+    Ok(goal)
 }
 
 #[tauri::command]
-async fn update_task(
-    task: Task,
+async fn update_focus_goal(
+    id: String,
+    payload: FocusGoalPayload,
     db: State<'_, Arc<Mutex<AppDb>>>,
     sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<Task, String> {
+) -> Result<FocusGoal, String> {
     let db = db.lock().await;
-    let updated = db_update_task(&db.db, &task).await?;
+    let goal = db_update_focus_goal(&db.db, &id, &payload).await?;
+    let reached = db_collect_reached_focus_goals(&db.db).await.unwrap_or_default();
+    let goal = db_get_focus_goal(&db.db, &goal.id).await?;
     drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
-    Ok(updated)
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_GOALS");
+    emit_focus_goals_reached(sync_hub.inner().as_ref(), &reached);
+    Ok(goal)
 }
 
 #[tauri::command]
-async fn delete_task(
+async fn delete_focus_goal(
     id: String,
     db: State<'_, Arc<Mutex<AppDb>>>,
     sync_hub: State<'_, Arc<SyncHub>>,
 ) -> Result<(), String> {
     let db = db.lock().await;
-    db_delete_task(&db.db, &id).await?;
+    db_delete_focus_goal(&db.db, &id).await?;
     drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_GOALS");
     Ok(())
 }
 
 #[tauri::command]
-async fn batch_create_tasks(
-    tasks: Vec<Task>,
+async fn get_focus_goal_progress(
+    date: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<FocusGoalProgress>, String> {
+    let db = db.lock().await;
+    db_get_focus_goal_progress(&db.db, date.as_deref()).await
+}
+
+#[tauri::command]
+async fn log_focus_interruption(
+    run_id: Option<String>,
+    payload: Option<LogFocusInterruptionPayload>,
     db: State<'_, Arc<Mutex<AppDb>>>,
     sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<usize, String> {
+) -> Result<FocusInterruption, String> {
     let db = db.lock().await;
-    let count = db_batch_create_tasks(&db.db, &tasks).await?;
+    let interruption =
+        db_log_focus_interruption(&db.db, run_id.as_deref(), &payload.unwrap_or_default()).await?;
     drop(db);
-    if count > 0 {
-        emit_sync_action(sync_hub.inner().as_ref(), "SYNC_TASKS");
-    }
-    Ok(count)
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_INTERRUPTIONS");
+    Ok(interruption)
 }
 
 #[tauri::command]
-async fn get_weekly_stats(
-    end_date: String,
+async fn get_focus_interruptions(
+    run_id: String,
     db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<WeeklyStats, String> {
+) -> Result<Vec<FocusInterruption>, String> {
     let db = db.lock().await;
-    db_get_weekly_stats(&db.db, &end_date).await
+    db_get_focus_interruptions(&db.db, &run_id).await
 }
 
-// ═══════════════════════════════════════════════════════════
-// Daily Log CRUD Commands
-// ═══════════════════════════════════════════════════════════
-
-fn parse_frontmatter_value<'a>(lines: &'a [String], key: &str) -> Option<&'a str> {
-    lines
-        .iter()
-        .find_map(|line| line.strip_prefix(&format!("{}:", key)).map(|v| v.trim()))
+#[tauri::command]
+async fn delete_focus_interruption(
+    id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db_delete_focus_interruption(&db.db, &id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_INTERRUPTIONS");
+    Ok(())
 }
 
-fn sanitize_title_for_filename(title: &str) -> String {
-    title
-        .chars()
-        .map(|ch| {
-            if ch.is_alphanumeric() || ch == '-' || ch == '_' {
-                ch
-            } else {
-                '-'
-            }
-        })
-        .collect::<String>()
-        .trim_matches('-')
-        .chars()
-        .take(24)
-        .collect()
+#[tauri::command]
+async fn get_focus_interruption_stats(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<FocusInterruptionStats, String> {
+    let db = db.lock().await;
+    db_get_focus_interruption_stats(&db.db, start_date.as_deref(), end_date.as_deref()).await
 }
 
-fn normalize_log_date(input: &str) -> String {
-    let trimmed = input.trim();
-    if trimmed.len() >= 10 {
-        let prefix = &trimmed[0..10];
-        if prefix.chars().nth(4) == Some('-') && prefix.chars().nth(7) == Some('-') {
-            return prefix.to_string();
-        }
-    }
-    Local::now().format("%Y-%m-%d").to_string()
+#[tauri::command]
+async fn get_focus_stats(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    dimension: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<FocusStatsResult, String> {
+    let db = db.lock().await;
+    db_get_focus_stats(
+        &db.db,
+        start_date.as_deref(),
+        end_date.as_deref(),
+        dimension.as_deref(),
+    )
+    .await
 }
 
-fn build_log_stem(date: &str, title: &str) -> String {
-    let time_part = Local::now().format("%H-%M-%S").to_string();
-    let slug = sanitize_title_for_filename(title);
-    if slug.is_empty() {
-        format!("{}_{}", date, time_part)
-    } else {
-        format!("{}_{}_{}", date, time_part, slug)
-    }
+#[tauri::command]
+async fn get_wrong_questions(
+    filter: Option<WrongQuestionFilter>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<WrongQuestion>, String> {
+    let db = db.lock().await;
+    let effective = filter.unwrap_or(WrongQuestionFilter {
+        subject: None,
+        mastery_level: None,
+        search_keyword: None,
+        is_archived: Some(0),
+        error_cause: None,
+    });
+    db_get_wrong_questions(&db.db, &effective).await
 }
 
-fn parse_daily_log_markdown(raw: &str, fallback_stem: &str) -> DailyLog {
-    let mut frontmatter: Vec<String> = Vec::new();
-    let mut body = raw.to_string();
-
-    if raw.starts_with("---\n") {
-        let mut parts = raw.splitn(3, "---\n");
-        let _ = parts.next();
-        if let (Some(fm), Some(rest)) = (parts.next(), parts.next()) {
-            frontmatter = fm.lines().map(|s| s.trim().to_string()).collect();
-            body = rest.to_string();
-        }
+#[tauri::command]
+async fn create_wrong_question(
+    mut question: WrongQuestion,
+    add_to_current_week: Option<bool>,
+    allow_duplicate: Option<bool>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<WrongQuestionCreateResult, String> {
+    if question.question_content.trim().is_empty() {
+        return Err("question_content 不能为空".to_string());
     }
-
-    let fallback_date = normalize_log_date(fallback_stem);
-    let id = parse_frontmatter_value(&frontmatter, "id")
-        .map(|v| v.to_string())
-        .unwrap_or_else(|| fallback_stem.to_string());
-    let date = parse_frontmatter_value(&frontmatter, "date")
-        .map(|v| v.to_string())
-        .unwrap_or_else(|| fallback_date.clone());
-    let title = parse_frontmatter_value(&frontmatter, "title")
-        .map(|v| v.to_string())
-        .unwrap_or_else(|| format!("留痕 {}", fallback_date));
-    let mood = parse_frontmatter_value(&frontmatter, "mood")
-        .map(|v| v.to_string())
-        .unwrap_or_else(|| "neutral".to_string());
-    let sync_rate = parse_frontmatter_value(&frontmatter, "sync_rate")
-        .and_then(|v| v.parse::<i32>().ok())
-        .unwrap_or(80);
-    let tags = parse_frontmatter_value(&frontmatter, "tags")
-        .map(|v| v.to_string())
-        .unwrap_or_default();
-    let auto_generated = parse_frontmatter_value(&frontmatter, "auto_generated")
-        .map(|v| v.eq_ignore_ascii_case("true"))
-        .unwrap_or(false);
-    let created_at = parse_frontmatter_value(&frontmatter, "created_at")
-        .map(|v| v.to_string())
-        .unwrap_or_else(now_iso);
-    let updated_at = parse_frontmatter_value(&frontmatter, "updated_at")
-        .map(|v| v.to_string())
-        .unwrap_or_else(now_iso);
-
-    DailyLog {
-        id,
-        date,
-        title,
-        content: body.trim_start_matches('\n').to_string(),
-        mood,
-        sync_rate,
-        tags,
-        auto_generated,
-        created_at,
-        updated_at,
+    if question.ai_solution.trim().is_empty() {
+        return Err("ai_solution 不能为空".to_string());
     }
-}
-
-fn daily_log_to_markdown(log: &DailyLog) -> String {
-    format!(
-        "---\nid: {}\ndate: {}\ntitle: {}\nmood: {}\nsync_rate: {}\ntags: {}\nauto_generated: {}\ncreated_at: {}\nupdated_at: {}\n---\n\n{}\n",
-        log.id,
-        log.date,
-        log.title,
-        log.mood,
-        log.sync_rate,
-        log.tags,
-        if log.auto_generated { "true" } else { "false" },
-        log.created_at,
-        log.updated_at,
-        log.content
-    )
-}
 
-fn now_iso() -> String {
-    Utc::now().to_rfc3339()
-}
-
-async fn resolve_log_path_by_id(logs_dir: &Path, id: &str) -> Result<Option<PathBuf>, String> {
-    let direct = logs_dir.join(format!("{}.md", id));
-    if fs::metadata(&direct).await.is_ok() {
-        return Ok(Some(direct));
+    let now = now_iso();
+    if question.id.trim().is_empty() {
+        question.id = gen_focus_id("wrong-question");
+    }
+    if question.subject.trim().is_empty() {
+        question.subject = "其他".to_string();
+    }
+    if question.tags_json.trim().is_empty() {
+        question.tags_json = "[]".to_string();
+    }
+    if question.source.trim().is_empty() {
+        question.source = "ai_chat".to_string();
     }
+    if question.created_at.trim().is_empty() {
+        question.created_at = now.clone();
+    }
+    question.updated_at = now;
 
-    let mut entries = fs::read_dir(logs_dir).await.map_err(|e| {
-        format!(
-            "Failed to read logs dir {}: {}",
-            logs_dir.to_string_lossy(),
-            e
+    let db = db.lock().await;
+    let result = db_create_wrong_question(&db.db, &question, allow_duplicate.unwrap_or(false)).await?;
+    let Some(created) = result.question.as_ref() else {
+        return Ok(result);
+    };
+
+    if add_to_current_week.unwrap_or(false) {
+        let week_start = current_week_start_str();
+        let (_, week_end) = parse_week_start_monday(&week_start)?;
+        db_create_weekly_review_item_if_absent(
+            &db.db,
+            &week_start,
+            &week_end,
+            &created.id,
+            &make_title_snapshot(&created.question_content, &created.ai_solution),
+            None,
         )
-    })?;
-    while let Some(entry) = entries
-        .next_entry()
-        .await
-        .map_err(|e| format!("Failed to iterate logs dir: {}", e))?
-    {
-        let path = entry.path();
-        if path.extension().and_then(|e| e.to_str()) != Some("md") {
-            continue;
-        }
-        let fallback_stem = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
-        let raw = fs::read_to_string(&path)
-            .await
-            .map_err(|e| format!("Failed to read log file {}: {}", path.to_string_lossy(), e))?;
-        let parsed = parse_daily_log_markdown(&raw, &fallback_stem);
-        if parsed.id == id {
-            return Ok(Some(path));
-        }
+        .await?;
     }
-    Ok(None)
+
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(result)
 }
 
 #[tauri::command]
-async fn get_daily_logs(app: tauri::AppHandle) -> Result<Vec<DailyLog>, String> {
-    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
-    let logs_dir = root.join("Logs");
-    fs::create_dir_all(&logs_dir).await.map_err(|e| {
-        format!(
-            "Failed to ensure logs dir {}: {}",
-            logs_dir.to_string_lossy(),
-            e
-        )
-    })?;
+async fn update_wrong_question(
+    id: String,
+    mut question: WrongQuestion,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<WrongQuestion, String> {
+    question.id = id;
+    question.updated_at = now_iso();
+    let db = db.lock().await;
+    let updated = db_update_wrong_question(&db.db, &question).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    Ok(updated)
+}
 
-    let mut entries = fs::read_dir(&logs_dir).await.map_err(|e| {
-        format!(
-            "Failed to read logs dir {}: {}",
-            logs_dir.to_string_lossy(),
-            e
-        )
-    })?;
+#[tauri::command]
+async fn review_wrong_question(
+    id: String,
+    grade: String,
+    response_ms: Option<i64>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<WrongQuestion, String> {
+    let grade = ReviewGrade::parse(&grade)?;
+    let response_ms = validate_response_ms(response_ms)?;
+    let db = db.lock().await;
+    let reviewed = db_review_wrong_question(&db.db, &id, grade, response_ms).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    Ok(reviewed)
+}
 
-    let mut logs: Vec<DailyLog> = Vec::new();
-    while let Some(entry) = entries
-        .next_entry()
-        .await
-        .map_err(|e| format!("Failed to iterate logs dir: {}", e))?
-    {
-        let path = entry.path();
-        if path.extension().and_then(|e| e.to_str()) != Some("md") {
-            continue;
-        }
+#[tauri::command]
+async fn get_due_wrong_questions(
+    date: Option<String>,
+    subject: Option<String>,
+    limit: Option<i64>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<WrongQuestion>, String> {
+    let db = db.lock().await;
+    db_get_due_wrong_questions(&db.db, date.as_deref(), subject.as_deref(), limit).await
+}
 
-        let fallback_stem = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_string();
-        let raw = fs::read_to_string(&path)
-            .await
-            .map_err(|e| format!("Failed to read log file {}: {}", path.to_string_lossy(), e))?;
-        logs.push(parse_daily_log_markdown(&raw, &fallback_stem));
-    }
+#[tauri::command]
+async fn get_review_history(
+    item_type: String,
+    item_id: String,
+    limit: Option<i64>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<ReviewLogEntry>, String> {
+    let kind = parse_review_item_type(&item_type)?;
+    let db = db.lock().await;
+    db_get_review_history(&db.db, kind, &item_id, limit).await
+}
 
-    logs.sort_by(|a, b| b.id.cmp(&a.id));
-    Ok(logs)
+#[tauri::command]
+async fn get_review_curves(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    item_type: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<ReviewCurves, String> {
+    let db = db.lock().await;
+    db_get_review_curves(&db.db, start_date.as_deref(), end_date.as_deref(), item_type.as_deref()).await
 }
 
 #[tauri::command]
-async fn create_daily_log(app: tauri::AppHandle, mut log: DailyLog) -> Result<DailyLog, String> {
-    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
-    let logs_dir = root.join("Logs");
-    fs::create_dir_all(&logs_dir).await.map_err(|e| {
-        format!(
-            "Failed to ensure logs dir {}: {}",
-            logs_dir.to_string_lossy(),
-            e
-        )
-    })?;
+async fn get_review_scheduler_settings(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<ReviewSchedulerSettings, String> {
+    let db = db.lock().await;
+    db_get_review_scheduler_settings(&db.db).await
+}
 
-    let normalized_date = normalize_log_date(&log.date);
-    log.date = normalized_date.clone();
+#[tauri::command]
+async fn update_review_scheduler_settings(
+    payload: ReviewSchedulerSettingsPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<ReviewSchedulerSettings, String> {
+    let db = db.lock().await;
+    db_update_review_scheduler_settings(&db.db, &payload).await
+}
 
-    let mut stem = build_log_stem(&normalized_date, &log.title);
-    let mut file_path = logs_dir.join(format!("{}.md", stem));
-    let mut suffix = 1usize;
-    while fs::metadata(&file_path).await.is_ok() {
-        stem = format!(
-            "{}-{}",
-            build_log_stem(&normalized_date, &log.title),
-            suffix
-        );
-        file_path = logs_dir.join(format!("{}.md", stem));
-        suffix += 1;
-    }
+#[tauri::command]
+async fn optimize_review_scheduler(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<FsrsOptimizationResult, String> {
+    let db_guard = db.lock().await;
+    let (histories, start) = db_load_fsrs_training_set(&db_guard.db).await?;
+    drop(db_guard);
 
-    log.id = stem;
-    if log.created_at.trim().is_empty() {
-        log.created_at = now_iso();
+    let result = optimize_fsrs(start, histories).await?;
+    if result.applied {
+        let db = db.lock().await;
+        db_save_fsrs_weights(&db.db, &result.weights).await?;
     }
-    log.updated_at = now_iso();
+    Ok(result)
+}
 
-    let markdown = daily_log_to_markdown(&log);
-    fs::write(&file_path, markdown).await.map_err(|e| {
-        format!(
-            "Failed to write log file {}: {}",
-            file_path.to_string_lossy(),
-            e
-        )
-    })?;
+#[tauri::command]
+async fn archive_wrong_question(
+    id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db_archive_wrong_question(&db.db, &id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(())
+}
 
-    Ok(log)
+#[tauri::command]
+async fn delete_wrong_question(
+    id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db_delete_wrong_question(&db.db, &id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(())
 }
 
 #[tauri::command]
-async fn update_daily_log(app: tauri::AppHandle, mut log: DailyLog) -> Result<DailyLog, String> {
-    if log.id.trim().is_empty() {
-        return Err("Daily log id is required for update".to_string());
+async fn get_weekly_review_items(
+    week_start: String,
+    auto_populate: Option<bool>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<Vec<WeeklyReviewItem>, String> {
+    let db = db.lock().await;
+    let (rows, added) = db_get_weekly_review_items(&db.db, &week_start, auto_populate).await?;
+    drop(db);
+    if added > 0 {
+        emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
     }
+    Ok(rows)
+}
 
-    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
-    let logs_dir = root.join("Logs");
-    fs::create_dir_all(&logs_dir).await.map_err(|e| {
-        format!(
-            "Failed to ensure logs dir {}: {}",
-            logs_dir.to_string_lossy(),
-            e
-        )
-    })?;
-
-    let existing_path = resolve_log_path_by_id(&logs_dir, &log.id)
-        .await?
-        .ok_or_else(|| format!("Daily log not found for id {}", log.id))?;
-
-    let existing_raw = fs::read_to_string(&existing_path).await.map_err(|e| {
-        format!(
-            "Failed to read existing log file {}: {}",
-            existing_path.to_string_lossy(),
-            e
-        )
-    })?;
-    let fallback_stem = existing_path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("")
-        .to_string();
-    let existing_log = parse_daily_log_markdown(&existing_raw, &fallback_stem);
-
-    if log.date.trim().is_empty() {
-        log.date = existing_log.date;
-    } else {
-        log.date = normalize_log_date(&log.date);
-    }
-    if log.created_at.trim().is_empty() {
-        log.created_at = existing_log.created_at;
-    }
-    log.updated_at = now_iso();
+#[tauri::command]
+async fn add_weekly_review_item(
+    week_start: String,
+    wrong_question_id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<WeeklyReviewItem, String> {
+    let db = db.lock().await;
+    let item = db_add_weekly_review_item(&db.db, &week_start, &wrong_question_id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(item)
+}
 
-    let markdown = daily_log_to_markdown(&log);
-    fs::write(&existing_path, markdown).await.map_err(|e| {
-        format!(
-            "Failed to update log file {}: {}",
-            existing_path.to_string_lossy(),
-            e
-        )
-    })?;
+#[tauri::command]
+async fn remove_weekly_review_item(
+    item_id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db_remove_weekly_review_item(&db.db, &item_id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(())
+}
 
-    Ok(log)
+#[tauri::command]
+async fn check_wrong_question_duplicates(
+    question_content: String,
+    exclude_id: Option<String>,
+    subject: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<WrongQuestionDuplicate>, String> {
+    let db = db.lock().await;
+    db_find_duplicate_wrong_questions(&db.db, &question_content, exclude_id.as_deref(), subject.as_deref()).await
 }
 
 #[tauri::command]
-async fn delete_daily_log(app: tauri::AppHandle, id: String) -> Result<(), String> {
-    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
-    let logs_dir = root.join("Logs");
-    fs::create_dir_all(&logs_dir).await.map_err(|e| {
-        format!(
-            "Failed to ensure logs dir {}: {}",
-            logs_dir.to_string_lossy(),
-            e
-        )
-    })?;
+async fn merge_wrong_questions(
+    target_id: String,
+    source_ids: Vec<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<WrongQuestion, String> {
+    let db = db.lock().await;
+    let merged = db_merge_wrong_questions(&db.db, &target_id, &source_ids).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    Ok(merged)
+}
 
-    if let Some(path) = resolve_log_path_by_id(&logs_dir, &id).await? {
-        fs::remove_file(&path).await.map_err(|e| {
-            format!(
-                "Failed to delete log file {}: {}",
-                path.to_string_lossy(),
-                e
-            )
-        })?;
-    }
+#[tauri::command]
+async fn export_wrong_question_booklet(
+    app: tauri::AppHandle,
+    payload: WrongQuestionBookletPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<WrongQuestionBookletResult, String> {
+    let workspace_root = ensure_workspace_dirs(&app).await?;
+    let db = db.lock().await;
+    write_wrong_question_booklet(&db.db, &workspace_root, &payload).await
+}
 
-    Ok(())
+#[tauri::command]
+async fn export_anki_package(
+    app: tauri::AppHandle,
+    payload: AnkiExportPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<AnkiExportResult, String> {
+    let workspace_root = ensure_workspace_dirs(&app).await?;
+    let db = db.lock().await;
+    write_anki_package(&db.db, &workspace_root, &payload).await
 }
 
-// ═══════════════════════════════════════════════════════════
-// Focus Session Commands
-// ═══════════════════════════════════════════════════════════
+#[tauri::command]
+async fn import_anki_package(
+    app: tauri::AppHandle,
+    payload: AnkiImportPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<AnkiImportReport, String> {
+    let workspace_root = ensure_workspace_dirs(&app).await?;
+    let db = db.lock().await;
+    db_import_anki_package(&db.db, &workspace_root, &payload).await
+}
 
 #[tauri::command]
-async fn get_focus_sessions(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<FocusSession>, String> {
+async fn get_mastery_rules(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<MasteryRule>, String> {
     let db = db.lock().await;
-    let rows = sqlx::query_as::<_, FocusSession>(
-        "SELECT id, date, checked_in_at, checked_out_at, total_focus_seconds, active_task_id FROM focus_sessions ORDER BY date DESC"
-    )
-    .fetch_all(&db.db)
-    .await
-    .map_err(|e| format!("Failed to fetch focus sessions: {}", e))?;
-    Ok(rows)
+    db_get_mastery_rules(&db.db).await
 }
 
 #[tauri::command]
-async fn upsert_focus_session(
-    session: FocusSession,
+async fn save_mastery_rule(
+    payload: MasteryRulePayload,
     db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<FocusSession, String> {
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<MasteryRule, String> {
     let db = db.lock().await;
-    sqlx::query(
-        "INSERT INTO focus_sessions (id, date, checked_in_at, checked_out_at, total_focus_seconds, active_task_id) VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT(id) DO UPDATE SET checked_in_at=excluded.checked_in_at, checked_out_at=excluded.checked_out_at, total_focus_seconds=excluded.total_focus_seconds, active_task_id=excluded.active_task_id"
-    )
-    .bind(&session.id)
-    .bind(&session.date)
-    .bind(&session.checked_in_at)
-    .bind(&session.checked_out_at)
-    .bind(&session.total_focus_seconds)
-    .bind(&session.active_task_id)
-    .execute(&db.db)
-    .await
-    .map_err(|e| format!("Failed to upsert focus session: {}", e))?;
-    Ok(session)
+    let rule = db_upsert_mastery_rule(&db.db, &payload).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    Ok(rule)
 }
 
 #[tauri::command]
-async fn get_focus_templates(
-    include_archived: Option<bool>,
+async fn delete_mastery_rule(
+    subject: String,
     db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Vec<FocusTemplate>, String> {
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<(), String> {
     let db = db.lock().await;
-    db_get_focus_templates(&db.db, include_archived.unwrap_or(false)).await
+    db_delete_mastery_rule(&db.db, &subject).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    Ok(())
 }
 
 #[tauri::command]
-async fn create_focus_template(
-    mut template: FocusTemplate,
+async fn get_knowledge_tree(
+    recent_days: Option<i64>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<KnowledgeNodeStats>, String> {
+    let db = db.lock().await;
+    db_get_knowledge_tree(&db.db, recent_days).await
+}
+
+#[tauri::command]
+async fn create_knowledge_node(
+    payload: KnowledgeNodePayload,
     db: State<'_, Arc<Mutex<AppDb>>>,
     sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<FocusTemplate, String> {
-    if template.name.trim().is_empty() {
-        return Err("template.name 不能为空".to_string());
-    }
-    if template.duration_minutes <= 0 {
-        return Err("template.duration_minutes 必须大于 0".to_string());
-    }
-    let now = now_iso();
-    if template.id.trim().is_empty() {
-        template.id = gen_focus_id("focus-template");
-    }
-    if template.timer_type.trim().is_empty() {
-        template.timer_type = "pomodoro".to_string();
-    }
-    if template.tags_json.trim().is_empty() {
-        template.tags_json = "[]".to_string();
-    }
-    if template.created_at.trim().is_empty() {
-        template.created_at = now.clone();
-    }
-    template.updated_at = now;
+) -> Result<KnowledgeNode, String> {
+    let db = db.lock().await;
+    let node = db_create_knowledge_node(&db.db, &payload).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_KNOWLEDGE_TREE");
+    Ok(node)
+}
 
+#[tauri::command]
+async fn update_knowledge_node(
+    id: String,
+    payload: KnowledgeNodePayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<KnowledgeNode, String> {
     let db = db.lock().await;
-    let created = db_create_focus_template(&db.db, &template).await?;
+    let node = db_update_knowledge_node(&db.db, &id, &payload).await?;
     drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_TEMPLATES");
-    Ok(created)
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_KNOWLEDGE_TREE");
+    Ok(node)
 }
 
 #[tauri::command]
-async fn update_focus_template(
+async fn delete_knowledge_node(
     id: String,
-    mut template: FocusTemplate,
     db: State<'_, Arc<Mutex<AppDb>>>,
     sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<FocusTemplate, String> {
-    if template.name.trim().is_empty() {
-        return Err("template.name 不能为空".to_string());
-    }
-    if template.duration_minutes <= 0 {
-        return Err("template.duration_minutes 必须大于 0".to_string());
-    }
-    if template.timer_type.trim().is_empty() {
-        template.timer_type = "pomodoro".to_string();
-    }
-    if template.tags_json.trim().is_empty() {
-        template.tags_json = "[]".to_string();
-    }
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db_delete_knowledge_node(&db.db, &id).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_KNOWLEDGE_TREE");
+    Ok(())
+}
 
-    template.id = id;
-    template.updated_at = now_iso();
-    if template.created_at.trim().is_empty() {
-        template.created_at = template.updated_at.clone();
-    }
+#[tauri::command]
+async fn get_item_knowledge_nodes(
+    item_type: String,
+    item_id: String,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<KnowledgeNode>, String> {
+    let kind = parse_review_item_type(&item_type)?;
+    let db = db.lock().await;
+    db_get_item_knowledge_nodes(&db.db, kind, &item_id).await
+}
 
+#[tauri::command]
+async fn assign_knowledge_nodes(
+    item_type: String,
+    item_id: String,
+    node_ids: Vec<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<Vec<KnowledgeNode>, String> {
+    let kind = parse_review_item_type(&item_type)?;
     let db = db.lock().await;
-    let updated = db_update_focus_template(&db.db, &template).await?;
+    let nodes = db_assign_knowledge_nodes(&db.db, kind, &item_id, &node_ids).await?;
     drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_TEMPLATES");
-    Ok(updated)
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_KNOWLEDGE_TREE");
+    Ok(nodes)
 }
 
 #[tauri::command]
-async fn archive_focus_template(
-    id: String,
+async fn get_weekly_review_settings(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<WeeklyReviewSettings, String> {
+    let db = db.lock().await;
+    db_get_weekly_review_settings(&db.db).await
+}
+
+#[tauri::command]
+async fn update_weekly_review_settings(
+    payload: WeeklyReviewSettingsPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<WeeklyReviewSettings, String> {
+    let db = db.lock().await;
+    db_update_weekly_review_settings(&db.db, &payload).await
+}
+
+#[tauri::command]
+async fn toggle_weekly_review_item_done(
+    item_id: String,
+    done: bool,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<WeeklyReviewItem, String> {
+    let db = db.lock().await;
+    let row = db_toggle_weekly_review_item_done(&db.db, &item_id, done).await?;
+    drop(db);
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
+    Ok(row)
+}
+
+#[tauri::command]
+async fn carry_weekly_review_items_to_next_week(
+    item_ids: Vec<String>,
+    from_week_start: String,
     db: State<'_, Arc<Mutex<AppDb>>>,
     sync_hub: State<'_, Arc<SyncHub>>,
 ) -> Result<(), String> {
     let db = db.lock().await;
-    db_archive_focus_template(&db.db, &id).await?;
+    db_carry_weekly_review_items_to_next_week(&db.db, &from_week_start, &item_ids).await?;
     drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_TEMPLATES");
+    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
     Ok(())
 }
 
 #[tauri::command]
-async fn start_focus_run(
-    payload: StartFocusRunPayload,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<FocusRun, String> {
+async fn get_wrong_question_stats(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<WrongQuestionStats, String> {
+    let db = db.lock().await;
+    db_get_wrong_question_stats(&db.db).await
+}
+
+#[tauri::command]
+async fn save_chat_image(
+    app: tauri::AppHandle,
+    image_data: Vec<u8>,
+    ext: String,
+) -> Result<String, String> {
+    if image_data.is_empty() {
+        return Err("image_data 不能为空".to_string());
+    }
+    let root = ensure_workspace_dirs(&app).await?;
+    save_chat_image_to_workspace(&root, &image_data, &ext).await
+}
+
+// ═══════════════════════════════════════════════════════════
+// Video Bookmark Commands (SQLite)
+// ═══════════════════════════════════════════════════════════
+
+#[tauri::command]
+async fn get_video_bookmarks(
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<Vec<VideoBookmark>, String> {
+    let db = db.lock().await;
+    let rows = sqlx::query_as::<_, VideoBookmark>(
+        "SELECT id, bvid, title, pic, owner_name, duration, created_at FROM video_bookmarks ORDER BY created_at DESC"
+    )
+    .fetch_all(&db.db)
+    .await
+    .map_err(|e| format!("Failed to fetch video bookmarks: {}", e))?;
+    Ok(rows)
+}
+
+#[tauri::command]
+async fn add_video_bookmark(
+    bookmark: VideoBookmark,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<VideoBookmark, String> {
+    let db = db.lock().await;
+    sqlx::query(
+        "INSERT INTO video_bookmarks (id, bvid, title, pic, owner_name, duration, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
+    )
+    .bind(&bookmark.id)
+    .bind(&bookmark.bvid)
+    .bind(&bookmark.title)
+    .bind(&bookmark.pic)
+    .bind(&bookmark.owner_name)
+    .bind(bookmark.duration)
+    .bind(&bookmark.created_at)
+    .execute(&db.db)
+    .await
+    .map_err(|e| format!("Failed to add video bookmark: {}", e))?;
+    Ok(bookmark)
+}
+
+#[tauri::command]
+async fn delete_video_bookmark(id: String, db: State<'_, Arc<Mutex<AppDb>>>) -> Result<(), String> {
     let db = db.lock().await;
-    let started = db_start_focus_run(&db.db, &payload).await?;
-    drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_RUNS");
-    Ok(started)
+    sqlx::query("DELETE FROM video_bookmarks WHERE id = ?")
+        .bind(id)
+        .execute(&db.db)
+        .await
+        .map_err(|e| format!("Failed to delete video bookmark: {}", e))?;
+    Ok(())
 }
 
-#[tauri::command]
-async fn finish_focus_run(
-    run_id: String,
-    payload: FinishFocusRunPayload,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<FocusRun, String> {
-    let db = db.lock().await;
-    let finished = db_finish_focus_run(&db.db, &run_id, &payload).await?;
-    drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_RUNS");
-    Ok(finished)
+// ═══════════════════════════════════════════════════════════
+// Resource CRUD Commands (SQLite)
+// ═══════════════════════════════════════════════════════════
+
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+pub struct Resource {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub file_type: String,
+    pub subject: String,
+    pub size_bytes: i64,
+    pub created_at: String,
 }
 
 #[tauri::command]
-async fn get_focus_runs(
-    start_date: Option<String>,
-    end_date: Option<String>,
-    status: Option<String>,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Vec<FocusRun>, String> {
+async fn get_resources(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<Resource>, String> {
     let db = db.lock().await;
-    db_get_focus_runs(
-        &db.db,
-        start_date.as_deref(),
-        end_date.as_deref(),
-        status.as_deref(),
-    )
-    .await
+    db_get_resources_rows(&db.db).await
 }
 
 #[tauri::command]
-async fn get_focus_stats(
-    start_date: Option<String>,
-    end_date: Option<String>,
-    dimension: Option<String>,
+async fn add_resource(
+    resource: Resource,
     db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<FocusStatsResult, String> {
+) -> Result<Resource, String> {
     let db = db.lock().await;
-    db_get_focus_stats(
-        &db.db,
-        start_date.as_deref(),
-        end_date.as_deref(),
-        dimension.as_deref(),
+    sqlx::query(
+        "INSERT OR REPLACE INTO resources (id, name, path, file_type, subject, size_bytes, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
     )
+    .bind(&resource.id)
+    .bind(&resource.name)
+    .bind(&resource.path)
+    .bind(&resource.file_type)
+    .bind(&resource.subject)
+    .bind(resource.size_bytes)
+    .bind(&resource.created_at)
+    .execute(&db.db)
     .await
+    .map_err(|e| format!("Failed to add resource: {}", e))?;
+    Ok(resource)
 }
 
 #[tauri::command]
-async fn get_wrong_questions(
-    filter: Option<WrongQuestionFilter>,
+async fn delete_resource(
+    app: tauri::AppHandle,
+    id: String,
     db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Vec<WrongQuestion>, String> {
+) -> Result<(), String> {
+    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
+    let resources_base = root.join("Resources");
+
     let db = db.lock().await;
-    let effective = filter.unwrap_or(WrongQuestionFilter {
-        subject: None,
-        mastery_level: None,
-        search_keyword: None,
-        is_archived: Some(0),
-    });
-    db_get_wrong_questions(&db.db, &effective).await
-}
+    let path_row = sqlx::query_scalar::<_, String>("SELECT path FROM resources WHERE id = ?")
+        .bind(&id)
+        .fetch_optional(&db.db)
+        .await
+        .map_err(|e| format!("Failed to query resource path: {}", e))?;
 
-#[tauri::command]
-async fn create_wrong_question(
-    mut question: WrongQuestion,
-    add_to_current_week: Option<bool>,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<WrongQuestion, String> {
-    if question.question_content.trim().is_empty() {
-        return Err("question_content 不能为空".to_string());
-    }
-    if question.ai_solution.trim().is_empty() {
-        return Err("ai_solution 不能为空".to_string());
+    if let Some(relative_path) = path_row {
+        let sanitized = sanitize_relative_path(&relative_path)?;
+        let full_path = resources_base.join(sanitized);
+
+        if let Ok(metadata) = fs::metadata(&full_path).await {
+            if metadata.is_file() {
+                fs::remove_file(&full_path).await.map_err(|e| {
+                    format!(
+                        "Failed to delete resource file {}: {}",
+                        full_path.to_string_lossy(),
+                        e
+                    )
+                })?;
+            } else if metadata.is_dir() {
+                fs::remove_dir_all(&full_path).await.map_err(|e| {
+                    format!(
+                        "Failed to delete resource directory {}: {}",
+                        full_path.to_string_lossy(),
+                        e
+                    )
+                })?;
+            }
+        }
     }
 
-    let now = now_iso();
-    if question.id.trim().is_empty() {
-        question.id = gen_focus_id("wrong-question");
+    sqlx::query("DELETE FROM resources WHERE id = ?")
+        .bind(id)
+        .execute(&db.db)
+        .await
+        .map_err(|e| format!("Failed to delete resource: {}", e))?;
+    Ok(())
+}
+
+    // ═══════════════════════════════════════════════════════════
+    // Quiz Question Commands (SQLite)
+    // ═══════════════════════════════════════════════════════════
+
+    #[tauri::command]
+    async fn get_questions(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<Question>, String> {
+        let db = db.lock().await;
+        db_fetch_all_questions(&db.db).await
     }
-    if question.subject.trim().is_empty() {
-        question.subject = "其他".to_string();
+
+    #[tauri::command]
+    async fn get_due_questions(
+        subject: Option<String>,
+        db: State<'_, Arc<Mutex<AppDb>>>,
+    ) -> Result<Vec<Question>, String> {
+        let db = db.lock().await;
+
+        db_fetch_due_questions(&db.db, subject.as_deref()).await
     }
-    if question.tags_json.trim().is_empty() {
-        question.tags_json = "[]".to_string();
+
+    #[tauri::command]
+    async fn create_question(
+        question: Question,
+        db: State<'_, Arc<Mutex<AppDb>>>,
+    ) -> Result<Question, String> {
+        let db = db.lock().await;
+        sqlx::query(
+            "INSERT OR REPLACE INTO questions (id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval, tags_json) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&question.id)
+        .bind(&question.subject)
+        .bind(&question.r#type)
+        .bind(&question.stem)
+        .bind(&question.options)
+        .bind(&question.answer)
+        .bind(&question.explanation)
+        .bind(&question.source_files)
+        .bind(question.difficulty)
+        .bind(&question.created_at)
+        .bind(&question.next_review)
+        .bind(question.review_count)
+        .bind(question.correct_count)
+        .bind(question.ease_factor)
+        .bind(question.r#interval)
+        .bind(&question.tags_json)
+        .execute(&db.db)
+        .await
+        .map_err(|e| format!("Failed to create question: {}", e))?;
+        Ok(question)
     }
-    if question.source.trim().is_empty() {
-        question.source = "ai_chat".to_string();
+
+#[tauri::command]
+async fn read_local_file_text(app: tauri::AppHandle, path: String) -> Result<String, String> {
+    let input_path = PathBuf::from(&path);
+
+    let resolved_path = if input_path.is_absolute() {
+        input_path
+    } else {
+        let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
+        let resources_base = root.join("Resources");
+        let sanitized = sanitize_relative_path(&path)?;
+        resources_base.join(sanitized)
+    };
+
+    let extension = resolved_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if extension == "pdf" {
+        return Err("暂不支持读取 PDF 文本，请先转换为 .md 或 .txt".to_string());
     }
-    if question.created_at.trim().is_empty() {
-        question.created_at = now.clone();
+
+    if extension != "md" && extension != "txt" {
+        return Err("当前仅支持 .md / .txt 文本文件".to_string());
     }
-    question.updated_at = now;
 
-    let db = db.lock().await;
-    let created = db_create_wrong_question(&db.db, &question).await?;
+    fs::read_to_string(&resolved_path)
+        .await
+        .map_err(|e| format!("Failed to read file {}: {}", resolved_path.to_string_lossy(), e))
+}
 
-    if add_to_current_week.unwrap_or(false) {
-        let week_start = current_week_start_str();
-        let (_, week_end) = parse_week_start_monday(&week_start)?;
-        db_create_weekly_review_item_if_absent(
-            &db.db,
-            &week_start,
-            &week_end,
-            &created.id,
-            &make_title_snapshot(&created.question_content, &created.ai_solution),
-            None,
-        )
-        .await?;
-    }
+#[tauri::command]
+async fn answer_question(
+    id: String,
+    is_correct: Option<bool>,
+    grade: Option<String>,
+    response_ms: Option<i64>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<(), String> {
+    // Older clients only send a binary result: correct → good, wrong → again.
+    let grade = match (grade.as_deref(), is_correct) {
+        (Some(raw), _) => ReviewGrade::parse(raw)?,
+        (None, Some(true)) => ReviewGrade::Good,
+        (None, Some(false)) => ReviewGrade::Again,
+        (None, None) => return Err("缺少 grade 或 is_correct".to_string()),
+    };
+    let response_ms = validate_response_ms(response_ms)?;
+    let db = db.lock().await;
+    db_answer_question(&db.db, &id, grade, response_ms).await
+}
 
-    drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
-    Ok(created)
+#[tauri::command]
+async fn get_ai_sessions(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<AiSession>, String> {
+    let db = db.lock().await;
+    let rows = sqlx::query_as::<_, AiSession>(
+        "SELECT id, title, created_at, updated_at FROM ai_sessions ORDER BY updated_at DESC",
+    )
+    .fetch_all(&db.db)
+    .await
+    .map_err(|e| format!("Failed to fetch ai sessions: {}", e))?;
+    Ok(rows)
 }
 
 #[tauri::command]
-async fn update_wrong_question(
-    id: String,
-    mut question: WrongQuestion,
+async fn create_ai_session(
+    title: Option<String>,
     db: State<'_, Arc<Mutex<AppDb>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<WrongQuestion, String> {
-    question.id = id;
-    question.updated_at = now_iso();
+) -> Result<String, String> {
     let db = db.lock().await;
-    let updated = db_update_wrong_question(&db.db, &question).await?;
-    drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
-    Ok(updated)
+    let id = format!(
+        "ai-session-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| format!("SystemTime error: {}", e))?
+            .as_nanos()
+    );
+    let final_title = title
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "新对话".to_string());
+
+    sqlx::query(
+        "INSERT INTO ai_sessions (id, title, created_at, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
+    )
+    .bind(&id)
+    .bind(final_title)
+    .execute(&db.db)
+    .await
+    .map_err(|e| format!("Failed to create ai session: {}", e))?;
+
+    Ok(id)
 }
 
 #[tauri::command]
-async fn archive_wrong_question(
-    id: String,
+async fn update_ai_session_title(
+    session_id: String,
+    title: String,
     db: State<'_, Arc<Mutex<AppDb>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
 ) -> Result<(), String> {
     let db = db.lock().await;
-    db_archive_wrong_question(&db.db, &id).await?;
-    drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    let final_title = title.trim();
+    if final_title.is_empty() {
+        return Err("Title cannot be empty".to_string());
+    }
+
+    sqlx::query("UPDATE ai_sessions SET title = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(final_title)
+        .bind(session_id)
+        .execute(&db.db)
+        .await
+        .map_err(|e| format!("Failed to update ai session title: {}", e))?;
+
     Ok(())
 }
 
 #[tauri::command]
-async fn delete_wrong_question(
-    id: String,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<(), String> {
+async fn delete_ai_session(session_id: String, db: State<'_, Arc<Mutex<AppDb>>>) -> Result<(), String> {
     let db = db.lock().await;
-    db_delete_wrong_question(&db.db, &id).await?;
-    drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
+    sqlx::query("DELETE FROM ai_sessions WHERE id = ?")
+        .bind(session_id)
+        .execute(&db.db)
+        .await
+        .map_err(|e| format!("Failed to delete ai session: {}", e))?;
     Ok(())
 }
 
 #[tauri::command]
-async fn get_weekly_review_items(
-    week_start: String,
+async fn get_ai_messages(
+    session_id: String,
     db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Vec<WeeklyReviewItem>, String> {
+) -> Result<Vec<AiMessage>, String> {
     let db = db.lock().await;
-    db_get_weekly_review_items(&db.db, &week_start).await
+    let rows = sqlx::query_as::<_, AiMessage>(
+        "SELECT id, session_id, role, content, image_path, created_at FROM ai_messages WHERE session_id = ? ORDER BY created_at ASC",
+    )
+    .bind(session_id)
+    .fetch_all(&db.db)
+    .await
+    .map_err(|e| format!("Failed to fetch ai messages: {}", e))?;
+    Ok(rows)
 }
 
 #[tauri::command]
-async fn toggle_weekly_review_item_done(
-    item_id: String,
-    done: bool,
+async fn add_ai_message(
+    session_id: String,
+    role: String,
+    content: String,
+    image_path: Option<String>,
     db: State<'_, Arc<Mutex<AppDb>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<WeeklyReviewItem, String> {
+) -> Result<String, String> {
     let db = db.lock().await;
-    let row = db_toggle_weekly_review_item_done(&db.db, &item_id, done).await?;
-    drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WRONG_QUESTIONS");
-    Ok(row)
+    let normalized_role = match role.trim().to_lowercase().as_str() {
+        "user" => "user",
+        "assistant" | "model" => "assistant",
+        _ => return Err("Invalid role. Expected user or assistant".to_string()),
+    };
+
+    let id = format!(
+        "ai-msg-{}",
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(|e| format!("SystemTime error: {}", e))?
+            .as_nanos()
+    );
+
+    let mut tx = db
+        .db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin ai message tx: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO ai_messages (id, session_id, role, content, image_path, created_at) VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+    )
+    .bind(&id)
+    .bind(&session_id)
+    .bind(normalized_role)
+    .bind(&content)
+    .bind(&image_path)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to add ai message: {}", e))?;
+
+    sqlx::query("UPDATE ai_sessions SET updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+        .bind(&session_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to touch ai session updated_at: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit ai message tx: {}", e))?;
+
+    Ok(id)
 }
 
+/// Recursively copy an entire folder into Resources/ and return all copied file entries.
 #[tauri::command]
-async fn carry_weekly_review_items_to_next_week(
-    item_ids: Vec<String>,
-    from_week_start: String,
+async fn batch_copy_folder_to_resources(
+    app: tauri::AppHandle,
+    folder_path: String,
     db: State<'_, Arc<Mutex<AppDb>>>,
-    sync_hub: State<'_, Arc<SyncHub>>,
-) -> Result<(), String> {
-    let db = db.lock().await;
-    db_carry_weekly_review_items_to_next_week(&db.db, &from_week_start, &item_ids).await?;
-    drop(db);
-    emit_sync_action(sync_hub.inner().as_ref(), "SYNC_WEEKLY_REVIEW_ITEMS");
-    Ok(())
-}
+) -> Result<Vec<Resource>, String> {
+    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
+    let resources_base = root.join("Resources");
+    let source = PathBuf::from(&folder_path);
+
+    if !source.is_dir() {
+        return Err(format!("Source is not a directory: {}", folder_path));
+    }
+
+    let folder_name = source
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("imported")
+        .to_string();
+
+    let dest_dir = resources_base.join(&folder_name);
+    fs::create_dir_all(&dest_dir)
+        .await
+        .map_err(|e| format!("Failed to create dest dir: {}", e))?;
+
+    // Recursively collect all files
+    let mut stack: Vec<PathBuf> = vec![source.clone()];
+    let mut entries: Vec<Resource> = Vec::new();
+
+    while let Some(dir) = stack.pop() {
+        let mut reader = fs::read_dir(&dir)
+            .await
+            .map_err(|e| format!("Failed to read dir {}: {}", dir.to_string_lossy(), e))?;
+
+        while let Some(entry) = reader
+            .next_entry()
+            .await
+            .map_err(|e| format!("Failed to iterate dir: {}", e))?
+        {
+            let entry_path = entry.path();
+            let metadata = fs::metadata(&entry_path)
+                .await
+                .map_err(|e| format!("Failed to stat {}: {}", entry_path.to_string_lossy(), e))?;
+
+            if metadata.is_dir() {
+                // Mirror sub-directory structure
+                let rel = entry_path
+                    .strip_prefix(&source)
+                    .map_err(|e| format!("strip_prefix error: {}", e))?;
+                let sub_dest = dest_dir.join(rel);
+                fs::create_dir_all(&sub_dest)
+                    .await
+                    .map_err(|e| format!("Failed to create sub dir: {}", e))?;
+                stack.push(entry_path);
+            } else if metadata.is_file() {
+                let rel = entry_path
+                    .strip_prefix(&source)
+                    .map_err(|e| format!("strip_prefix error: {}", e))?;
+                let dest_file = dest_dir.join(rel);
+                // Ensure parent directory exists
+                if let Some(parent) = dest_file.parent() {
+                    fs::create_dir_all(parent)
+                        .await
+                        .map_err(|e| format!("Failed to create parent dir: {}", e))?;
+                }
+                fs::copy(&entry_path, &dest_file)
+                    .await
+                    .map_err(|e| format!("Failed to copy file: {}", e))?;
+
+                let file_name = entry_path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("unknown")
+                    .to_string();
+
+                let ext = entry_path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or("file")
+                    .to_lowercase();
+
+                let rel_path = dest_file
+                    .strip_prefix(&resources_base)
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_else(|_| dest_file.to_string_lossy().to_string());
 
-#[tauri::command]
-async fn get_wrong_question_stats(
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<WrongQuestionStats, String> {
-    let db = db.lock().await;
-    db_get_wrong_question_stats(&db.db).await
-}
+                let now = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
+                let id = format!(
+                    "res-{}-{}",
+                    chrono::Utc::now().timestamp_millis(),
+                    &uuid_short()
+                );
 
-#[tauri::command]
-async fn save_chat_image(
-    app: tauri::AppHandle,
-    image_data: Vec<u8>,
-    ext: String,
-) -> Result<String, String> {
-    if image_data.is_empty() {
-        return Err("image_data 不能为空".to_string());
+                entries.push(Resource {
+                    id,
+                    name: file_name,
+                    path: rel_path,
+                    file_type: ext,
+                    subject: String::new(),
+                    size_bytes: metadata.len() as i64,
+                    created_at: now,
+                });
+            }
+        }
     }
-    let root = ensure_workspace_dirs(&app).await?;
-    save_chat_image_to_workspace(&root, &image_data, &ext).await
-}
-
-// ═══════════════════════════════════════════════════════════
-// Video Bookmark Commands (SQLite)
-// ═══════════════════════════════════════════════════════════
 
-#[tauri::command]
-async fn get_video_bookmarks(
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Vec<VideoBookmark>, String> {
+    // Batch insert into SQLite
     let db = db.lock().await;
-    let rows = sqlx::query_as::<_, VideoBookmark>(
-        "SELECT id, bvid, title, pic, owner_name, duration, created_at FROM video_bookmarks ORDER BY created_at DESC"
-    )
-    .fetch_all(&db.db)
-    .await
-    .map_err(|e| format!("Failed to fetch video bookmarks: {}", e))?;
-    Ok(rows)
-}
+    for res in &entries {
+        sqlx::query(
+            "INSERT OR REPLACE INTO resources (id, name, path, file_type, subject, size_bytes, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&res.id)
+        .bind(&res.name)
+        .bind(&res.path)
+        .bind(&res.file_type)
+        .bind(&res.subject)
+        .bind(res.size_bytes)
+        .bind(&res.created_at)
+        .execute(&db.db)
+        .await
+        .map_err(|e| format!("Failed to insert resource: {}", e))?;
+    }
 
-#[tauri::command]
-async fn add_video_bookmark(
-    bookmark: VideoBookmark,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<VideoBookmark, String> {
-    let db = db.lock().await;
-    sqlx::query(
-        "INSERT INTO video_bookmarks (id, bvid, title, pic, owner_name, duration, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)"
-    )
-    .bind(&bookmark.id)
-    .bind(&bookmark.bvid)
-    .bind(&bookmark.title)
-    .bind(&bookmark.pic)
-    .bind(&bookmark.owner_name)
-    .bind(bookmark.duration)
-    .bind(&bookmark.created_at)
-    .execute(&db.db)
-    .await
-    .map_err(|e| format!("Failed to add video bookmark: {}", e))?;
-    Ok(bookmark)
+    Ok(entries)
 }
 
-#[tauri::command]
-async fn delete_video_bookmark(id: String, db: State<'_, Arc<Mutex<AppDb>>>) -> Result<(), String> {
-    let db = db.lock().await;
-    sqlx::query("DELETE FROM video_bookmarks WHERE id = ?")
-        .bind(id)
-        .execute(&db.db)
-        .await
-        .map_err(|e| format!("Failed to delete video bookmark: {}", e))?;
-    Ok(())
+/// Generate a short random ID segment
+fn uuid_short() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    let s = RandomState::new();
+    let mut hasher = s.build_hasher();
+    hasher.write_u128(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos());
+    format!("{:x}", hasher.finish())
 }
 
 // ═══════════════════════════════════════════════════════════
-// Resource CRUD Commands (SQLite)
+// Markdown Import Command
 // ═══════════════════════════════════════════════════════════
 
-#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
-pub struct Resource {
-    pub id: String,
-    pub name: String,
-    pub path: String,
-    pub file_type: String,
-    pub subject: String,
-    pub size_bytes: i64,
-    pub created_at: String,
-}
-
 #[tauri::command]
-async fn get_resources(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<Resource>, String> {
-    let db = db.lock().await;
-    db_get_resources_rows(&db.db).await
-}
+async fn parse_markdown_plan(content: String) -> Result<ImportReport, String> {
+    let mut tasks: Vec<ImportedTask> = Vec::new();
+    let mut current_date = String::new();
 
-#[tauri::command]
-async fn add_resource(
-    resource: Resource,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Resource, String> {
-    let db = db.lock().await;
-    sqlx::query(
-        "INSERT OR REPLACE INTO resources (id, name, path, file_type, subject, size_bytes, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
-    )
-    .bind(&resource.id)
-    .bind(&resource.name)
-    .bind(&resource.path)
-    .bind(&resource.file_type)
-    .bind(&resource.subject)
-    .bind(resource.size_bytes)
-    .bind(&resource.created_at)
-    .execute(&db.db)
-    .await
-    .map_err(|e| format!("Failed to add resource: {}", e))?;
-    Ok(resource)
-}
+    for line in content.lines() {
+        let trimmed = line.trim();
 
-#[tauri::command]
-async fn delete_resource(
-    app: tauri::AppHandle,
-    id: String,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<(), String> {
-    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
-    let resources_base = root.join("Resources");
+        // Parse date headers: ## 2026-03-01 or ## YYYY-MM-DD ...
+        if trimmed.starts_with("## ") || trimmed.starts_with("# ") {
+            let header = trimmed.trim_start_matches('#').trim();
+            // Try extract ISO date
+            if let Some(date) = extract_iso_date(header) {
+                current_date = date;
+            }
+        }
 
-    let db = db.lock().await;
-    let path_row = sqlx::query_scalar::<_, String>("SELECT path FROM resources WHERE id = ?")
-        .bind(&id)
-        .fetch_optional(&db.db)
-        .await
-        .map_err(|e| format!("Failed to query resource path: {}", e))?;
+        // Parse task lines: - [ ] or - [x]
+        if trimmed.starts_with("- [ ] ")
+            || trimmed.starts_with("- [x] ")
+            || trimmed.starts_with("- [X] ")
+        {
+            let is_done = trimmed.starts_with("- [x] ") || trimmed.starts_with("- [X] ");
+            let rest = &trimmed[6..];
+            let mut start_time: Option<String> = None;
+            let mut rest_text = rest.trim().to_string();
 
-    if let Some(relative_path) = path_row {
-        let sanitized = sanitize_relative_path(&relative_path)?;
-        let full_path = resources_base.join(sanitized);
+            if rest_text.starts_with('[') {
+                if let Some(end_idx) = rest_text.find(']') {
+                    let maybe_time = rest_text[1..end_idx].trim();
+                    if parse_clock_minutes(maybe_time).is_some() {
+                        start_time = Some(maybe_time.to_string());
+                        rest_text = rest_text[end_idx + 1..].trim().to_string();
+                    }
+                }
+            }
 
-        if let Ok(metadata) = fs::metadata(&full_path).await {
-            if metadata.is_file() {
-                fs::remove_file(&full_path).await.map_err(|e| {
-                    format!(
-                        "Failed to delete resource file {}: {}",
-                        full_path.to_string_lossy(),
-                        e
-                    )
-                })?;
-            } else if metadata.is_dir() {
-                fs::remove_dir_all(&full_path).await.map_err(|e| {
-                    format!(
-                        "Failed to delete resource directory {}: {}",
-                        full_path.to_string_lossy(),
-                        e
-                    )
-                })?;
+            // Extract @date
+            let mut task_date = current_date.clone();
+            let mut task_text = rest_text.clone();
+            if let Some(pos) = rest_text.find('@') {
+                let after_at = &rest_text[pos + 1..];
+                if let Some(date) = extract_iso_date(after_at) {
+                    task_date = date;
+                }
+                task_text = rest_text[..pos].trim().to_string();
+                // Remove trailing date from text if present
+                if let Some(space_pos) = task_text.rfind(" @") {
+                    task_text = task_text[..space_pos].trim().to_string();
+                }
+            }
+
+            // Extract #tags and #priority
+            let mut tags: Vec<String> = Vec::new();
+            let mut priority = "medium".to_string();
+            let mut clean_title = String::new();
+
+            if task_text.ends_with(')') {
+                if let Some(open_idx) = task_text.rfind('(') {
+                    let level = task_text[open_idx + 1..task_text.len() - 1]
+                        .trim()
+                        .to_lowercase();
+                    match level.as_str() {
+                        "high" => {
+                            priority = "high".to_string();
+                            task_text = task_text[..open_idx].trim().to_string();
+                        }
+                        "low" => {
+                            priority = "low".to_string();
+                            task_text = task_text[..open_idx].trim().to_string();
+                        }
+                        "medium" => {
+                            priority = "medium".to_string();
+                            task_text = task_text[..open_idx].trim().to_string();
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            for part in task_text.split_whitespace() {
+                if part.starts_with('#') {
+                    let tag = part.trim_start_matches('#').to_lowercase();
+                    match tag.as_str() {
+                        "high" | "urgent" => priority = "high".to_string(),
+                        "low" => priority = "low".to_string(),
+                        "medium" => priority = "medium".to_string(),
+                        _ => tags.push(tag),
+                    }
+                } else {
+                    if !clean_title.is_empty() {
+                        clean_title.push(' ');
+                    }
+                    clean_title.push_str(part);
+                }
+            }
+
+            if !clean_title.is_empty() {
+                tasks.push(ImportedTask {
+                    title: clean_title,
+                    date: if task_date.is_empty() {
+                        chrono_today()
+                    } else {
+                        task_date
+                    },
+                    priority,
+                    tags,
+                    status: if is_done {
+                        "done".to_string()
+                    } else {
+                        "todo".to_string()
+                    },
+                    start_time,
+                    duration: None,
+                });
             }
         }
     }
 
-    sqlx::query("DELETE FROM resources WHERE id = ?")
-        .bind(id)
-        .execute(&db.db)
-        .await
-        .map_err(|e| format!("Failed to delete resource: {}", e))?;
-    Ok(())
+    Ok(build_import_report(tasks))
 }
 
-    // ═══════════════════════════════════════════════════════════
-    // Quiz Question Commands (SQLite)
-    // ═══════════════════════════════════════════════════════════
-
-    #[tauri::command]
-    async fn get_questions(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<Question>, String> {
-        let db = db.lock().await;
-        db_fetch_all_questions(&db.db).await
+/// Shared by the Markdown, Org-mode and todo.txt parsers so all three report the same way.
+fn build_import_report(mut tasks: Vec<ImportedTask>) -> ImportReport {
+    infer_import_durations(&mut tasks);
+    ImportReport {
+        total: tasks.len(),
+        created: 0,
+        skipped: 0,
+        tasks,
     }
+}
 
-    #[tauri::command]
-    async fn get_due_questions(
-        subject: Option<String>,
-        db: State<'_, Arc<Mutex<AppDb>>>,
-    ) -> Result<Vec<Question>, String> {
-        let db = db.lock().await;
+// ═══════════════════════════════════════════════════════════
+// Org-mode / todo.txt Import & Export
+// ═══════════════════════════════════════════════════════════
 
-        db_fetch_due_questions(&db.db, subject.as_deref()).await
+fn is_plain_iso_date(s: &str) -> bool {
+    s.len() == 10 && chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok()
+}
+
+fn priority_from_letter(letter: char) -> &'static str {
+    match letter.to_ascii_uppercase() {
+        'A' => "high",
+        'B' => "medium",
+        _ => "low",
     }
+}
 
-    #[tauri::command]
-    async fn create_question(
-        question: Question,
-        db: State<'_, Arc<Mutex<AppDb>>>,
-    ) -> Result<Question, String> {
-        let db = db.lock().await;
-        sqlx::query(
-            "INSERT OR REPLACE INTO questions (id, subject, type, stem, options, answer, explanation, source_files, difficulty, created_at, next_review, review_count, correct_count, ease_factor, interval) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
-        )
-        .bind(&question.id)
-        .bind(&question.subject)
-        .bind(&question.r#type)
-        .bind(&question.stem)
-        .bind(&question.options)
-        .bind(&question.answer)
-        .bind(&question.explanation)
-        .bind(&question.source_files)
-        .bind(question.difficulty)
-        .bind(&question.created_at)
-        .bind(&question.next_review)
-        .bind(question.review_count)
-        .bind(question.correct_count)
-        .bind(question.ease_factor)
-        .bind(question.r#interval)
-        .execute(&db.db)
-        .await
-        .map_err(|e| format!("Failed to create question: {}", e))?;
-        Ok(question)
+fn priority_to_letter(priority: &str) -> char {
+    match priority {
+        "high" => 'A',
+        "low" => 'C',
+        _ => 'B',
     }
+}
 
-#[tauri::command]
-async fn read_local_file_text(app: tauri::AppHandle, path: String) -> Result<String, String> {
-    let input_path = PathBuf::from(&path);
+fn org_keyword_status(word: &str) -> Option<&'static str> {
+    match word {
+        "TODO" | "NEXT" | "WAITING" | "HOLD" => Some("todo"),
+        "STARTED" | "DOING" | "IN-PROGRESS" => Some("in-progress"),
+        "DONE" | "CANCELLED" | "CANCELED" => Some("done"),
+        _ => None,
+    }
+}
 
-    let resolved_path = if input_path.is_absolute() {
-        input_path
-    } else {
-        let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
-        let resources_base = root.join("Resources");
-        let sanitized = sanitize_relative_path(&path)?;
-        resources_base.join(sanitized)
-    };
+/// Text after the leading stars of an Org headline (`** TODO Title`), if the line is one.
+fn org_headline_text(line: &str) -> Option<&str> {
+    let rest = line.trim_start_matches('*');
+    if rest.len() == line.len() || !rest.starts_with(' ') {
+        return None;
+    }
+    Some(rest.trim())
+}
 
-    let extension = resolved_path
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+/// Inner text of the active timestamp following `keyword`, e.g. `SCHEDULED: <2026-03-01 Sun 09:00>`.
+fn extract_org_timestamp<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let pos = line.find(keyword)?;
+    let after = &line[pos + keyword.len()..];
+    let open = after.find('<')?;
+    let close = after[open..].find('>')? + open;
+    Some(&after[open + 1..close])
+}
 
-    if extension == "pdf" {
-        return Err("暂不支持读取 PDF 文本，请先转换为 .md 或 .txt".to_string());
+/// Start time and optional duration (hours) from a timestamp body such as `2026-03-01 Sun 09:00-10:30`.
+fn parse_stamp_time_range(stamp: &str) -> (Option<String>, Option<f64>) {
+    for token in stamp.split_whitespace() {
+        let (start, end) = match token.split_once('-') {
+            Some((s, e)) => (s, Some(e)),
+            None => (token, None),
+        };
+        if let Some(start_minutes) = parse_clock_minutes(start) {
+            let duration = end
+                .and_then(parse_clock_minutes)
+                .filter(|end_minutes| *end_minutes > start_minutes)
+                .map(|end_minutes| {
+                    ((end_minutes - start_minutes) as f64 / 60.0 * 10.0).round() / 10.0
+                });
+            return (
+                Some(format!("{:02}:{:02}", start_minutes / 60, start_minutes % 60)),
+                duration,
+            );
+        }
     }
+    (None, None)
+}
 
-    if extension != "md" && extension != "txt" {
-        return Err("当前仅支持 .md / .txt 文本文件".to_string());
+fn apply_org_timestamp(task: &mut ImportedTask, stamp: &str) {
+    if let Some(date) = extract_iso_date(stamp) {
+        task.date = date;
+    }
+    let (start_time, duration) = parse_stamp_time_range(stamp);
+    if start_time.is_some() {
+        task.start_time = start_time;
+        task.duration = duration;
     }
-
-    fs::read_to_string(&resolved_path)
-        .await
-        .map_err(|e| format!("Failed to read file {}: {}", resolved_path.to_string_lossy(), e))
 }
 
-#[tauri::command]
-async fn answer_question(
-    id: String,
-    is_correct: bool,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<(), String> {
-    let db = db.lock().await;
+fn parse_org_tasks(content: &str) -> Vec<ImportedTask> {
+    let mut tasks: Vec<ImportedTask> = Vec::new();
+    let mut current_date = String::new();
+    let mut current: Option<usize> = None;
+    let mut has_scheduled = false;
 
-    let row = sqlx::query_as::<_, (i32, i32, f64, i32)>(
-        "SELECT review_count, correct_count, ease_factor, interval FROM questions WHERE id = ?",
-    )
-    .bind(&id)
-    .fetch_optional(&db.db)
-    .await
-    .map_err(|e| format!("Failed to fetch question stats: {}", e))?
-    .ok_or_else(|| "Question not found".to_string())?;
+    for line in content.lines() {
+        if let Some(text) = org_headline_text(line) {
+            current = None;
+            has_scheduled = false;
+
+            let mut words: Vec<&str> = text.split_whitespace().collect();
+            let status = match words.first().and_then(|w| org_keyword_status(w)) {
+                Some(status) => status,
+                None => {
+                    // Plain headlines such as `* 2026-03-01` group the tasks below them.
+                    if let Some(date) = extract_iso_date(text) {
+                        current_date = date;
+                    }
+                    continue;
+                }
+            };
+            words.remove(0);
+
+            let mut priority = "medium";
+            if let Some(cookie) = words.first().copied() {
+                if cookie.len() == 4 && cookie.starts_with("[#") && cookie.ends_with(']') {
+                    priority = priority_from_letter(cookie.chars().nth(2).unwrap_or('B'));
+                    words.remove(0);
+                }
+            }
 
-    let (review_count, correct_count, ease_factor, interval_days) = row;
-    let new_review_count = review_count + 1;
-    let new_correct_count = if is_correct {
-        correct_count + 1
-    } else {
-        correct_count
-    };
+            let mut tags: Vec<String> = Vec::new();
+            if let Some(last) = words.last().copied() {
+                if last.len() > 2 && last.starts_with(':') && last.ends_with(':') {
+                    tags = last
+                        .split(':')
+                        .filter(|t| !t.is_empty())
+                        .map(|t| t.to_string())
+                        .collect();
+                    words.pop();
+                }
+            }
 
-    let (new_interval_days, new_ease_factor) = if is_correct {
-        let interval = if new_review_count == 1 {
-            1
-        } else if new_review_count == 2 {
-            6
-        } else {
-            ((interval_days as f64) * ease_factor).round().max(1.0) as i32
-        };
+            let title = words.join(" ");
+            if title.is_empty() {
+                continue;
+            }
 
-        let ef = (ease_factor + 0.05).max(1.3);
-        (interval, ef)
-    } else {
-        let ef = (ease_factor - 0.2).max(1.3);
-        (1, ef)
-    };
+            tasks.push(ImportedTask {
+                title,
+                date: if current_date.is_empty() {
+                    chrono_today()
+                } else {
+                    current_date.clone()
+                },
+                priority: priority.to_string(),
+                tags,
+                status: status.to_string(),
+                start_time: None,
+                duration: None,
+            });
+            current = Some(tasks.len() - 1);
+            continue;
+        }
 
-    sqlx::query(
-        "UPDATE questions
-         SET review_count = ?,
-             correct_count = ?,
-             ease_factor = ?,
-             interval = ?,
-             next_review = datetime('now', '+' || ? || ' day')
-         WHERE id = ?",
-    )
-    .bind(new_review_count)
-    .bind(new_correct_count)
-    .bind(new_ease_factor)
-    .bind(new_interval_days)
-    .bind(new_interval_days)
-    .bind(&id)
-    .execute(&db.db)
-    .await
-    .map_err(|e| format!("Failed to update question answer stats: {}", e))?;
+        let Some(idx) = current else {
+            continue;
+        };
+        let trimmed = line.trim();
+        if let Some(stamp) = extract_org_timestamp(trimmed, "SCHEDULED:") {
+            apply_org_timestamp(&mut tasks[idx], stamp);
+            has_scheduled = true;
+        }
+        if !has_scheduled {
+            if let Some(stamp) = extract_org_timestamp(trimmed, "DEADLINE:") {
+                apply_org_timestamp(&mut tasks[idx], stamp);
+            }
+        }
+    }
 
-    Ok(())
+    tasks
 }
 
-#[tauri::command]
-async fn get_ai_sessions(db: State<'_, Arc<Mutex<AppDb>>>) -> Result<Vec<AiSession>, String> {
-    let db = db.lock().await;
-    let rows = sqlx::query_as::<_, AiSession>(
-        "SELECT id, title, created_at, updated_at FROM ai_sessions ORDER BY updated_at DESC",
-    )
-    .fetch_all(&db.db)
-    .await
-    .map_err(|e| format!("Failed to fetch ai sessions: {}", e))?;
-    Ok(rows)
-}
+fn parse_todotxt_line(line: &str) -> Option<ImportedTask> {
+    let mut rest = line.trim();
+    if rest.is_empty() {
+        return None;
+    }
 
-#[tauri::command]
-async fn create_ai_session(
-    title: Option<String>,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<String, String> {
-    let db = db.lock().await;
-    let id = format!(
-        "ai-session-{}",
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| format!("SystemTime error: {}", e))?
-            .as_nanos()
-    );
-    let final_title = title
-        .map(|v| v.trim().to_string())
-        .filter(|v| !v.is_empty())
-        .unwrap_or_else(|| "新对话".to_string());
+    let mut status = "todo";
+    if let Some(after) = rest.strip_prefix("x ") {
+        status = "done";
+        rest = after.trim_start();
+    }
 
-    sqlx::query(
-        "INSERT INTO ai_sessions (id, title, created_at, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP, CURRENT_TIMESTAMP)",
-    )
-    .bind(&id)
-    .bind(final_title)
-    .execute(&db.db)
-    .await
-    .map_err(|e| format!("Failed to create ai session: {}", e))?;
+    let mut words: Vec<&str> = rest.split_whitespace().collect();
+    let mut priority: Option<&str> = None;
+    if let Some(first) = words.first().copied() {
+        let bytes = first.as_bytes();
+        if bytes.len() == 3 && bytes[0] == b'(' && bytes[2] == b')' && bytes[1].is_ascii_uppercase() {
+            priority = Some(priority_from_letter(bytes[1] as char));
+            words.remove(0);
+        }
+    }
+    // Completion and/or creation dates carry no planning information.
+    for _ in 0..2 {
+        match words.first().copied() {
+            Some(w) if is_plain_iso_date(w) => {
+                words.remove(0);
+            }
+            _ => break,
+        }
+    }
 
-    Ok(id)
-}
+    let mut title_words: Vec<&str> = Vec::new();
+    let mut tags: Vec<String> = Vec::new();
+    let mut threshold: Option<String> = None;
+    let mut due: Option<String> = None;
+    let mut start_time: Option<String> = None;
+    let mut duration: Option<f64> = None;
 
-#[tauri::command]
-async fn update_ai_session_title(
-    session_id: String,
-    title: String,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<(), String> {
-    let db = db.lock().await;
-    let final_title = title.trim();
-    if final_title.is_empty() {
-        return Err("Title cannot be empty".to_string());
+    for word in words {
+        if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            tags.push(project.to_string());
+            continue;
+        }
+        if word.len() > 1 && word.starts_with('@') {
+            tags.push(word.to_string());
+            continue;
+        }
+        if let Some((key, value)) = word.split_once(':') {
+            match key {
+                "due" if is_plain_iso_date(value) => {
+                    due = Some(value.to_string());
+                    continue;
+                }
+                "t" if is_plain_iso_date(value) => {
+                    threshold = Some(value.to_string());
+                    continue;
+                }
+                "time" => {
+                    if let Some(minutes) = parse_clock_minutes(value) {
+                        start_time = Some(format!("{:02}:{:02}", minutes / 60, minutes % 60));
+                        continue;
+                    }
+                }
+                "dur" => {
+                    if let Ok(hours) = value.parse::<f64>() {
+                        if hours > 0.0 {
+                            duration = Some(hours);
+                            continue;
+                        }
+                    }
+                }
+                "pri" if value.len() == 1 => {
+                    priority = Some(priority_from_letter(value.chars().next().unwrap_or('B')));
+                    continue;
+                }
+                "status" if status != "done" && matches!(value, "todo" | "in-progress") => {
+                    status = if value == "in-progress" { "in-progress" } else { "todo" };
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        title_words.push(word);
     }
 
-    sqlx::query("UPDATE ai_sessions SET title = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(final_title)
-        .bind(session_id)
-        .execute(&db.db)
-        .await
-        .map_err(|e| format!("Failed to update ai session title: {}", e))?;
-
-    Ok(())
+    let title = title_words.join(" ");
+    if title.is_empty() {
+        return None;
+    }
+
+    Some(ImportedTask {
+        title,
+        date: threshold.or(due).unwrap_or_else(chrono_today),
+        priority: priority.unwrap_or("medium").to_string(),
+        tags,
+        status: status.to_string(),
+        start_time,
+        duration,
+    })
 }
 
-#[tauri::command]
-async fn delete_ai_session(session_id: String, db: State<'_, Arc<Mutex<AppDb>>>) -> Result<(), String> {
-    let db = db.lock().await;
-    sqlx::query("DELETE FROM ai_sessions WHERE id = ?")
-        .bind(session_id)
-        .execute(&db.db)
-        .await
-        .map_err(|e| format!("Failed to delete ai session: {}", e))?;
-    Ok(())
+fn task_end_clock(task: &Task) -> Option<String> {
+    let start = parse_clock_minutes(&task.start_time)?;
+    if task.duration <= 0.0 {
+        return None;
+    }
+    let end = start + (task.duration * 60.0).round() as i32;
+    if end >= 24 * 60 {
+        return None;
+    }
+    Some(format!("{:02}:{:02}", end / 60, end % 60))
 }
 
-#[tauri::command]
-async fn get_ai_messages(
-    session_id: String,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Vec<AiMessage>, String> {
-    let db = db.lock().await;
-    let rows = sqlx::query_as::<_, AiMessage>(
-        "SELECT id, session_id, role, content, image_path, created_at FROM ai_messages WHERE session_id = ? ORDER BY created_at ASC",
-    )
-    .bind(session_id)
-    .fetch_all(&db.db)
-    .await
-    .map_err(|e| format!("Failed to fetch ai messages: {}", e))?;
-    Ok(rows)
+fn sanitize_export_tag(tag: &str, extra_invalid: char) -> String {
+    tag.chars()
+        .map(|c| if c.is_whitespace() || c == extra_invalid { '_' } else { c })
+        .collect()
 }
 
-#[tauri::command]
-async fn add_ai_message(
-    session_id: String,
-    role: String,
-    content: String,
-    image_path: Option<String>,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<String, String> {
-    let db = db.lock().await;
-    let normalized_role = match role.trim().to_lowercase().as_str() {
-        "user" => "user",
-        "assistant" | "model" => "assistant",
-        _ => return Err("Invalid role. Expected user or assistant".to_string()),
-    };
+fn tasks_to_org(tasks: &[Task]) -> String {
+    let mut out = String::new();
+    let mut current_date: Option<&str> = None;
 
-    let id = format!(
-        "ai-msg-{}",
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| format!("SystemTime error: {}", e))?
-            .as_nanos()
-    );
+    for task in tasks {
+        if current_date != Some(task.date.as_str()) {
+            if current_date.is_some() {
+                out.push('\n');
+            }
+            out.push_str(&format!("* {}\n", task.date));
+            current_date = Some(task.date.as_str());
+        }
 
-    let mut tx = db
-        .db
-        .begin()
-        .await
-        .map_err(|e| format!("Failed to begin ai message tx: {}", e))?;
+        let keyword = match task.status.as_str() {
+            "done" => "DONE",
+            "in-progress" => "STARTED",
+            _ => "TODO",
+        };
+        out.push_str(&format!(
+            "** {} [#{}] {}",
+            keyword,
+            priority_to_letter(&task.priority),
+            task.title.trim()
+        ));
+        let tags: Vec<String> = split_task_tags(&task.tags)
+            .iter()
+            .map(|t| sanitize_export_tag(t, ':'))
+            .collect();
+        if !tags.is_empty() {
+            out.push_str(&format!(" :{}:", tags.join(":")));
+        }
+        out.push('\n');
+
+        let weekday = chrono::NaiveDate::parse_from_str(&task.date, "%Y-%m-%d")
+            .map(|d| format!(" {}", d.format("%a")))
+            .unwrap_or_default();
+        let time = match (parse_clock_minutes(&task.start_time), task_end_clock(task)) {
+            (Some(_), Some(end)) => format!(" {}-{}", task.start_time, end),
+            (Some(_), None) => format!(" {}", task.start_time),
+            _ => String::new(),
+        };
+        out.push_str(&format!("   SCHEDULED: <{}{}{}>\n", task.date, weekday, time));
 
-    sqlx::query(
-        "INSERT INTO ai_messages (id, session_id, role, content, image_path, created_at) VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
-    )
-    .bind(&id)
-    .bind(&session_id)
-    .bind(normalized_role)
-    .bind(&content)
-    .bind(&image_path)
-    .execute(&mut *tx)
-    .await
-    .map_err(|e| format!("Failed to add ai message: {}", e))?;
+        for line in task.description.lines().filter(|l| !l.trim().is_empty()) {
+            out.push_str(&format!("   {}\n", line.trim_end()));
+        }
+    }
 
-    sqlx::query("UPDATE ai_sessions SET updated_at = CURRENT_TIMESTAMP WHERE id = ?")
-        .bind(&session_id)
-        .execute(&mut *tx)
-        .await
-        .map_err(|e| format!("Failed to touch ai session updated_at: {}", e))?;
+    out
+}
 
-    tx.commit()
-        .await
-        .map_err(|e| format!("Failed to commit ai message tx: {}", e))?;
+fn tasks_to_todotxt(tasks: &[Task]) -> String {
+    let mut out = String::new();
 
-    Ok(id)
-}
+    for task in tasks {
+        let mut parts: Vec<String> = Vec::new();
+        let created = task.created_at.get(..10).filter(|d| is_plain_iso_date(d));
+        if task.status == "done" {
+            parts.push("x".to_string());
+            if let Some(completed) = task.updated_at.get(..10).filter(|d| is_plain_iso_date(d)) {
+                parts.push(completed.to_string());
+                if let Some(created) = created {
+                    parts.push(created.to_string());
+                }
+            }
+        } else {
+            parts.push(format!("({})", priority_to_letter(&task.priority)));
+            if let Some(created) = created {
+                parts.push(created.to_string());
+            }
+        }
 
-/// Recursively copy an entire folder into Resources/ and return all copied file entries.
-#[tauri::command]
-async fn batch_copy_folder_to_resources(
-    app: tauri::AppHandle,
-    folder_path: String,
-    db: State<'_, Arc<Mutex<AppDb>>>,
-) -> Result<Vec<Resource>, String> {
-    let root = PathBuf::from(ensure_workspace_dirs(&app).await?);
-    let resources_base = root.join("Resources");
-    let source = PathBuf::from(&folder_path);
+        parts.push(task.title.trim().to_string());
+        for tag in split_task_tags(&task.tags) {
+            let tag = sanitize_export_tag(&tag, ' ');
+            if tag.starts_with('@') {
+                parts.push(tag);
+            } else {
+                parts.push(format!("+{}", tag));
+            }
+        }
 
-    if !source.is_dir() {
-        return Err(format!("Source is not a directory: {}", folder_path));
+        parts.push(format!("due:{}", task.date));
+        if parse_clock_minutes(&task.start_time).is_some() {
+            parts.push(format!("time:{}", task.start_time));
+        }
+        if task.duration > 0.0 {
+            parts.push(format!("dur:{}", task.duration));
+        }
+        if task.status == "done" {
+            parts.push(format!("pri:{}", priority_to_letter(&task.priority)));
+        } else if task.status == "in-progress" {
+            parts.push("status:in-progress".to_string());
+        }
+
+        out.push_str(&parts.join(" "));
+        out.push('\n');
     }
 
-    let folder_name = source
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("imported")
-        .to_string();
+    out
+}
 
-    let dest_dir = resources_base.join(&folder_name);
-    fs::create_dir_all(&dest_dir)
+async fn db_get_tasks_by_filter(
+    pool: &sqlx::SqlitePool,
+    filter: &TaskFilter,
+) -> Result<Vec<Task>, String> {
+    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+        "SELECT id, title, description, status, priority, date, start_time, duration, tags, repeat_type, timer_type, timer_duration, position, created_at, updated_at FROM tasks WHERE 1=1",
+    );
+    push_task_filter(&mut builder, filter)?;
+    builder.push(" ORDER BY date ASC, start_time ASC, created_at ASC");
+
+    builder
+        .build_query_as::<Task>()
+        .fetch_all(pool)
         .await
-        .map_err(|e| format!("Failed to create dest dir: {}", e))?;
+        .map_err(|e| format!("Failed to get tasks for export: {}", e))
+}
 
-    // Recursively collect all files
-    let mut stack: Vec<PathBuf> = vec![source.clone()];
-    let mut entries: Vec<Resource> = Vec::new();
+#[tauri::command]
+async fn parse_org_plan(content: String) -> Result<ImportReport, String> {
+    Ok(build_import_report(parse_org_tasks(&content)))
+}
 
-    while let Some(dir) = stack.pop() {
-        let mut reader = fs::read_dir(&dir)
-            .await
-            .map_err(|e| format!("Failed to read dir {}: {}", dir.to_string_lossy(), e))?;
+#[tauri::command]
+async fn parse_todotxt_plan(content: String) -> Result<ImportReport, String> {
+    let tasks = content.lines().filter_map(parse_todotxt_line).collect();
+    Ok(build_import_report(tasks))
+}
 
-        while let Some(entry) = reader
-            .next_entry()
-            .await
-            .map_err(|e| format!("Failed to iterate dir: {}", e))?
-        {
-            let entry_path = entry.path();
-            let metadata = fs::metadata(&entry_path)
-                .await
-                .map_err(|e| format!("Failed to stat {}: {}", entry_path.to_string_lossy(), e))?;
+#[tauri::command]
+async fn export_tasks_text(
+    format: String,
+    filter: Option<TaskFilter>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<String, String> {
+    let db = db.lock().await;
+    let tasks = db_get_tasks_by_filter(&db.db, &filter.unwrap_or_default()).await?;
+    match format.trim().to_lowercase().as_str() {
+        "org" => Ok(tasks_to_org(&tasks)),
+        "todotxt" | "todo.txt" => Ok(tasks_to_todotxt(&tasks)),
+        other => Err(format!("不支持的导出格式: {}", other)),
+    }
+}
 
-            if metadata.is_dir() {
-                // Mirror sub-directory structure
-                let rel = entry_path
-                    .strip_prefix(&source)
-                    .map_err(|e| format!("strip_prefix error: {}", e))?;
-                let sub_dest = dest_dir.join(rel);
-                fs::create_dir_all(&sub_dest)
-                    .await
-                    .map_err(|e| format!("Failed to create sub dir: {}", e))?;
-                stack.push(entry_path);
-            } else if metadata.is_file() {
-                let rel = entry_path
-                    .strip_prefix(&source)
-                    .map_err(|e| format!("strip_prefix error: {}", e))?;
-                let dest_file = dest_dir.join(rel);
-                // Ensure parent directory exists
-                if let Some(parent) = dest_file.parent() {
-                    fs::create_dir_all(parent)
-                        .await
-                        .map_err(|e| format!("Failed to create parent dir: {}", e))?;
-                }
-                fs::copy(&entry_path, &dest_file)
-                    .await
-                    .map_err(|e| format!("Failed to copy file: {}", e))?;
+// ═══════════════════════════════════════════════════════════
+// Focus History Export & CSV Import
+// ═══════════════════════════════════════════════════════════
 
-                let file_name = entry_path
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or("unknown")
-                    .to_string();
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct FocusCsvColumnMapping {
+    pub started_at: String, // header name, or 0-based column index when has_header is false
+    pub ended_at: Option<String>,
+    pub duration: Option<String>,
+    pub duration_unit: Option<String>, // "minutes" (default) | "seconds"
+    pub tags: Option<String>,
+    pub note: Option<String>,
+    pub timer_type: Option<String>,
+    pub template: Option<String>, // matched against focus template names
+}
 
-                let ext = entry_path
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("file")
-                    .to_lowercase();
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusCsvImportPayload {
+    pub content: String,
+    pub mapping: FocusCsvColumnMapping,
+    pub delimiter: Option<String>,     // defaults to ","
+    pub has_header: Option<bool>,      // defaults to true
+    pub tag_separator: Option<String>, // defaults to ";"
+    pub timer_type: Option<String>,    // used when no timer_type column is mapped
+}
 
-                let rel_path = dest_file
-                    .strip_prefix(&resources_base)
-                    .map(|p| p.to_string_lossy().to_string())
-                    .unwrap_or_else(|_| dest_file.to_string_lossy().to_string());
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FocusImportReport {
+    pub total: usize,
+    pub created: usize,
+    pub skipped: usize, // duplicates by start time
+    pub errors: Vec<String>,
+}
 
-                let now = Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string();
-                let id = format!(
-                    "res-{}-{}",
-                    chrono::Utc::now().timestamp_millis(),
-                    &uuid_short()
-                );
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
+struct FocusExportRow {
+    id: String,
+    date: String,
+    started_at: String,
+    ended_at: Option<String>,
+    status: String,
+    timer_type: String,
+    template_name: Option<String>,
+    tags_json: String,
+    planned_minutes: i32,
+    actual_seconds: i64,
+    break_seconds: i64,
+    cycle: i32,
+    source: String,
+    note: Option<String>,
+}
 
-                entries.push(Resource {
-                    id,
-                    name: file_name,
-                    path: rel_path,
-                    file_type: ext,
-                    subject: String::new(),
-                    size_bytes: metadata.len() as i64,
-                    created_at: now,
-                });
-            }
+async fn db_get_focus_export_rows(
+    pool: &sqlx::SqlitePool,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<Vec<FocusExportRow>, String> {
+    for date in [start_date, end_date].into_iter().flatten() {
+        if !is_plain_iso_date(date) {
+            return Err(format!("非法的日期: {}", date));
         }
     }
+    sqlx::query_as::<_, FocusExportRow>(
+        "SELECT fr.id, fr.date, fr.started_at, fr.ended_at, fr.status, fr.timer_type, ft.name AS template_name,
+                fr.tags_json, fr.planned_minutes, fr.actual_seconds, fr.break_seconds, fr.cycle, fr.source, fr.note
+         FROM focus_runs fr
+         LEFT JOIN focus_templates ft ON fr.template_id = ft.id
+         WHERE (? IS NULL OR fr.date >= ?) AND (? IS NULL OR fr.date <= ?)
+         ORDER BY fr.started_at ASC",
+    )
+    .bind(start_date)
+    .bind(start_date)
+    .bind(end_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch focus runs for export: {}", e))
+}
 
-    // Batch insert into SQLite
-    let db = db.lock().await;
-    for res in &entries {
-        sqlx::query(
-            "INSERT OR REPLACE INTO resources (id, name, path, file_type, subject, size_bytes, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
-        )
-        .bind(&res.id)
-        .bind(&res.name)
-        .bind(&res.path)
-        .bind(&res.file_type)
-        .bind(&res.subject)
-        .bind(res.size_bytes)
-        .bind(&res.created_at)
-        .execute(&db.db)
-        .await
-        .map_err(|e| format!("Failed to insert resource: {}", e))?;
-    }
-
-    Ok(entries)
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
 }
 
-/// Generate a short random ID segment
-fn uuid_short() -> String {
-    use std::collections::hash_map::RandomState;
-    use std::hash::{BuildHasher, Hasher};
-    let s = RandomState::new();
-    let mut hasher = s.build_hasher();
-    hasher.write_u128(std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_nanos());
-    format!("{:x}", hasher.finish())
+fn focus_runs_to_csv(rows: &[FocusExportRow]) -> String {
+    let mut out = String::from(
+        "id,date,started_at,ended_at,status,timer_type,template,tags,planned_minutes,actual_seconds,break_seconds,cycle,source,note\n",
+    );
+    for row in rows {
+        let fields = [
+            row.id.clone(),
+            row.date.clone(),
+            row.started_at.clone(),
+            row.ended_at.clone().unwrap_or_default(),
+            row.status.clone(),
+            row.timer_type.clone(),
+            row.template_name.clone().unwrap_or_default(),
+            parse_focus_tags(&row.tags_json).join(";"),
+            row.planned_minutes.to_string(),
+            row.actual_seconds.to_string(),
+            row.break_seconds.to_string(),
+            row.cycle.to_string(),
+            row.source.clone(),
+            row.note.clone().unwrap_or_default(),
+        ];
+        let line = fields.iter().map(|f| csv_escape(f)).collect::<Vec<_>>().join(",");
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
 }
 
-// ═══════════════════════════════════════════════════════════
-// Markdown Import Command
-// ═══════════════════════════════════════════════════════════
+fn focus_runs_to_json(rows: &[FocusExportRow]) -> Result<String, String> {
+    let items = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "id": row.id,
+                "date": row.date,
+                "started_at": row.started_at,
+                "ended_at": row.ended_at,
+                "status": row.status,
+                "timer_type": row.timer_type,
+                "template": row.template_name,
+                "tags": parse_focus_tags(&row.tags_json),
+                "planned_minutes": row.planned_minutes,
+                "actual_seconds": row.actual_seconds,
+                "break_seconds": row.break_seconds,
+                "cycle": row.cycle,
+                "source": row.source,
+                "note": row.note,
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::to_string_pretty(&items).map_err(|e| format!("Failed to serialize focus runs: {}", e))
+}
 
-#[tauri::command]
-async fn parse_markdown_plan(content: String) -> Result<ImportReport, String> {
-    let mut tasks: Vec<ImportedTask> = Vec::new();
-    let mut current_date = String::new();
+async fn db_export_focus_runs(
+    pool: &sqlx::SqlitePool,
+    format: &str,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<String, String> {
+    let format = format.trim().to_lowercase();
+    if format != "csv" && format != "json" {
+        return Err(format!("不支持的导出格式: {}", format));
+    }
+    let rows = db_get_focus_export_rows(pool, start_date, end_date).await?;
+    if format == "csv" {
+        Ok(focus_runs_to_csv(&rows))
+    } else {
+        focus_runs_to_json(&rows)
+    }
+}
+
+/// Minimal RFC 4180 reader: quoted fields may contain delimiters, doubled quotes and newlines.
+fn parse_csv_records(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' {
+            in_quotes = true;
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            record.push(std::mem::take(&mut field));
+            if record.iter().any(|f| !f.trim().is_empty()) {
+                records.push(std::mem::take(&mut record));
+            } else {
+                record.clear();
+            }
+        } else {
+            field.push(c);
+        }
+    }
+    record.push(field);
+    if record.iter().any(|f| !f.trim().is_empty()) {
+        records.push(record);
+    }
+    records
+}
 
-    for line in content.lines() {
-        let trimmed = line.trim();
+fn parse_import_timestamp(raw: &str) -> Option<chrono::DateTime<Local>> {
+    use chrono::TimeZone;
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return None;
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.with_timezone(&Local));
+    }
+    if raw.len() >= 10 && raw.chars().all(|c| c.is_ascii_digit()) {
+        let value: i64 = raw.parse().ok()?;
+        let secs = if raw.len() >= 13 { value / 1000 } else { value };
+        return Local.timestamp_opt(secs, 0).single();
+    }
+    const FORMATS: [&str; 6] = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y/%m/%d %H:%M:%S",
+        "%Y/%m/%d %H:%M",
+    ];
+    FORMATS.iter().find_map(|fmt| {
+        chrono::NaiveDateTime::parse_from_str(raw, fmt)
+            .ok()
+            .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+    })
+}
 
-        // Parse date headers: ## 2026-03-01 or ## YYYY-MM-DD ...
-        if trimmed.starts_with("## ") || trimmed.starts_with("# ") {
-            let header = trimmed.trim_start_matches('#').trim();
-            // Try extract ISO date
-            if let Some(date) = extract_iso_date(header) {
-                current_date = date;
-            }
+/// Accepts a plain number in `unit` or a clock value ("H:MM:SS" / "H:MM").
+// Longest single run an import may create; anything above is treated as a bad cell.
+const FOCUS_IMPORT_MAX_SECONDS: i64 = 24 * 3600;
+
+/// `h:mm[:ss]` or a number of minutes/seconds; `None` unless it lies in (0, 24h].
+fn parse_import_duration_seconds(raw: &str, unit: &str) -> Option<i64> {
+    let raw = raw.trim();
+    let seconds = if raw.contains(':') {
+        let parts = raw
+            .split(':')
+            .map(|p| p.trim().parse::<i64>().ok().filter(|v| *v >= 0))
+            .collect::<Option<Vec<_>>>()?;
+        let (h, m, s) = match parts.as_slice() {
+            [h, m, s] => (*h, *m, *s),
+            [h, m] => (*h, *m, 0),
+            _ => return None,
+        };
+        h.checked_mul(3600)?.checked_add(m.checked_mul(60)?)?.checked_add(s)?
+    } else {
+        let value: f64 = raw.parse().ok()?;
+        let seconds = if unit == "seconds" { value } else { value * 60.0 };
+        if !seconds.is_finite() || seconds > FOCUS_IMPORT_MAX_SECONDS as f64 {
+            return None;
         }
+        seconds.round() as i64
+    };
+    (seconds > 0 && seconds <= FOCUS_IMPORT_MAX_SECONDS).then_some(seconds)
+}
 
-        // Parse task lines: - [ ] or - [x]
-        if trimmed.starts_with("- [ ] ")
-            || trimmed.starts_with("- [x] ")
-            || trimmed.starts_with("- [X] ")
-        {
-            let is_done = trimmed.starts_with("- [x] ") || trimmed.starts_with("- [X] ");
-            let rest = &trimmed[6..];
-            let mut start_time: Option<String> = None;
-            let mut rest_text = rest.trim().to_string();
+fn resolve_csv_column(header: Option<&[String]>, column: &str) -> Result<usize, String> {
+    let column = column.trim();
+    if let Some(header) = header {
+        if let Some(idx) = header.iter().position(|h| h.trim().eq_ignore_ascii_case(column)) {
+            return Ok(idx);
+        }
+    }
+    column
+        .parse::<usize>()
+        .map_err(|_| format!("找不到列: {}", column))
+}
 
-            if rest_text.starts_with('[') {
-                if let Some(end_idx) = rest_text.find(']') {
-                    let maybe_time = rest_text[1..end_idx].trim();
-                    if parse_clock_minutes(maybe_time).is_some() {
-                        start_time = Some(maybe_time.to_string());
-                        rest_text = rest_text[end_idx + 1..].trim().to_string();
-                    }
-                }
-            }
+/// Imports runs from another timer app's CSV export. Each row becomes a completed run with
+/// `source = 'import'`; rows whose start time already exists (to the second) are skipped.
+async fn db_import_focus_runs_csv(
+    pool: &sqlx::SqlitePool,
+    payload: &FocusCsvImportPayload,
+) -> Result<FocusImportReport, String> {
+    let delimiter = match payload.delimiter.as_deref() {
+        None | Some("") => ',',
+        Some("\\t") | Some("tab") => '\t',
+        Some(d) => d.chars().next().unwrap_or(','),
+    };
+    let mut records = parse_csv_records(&payload.content, delimiter);
+    let header = if payload.has_header.unwrap_or(true) && !records.is_empty() {
+        Some(records.remove(0))
+    } else {
+        None
+    };
 
-            // Extract @date
-            let mut task_date = current_date.clone();
-            let mut task_text = rest_text.clone();
-            if let Some(pos) = rest_text.find('@') {
-                let after_at = &rest_text[pos + 1..];
-                if let Some(date) = extract_iso_date(after_at) {
-                    task_date = date;
-                }
-                task_text = rest_text[..pos].trim().to_string();
-                // Remove trailing date from text if present
-                if let Some(space_pos) = task_text.rfind(" @") {
-                    task_text = task_text[..space_pos].trim().to_string();
-                }
-            }
+    let mapping = &payload.mapping;
+    let header_ref = header.as_deref();
+    let started_col = resolve_csv_column(header_ref, &mapping.started_at)?;
+    let optional_col = |column: &Option<String>| -> Result<Option<usize>, String> {
+        column
+            .as_deref()
+            .filter(|c| !c.trim().is_empty())
+            .map(|c| resolve_csv_column(header_ref, c))
+            .transpose()
+    };
+    let ended_col = optional_col(&mapping.ended_at)?;
+    let duration_col = optional_col(&mapping.duration)?;
+    let tags_col = optional_col(&mapping.tags)?;
+    let note_col = optional_col(&mapping.note)?;
+    let timer_type_col = optional_col(&mapping.timer_type)?;
+    let template_col = optional_col(&mapping.template)?;
+    if ended_col.is_none() && duration_col.is_none() {
+        return Err("需要映射 ended_at 或 duration 列".to_string());
+    }
+    let duration_unit = mapping
+        .duration_unit
+        .as_deref()
+        .unwrap_or("minutes")
+        .trim()
+        .to_lowercase();
+    let tag_separator = payload
+        .tag_separator
+        .clone()
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| ";".to_string());
+    let default_timer_type = payload
+        .timer_type
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or("import")
+        .to_string();
 
-            // Extract #tags and #priority
-            let mut tags: Vec<String> = Vec::new();
-            let mut priority = "medium".to_string();
-            let mut clean_title = String::new();
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to begin focus import tx: {}", e))?;
 
-            if task_text.ends_with(')') {
-                if let Some(open_idx) = task_text.rfind('(') {
-                    let level = task_text[open_idx + 1..task_text.len() - 1]
-                        .trim()
-                        .to_lowercase();
-                    match level.as_str() {
-                        "high" => {
-                            priority = "high".to_string();
-                            task_text = task_text[..open_idx].trim().to_string();
-                        }
-                        "low" => {
-                            priority = "low".to_string();
-                            task_text = task_text[..open_idx].trim().to_string();
-                        }
-                        "medium" => {
-                            priority = "medium".to_string();
-                            task_text = task_text[..open_idx].trim().to_string();
-                        }
-                        _ => {}
-                    }
-                }
-            }
+    let mut seen_starts = sqlx::query_scalar::<_, String>("SELECT started_at FROM focus_runs")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to fetch existing focus run starts: {}", e))?
+        .iter()
+        .filter_map(|ts| focus_timestamp_secs(ts))
+        .collect::<std::collections::HashSet<i64>>();
+    let templates = sqlx::query_as::<_, (String, String)>("SELECT id, name FROM focus_templates")
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to fetch focus templates: {}", e))?;
 
-            for part in task_text.split_whitespace() {
-                if part.starts_with('#') {
-                    let tag = part.trim_start_matches('#').to_lowercase();
-                    match tag.as_str() {
-                        "high" | "urgent" => priority = "high".to_string(),
-                        "low" => priority = "low".to_string(),
-                        "medium" => priority = "medium".to_string(),
-                        _ => tags.push(tag),
-                    }
-                } else {
-                    if !clean_title.is_empty() {
-                        clean_title.push(' ');
-                    }
-                    clean_title.push_str(part);
-                }
-            }
+    let mut report = FocusImportReport {
+        total: records.len(),
+        created: 0,
+        skipped: 0,
+        errors: Vec::new(),
+    };
+    let line_offset = if header.is_some() { 2 } else { 1 };
+    for (idx, record) in records.iter().enumerate() {
+        let line = idx + line_offset;
+        let cell = |col: Option<usize>| {
+            col.and_then(|c| record.get(c))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+        };
 
-            if !clean_title.is_empty() {
-                tasks.push(ImportedTask {
-                    title: clean_title,
-                    date: if task_date.is_empty() {
-                        chrono_today()
-                    } else {
-                        task_date
-                    },
-                    priority,
-                    tags,
-                    status: if is_done {
-                        "done".to_string()
-                    } else {
-                        "todo".to_string()
-                    },
-                    start_time,
-                    duration: None,
-                });
-            }
+        let Some(started) = cell(Some(started_col)).and_then(parse_import_timestamp) else {
+            report.errors.push(format!("第 {} 行: 无法解析开始时间", line));
+            continue;
+        };
+        let ended = cell(ended_col).and_then(parse_import_timestamp);
+        let seconds = match cell(duration_col) {
+            Some(raw) => parse_import_duration_seconds(raw, &duration_unit),
+            None => ended.map(|end| (end - started).num_seconds()),
+        };
+        let Some(seconds) = seconds.filter(|s| *s > 0 && *s <= FOCUS_IMPORT_MAX_SECONDS) else {
+            report.errors.push(format!("第 {} 行: 无法确定专注时长（需在 24 小时以内）", line));
+            continue;
+        };
+        let Some(ended) = ended.or_else(|| started.checked_add_signed(chrono::Duration::seconds(seconds))) else {
+            report.errors.push(format!("第 {} 行: 结束时间超出范围", line));
+            continue;
+        };
+        if !seen_starts.insert(started.timestamp()) {
+            report.skipped += 1;
+            continue;
         }
+
+        let tags = cell(tags_col)
+            .map(|raw| {
+                raw.split(tag_separator.as_str())
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let template_id = cell(template_col).and_then(|name| {
+            templates
+                .iter()
+                .find(|(_, t)| t.trim().eq_ignore_ascii_case(name))
+                .map(|(id, _)| id.clone())
+        });
+        let run_id = gen_unique_id("focus-run");
+        let started_at = started.with_timezone(&Utc).to_rfc3339();
+        let ended_at = ended.with_timezone(&Utc).to_rfc3339();
+        let now = now_iso();
+        sqlx::query(
+            "INSERT INTO focus_runs (id, source, template_id, task_id, timer_type, planned_minutes, actual_seconds, status, started_at, ended_at, date, tags_json, note, created_at, updated_at) VALUES (?, 'import', ?, NULL, ?, ?, ?, 'completed', ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&run_id)
+        .bind(&template_id)
+        .bind(cell(timer_type_col).unwrap_or(&default_timer_type))
+        .bind(((seconds + 59) / 60) as i32)
+        .bind(seconds)
+        .bind(&started_at)
+        .bind(&ended_at)
+        .bind(started.format("%Y-%m-%d").to_string())
+        .bind(serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_string()))
+        .bind(cell(note_col))
+        .bind(&now)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to import focus run: {}", e))?;
+        db_open_focus_segment(&mut tx, &run_id, "work", 1, 0, &started_at).await?;
+        db_close_focus_segments(&mut tx, &run_id, &ended_at).await?;
+        report.created += 1;
     }
 
-    infer_import_durations(&mut tasks);
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit focus import tx: {}", e))?;
+    Ok(report)
+}
 
-    let total = tasks.len();
-    Ok(ImportReport {
-        total,
-        created: 0,
-        skipped: 0,
-        tasks,
-    })
+#[tauri::command]
+async fn export_focus_runs(
+    format: String,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+) -> Result<String, String> {
+    let db = db.lock().await;
+    db_export_focus_runs(&db.db, &format, start_date.as_deref(), end_date.as_deref()).await
+}
+
+#[tauri::command]
+async fn import_focus_runs_csv(
+    payload: FocusCsvImportPayload,
+    db: State<'_, Arc<Mutex<AppDb>>>,
+    sync_hub: State<'_, Arc<SyncHub>>,
+) -> Result<FocusImportReport, String> {
+    let db = db.lock().await;
+    let report = db_import_focus_runs_csv(&db.db, &payload).await?;
+    drop(db);
+    if report.created > 0 {
+        emit_sync_action(sync_hub.inner().as_ref(), "SYNC_FOCUS_RUNS");
+    }
+    Ok(report)
 }
 
 fn parse_clock_minutes(input: &str) -> Option<i32> {
@@ -5041,9 +14815,11 @@ async fn reset_all_data(
             "DELETE FROM ai_sessions",
             "DELETE FROM tasks",
             "DELETE FROM focus_sessions",
+            "DELETE FROM attendance_records",
             "DELETE FROM video_bookmarks",
             "DELETE FROM resources",
             "DELETE FROM questions",
+            "DELETE FROM knowledge_node_items WHERE item_type = 'question'",
         ];
 
         for sql in delete_sql {
@@ -5531,6 +15307,7 @@ pub fn run() {
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
                 api.prevent_close();
@@ -5552,14 +15329,23 @@ pub fn run() {
 
             let tray_show =
                 MenuItem::with_id(app, "tray_toggle", "显示/隐藏面板", true, None::<&str>)?;
+            let tray_focus_pause =
+                MenuItem::with_id(app, "tray_focus_pause", "暂停/继续专注", true, None::<&str>)?;
+            let tray_focus_finish =
+                MenuItem::with_id(app, "tray_focus_finish", "结束专注", true, None::<&str>)?;
             let tray_quit = MenuItem::with_id(app, "tray_quit", "退出系统", true, None::<&str>)?;
-            let tray_menu = Menu::with_items(app, &[&tray_show, &tray_quit])?;
+            let tray_menu = Menu::with_items(
+                app,
+                &[&tray_show, &tray_focus_pause, &tray_focus_finish, &tray_quit],
+            )?;
 
             let mut tray_builder = TrayIconBuilder::with_id("eva_tray")
                 .menu(&tray_menu)
-                .tooltip("EVA 考研辅助终端")
+                .tooltip(TRAY_DEFAULT_TOOLTIP)
                 .on_menu_event(|app: &tauri::AppHandle, event| match event.id.as_ref() {
                     "tray_toggle" => toggle_main_window(app),
+                    "tray_focus_pause" => spawn_tray_focus_action(app, false),
+                    "tray_focus_finish" => spawn_tray_focus_action(app, true),
                     "tray_quit" => {
                         app.exit(0);
                     }
@@ -5614,8 +15400,23 @@ pub fn run() {
 
                 match init_db(&db_path).await {
                     Ok(pool) => {
+                        match db_mark_interrupted_focus_runs(&pool).await {
+                            Ok(0) => {}
+                            Ok(count) => {
+                                log::warn!("Marked {} orphaned focus runs as interrupted", count);
+                                let _ = app_handle.emit(
+                                    "focus-runs-interrupted",
+                                    json!({ "count": count }).to_string(),
+                                );
+                            }
+                            Err(e) => log::error!("Focus run recovery failed: {}", e),
+                        }
                         let db_state = Arc::new(Mutex::new(AppDb { db: pool }));
-                        app_handle.manage(db_state);
+                        app_handle.manage(db_state.clone());
+                        tauri::async_runtime::spawn(focus_timer_watcher(
+                            app_handle.clone(),
+                            db_state,
+                        ));
                         log::info!("SQLite database initialized at: {}", db_path);
                     }
                     Err(e) => {
@@ -5631,10 +15432,25 @@ pub fn run() {
             toggle_local_server,
             get_tasks,
             get_tasks_by_date,
+            query_tasks,
             create_task,
             update_task,
             delete_task,
             batch_create_tasks,
+            get_task_statuses,
+            save_task_status,
+            delete_task_status,
+            reorder_task_statuses,
+            get_task_board,
+            move_task,
+            reorder_tasks,
+            bulk_update_tasks,
+            bulk_delete_tasks,
+            get_task_plan_templates,
+            save_week_as_task_plan_template,
+            update_task_plan_template,
+            delete_task_plan_template,
+            apply_task_plan_template,
             get_weekly_stats,
             get_daily_logs,
             create_daily_log,
@@ -5642,28 +15458,78 @@ pub fn run() {
             delete_daily_log,
             get_focus_sessions,
             upsert_focus_session,
+            get_attendance_records,
             get_focus_templates,
             create_focus_template,
             update_focus_template,
             archive_focus_template,
             start_focus_run,
             finish_focus_run,
+            get_active_focus_run,
+            pause_focus_run,
+            resume_focus_run,
+            heartbeat_focus_run,
+            extend_focus_run,
+            start_focus_break,
+            skip_focus_break,
+            get_interrupted_focus_runs,
+            recover_focus_run,
             get_focus_runs,
+            export_focus_runs,
+            import_focus_runs_csv,
             get_focus_stats,
+            get_focus_timeseries,
+            get_focus_goals,
+            create_focus_goal,
+            update_focus_goal,
+            delete_focus_goal,
+            get_focus_goal_progress,
+            log_focus_interruption,
+            get_focus_interruptions,
+            delete_focus_interruption,
+            get_focus_interruption_stats,
             get_wrong_questions,
             create_wrong_question,
             update_wrong_question,
             archive_wrong_question,
+            review_wrong_question,
+            get_due_wrong_questions,
+            get_review_history,
+            get_review_curves,
+            get_review_scheduler_settings,
+            update_review_scheduler_settings,
+            optimize_review_scheduler,
             delete_wrong_question,
             get_weekly_review_items,
             toggle_weekly_review_item_done,
             carry_weekly_review_items_to_next_week,
+            add_weekly_review_item,
+            remove_weekly_review_item,
+            get_weekly_review_settings,
+            check_wrong_question_duplicates,
+            merge_wrong_questions,
+            export_wrong_question_booklet,
+            export_anki_package,
+            import_anki_package,
+            get_mastery_rules,
+            save_mastery_rule,
+            delete_mastery_rule,
+            get_knowledge_tree,
+            create_knowledge_node,
+            update_knowledge_node,
+            delete_knowledge_node,
+            get_item_knowledge_nodes,
+            assign_knowledge_nodes,
+            update_weekly_review_settings,
             get_wrong_question_stats,
             save_chat_image,
             get_video_bookmarks,
             add_video_bookmark,
             delete_video_bookmark,
             parse_markdown_plan,
+            parse_org_plan,
+            parse_todotxt_plan,
+            export_tasks_text,
             ai_proxy,
             fetch_bilibili_metadata,
             initialize_workspace,
@@ -5705,3 +15571,249 @@ pub fn run() {
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fsrs() -> FsrsScheduler {
+        FsrsScheduler {
+            weights: FSRS_DEFAULT_WEIGHTS,
+            desired_retention: 0.9,
+        }
+    }
+
+    fn new_card() -> ReviewCard {
+        ReviewCard {
+            ease_factor: 2.5,
+            interval_days: 0,
+            stability: 0.0,
+            difficulty: 0.0,
+            reps: 0,
+            elapsed_days: 0.0,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-3, "expected {expected}, got {actual}");
+    }
+
+    #[test]
+    fn fsrs_first_review_uses_initial_weights() {
+        let scheduler = fsrs();
+        let good = scheduler.next(&new_card(), ReviewGrade::Good);
+        assert_close(good.stability, 3.7145);
+        assert_close(good.difficulty, 5.1618);
+        assert_eq!(good.interval_days, 4);
+
+        let again = scheduler.next(&new_card(), ReviewGrade::Again);
+        assert_close(again.stability, 0.4872);
+        assert_close(again.difficulty, 7.6214);
+        assert_eq!(again.interval_days, 1);
+
+        let easy = scheduler.next(&new_card(), ReviewGrade::Easy);
+        assert_close(easy.difficulty, 3.932);
+        assert_eq!(easy.interval_days, 14);
+    }
+
+    #[test]
+    fn fsrs_recall_is_ninety_percent_after_one_stability() {
+        assert_close(FsrsScheduler::retrievability(10.0, 10.0), 0.9);
+        // At 90% desired retention the interval equals the stability.
+        assert_eq!(fsrs().interval_for(10.0), 10);
+    }
+
+    #[test]
+    fn fsrs_second_review_matches_reference() {
+        let scheduler = fsrs();
+        let card = ReviewCard {
+            reps: 1,
+            elapsed_days: 4.0,
+            ..scheduler.next(&new_card(), ReviewGrade::Good)
+        };
+
+        let good = scheduler.next(&card, ReviewGrade::Good);
+        assert_close(good.stability, 14.8081);
+        assert_close(good.difficulty, 5.1618);
+        assert_eq!(good.interval_days, 15);
+
+        let again = scheduler.next(&card, ReviewGrade::Again);
+        assert_close(again.stability, 1.4332);
+        assert_eq!(again.interval_days, 1);
+    }
+
+    #[test]
+    fn sm2_good_follows_one_six_then_ease() {
+        let first = sm2_schedule(2.5, 0, ReviewGrade::Good);
+        assert_eq!(first.interval_days, 1);
+        let second = sm2_schedule(first.ease_factor, first.interval_days, ReviewGrade::Good);
+        assert_eq!(second.interval_days, 6);
+        let third = sm2_schedule(second.ease_factor, second.interval_days, ReviewGrade::Good);
+        assert_eq!(third.interval_days, 15);
+        assert_close(third.ease_factor, 2.5);
+    }
+
+    #[test]
+    fn sm2_grades_adjust_ease_and_interval() {
+        let again = sm2_schedule(2.5, 15, ReviewGrade::Again);
+        assert_close(again.ease_factor, 2.3);
+        assert_eq!(again.interval_days, 1);
+
+        let hard = sm2_schedule(2.5, 10, ReviewGrade::Hard);
+        assert_close(hard.ease_factor, 2.35);
+        assert_eq!(hard.interval_days, 12);
+
+        let easy = sm2_schedule(2.5, 6, ReviewGrade::Easy);
+        assert_close(easy.ease_factor, 2.65);
+        assert_eq!(easy.interval_days, 20);
+    }
+
+    #[test]
+    fn sm2_clamps_ease_and_interval() {
+        assert_close(sm2_schedule(1.3, 5, ReviewGrade::Again).ease_factor, 1.3);
+        assert_close(sm2_schedule(3.0, 5, ReviewGrade::Easy).ease_factor, 3.0);
+        assert_eq!(sm2_schedule(3.0, 3000, ReviewGrade::Good).interval_days, 3650);
+        assert_close(sm2_schedule(0.0, 0, ReviewGrade::Good).ease_factor, 2.5);
+    }
+
+    #[test]
+    fn org_parses_keywords_priority_tags_and_schedule() {
+        let content = "* 2026-03-01\n\
+                       ** TODO [#A] 复习线代 :math:review:\n\
+                       SCHEDULED: <2026-03-02 Mon 09:00-10:30>\n\
+                       ** DONE 背单词\n\
+                       DEADLINE: <2026-03-05 Thu>\n\
+                       ** Notes without a keyword\n";
+        let tasks = parse_org_tasks(content);
+        assert_eq!(tasks.len(), 2);
+
+        assert_eq!(tasks[0].title, "复习线代");
+        assert_eq!(tasks[0].priority, "high");
+        assert_eq!(tasks[0].tags, vec!["math", "review"]);
+        assert_eq!(tasks[0].status, "todo");
+        assert_eq!(tasks[0].date, "2026-03-02");
+        assert_eq!(tasks[0].start_time.as_deref(), Some("09:00"));
+        assert_eq!(tasks[0].duration, Some(1.5));
+
+        assert_eq!(tasks[1].title, "背单词");
+        assert_eq!(tasks[1].status, "done");
+        assert_eq!(tasks[1].priority, "medium");
+        assert_eq!(tasks[1].date, "2026-03-05");
+        assert_eq!(tasks[1].start_time, None);
+    }
+
+    #[test]
+    fn org_scheduled_wins_over_deadline() {
+        let content = "* STARTED 写论文\nDEADLINE: <2026-04-10 Fri> SCHEDULED: <2026-04-01 Wed>\n";
+        let tasks = parse_org_tasks(content);
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].status, "in-progress");
+        assert_eq!(tasks[0].date, "2026-04-01");
+    }
+
+    #[test]
+    fn todotxt_parses_priority_projects_and_keys() {
+        let task = parse_todotxt_line(
+            "(A) 2026-03-01 刷真题 +数学 @library due:2026-03-08 t:2026-03-03 time:9:30 dur:2",
+        )
+        .expect("task");
+        assert_eq!(task.title, "刷真题");
+        assert_eq!(task.priority, "high");
+        assert_eq!(task.tags, vec!["数学", "@library"]);
+        assert_eq!(task.date, "2026-03-03"); // threshold wins over due
+        assert_eq!(task.start_time.as_deref(), Some("09:30"));
+        assert_eq!(task.duration, Some(2.0));
+        assert_eq!(task.status, "todo");
+    }
+
+    #[test]
+    fn todotxt_handles_completion_and_plain_lines() {
+        let done = parse_todotxt_line("x 2026-03-02 2026-03-01 交作业 pri:C due:2026-03-02").expect("task");
+        assert_eq!(done.status, "done");
+        assert_eq!(done.title, "交作业");
+        assert_eq!(done.priority, "low");
+        assert_eq!(done.date, "2026-03-02");
+
+        let unknown_key = parse_todotxt_line("看 note:ch3 status:in-progress").expect("task");
+        assert_eq!(unknown_key.title, "看 note:ch3");
+        assert_eq!(unknown_key.status, "in-progress");
+
+        assert!(parse_todotxt_line("   ").is_none());
+        assert!(parse_todotxt_line("(B) +only-project").is_none());
+    }
+
+    #[test]
+    fn csv_handles_quotes_delimiters_and_newlines() {
+        let content = "\u{feff}title,note\r\n\"a, b\",\"say \"\"hi\"\"\"\n\"multi\nline\",x\n,\n";
+        let records = parse_csv_records(content, ',');
+        assert_eq!(
+            records,
+            vec![
+                vec!["title".to_string(), "note".to_string()],
+                vec!["a, b".to_string(), "say \"hi\"".to_string()],
+                vec!["multi\nline".to_string(), "x".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn anki_html_offsets_survive_non_ascii_case_mapping() {
+        // 'İ' and the Kelvin sign lowercase to a different UTF-8 length.
+        let html = "İK<IMG SRC=\"a b.png\">ẞ<img src='c.jpg'/><img alt=x>";
+        assert_eq!(anki_image_refs(html), vec!["a b.png", "c.jpg"]);
+
+        let media = HashMap::from([("a b.png".to_string(), "ErrorImages/Anki/a b.png".to_string())]);
+        assert_eq!(
+            anki_field_to_text("İ前<br>K<IMG SRC=\"a b.png\"><div>后 &amp; c</div>", &media),
+            "İ前\nK\n![](ErrorImages/Anki/a b.png)\n后 & c"
+        );
+        assert_eq!(anki_field_to_text("ẞ<img src='c.jpg'>", &media), "ẞ\n![](c.jpg)");
+    }
+
+    #[test]
+    fn anki_due_follows_queue() {
+        assert!(matches!(anki_due_kind(Some(1), Some(1_700_000_000)), Some(AnkiDue::Timestamp(1_700_000_000))));
+        assert!(matches!(anki_due_kind(Some(2), Some(420)), Some(AnkiDue::Day(420))));
+        assert!(matches!(anki_due_kind(Some(3), Some(421)), Some(AnkiDue::Day(421))));
+        assert!(matches!(anki_due_kind(Some(-1), Some(1_700_000_000)), Some(AnkiDue::Timestamp(_))));
+        assert!(matches!(anki_due_kind(Some(-2), Some(30)), Some(AnkiDue::Day(30))));
+        assert!(anki_due_kind(Some(0), Some(5)).is_none());
+        assert!(anki_due_kind(None, Some(5)).is_none());
+    }
+
+    #[test]
+    fn anki_deck_maps_to_quiz_subject() {
+        assert_eq!(anki_deck_subject("考研::数一::高数"), Some("数一"));
+        assert_eq!(anki_deck_subject("408"), Some("408"));
+        assert_eq!(anki_deck_subject("Default"), None);
+    }
+
+    #[test]
+    fn import_duration_accepts_clock_and_units() {
+        assert_eq!(parse_import_duration_seconds("1:30", "minutes"), Some(5400));
+        assert_eq!(parse_import_duration_seconds("0:25:30", "minutes"), Some(1530));
+        assert_eq!(parse_import_duration_seconds("25", "minutes"), Some(1500));
+        assert_eq!(parse_import_duration_seconds("90.4", "seconds"), Some(90));
+        assert_eq!(parse_import_duration_seconds("24:00", "minutes"), Some(86400));
+    }
+
+    #[test]
+    fn import_duration_rejects_overflowing_and_bad_values() {
+        for raw in ["1e30", "inf", "-inf", "NaN", "99999999999:0:0", "9223372036854775807:0", "24:00:01", "-5", "0", "1:-5", "abc", "1:2:3:4"] {
+            assert_eq!(parse_import_duration_seconds(raw, "minutes"), None, "{raw}");
+        }
+        assert_eq!(parse_import_duration_seconds("86401", "seconds"), None);
+    }
+
+    #[test]
+    fn csv_respects_custom_delimiter_and_missing_trailing_newline() {
+        let records = parse_csv_records("a;\"b;c\"\n1;2", ';');
+        assert_eq!(
+            records,
+            vec![
+                vec!["a".to_string(), "b;c".to_string()],
+                vec!["1".to_string(), "2".to_string()],
+            ]
+        );
+    }
+}
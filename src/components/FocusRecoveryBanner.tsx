@@ -0,0 +1,100 @@
+import { useCallback, useEffect, useState } from "react";
+import { AlertTriangle } from "lucide-react";
+import {
+  fetchInterruptedFocusRuns,
+  onFocusRunsInterrupted,
+  recoverFocusRun,
+  type FocusRecoveryAction,
+  type InterruptedFocusRun,
+} from "../utils/apiBridge";
+import { useSync } from "../hooks/useSync";
+
+function formatStartedAt(iso: string): string {
+  const date = new Date(iso);
+  if (Number.isNaN(date.getTime())) return iso;
+  const pad = (n: number) => String(n).padStart(2, "0");
+  return `${pad(date.getMonth() + 1)}-${pad(date.getDate())} ${pad(date.getHours())}:${pad(date.getMinutes())}`;
+}
+
+// Lists focus runs the app found still active after a crash so the user can settle or discard them.
+export function FocusRecoveryBanner() {
+  const [runs, setRuns] = useState<InterruptedFocusRun[]>([]);
+  const [busyId, setBusyId] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  const load = useCallback(() => {
+    fetchInterruptedFocusRuns()
+      .then(setRuns)
+      .catch((err) => console.warn("[FocusRecoveryBanner] Failed to load interrupted runs:", err));
+  }, []);
+
+  useEffect(() => {
+    // The startup event can fire before this listener exists, so also load once on mount.
+    load();
+    let disposed = false;
+    let unlisten: (() => void) | null = null;
+    onFocusRunsInterrupted(load)
+      .then((dispose) => {
+        if (disposed) {
+          dispose();
+          return;
+        }
+        unlisten = dispose;
+      })
+      .catch((err) => console.warn("[FocusRecoveryBanner] Failed to listen for interrupted runs:", err));
+    return () => {
+      disposed = true;
+      if (unlisten) unlisten();
+    };
+  }, [load]);
+
+  useSync("SYNC_FOCUS_RUNS", load);
+
+  const handleRecover = async (run: InterruptedFocusRun, action: FocusRecoveryAction) => {
+    setBusyId(run.id);
+    setError(null);
+    try {
+      await recoverFocusRun(run.id, action);
+      setRuns((prev) => prev.filter((item) => item.id !== run.id));
+    } catch (err) {
+      setError(String((err as Error)?.message ?? err));
+    } finally {
+      setBusyId(null);
+    }
+  };
+
+  if (runs.length === 0) return null;
+
+  return (
+    <div className="mb-4 rounded-2xl border border-amber-300/60 dark:border-amber-500/40 bg-amber-50/90 dark:bg-amber-900/20 backdrop-blur-xl p-4 space-y-3">
+      <div className="flex items-center gap-2 text-sm font-semibold text-amber-800 dark:text-amber-200">
+        <AlertTriangle className="w-4 h-4" />
+        检测到 {runs.length} 个因应用异常退出而中断的专注
+      </div>
+      {runs.map((run) => (
+        <div key={run.id} className="flex flex-wrap items-center justify-between gap-2 text-sm">
+          <span className="text-gray-700 dark:text-gray-300">
+            {formatStartedAt(run.started_at)} 开始 · 计划 {run.planned_minutes} 分钟
+          </span>
+          <div className="flex gap-2">
+            <button
+              disabled={busyId === run.id}
+              onClick={() => void handleRecover(run, "finalize")}
+              className="px-3 py-1 rounded-lg bg-[#88B5D3] hover:bg-[#6f9fbe] text-white text-xs font-semibold disabled:opacity-50"
+            >
+              按已记录时长结算
+            </button>
+            <button
+              disabled={busyId === run.id}
+              onClick={() => void handleRecover(run, "abort")}
+              className="px-3 py-1 rounded-lg border border-gray-300/80 dark:border-[#30435c] text-xs disabled:opacity-50"
+            >
+              放弃
+            </button>
+          </div>
+        </div>
+      ))}
+      {error && <p className="text-xs text-red-600 dark:text-red-400">{error}</p>}
+    </div>
+  );
+}
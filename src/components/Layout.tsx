@@ -1,6 +1,7 @@
 import { Link, Outlet } from "react-router-dom";
 import { Sidebar } from "./Sidebar";
 import { GlobalClock } from "./GlobalClock";
+import { FocusRecoveryBanner } from "./FocusRecoveryBanner";
 import { useEffect } from "react";
 import { getSettings } from "../lib/settings";
 import { useLocation } from "react-router-dom";
@@ -55,6 +56,7 @@ export function Layout() {
         <Sidebar />
         <main className="md:ml-64 min-h-screen pb-24 md:pb-0">
           <div className={`${isNotesPage ? "max-w-[96rem]" : "max-w-5xl"} mx-auto p-4 md:p-8 lg:p-10`}>
+            <FocusRecoveryBanner />
             <Outlet />
           </div>
         </main>
@@ -10,6 +10,10 @@ import {
   fetchFocusTemplates,
   fetchWrongQuestionStats,
   finishFocusRun,
+  heartbeatFocusRun,
+  isActiveFocusRunConflict,
+  pauseFocusRun,
+  resumeFocusRun,
   modifyTask,
   startFocusRun,
   type FocusTemplate,
@@ -445,6 +449,16 @@ export function Dashboard() {
     return () => clearInterval(timer);
   }, [isPomodoroRunning, pomodoroSeconds]);
 
+  useEffect(() => {
+    if (!activeRunId || !isPomodoroRunning) return;
+    const heartbeat = setInterval(() => {
+      heartbeatFocusRun(activeRunId).catch((error) => {
+        console.warn("focus run heartbeat failed:", error);
+      });
+    }, 60_000);
+    return () => clearInterval(heartbeat);
+  }, [activeRunId, isPomodoroRunning]);
+
   useEffect(() => {
     if (pomodoroMode !== "template") return;
     if (!selectedTemplate) return;
@@ -663,12 +677,11 @@ export function Dashboard() {
       : Math.max(0, pomodoroTotal - pomodoroSeconds);
 
     try {
-      await finishFocusRun(activeRunId, {
+      const finished = await finishFocusRun(activeRunId, {
         actual_seconds: actualSeconds,
         status,
       });
-      const minutes = Math.floor(actualSeconds / 60);
-      setLastFocusMinutes(minutes);
+      setLastFocusMinutes(Math.floor(finished.actual_seconds / 60));
       await refreshFocusSnapshots(activeRunTemplateId);
     } catch (error) {
       console.warn("finish dashboard focus run failed:", error);
@@ -689,10 +702,19 @@ export function Dashboard() {
   const handlePomodoroToggle = async () => {
     if (isPomodoroRunning) {
       setIsPomodoroRunning(false);
+      if (activeRunId) {
+        pauseFocusRun(activeRunId).catch((error) => {
+          console.warn("pause dashboard focus run failed:", error);
+        });
+      }
       return;
     }
 
-    if (!activeRunId) {
+    if (activeRunId) {
+      resumeFocusRun(activeRunId).catch((error) => {
+        console.warn("resume dashboard focus run failed:", error);
+      });
+    } else {
       let payloadTemplateId: string | null = null;
       let payloadTaskId: string | null = null;
       let payloadTimerType: "pomodoro" | "countdown" = "pomodoro";
@@ -718,8 +740,8 @@ export function Dashboard() {
       }
 
       try {
-        const run = await startFocusRun({
-          source: "dashboard",
+        const payload = {
+          source: "dashboard" as const,
           template_id: payloadTemplateId,
           task_id: payloadTaskId,
           timer_type: payloadTimerType,
@@ -727,6 +749,10 @@ export function Dashboard() {
           date: today,
           tags_json: JSON.stringify(payloadTags),
           note: null,
+        };
+        const run = await startFocusRun(payload).catch((error) => {
+          if (!isActiveFocusRunConflict(error) || !window.confirm("另一设备上有进行中的专注，是否结束它并开始新的专注？")) throw error;
+          return startFocusRun({ ...payload, take_over: true });
         });
         setActiveRunId(run.id);
         setActiveRunTemplateId(payloadTemplateId);
@@ -5,6 +5,7 @@ import {
   carryWeeklyReviewItemsToNextWeek,
   createWrongQuestion,
   deleteWrongQuestion,
+  describeWrongQuestionDuplicates,
   fetchWeeklyReviewItems,
   fetchWrongQuestionStats,
   fetchWrongQuestions,
@@ -425,7 +426,7 @@ export function ErrorBook() {
                 onClick={async () => {
                   if (!createForm.question.trim() || !createForm.solution.trim()) return;
                   const now = new Date().toISOString();
-                  await createWrongQuestion({
+                  const payload: Partial<WrongQuestion> = {
                     id: "",
                     subject: createForm.subject,
                     tags_json: JSON.stringify(createForm.tags.split(",").map((x) => x.trim()).filter(Boolean)),
@@ -448,7 +449,15 @@ export function ErrorBook() {
                     is_archived: 0,
                     created_at: now,
                     updated_at: now,
-                  }, true);
+                  };
+                  const result = await createWrongQuestion(payload, true);
+                  if (!result.question) {
+                    const proceed = window.confirm(
+                      `错题本里已有相似题目：\n${describeWrongQuestionDuplicates(result.duplicates)}\n\n仍要保存吗？`
+                    );
+                    if (!proceed) return;
+                    await createWrongQuestion(payload, true, true);
+                  }
                   setShowCreate(false);
                   await loadData();
                 }}
@@ -10,6 +10,7 @@ import { isTauriAvailable } from "../lib/dataService";
 import { open } from "@tauri-apps/plugin-dialog";
 import {
   createWrongQuestion,
+  describeWrongQuestionDuplicates,
   fetchNoteContent,
   fetchNotesTree,
   getImageUrl,
@@ -1413,7 +1414,14 @@ export function Notes() {
         updated_at: now,
       };
 
-      await createWrongQuestion(payload, true);
+      const result = await createWrongQuestion(payload, true);
+      if (!result.question) {
+        const proceed = window.confirm(
+          `错题本里已有相似题目：\n${describeWrongQuestionDuplicates(result.duplicates)}\n\n仍要保存吗？`
+        );
+        if (!proceed) return;
+        await createWrongQuestion(payload, true, true);
+      }
 
       if (captureForm.syncToBlog) {
         const today = new Date();
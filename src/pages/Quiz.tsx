@@ -7,7 +7,14 @@ import remarkGfm from "remark-gfm";
 import remarkMath from "remark-math";
 import rehypeKatex from "rehype-katex";
 import { chatCompletionToText } from "../utils/aiClient";
-import { answerQuizQuestion, createQuizQuestion, fetchQuizQuestions, readLocalResourceText } from "../utils/apiBridge";
+import {
+  answerQuizQuestion,
+  createQuizQuestion,
+  fetchQuizQuestions,
+  gradeQuizQuestion,
+  readLocalResourceText,
+  type ReviewGrade,
+} from "../utils/apiBridge";
 import "katex/dist/katex.min.css";
 
 type Subject = "408" | "数一" | "英一" | "政治";
@@ -48,6 +55,14 @@ type QuizLocationState = {
 };
 
 type FormMode = "manual" | "smart";
+// Flashcards (e.g. Anki imports) have no options: reveal the back, then self-grade.
+const flashcardGrades: Array<{ grade: ReviewGrade; label: string; className: string }> = [
+  { grade: "again", label: "重来", className: "border-red-300 text-red-600 hover:bg-red-50 dark:border-red-500/40 dark:text-red-300 dark:hover:bg-red-500/10" },
+  { grade: "hard", label: "困难", className: "border-amber-300 text-amber-600 hover:bg-amber-50 dark:border-amber-500/40 dark:text-amber-300 dark:hover:bg-amber-500/10" },
+  { grade: "good", label: "良好", className: "border-emerald-300 text-emerald-600 hover:bg-emerald-50 dark:border-emerald-500/40 dark:text-emerald-300 dark:hover:bg-emerald-500/10" },
+  { grade: "easy", label: "简单", className: "border-sky-300 text-sky-600 hover:bg-sky-50 dark:border-sky-500/40 dark:text-sky-300 dark:hover:bg-sky-500/10" },
+];
+
 type PracticeMode = "all" | "due";
 
 const emptyForm: NewQuestionForm = {
@@ -69,6 +84,7 @@ export function Quiz() {
   const [loadError, setLoadError] = useState<string | null>(null);
   const [currentIndex, setCurrentIndex] = useState(0);
   const [selected, setSelected] = useState<string | null>(null);
+  const [revealed, setRevealed] = useState(false);
   const [isSubmittingAnswer, setIsSubmittingAnswer] = useState(false);
   const [showCreateModal, setShowCreateModal] = useState(false);
   const [createForm, setCreateForm] = useState<NewQuestionForm>(emptyForm);
@@ -161,10 +177,13 @@ export function Quiz() {
         mode: mode === "due" ? "due" : "all",
         subject,
       });
-      const filtered = rows.filter((item) => item.subject === subject && item.type === "choice");
+      const filtered = rows.filter(
+        (item) => item.subject === subject && (item.type === "choice" || item.type === "flashcard")
+      );
       setQuestions(filtered);
       setCurrentIndex(0);
       setSelected(null);
+      setRevealed(false);
     } catch (err: any) {
       setLoadError(`当前环境不支持本地数据库命令或 IPC 调用失败：${err?.message || String(err)}`);
       setQuestions([]);
@@ -284,6 +303,7 @@ export function Quiz() {
 
   const currentQuestion = questions[currentIndex] || null;
   const options = useMemo(() => parseOptions(currentQuestion?.options || null), [currentQuestion?.options]);
+  const isFlashcard = currentQuestion?.type === "flashcard";
   const correctAnswer = (currentQuestion?.answer || "").toUpperCase();
   const hasAnswered = selected !== null;
   const isCurrentCorrect = selected !== null && selected === correctAnswer;
@@ -406,11 +426,26 @@ export function Quiz() {
     }
   };
 
+  const handleGrade = async (grade: ReviewGrade) => {
+    if (!currentQuestion || hasAnswered || isSubmittingAnswer) return;
+    setSelected(grade);
+
+    setIsSubmittingAnswer(true);
+    try {
+      await gradeQuizQuestion(currentQuestion.id, grade);
+    } catch (err: any) {
+      setLoadError(err?.message || String(err));
+    } finally {
+      setIsSubmittingAnswer(false);
+    }
+  };
+
   const goNext = () => {
     if (questions.length === 0) return;
     const next = (currentIndex + 1) % questions.length;
     setCurrentIndex(next);
     setSelected(null);
+    setRevealed(false);
   };
 
   return (
@@ -513,7 +548,7 @@ export function Quiz() {
                 Question {currentIndex + 1} of {questions.length}
               </span>
               <span className="text-xs font-bold text-gray-500 dark:text-gray-400 bg-gray-100 dark:bg-gray-800 px-3 py-1.5 rounded-full uppercase tracking-widest">
-                {currentQuestion.subject} · 单选题
+                {currentQuestion.subject} · {isFlashcard ? "闪卡" : "单选题"}
               </span>
             </div>
 
@@ -525,6 +560,45 @@ export function Quiz() {
               </div>
             </div>
 
+            {isFlashcard ? (
+              !revealed ? (
+                <button
+                  onClick={() => setRevealed(true)}
+                  className="w-full p-5 rounded-2xl border-2 border-dashed border-gray-200 dark:border-gray-700 text-gray-600 dark:text-gray-300 font-medium hover:border-indigo-300 dark:hover:border-indigo-400 hover:bg-indigo-50/50 dark:hover:bg-indigo-500/10 transition-all"
+                >
+                  显示答案
+                </button>
+              ) : (
+                <div className="space-y-4">
+                  <div className="bg-emerald-50/60 dark:bg-emerald-500/10 border-2 border-emerald-200 dark:border-emerald-500/30 rounded-2xl p-5">
+                    <div className="text-xs font-semibold text-emerald-700 dark:text-emerald-300 mb-2">答案</div>
+                    <div className="prose prose-base dark:prose-invert max-w-none">
+                      <ReactMarkdown remarkPlugins={[remarkGfm, remarkMath]} rehypePlugins={[rehypeKatex]}>
+                        {currentQuestion.answer || "（背面为空）"}
+                      </ReactMarkdown>
+                    </div>
+                  </div>
+                  <div className="grid grid-cols-2 md:grid-cols-4 gap-3">
+                    {flashcardGrades.map((item) => (
+                      <button
+                        key={item.grade}
+                        onClick={() => handleGrade(item.grade)}
+                        disabled={hasAnswered || isSubmittingAnswer}
+                        className={cn(
+                          "py-3 rounded-xl border-2 font-semibold text-sm transition-all",
+                          item.className,
+                          selected === item.grade && "ring-2 ring-offset-2 ring-current",
+                          hasAnswered && selected !== item.grade && "opacity-50",
+                          !hasAnswered && "active:scale-[0.98]"
+                        )}
+                      >
+                        {item.label}
+                      </button>
+                    ))}
+                  </div>
+                </div>
+              )
+            ) : (
             <div className="space-y-4">
               {options.map((option) => {
                 const isSelected = selected === option.id;
@@ -568,11 +642,12 @@ export function Quiz() {
                 );
               })}
             </div>
+            )}
 
             {hasAnswered && (
               <div className="mt-8 border-t border-gray-200/60 dark:border-gray-800 pt-6 animate-in slide-in-from-bottom-3 fade-in duration-400">
                 <p className="text-sm font-medium text-gray-700 dark:text-gray-300 mb-4">
-                  {isCurrentCorrect ? "🎉 回答正确，已记录本次刷题结果。" : `💡 回答错误，正确答案是 ${correctAnswer}。已记录本次刷题结果。`}
+                  {isFlashcard ? "已记录本次复习结果。" : isCurrentCorrect ? "🎉 回答正确，已记录本次刷题结果。" : `💡 回答错误，正确答案是 ${correctAnswer}。已记录本次刷题结果。`}
                 </p>
                 <div className="bg-gray-50/70 dark:bg-[#0f1826]/55 border border-gray-200 dark:border-gray-800 rounded-2xl p-4">
                   <div className="text-xs font-semibold text-gray-500 dark:text-gray-400 mb-2">解析</div>
@@ -18,6 +18,10 @@ import {
   fetchFocusTemplates,
   fetchTasks,
   finishFocusRun,
+  heartbeatFocusRun,
+  isActiveFocusRunConflict,
+  pauseFocusRun,
+  resumeFocusRun,
   modifyTask,
   removeTask,
   startFocusRun,
@@ -280,6 +284,16 @@ export function Tasks() {
     };
   }, [activeTimerTask?.timerDuration, finalizeFocusRun, isTimerRunning, timeLeft]);
 
+  useEffect(() => {
+    if (!activeRunId || !isTimerRunning) return;
+    const heartbeat = setInterval(() => {
+      heartbeatFocusRun(activeRunId).catch((error) => {
+        console.warn("focus run heartbeat failed:", error);
+      });
+    }, 60_000);
+    return () => clearInterval(heartbeat);
+  }, [activeRunId, isTimerRunning]);
+
   const startTimerForTask = useCallback(async (task: Task) => {
     if (activeRunId) {
       const currentPlanned = (activeTimerTask?.timerDuration || 25) * 60;
@@ -298,7 +312,14 @@ export function Tasks() {
     };
 
     try {
-      const run = await startFocusRun(payload);
+      const run = await startFocusRun(payload).catch((error) => {
+        if (!isActiveFocusRunConflict(error) || !window.confirm("另一设备上有进行中的专注，是否结束它并开始新的专注？")) throw error;
+        return startFocusRun({ ...payload, take_over: true });
+      });
+      // The local timer starts paused; keep the server run in step until the user presses play.
+      await pauseFocusRun(run.id).catch((error) => {
+        console.warn("pause focus run failed:", error);
+      });
       setActiveRunId(run.id);
     } catch (error) {
       console.warn("start focus run failed:", error);
@@ -324,8 +345,21 @@ export function Tasks() {
     setTasks,
   ]);
 
-  const toggleTimer = () => setIsTimerRunning(!isTimerRunning);
+  const toggleTimer = () => {
+    if (activeRunId) {
+      const transition = isTimerRunning ? pauseFocusRun : resumeFocusRun;
+      transition(activeRunId).catch((error) => {
+        console.warn("sync focus run pause state failed:", error);
+      });
+    }
+    setIsTimerRunning(!isTimerRunning);
+  };
   const resetTimer = () => {
+    if (activeRunId && isTimerRunning) {
+      pauseFocusRun(activeRunId).catch((error) => {
+        console.warn("pause focus run failed:", error);
+      });
+    }
     setIsTimerRunning(false);
     if (activeTimerTask) setTimeLeft((activeTimerTask.timerDuration || 25) * 60);
   };
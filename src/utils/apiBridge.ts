@@ -92,6 +92,7 @@ export type StartFocusRunPayload = {
   date?: string | null;
   tags_json?: string | null;
   note?: string | null;
+  take_over?: boolean;
 };
 
 export type FinishFocusRunPayload = {
@@ -110,7 +111,7 @@ export type FocusRun = {
   timer_type: "pomodoro" | "countdown";
   planned_minutes: number;
   actual_seconds: number;
-  status: "running" | "completed" | "aborted";
+  status: "running" | "completed" | "aborted" | "interrupted";
   started_at: string;
   ended_at?: string | null;
   date: string;
@@ -168,13 +169,40 @@ export type WrongQuestion = {
   is_archived: 0 | 1 | number;
   created_at: string;
   updated_at: string;
+  // JSON array of cause keys; leave it out of an update to keep the stored causes.
+  error_causes_json?: string;
 };
 
+export type WrongQuestionErrorCause = "concept_gap" | "careless" | "calculation" | "misread" | "time_pressure";
+
+export type WrongQuestionDuplicate = {
+  question: WrongQuestion;
+  similarity: number;
+  exact: boolean;
+};
+
+// `question` is null when the save was held back because of `duplicates`;
+// resubmit with allowDuplicate to save anyway.
+export type WrongQuestionCreateResult = {
+  question: WrongQuestion | null;
+  duplicates: WrongQuestionDuplicate[];
+};
+
+export function describeWrongQuestionDuplicates(duplicates: WrongQuestionDuplicate[]): string {
+  return duplicates
+    .map((d) => {
+      const snippet = d.question.question_content.replace(/\s+/g, " ").trim().slice(0, 40);
+      return `· ${snippet}（${d.exact ? "完全相同" : `相似度 ${Math.round(d.similarity * 100)}%`}）`;
+    })
+    .join("\n");
+}
+
 export type WrongQuestionFilter = {
   subject?: string;
   mastery_level?: number;
   search_keyword?: string;
   is_archived?: number;
+  error_cause?: WrongQuestionErrorCause | string;
 };
 
 export type WeeklyReviewItem = {
@@ -196,6 +224,19 @@ export type SubjectStat = {
   unmastered: number;
 };
 
+export type ErrorCauseStat = {
+  cause: string;
+  count: number;
+  unmastered: number;
+};
+
+export type ErrorCauseTrendPoint = {
+  week_start: string; // Monday, YYYY-MM-DD
+  subject: string;
+  cause: string;
+  count: number;
+};
+
 export type WrongQuestionStats = {
   total_count: number;
   unmastered_count: number;
@@ -203,6 +244,8 @@ export type WrongQuestionStats = {
   weekly_done_count: number;
   this_week_new: number;
   by_subject: SubjectStat[];
+  by_cause: ErrorCauseStat[];
+  cause_trend: ErrorCauseTrendPoint[];
 };
 
 export const SYNC_WRONG_QUESTIONS = "SYNC_WRONG_QUESTIONS";
@@ -463,6 +506,16 @@ export async function answerQuizQuestion(id: string, isCorrect: boolean): Promis
   await invoke("answer_question", { id, isCorrect });
 }
 
+export type ReviewGrade = "again" | "hard" | "good" | "easy";
+
+export async function gradeQuizQuestion(id: string, grade: ReviewGrade): Promise<void> {
+  if (!isTauriRuntime()) {
+    throw new Error("当前 Web 模式暂不支持判题写回，请在桌面端执行。");
+  }
+  const invoke = await getInvoke();
+  await invoke("answer_question", { id, grade });
+}
+
 export async function readLocalResourceText(path: string): Promise<string> {
   if (!isTauriRuntime()) {
     throw new Error("当前 Web 模式暂不支持读取本地资源文件。");
@@ -669,6 +722,11 @@ export async function startFocusRun(payload: StartFocusRunPayload): Promise<Focu
   return (await response.json()) as FocusRun;
 }
 
+// Starts are rejected while another page or device has a run active; retry with take_over to end it.
+export function isActiveFocusRunConflict(error: unknown): boolean {
+  return String((error as Error)?.message ?? error).includes("已有进行中的专注");
+}
+
 export async function finishFocusRun(runId: string, payload: FinishFocusRunPayload): Promise<FocusRun> {
   if (!runId.trim()) {
     throw new Error("runId 不能为空");
@@ -695,6 +753,108 @@ export async function finishFocusRun(runId: string, payload: FinishFocusRunPaylo
   return (await response.json()) as FocusRun;
 }
 
+async function transitionFocusRun(runId: string, action: "pause" | "resume"): Promise<void> {
+  if (!runId.trim()) {
+    throw new Error("runId 不能为空");
+  }
+
+  if (isTauriRuntime()) {
+    const invoke = await getInvoke();
+    await invoke(`${action}_focus_run`, { runId });
+    return;
+  }
+
+  const response = await fetch(`${getLanBaseUrl()}/api/focus/runs/${encodeURIComponent(runId)}/${action}`, {
+    method: "POST",
+  });
+
+  if (!response.ok) {
+    const text = await response.text().catch(() => "");
+    throw new Error(`HTTP 请求失败 (${response.status}): ${text || response.statusText}`);
+  }
+}
+
+// The server measures focus time from its own segments, so local pauses must be reported.
+export async function pauseFocusRun(runId: string): Promise<void> {
+  await transitionFocusRun(runId, "pause");
+}
+
+export async function resumeFocusRun(runId: string): Promise<void> {
+  await transitionFocusRun(runId, "resume");
+}
+
+export async function heartbeatFocusRun(runId: string): Promise<void> {
+  if (!runId.trim()) return;
+
+  if (isTauriRuntime()) {
+    const invoke = await getInvoke();
+    await invoke("heartbeat_focus_run", { runId });
+    return;
+  }
+
+  const response = await fetch(`${getLanBaseUrl()}/api/focus/runs/${encodeURIComponent(runId)}/heartbeat`, {
+    method: "POST",
+  });
+
+  if (!response.ok && response.status !== 204) {
+    const text = await response.text().catch(() => "");
+    throw new Error(`HTTP 请求失败 (${response.status}): ${text || response.statusText}`);
+  }
+}
+
+// A run left active by a crash; elapsed_seconds is the focus time recorded before it stopped.
+export type InterruptedFocusRun = FocusRun & {
+  elapsed_seconds: number;
+};
+
+export type FocusRecoveryAction = "resume" | "finalize" | "abort";
+
+export async function fetchInterruptedFocusRuns(): Promise<InterruptedFocusRun[]> {
+  if (isTauriRuntime()) {
+    const invoke = await getInvoke();
+    const rows = await invoke<InterruptedFocusRun[]>("get_interrupted_focus_runs");
+    return Array.isArray(rows) ? rows : [];
+  }
+
+  const response = await fetch(`${getLanBaseUrl()}/api/focus/runs/interrupted`);
+  if (!response.ok) {
+    const text = await response.text().catch(() => "");
+    throw new Error(`HTTP 请求失败 (${response.status}): ${text || response.statusText}`);
+  }
+  const rows = (await response.json()) as InterruptedFocusRun[];
+  return Array.isArray(rows) ? rows : [];
+}
+
+export async function recoverFocusRun(runId: string, action: FocusRecoveryAction, capMinutes?: number): Promise<void> {
+  if (!runId.trim()) {
+    throw new Error("runId 不能为空");
+  }
+
+  if (isTauriRuntime()) {
+    const invoke = await getInvoke();
+    await invoke("recover_focus_run", { runId, action, capMinutes });
+    return;
+  }
+
+  const query = new URLSearchParams({ action });
+  if (typeof capMinutes === "number") query.set("cap_minutes", String(capMinutes));
+  const response = await fetch(`${getLanBaseUrl()}/api/focus/runs/${encodeURIComponent(runId)}/recover?${query.toString()}`, {
+    method: "POST",
+  });
+
+  if (!response.ok) {
+    const text = await response.text().catch(() => "");
+    throw new Error(`HTTP 请求失败 (${response.status}): ${text || response.statusText}`);
+  }
+}
+
+// The desktop app announces runs it found orphaned at startup; browsers only see them by fetching.
+export async function onFocusRunsInterrupted(callback: () => void): Promise<() => void> {
+  if (!isTauriRuntime()) return () => {};
+  const { listen } = await import("@tauri-apps/api/event");
+  return await listen("focus-runs-interrupted", () => callback());
+}
+
 export async function fetchFocusStats(options: FetchFocusStatsOptions = {}): Promise<FocusStatsResult> {
   const query = new URLSearchParams();
   if (options.startDate?.trim()) query.set("start_date", options.startDate.trim());
@@ -732,6 +892,7 @@ export async function fetchWrongQuestions(filter: WrongQuestionFilter = {}): Pro
   if (typeof filter.mastery_level === "number") query.set("mastery_level", String(filter.mastery_level));
   if (filter.search_keyword?.trim()) query.set("search_keyword", filter.search_keyword.trim());
   if (typeof filter.is_archived === "number") query.set("is_archived", String(filter.is_archived));
+  if (filter.error_cause?.trim()) query.set("error_cause", filter.error_cause.trim());
   const qs = query.toString();
   const response = await fetch(`${getLanBaseUrl()}/api/wrong-questions${qs ? `?${qs}` : ""}`);
   if (!response.ok) {
@@ -744,13 +905,15 @@ export async function fetchWrongQuestions(filter: WrongQuestionFilter = {}): Pro
 
 export async function createWrongQuestion(
   question: Partial<WrongQuestion>,
-  addToCurrentWeek = false
-): Promise<WrongQuestion> {
+  addToCurrentWeek = false,
+  allowDuplicate = false
+): Promise<WrongQuestionCreateResult> {
   if (isTauriRuntime()) {
     const invoke = await getInvoke();
-    return await invoke<WrongQuestion>("create_wrong_question", {
+    return await invoke<WrongQuestionCreateResult>("create_wrong_question", {
       question,
       addToCurrentWeek,
+      allowDuplicate,
     });
   }
 
@@ -762,15 +925,17 @@ export async function createWrongQuestion(
     body: JSON.stringify({
       question,
       add_to_current_week: addToCurrentWeek,
+      allow_duplicate: allowDuplicate,
     }),
   });
 
-  if (!response.ok) {
+  // 409 carries the duplicates that held the save back.
+  if (!response.ok && response.status !== 409) {
     const text = await response.text().catch(() => "");
     throw new Error(`HTTP 请求失败 (${response.status}): ${text || response.statusText}`);
   }
 
-  return (await response.json()) as WrongQuestion;
+  return (await response.json()) as WrongQuestionCreateResult;
 }
 
 export async function updateWrongQuestion(id: string, question: Partial<WrongQuestion>): Promise<WrongQuestion> {